@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use forge_engine::build_graphs_from_source;
+use forge_engine::{build_graphs_from_source, CacheStats, ParseCache};
 
 const SAMPLE_SOURCE: &str = r#"
 import 'package:flutter/widgets.dart';
@@ -56,5 +56,42 @@ fn benchmark_parse_graphs(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, benchmark_parse_graphs);
+/// Re-imports the samples through a warm cache, as an unchanged workspace
+/// would be, and checks that no sample is parsed again.
+fn benchmark_cached_reimport(c: &mut Criterion) {
+    let samples = build_benchmark_samples(100);
+    let files: Vec<String> = (0..samples.len())
+        .map(|idx| format!("lib/sample_{idx}.dart"))
+        .collect();
+    let cache_dir = tempfile::tempdir().expect("cache directory");
+    let cache = ParseCache::open(cache_dir.path()).expect("open parse cache");
+    for (sample, file) in samples.iter().zip(&files) {
+        cache.parse(sample, Some(file)).expect("warm parse cache");
+    }
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 0,
+            misses: samples.len()
+        }
+    );
+
+    c.bench_function("reimport_100_samples_cached", |b| {
+        b.iter(|| {
+            for (sample, file) in samples.iter().zip(&files) {
+                let parse = cache.parse(sample, Some(file)).expect("cached parse");
+                criterion::black_box(&parse);
+            }
+        })
+    });
+
+    let stats = cache.stats();
+    assert_eq!(stats.misses, samples.len(), "warm re-import parsed again");
+    println!(
+        "parse cache: {} hit(s), {} miss(es)",
+        stats.hits, stats.misses
+    );
+}
+
+criterion_group!(benches, benchmark_parse_graphs, benchmark_cached_reimport);
 criterion_main!(benches);
@@ -1,7 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use forge_engine::{
-    read_graph, AngularRenderer, ReactRenderer, RenderContext, RenderDialect, RenderOptions,
-    RendererAdapter, RiverpodAdapter, ScreenGraph,
+    read_graph, render_screens, AngularRenderer, Parallelism, ReactRenderer, RenderContext,
+    RenderDialect, RenderOptions, RendererAdapter, RiverpodAdapter, ScreenGraph,
 };
 use std::path::Path;
 
@@ -43,5 +43,27 @@ fn bench_angular(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_react, bench_angular);
+fn bench_react_screens(c: &mut Criterion) {
+    let fixtures = ["react_basic", "conditional_list", "builder_list"];
+    let screens: Vec<ScreenGraph> = (0..64)
+        .map(|idx| load_fixture(fixtures[idx % fixtures.len()]))
+        .collect();
+    let renderer = ReactRenderer;
+    let adapter = RiverpodAdapter::new();
+    let options = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Jsx,
+    };
+    let ctx = RenderContext::new(0, &adapter, &options);
+
+    c.bench_function("react_render_64_screens_sequential", |b| {
+        b.iter(|| render_screens(&renderer, &screens, &ctx, Parallelism::new().with_jobs(1)))
+    });
+    c.bench_function("react_render_64_screens_parallel", |b| {
+        b.iter(|| render_screens(&renderer, &screens, &ctx, Parallelism::new()))
+    });
+}
+
+criterion_group!(benches, bench_react, bench_angular, bench_react_screens);
 criterion_main!(benches);
@@ -0,0 +1,72 @@
+//! Stand-in for the external Dart analyzer used by the analyzer protocol
+//! tests. The first argument selects the behavior:
+//!
+//! - `rename` answers with the quick graph renamed to `<id>__analyzer` and a
+//!   warning diagnostic.
+//! - `crash` writes to stderr and exits with status 3.
+//! - `hang` reads the request and never answers.
+//! - `garbage` answers with text that is not JSON.
+//! - `ignore-input` answers without reading the request.
+
+use std::{
+    io::{self, Read, Write},
+    process, thread,
+    time::Duration,
+};
+
+use forge_engine::{
+    AnalyzerRequest, AnalyzerResponse, DiagnosticSeverity, ParseDiagnostic,
+    ANALYZER_PROTOCOL_VERSION,
+};
+
+fn main() {
+    let mode = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "rename".to_string());
+    if mode == "ignore-input" {
+        print!("{{\"graph\": {{\"id\": \"Ignored\", \"root\": {{\"widget\": \"Text\"}}}}}}");
+        return;
+    }
+
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .expect("read request");
+    let request: AnalyzerRequest = serde_json::from_str(&input).expect("parse request");
+    assert_eq!(request.version, ANALYZER_PROTOCOL_VERSION);
+
+    match mode.as_str() {
+        "rename" => {
+            let mut graph = request.quick_graph;
+            graph.id = format!("{}__analyzer", graph.id);
+            let response = AnalyzerResponse {
+                diagnostics: vec![ParseDiagnostic {
+                    severity: DiagnosticSeverity::Warning,
+                    message: format!(
+                        "analyzed {} bytes of {}",
+                        request.source.len(),
+                        request.file.as_deref().unwrap_or("<unknown>")
+                    ),
+                    screen: Some(graph.id.clone()),
+                    span: None,
+                }],
+                graph,
+            };
+            let stdout = io::stdout();
+            serde_json::to_writer(stdout.lock(), &response).expect("write response");
+            stdout.lock().flush().expect("flush response");
+        }
+        "crash" => {
+            eprintln!("analyzer crashed on purpose");
+            process::exit(3);
+        }
+        "hang" => loop {
+            thread::sleep(Duration::from_secs(60));
+        },
+        "garbage" => print!("this is not json"),
+        other => {
+            eprintln!("unknown mode {other}");
+            process::exit(64);
+        }
+    }
+}
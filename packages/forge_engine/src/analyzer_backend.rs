@@ -0,0 +1,273 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{ParseDiagnostic, ScreenGraph};
+
+/// Version of the JSON protocol spoken with external analyzers. Sent in every
+/// request so analyzers can reject requests they do not understand.
+pub const ANALYZER_PROTOCOL_VERSION: u32 = 1;
+
+/// Poll interval used while waiting for an analyzer process to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Backend that produces a fully analyzed graph for a screen the quick parser
+/// was not confident about.
+pub trait AnalyzerBackend: fmt::Debug + Send + Sync {
+    fn analyze(&self, request: &AnalyzerRequest) -> Result<AnalyzerResponse, AnalyzerError>;
+}
+
+/// Request sent to an external analyzer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerRequest {
+    pub version: u32,
+    /// Path of the Dart file, when known.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file: Option<String>,
+    pub source: String,
+    pub quick_graph: ScreenGraph,
+}
+
+impl AnalyzerRequest {
+    pub fn new(source: &str, quick_graph: &ScreenGraph) -> Self {
+        Self {
+            version: ANALYZER_PROTOCOL_VERSION,
+            file: quick_graph
+                .root
+                .span
+                .as_ref()
+                .and_then(|span| span.file.clone()),
+            source: source.to_string(),
+            quick_graph: quick_graph.clone(),
+        }
+    }
+}
+
+/// Response read back from an external analyzer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerResponse {
+    pub graph: ScreenGraph,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+#[derive(Debug, Error)]
+pub enum AnalyzerError {
+    #[error("failed to start analyzer {program}: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to communicate with analyzer {program}: {source}")]
+    Io {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("analyzer {program} did not finish within {timeout:?}")]
+    Timeout { program: String, timeout: Duration },
+    #[error("analyzer {program} exited with {status}{}", stderr_suffix(.stderr))]
+    Exited {
+        program: String,
+        status: String,
+        stderr: String,
+    },
+    #[error("analyzer {program} returned an invalid response: {source}")]
+    InvalidResponse {
+        program: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+fn stderr_suffix(stderr: &str) -> String {
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {stderr}")
+    }
+}
+
+/// Runs an external analyzer executable once per screen. The request is
+/// written as JSON to the process's stdin and the response is read as JSON
+/// from its stdout; a non-zero exit status is treated as a crash and stderr is
+/// kept for the error message.
+#[derive(Debug, Clone)]
+pub struct SubprocessAnalyzer {
+    program: PathBuf,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl SubprocessAnalyzer {
+    /// Time an analyzer may run before it is killed.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new<P: AsRef<Path>>(program: P) -> Self {
+        Self {
+            program: program.as_ref().to_path_buf(),
+            args: Vec::new(),
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn program_name(&self) -> String {
+        self.program.display().to_string()
+    }
+
+    fn io_error(&self, source: std::io::Error) -> AnalyzerError {
+        AnalyzerError::Io {
+            program: self.program_name(),
+            source,
+        }
+    }
+
+    /// Waits for `child` to exit, killing it once the timeout elapses.
+    fn wait(&self, child: &mut Child) -> Result<ExitStatus, AnalyzerError> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Some(status) = child.try_wait().map_err(|err| self.io_error(err))? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                // The process may exit between the last poll and the kill.
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AnalyzerError::Timeout {
+                    program: self.program_name(),
+                    timeout: self.timeout,
+                });
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl AnalyzerBackend for SubprocessAnalyzer {
+    fn analyze(&self, request: &AnalyzerRequest) -> Result<AnalyzerResponse, AnalyzerError> {
+        let payload = serde_json::to_vec(request).map_err(|source| AnalyzerError::Io {
+            program: self.program_name(),
+            source: source.into(),
+        })?;
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| AnalyzerError::Spawn {
+                program: self.program_name(),
+                source,
+            })?;
+
+        // Every pipe gets its own thread so a large request or response cannot
+        // deadlock against an analyzer that writes before it finishes reading.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = thread::spawn(move || stdin.write_all(&payload));
+        let stdout = read_to_end(child.stdout.take().expect("stdout is piped"));
+        let stderr = read_to_end(child.stderr.take().expect("stderr is piped"));
+
+        let status = self.wait(&mut child)?;
+        let stdout = join_output(stdout);
+        let stderr = String::from_utf8_lossy(&join_output(stderr)).into_owned();
+
+        if !status.success() {
+            return Err(AnalyzerError::Exited {
+                program: self.program_name(),
+                status: status.to_string(),
+                stderr,
+            });
+        }
+
+        // An analyzer that exits without reading its input closes the pipe
+        // early; that only matters if it also failed to answer.
+        let written = writer
+            .join()
+            .unwrap_or_else(|_| Err(std::io::Error::other("stdin writer panicked")));
+
+        serde_json::from_slice(&stdout).map_err(|source| match written {
+            Err(err) if stdout.is_empty() => self.io_error(err),
+            _ => AnalyzerError::InvalidResponse {
+                program: self.program_name(),
+                source,
+            },
+        })
+    }
+}
+
+fn read_to_end<R: Read + Send + 'static>(mut reader: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = reader.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+fn join_output(handle: JoinHandle<Vec<u8>>) -> Vec<u8> {
+    handle.join().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_program_reports_spawn_error() {
+        let analyzer = SubprocessAnalyzer::new("/nonexistent/forge-analyzer");
+        let graph: ScreenGraph = serde_json::from_value(serde_json::json!({
+            "id": "Home",
+            "root": { "widget": "Text", "props": {}, "children": [] }
+        }))
+        .unwrap();
+        let err = analyzer
+            .analyze(&AnalyzerRequest::new("", &graph))
+            .unwrap_err();
+        assert!(matches!(err, AnalyzerError::Spawn { .. }), "{err}");
+    }
+
+    #[test]
+    fn exit_errors_include_trimmed_stderr() {
+        let err = AnalyzerError::Exited {
+            program: "dart".to_string(),
+            status: "exit status: 3".to_string(),
+            stderr: "boom\n".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "analyzer dart exited with exit status: 3: boom"
+        );
+    }
+}
@@ -1,14 +1,20 @@
-use crate::{merge_screen_graphs, MergeOutcome, ScreenGraph};
+use crate::{
+    assign_node_ids, merge_screen_graphs, AnalyzerBackend, AnalyzerRequest, ConfidenceBreakdown,
+    DiagnosticSeverity, MergeOutcome, ParseDiagnostic, ParseReport, ScreenGraph,
+};
 use serde::Serialize;
+use std::sync::Arc;
 
 /// Hybrid analyzer service that routes between the native parser and an
 /// external analyzer based on confidence thresholds. When a fallback analyzer
 /// is required, the service reconciles the quick parse graph with the analyzer
 /// graph using the merge engine and returns the merged result alongside any
-/// conflicts.
+/// conflicts. Without a configured [AnalyzerBackend] the fallback keeps the
+/// quick parse graph.
 #[derive(Debug, Clone)]
 pub struct AnalyzerService {
     confidence_threshold: f32,
+    analyzer: Option<Arc<dyn AnalyzerBackend>>,
 }
 
 impl AnalyzerService {
@@ -20,9 +26,16 @@ impl AnalyzerService {
     pub fn new(confidence_threshold: f32) -> Self {
         Self {
             confidence_threshold: confidence_threshold.clamp(0.0, 1.0),
+            analyzer: None,
         }
     }
 
+    /// Uses `analyzer` for screens that fall back to the external analyzer.
+    pub fn with_analyzer<B: AnalyzerBackend + 'static>(mut self, analyzer: B) -> Self {
+        self.analyzer = Some(Arc::new(analyzer));
+        self
+    }
+
     /// Evaluates the provided confidence score and selects the processing
     /// strategy to follow.
     pub fn evaluate(&self, native_confidence: f32) -> AnalysisDecision {
@@ -35,12 +48,23 @@ impl AnalyzerService {
             strategy,
             native_confidence,
             threshold: self.confidence_threshold,
+            breakdown: None,
+        }
+    }
+
+    /// Scores the quick parse of `graph` with [ConfidenceBreakdown] and
+    /// selects the processing strategy from that score.
+    pub fn evaluate_parse(&self, graph: &ScreenGraph, report: &ParseReport) -> AnalysisDecision {
+        let breakdown = ConfidenceBreakdown::from_parse(graph, report);
+        AnalysisDecision {
+            breakdown: Some(breakdown.clone()),
+            ..self.evaluate(breakdown.score)
         }
     }
 
-    /// Runs the hybrid analyzer flow, invoking the mocked analyzer when the
-    /// fallback strategy is selected. Returns the merge outcome alongside the
-    /// decision metadata.
+    /// Runs the hybrid analyzer flow, invoking the configured analyzer when
+    /// the fallback strategy is selected. Returns the merge outcome alongside
+    /// the decision metadata.
     pub fn run(
         &self,
         source: &str,
@@ -49,7 +73,33 @@ impl AnalyzerService {
         native_confidence: f32,
     ) -> AnalysisOutcome {
         let decision = self.evaluate(native_confidence);
+        self.run_decision(source, base_graph, quick_graph, decision, Vec::new())
+    }
+
+    /// Runs the hybrid analyzer flow with the confidence computed from the
+    /// quick parse, see [AnalyzerService::evaluate_parse]. Diagnostics the
+    /// report holds for the screen are copied into the outcome ahead of any
+    /// reported by the analyzer.
+    pub fn run_with_report(
+        &self,
+        source: &str,
+        base_graph: &ScreenGraph,
+        quick_graph: ScreenGraph,
+        report: &ParseReport,
+    ) -> AnalysisOutcome {
+        let decision = self.evaluate_parse(&quick_graph, report);
+        let diagnostics = report.for_screen(&quick_graph.id).cloned().collect();
+        self.run_decision(source, base_graph, quick_graph, decision, diagnostics)
+    }
 
+    fn run_decision(
+        &self,
+        source: &str,
+        base_graph: &ScreenGraph,
+        quick_graph: ScreenGraph,
+        decision: AnalysisDecision,
+        mut diagnostics: Vec<ParseDiagnostic>,
+    ) -> AnalysisOutcome {
         let quick_owned = quick_graph;
         let quick_ref = &quick_owned;
         let mut analyzer_graph_out: Option<ScreenGraph> = None;
@@ -60,6 +110,15 @@ impl AnalyzerService {
             AnalysisStrategy::AnalyzerFallback => {
                 let invocation = self.invoke_analyzer(source, quick_ref);
                 analyzer_invoked = invocation.executed;
+                diagnostics.extend(invocation.diagnostics);
+                if let Some(error) = invocation.error {
+                    diagnostics.push(ParseDiagnostic {
+                        severity: DiagnosticSeverity::Error,
+                        message: format!("{error}; the quick parse is kept"),
+                        screen: Some(quick_ref.id.clone()),
+                        span: None,
+                    });
+                }
                 let analyzer_graph = invocation.graph.unwrap_or_else(|| quick_ref.clone());
                 let merge = merge_screen_graphs(base_graph, quick_ref, &analyzer_graph);
                 analyzer_graph_out = Some(analyzer_graph);
@@ -70,23 +129,44 @@ impl AnalyzerService {
         AnalysisOutcome {
             decision,
             analyzer_invoked,
-            diagnostics: Vec::new(),
+            diagnostics,
             quick_graph: quick_owned,
             analyzer_graph: analyzer_graph_out,
             merge,
         }
     }
 
-    /// Mock external analyzer call. Currently returns a stub indicating that the
-    /// analyzer would have been executed; this will be replaced with the real
-    /// Dart analyzer integration.
-    fn invoke_analyzer(&self, _source: &str, quick_graph: &ScreenGraph) -> AnalyzerInvocation {
-        let mut analyzer_graph = quick_graph.clone();
-        analyzer_graph.id = format!("{}__analyzer", quick_graph.id);
+    /// Sends the screen to the configured analyzer. Failures are returned in
+    /// the invocation rather than aborting the run, so the caller can keep the
+    /// quick parse graph.
+    fn invoke_analyzer(&self, source: &str, quick_graph: &ScreenGraph) -> AnalyzerInvocation {
+        let Some(analyzer) = &self.analyzer else {
+            return AnalyzerInvocation {
+                executed: false,
+                graph: None,
+                diagnostics: Vec::new(),
+                error: None,
+            };
+        };
 
-        AnalyzerInvocation {
-            executed: true,
-            graph: Some(analyzer_graph),
+        match analyzer.analyze(&AnalyzerRequest::new(source, quick_graph)) {
+            Ok(mut response) => {
+                // Analyzers need not know about node ids; nodes they leave
+                // without one get the id the quick parse would give them.
+                assign_node_ids(&mut response.graph);
+                AnalyzerInvocation {
+                    executed: true,
+                    graph: Some(response.graph),
+                    diagnostics: response.diagnostics,
+                    error: None,
+                }
+            }
+            Err(err) => AnalyzerInvocation {
+                executed: true,
+                graph: None,
+                diagnostics: Vec::new(),
+                error: Some(err.to_string()),
+            },
         }
     }
 }
@@ -103,6 +183,9 @@ pub struct AnalysisDecision {
     pub strategy: AnalysisStrategy,
     pub native_confidence: f32,
     pub threshold: f32,
+    /// How `native_confidence` was computed, when it came from the parse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<ConfidenceBreakdown>,
 }
 
 /// Outcome of running the hybrid analysis.
@@ -110,8 +193,9 @@ pub struct AnalysisDecision {
 pub struct AnalysisOutcome {
     pub decision: AnalysisDecision,
     pub analyzer_invoked: bool,
+    /// Import diagnostics for the screen, filled in by callers that parsed it.
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub diagnostics: Vec<String>,
+    pub diagnostics: Vec<ParseDiagnostic>,
     pub quick_graph: ScreenGraph,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub analyzer_graph: Option<ScreenGraph>,
@@ -125,19 +209,24 @@ pub enum AnalysisStrategy {
     AnalyzerFallback,
 }
 
-/// Stub describing the analyzer invocation. Expands once the real analyzer is
-/// wired in.
+/// Result of handing a screen to the external analyzer.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct AnalyzerInvocation {
+    /// Whether an analyzer was configured and started.
     pub executed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub graph: Option<ScreenGraph>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<ParseDiagnostic>,
+    /// Why the analyzer produced no graph, when it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{PropValue, WidgetNode};
+    use crate::{AnalyzerError, AnalyzerResponse, PropValue, WidgetNode};
     use serde_json::json;
     use std::collections::BTreeMap;
 
@@ -147,16 +236,25 @@ mod tests {
             "value".to_string(),
             PropValue::Literal {
                 value: serde_json::Value::String(value.to_string()),
+                span: None,
             },
         );
 
         ScreenGraph {
             id: id.to_string(),
             root: WidgetNode {
+                id: None,
                 widget: "Text".to_string(),
                 props,
                 children: vec![],
+                raw: None,
+                builders: Vec::new(),
+                component: None,
+                span: None,
             },
+            state: None,
+            consumer: None,
+            statements: None,
         }
     }
 
@@ -174,9 +272,32 @@ mod tests {
         assert!(outcome.merge.conflicts.is_empty());
     }
 
+    /// In-process analyzer that renames the screen, or fails when asked to.
+    #[derive(Debug)]
+    struct RenamingAnalyzer {
+        fail: bool,
+    }
+
+    impl AnalyzerBackend for RenamingAnalyzer {
+        fn analyze(&self, request: &AnalyzerRequest) -> Result<AnalyzerResponse, AnalyzerError> {
+            if self.fail {
+                return Err(AnalyzerError::Timeout {
+                    program: "renamer".to_string(),
+                    timeout: std::time::Duration::from_secs(1),
+                });
+            }
+            let mut graph = request.quick_graph.clone();
+            graph.id = format!("{}__analyzer", graph.id);
+            Ok(AnalyzerResponse {
+                graph,
+                diagnostics: Vec::new(),
+            })
+        }
+    }
+
     #[test]
     fn evaluate_invokes_analyzer_when_confidence_is_low() {
-        let service = AnalyzerService::new(0.8);
+        let service = AnalyzerService::new(0.8).with_analyzer(RenamingAnalyzer { fail: false });
         let base = make_graph("Sample", "base");
         let quick = make_graph("Sample", "quick");
         let outcome = service.run("class Demo {}", &base, quick, 0.5);
@@ -191,6 +312,33 @@ mod tests {
         assert_eq!(conflict.right, Some(json!("Sample__analyzer")));
     }
 
+    #[test]
+    fn fallback_without_analyzer_keeps_quick_graph() {
+        let service = AnalyzerService::new(0.8);
+        let base = make_graph("Sample", "quick");
+        let quick = make_graph("Sample", "quick");
+        let outcome = service.run("class Demo {}", &base, quick.clone(), 0.5);
+        assert!(!outcome.analyzer_invoked);
+        assert_eq!(outcome.analyzer_graph, Some(quick));
+        assert!(outcome.merge.conflicts.is_empty());
+        assert!(outcome.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn analyzer_failure_is_reported_and_quick_graph_kept() {
+        let service = AnalyzerService::new(0.8).with_analyzer(RenamingAnalyzer { fail: true });
+        let base = make_graph("Sample", "quick");
+        let quick = make_graph("Sample", "quick");
+        let outcome = service.run("class Demo {}", &base, quick.clone(), 0.5);
+        assert!(outcome.analyzer_invoked);
+        assert_eq!(outcome.merge.screen, quick);
+        assert_eq!(outcome.diagnostics.len(), 1);
+        let diagnostic = &outcome.diagnostics[0];
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostic.screen.as_deref(), Some("Sample"));
+        assert!(diagnostic.message.contains("did not finish within 1s"));
+    }
+
     #[test]
     fn threshold_is_clamped_to_valid_range() {
         let service = AnalyzerService::new(2.5);
@@ -1,5 +1,6 @@
+use crate::expr_codegen::CodegenTarget;
 use crate::renderer_adapter::{RenderContext, RenderResult, RenderUnit, RendererAdapter};
-use crate::{BindingReference, PropValue, WidgetNode};
+use crate::{BindingReference, PropValue, WidgetBuilder, WidgetNode, FOR_EACH_WIDGET};
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
 
@@ -28,13 +29,35 @@ impl RendererAdapter for AngularRenderer {
             .map(|(name, version)| (name.to_string(), version.to_string()))
             .collect()
     }
+
+    /// Logic lives in the component class, which is TypeScript.
+    fn codegen_target(&self) -> Option<CodegenTarget> {
+        Some(CodegenTarget::TypeScript)
+    }
 }
 
 fn render_element(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) -> String {
     let indent_str = " ".repeat(indent);
+    if let Some(raw) = &node.raw {
+        let location = node
+            .span
+            .as_ref()
+            .map(|span| format!(" at {span}"))
+            .unwrap_or_default();
+        let summary = raw
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .replace("--", "- -");
+        return format!("{indent_str}<!-- Unsupported Dart{location}: {summary} -->");
+    }
+    if node.is_structural() {
+        return render_control_flow(node, ctx, indent);
+    }
     let props = render_props(&node.props, ctx);
 
-    if node.children.is_empty() {
+    if node.children.is_empty() && node.builders.is_empty() {
         return format!("{indent_str}<{}{} />", node.widget, props);
     }
 
@@ -42,6 +65,11 @@ fn render_element(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) ->
     buffer.push_str(&format!("{indent_str}<{}{}>", node.widget, props));
     buffer.push('\n');
 
+    for builder in &node.builders {
+        buffer.push_str(&render_builder(builder, ctx, indent + 2));
+        buffer.push('\n');
+    }
+
     for child in &node.children {
         buffer.push_str(&render_element(child, ctx, indent + 2));
         buffer.push('\n');
@@ -52,6 +80,84 @@ fn render_element(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) ->
     buffer
 }
 
+/// Renders a builder callback as a named `ng-template` whose context exposes
+/// the callback parameters.
+fn render_builder(builder: &WidgetBuilder, ctx: &RenderContext<'_>, indent: usize) -> String {
+    let indent_str = " ".repeat(indent);
+    let inner_indent = " ".repeat(indent + 2);
+    let lets: String = builder
+        .parameters
+        .iter()
+        .map(|name| format!(" let-{name}=\"{name}\""))
+        .collect();
+    let mut rendered = format!("{indent_str}<ng-template #{}{lets}>\n", builder.argument);
+    if let Some(statements) = &builder.statements {
+        rendered.push_str(&format!(
+            "{inner_indent}<!-- Dart statements omitted: {} -->\n",
+            statements
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .replace("--", "- -")
+        ));
+    }
+    rendered.push_str(&render_element(&builder.template, ctx, indent + 2));
+    rendered.push('\n');
+    rendered.push_str(&indent_str);
+    rendered.push_str("</ng-template>");
+    rendered
+}
+
+/// Renders a structural node as an `@if`/`@else` or `@for` block.
+fn render_control_flow(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) -> String {
+    let indent_str = " ".repeat(indent);
+    let block = |child: Option<&WidgetNode>| {
+        let body = child
+            .map(|child| format!("{}\n", render_element(child, ctx, indent + 2)))
+            .unwrap_or_default();
+        format!("{{\n{body}{indent_str}}}")
+    };
+
+    if node.widget == FOR_EACH_WIDGET {
+        let iterable = node
+            .iterable()
+            .map(|value| render_expression(value, ctx))
+            .unwrap_or_else(|| "[]".to_string());
+        return format!(
+            "{indent_str}@for ({} of {iterable}; track $index) {}",
+            node.item_name(),
+            block(node.children.first())
+        );
+    }
+
+    let condition = node
+        .condition()
+        .map(|value| render_expression(value, ctx))
+        .unwrap_or_else(|| "false".to_string());
+    let mut rendered = format!(
+        "{indent_str}@if ({condition}) {}",
+        block(node.children.first())
+    );
+    if let Some(otherwise) = node.children.get(1) {
+        rendered.push_str(" @else ");
+        rendered.push_str(&block(Some(otherwise)));
+    }
+    rendered
+}
+
+/// Template expression for a prop used outside an attribute.
+fn render_expression(value: &PropValue, ctx: &RenderContext<'_>) -> String {
+    match value {
+        PropValue::Literal { value, .. } => match value {
+            Value::String(s) => format!("'{}'", s.replace('\'', "\\'")),
+            Value::Null => "null".to_string(),
+            other => literal_to_string(other),
+        },
+        PropValue::Expression { expression, .. } => expression.clone(),
+        PropValue::Binding { binding, .. } => render_binding(binding, ctx),
+    }
+}
+
 fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) -> String {
     if props.is_empty() {
         return String::new();
@@ -60,14 +166,14 @@ fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) ->
     let mut rendered = String::new();
     for (key, value) in props {
         match value {
-            PropValue::Literal { value } => {
+            PropValue::Literal { value, .. } => {
                 rendered.push(' ');
                 rendered.push_str(key);
                 rendered.push_str("=\"");
                 rendered.push_str(&escape_attr(&literal_to_string(value)));
                 rendered.push('"');
             }
-            PropValue::Expression { expression } => {
+            PropValue::Expression { expression, .. } => {
                 rendered.push(' ');
                 rendered.push('[');
                 rendered.push_str(key);
@@ -76,7 +182,7 @@ fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) ->
                 rendered.push_str(&escape_attr(expression));
                 rendered.push('"');
             }
-            PropValue::Binding { binding } => {
+            PropValue::Binding { binding, .. } => {
                 let expr = render_binding(binding, ctx);
                 rendered.push(' ');
                 rendered.push('[');
@@ -1,8 +1,11 @@
 use clap::{Parser, Subcommand};
 use forge_engine::{
-    build_graphs_from_source, generate_manifest, get_renderer, read_graph, renderer_names,
-    simulate_flow, AnalysisOutcome, AnalyzerService, EvalConfig, ForgeGraph, LogicError, LogicGraph,
-    RenderContext, RenderOptions, RiverpodAdapter, SchemaProject, SchemaWriter,
+    build_graphs_with_report, check_logic_graph, diff_documents, diff_screen_graphs,
+    generate_manifest, get_renderer, import_workspace_with, merge_documents_with, query_document,
+    query_screen_graph, read_graph, render_screens, renderer_names, simulate_flow, AnalysisOutcome,
+    AnalyzerService, EvalConfig, ForgeGraph, LogicError, LogicGraph, MergeConflict, MergeError,
+    MergeStrategy, Parallelism, ParseCache, RenderContext, RenderOptions, RiverpodAdapter,
+    SchemaDocument, SchemaProject, SchemaWriter, Selector, SubprocessAnalyzer, TaskProgress,
 };
 use jsonschema::JSONSchema;
 use serde::Serialize;
@@ -12,13 +15,14 @@ use std::{
     fs,
     path::{Path, PathBuf},
     process,
+    time::Duration,
 };
 
 #[derive(Debug, Parser)]
 #[command(name = "forge_engine", about = "Forge engine CLI", version)]
 struct Cli {
     /// Backwards-compatible shorthand for `parse --file`
-    #[arg(long, global = true)]
+    #[arg(long)]
     file: Option<PathBuf>,
 
     #[command(subcommand)]
@@ -45,6 +49,15 @@ fn run_export(
     let mut logic_graphs = Vec::new();
     for path in logic_files {
         let graph = read_logic_graph(path)?;
+        let type_errors = check_logic_graph(&graph);
+        if !type_errors.is_empty() {
+            let details: Vec<String> = type_errors.iter().map(ToString::to_string).collect();
+            return Err(format!(
+                "Logic graph {} failed type checking:\n  {}",
+                path.display(),
+                details.join("\n  ")
+            ));
+        }
         logic_graphs.push(graph);
     }
 
@@ -87,6 +100,161 @@ fn run_export(
     Ok(0)
 }
 
+fn run_merge(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    output: &Path,
+    report: Option<&Path>,
+    strategy: MergeStrategy,
+) -> Result<i32, String> {
+    let read = |path: &Path| -> Result<SchemaDocument, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+        SchemaDocument::from_json(&contents)
+            .map_err(|err| format!("Failed to parse schema document {}: {err}", path.display()))
+    };
+    let outcome = match merge_documents_with(&read(base)?, &read(ours)?, &read(theirs)?, strategy) {
+        Ok(outcome) => outcome,
+        Err(MergeError::Conflicts(conflicts)) => {
+            let total = write_merge_report(conflicts, report)?;
+            eprintln!(
+                "{total} merge conflict(s); {} was not written",
+                output.display()
+            );
+            return Ok(1);
+        }
+        Err(err) => return Err(format!("Merge failed: {err}")),
+    };
+
+    let payload = outcome
+        .document
+        .to_string_pretty()
+        .map_err(|err| format!("Failed to serialize merged document: {err}"))?;
+    validate_schema(&payload).map_err(|err| format!("Schema validation failed: {err}"))?;
+    fs::write(output, payload)
+        .map_err(|err| format!("Failed to write {}: {err}", output.display()))?;
+
+    if outcome.conflicts.is_empty() {
+        return Ok(0);
+    }
+
+    let total = write_merge_report(outcome.conflicts, report)?;
+    let kept = match strategy {
+        MergeStrategy::PreferLeft => "the --ours value",
+        MergeStrategy::KeepBoth => "the children of both sides and otherwise the --theirs value",
+        MergeStrategy::PreferRight | MergeStrategy::Fail => "the --theirs value",
+    };
+    eprintln!(
+        "{total} merge conflict(s) remain; {} kept {kept}",
+        output.display()
+    );
+    Ok(1)
+}
+
+/// Writes the JSON conflict report to `report`, or stdout without one, and
+/// returns the number of conflicts.
+fn write_merge_report(
+    conflicts: Vec<MergeConflict>,
+    report: Option<&Path>,
+) -> Result<usize, String> {
+    let summary = MergeReport {
+        total_conflicts: conflicts.len(),
+        conflicts,
+    };
+    let json = serde_json::to_string_pretty(&summary)
+        .map_err(|err| format!("Failed to serialize merge report: {err}"))?;
+    match report {
+        Some(path) => fs::write(path, json)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?,
+        None => println!("{json}"),
+    }
+    Ok(summary.total_conflicts)
+}
+
+/// Reads a JSON file; schema documents are told from screen graphs by their
+/// `forge_schema_version` key.
+fn read_json(path: &Path) -> Result<Value, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Failed to parse {}: {err}", path.display()))
+}
+
+fn is_document(value: &Value) -> bool {
+    value.get("forge_schema_version").is_some()
+}
+
+fn run_diff(before: &Path, after: &Path, json: bool, output: Option<&Path>) -> Result<i32, String> {
+    let (before_value, after_value) = (read_json(before)?, read_json(after)?);
+
+    let diff = match (is_document(&before_value), is_document(&after_value)) {
+        (true, true) => {
+            let parse = |value: Value, path: &Path| {
+                SchemaDocument::from_value(value).map_err(|err| {
+                    format!("Failed to parse schema document {}: {err}", path.display())
+                })
+            };
+            diff_documents(&parse(before_value, before)?, &parse(after_value, after)?)
+        }
+        (false, false) => {
+            let parse = |value: Value, path: &Path| {
+                serde_json::from_value(value).map_err(|err| {
+                    format!("Failed to parse screen graph {}: {err}", path.display())
+                })
+            };
+            diff_screen_graphs(&parse(before_value, before)?, &parse(after_value, after)?)
+        }
+        _ => {
+            return Err(
+                "Cannot diff a schema document against a screen graph; pass two of a kind".into(),
+            )
+        }
+    };
+
+    let payload = serde_json::to_string_pretty(&diff)
+        .map_err(|err| format!("Failed to serialize diff: {err}"))?;
+    if let Some(path) = output {
+        fs::write(path, &payload)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+    }
+    if json {
+        println!("{payload}");
+    } else {
+        println!("{}", diff.summary());
+    }
+    Ok(if diff.is_empty() { 0 } else { 3 })
+}
+
+fn run_query(selector: &str, input: &Path, json: bool) -> Result<i32, String> {
+    let selector = Selector::parse(selector).map_err(|err| err.to_string())?;
+    let value = read_json(input)?;
+    let matches = if is_document(&value) {
+        let document = SchemaDocument::from_value(value)
+            .map_err(|err| format!("Failed to parse schema document {}: {err}", input.display()))?;
+        query_document(&document, &selector)
+    } else {
+        let graph = serde_json::from_value(value)
+            .map_err(|err| format!("Failed to parse screen graph {}: {err}", input.display()))?;
+        query_screen_graph(&graph, &selector)
+    };
+
+    if json {
+        let payload = serde_json::to_string_pretty(&matches)
+            .map_err(|err| format!("Failed to serialize query matches: {err}"))?;
+        println!("{payload}");
+    } else {
+        for found in &matches {
+            match &found.node_id {
+                Some(id) => println!("{}\t{}\t#{id}", found.path, found.widget),
+                None => println!("{}\t{}", found.path, found.widget),
+            }
+        }
+        eprintln!("{} match(es) for {selector}", matches.len());
+    }
+    Ok(0)
+}
+
 fn validate_schema(payload: &str) -> Result<(), String> {
     static VALIDATOR: once_cell::sync::OnceCell<JSONSchema> = once_cell::sync::OnceCell::new();
     let validator = VALIDATOR.get_or_try_init(|| {
@@ -211,8 +379,31 @@ enum Commands {
         file: PathBuf,
         #[arg(long)]
         output: Option<PathBuf>,
-        #[arg(long, default_value_t = 0.5)]
-        confidence: f32,
+        /// External analyzer executable used when the quick parse is not confident
+        #[arg(long, value_name = "PROGRAM")]
+        analyzer: Option<PathBuf>,
+        /// Argument passed to the analyzer executable, may be repeated
+        #[arg(long = "analyzer-arg", value_name = "ARG", allow_hyphen_values = true)]
+        analyzer_args: Vec<String>,
+        /// Seconds the analyzer may run before it is killed
+        #[arg(long, value_name = "SECONDS")]
+        analyzer_timeout: Option<u64>,
+    },
+    /// Imports every Dart file of a project and resolves custom widgets across files
+    Workspace {
+        #[arg(long)]
+        root: PathBuf,
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Directory of the parse cache; unchanged files are not parsed again
+        #[arg(long, value_name = "DIR")]
+        cache: Option<PathBuf>,
+        /// Worker threads used to parse files; defaults to one per CPU
+        #[arg(long, short = 'j', default_value_t = 0)]
+        jobs: usize,
+        /// Print a line to stderr as each file finishes
+        #[arg(long)]
+        progress: bool,
     },
     /// Simulates a logic flow and returns its evaluation trace
     Simulate {
@@ -233,14 +424,18 @@ enum Commands {
     },
     /// Renders a Forge UI graph into target framework code
     Render {
-        #[arg(long, short = 'f')]
-        file: PathBuf,
+        /// Screen graph to render, may be repeated
+        #[arg(long = "file", short = 'f', required = true)]
+        files: Vec<PathBuf>,
         #[arg(long, short = 't', value_name = "FRAMEWORK")]
         framework: String,
         #[arg(long, short = 'o')]
         out_dir: Option<PathBuf>,
         #[arg(long)]
         emit_manifest: bool,
+        /// Worker threads used to render screens; defaults to one per CPU
+        #[arg(long, short = 'j', default_value_t = 0)]
+        jobs: usize,
     },
     /// Exports a Forge graph file into canonical schema JSON
     Export {
@@ -255,6 +450,63 @@ enum Commands {
         #[arg(long, value_name = "PROJECT_NAME")]
         project_name: Option<String>,
     },
+    /// Compares two screen graphs or two schema documents; exits with 3 when
+    /// they differ
+    Diff {
+        #[arg(long)]
+        before: PathBuf,
+        #[arg(long)]
+        after: PathBuf,
+        /// Print the JSON change list instead of the summary
+        #[arg(long)]
+        json: bool,
+        /// Also write the JSON change list to this file
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    /// Finds nodes matching a CSS-like selector such as
+    /// `Scaffold > Column Text[data^="Hello"]` in a screen graph or schema
+    /// document
+    Query {
+        selector: String,
+        #[arg(long, short = 'f')]
+        input: PathBuf,
+        /// Print matches as JSON instead of one line per node
+        #[arg(long)]
+        json: bool,
+    },
+    /// Three-way merges schema documents; exits with 1 when conflicts remain,
+    /// so it can serve as a git merge driver:
+    /// `forge_engine merge --base %O --ours %A --theirs %B -o %A`
+    Merge {
+        #[arg(long)]
+        base: PathBuf,
+        #[arg(long)]
+        ours: PathBuf,
+        #[arg(long)]
+        theirs: PathBuf,
+        /// Merged document, written even when conflicts remain; may be the `--ours` file
+        #[arg(long, short = 'o')]
+        output: PathBuf,
+        /// File for the JSON conflict report; printed to stdout when omitted
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// How conflicts are settled: prefer-left (`--ours`), prefer-right
+        /// (`--theirs`), keep-both, or fail without writing the output
+        #[arg(long, value_parser = parse_strategy, default_value = "prefer-right")]
+        strategy: MergeStrategy,
+    },
+}
+
+fn parse_strategy(value: &str) -> Result<MergeStrategy, String> {
+    serde_json::from_value(Value::String(value.to_string()))
+        .map_err(|_| "expected prefer-left, prefer-right, keep-both or fail".to_string())
+}
+
+#[derive(Debug, Serialize)]
+struct MergeReport {
+    total_conflicts: usize,
+    conflicts: Vec<MergeConflict>,
 }
 
 #[derive(Debug, Serialize)]
@@ -283,8 +535,39 @@ fn run_with_args(args: &[String]) -> Result<i32, String> {
         Some(Commands::Analyze {
             file,
             output,
-            confidence,
-        }) => run_analyze_file(&file, output, confidence),
+            analyzer,
+            analyzer_args,
+            analyzer_timeout,
+        }) => {
+            let analyzer = analyzer.map(|program| {
+                let analyzer = SubprocessAnalyzer::new(program).with_args(analyzer_args);
+                match analyzer_timeout {
+                    Some(seconds) => analyzer.with_timeout(Duration::from_secs(seconds)),
+                    None => analyzer,
+                }
+            });
+            run_analyze_file(&file, output, analyzer)
+        }
+        Some(Commands::Workspace {
+            root,
+            output,
+            cache,
+            jobs,
+            progress,
+        }) => {
+            let report_progress = |update: TaskProgress<'_>| {
+                let status = if update.failed { "failed" } else { "parsed" };
+                eprintln!(
+                    "[{}/{}] {status} {}",
+                    update.completed, update.total, update.item
+                );
+            };
+            let mut parallelism = Parallelism::new().with_jobs(jobs);
+            if progress {
+                parallelism = parallelism.with_progress(&report_progress);
+            }
+            run_workspace(&root, output, cache.as_deref(), parallelism)
+        }
         Some(Commands::Simulate {
             flow,
             graph,
@@ -303,11 +586,12 @@ fn run_with_args(args: &[String]) -> Result<i32, String> {
             max_trace,
         ),
         Some(Commands::Render {
-            file,
+            files,
             framework,
             out_dir,
             emit_manifest,
-        }) => run_render(&file, &framework, out_dir.as_deref(), emit_manifest),
+            jobs,
+        }) => run_render(&files, &framework, out_dir.as_deref(), emit_manifest, jobs),
         Some(Commands::Export {
             screens,
             logic,
@@ -321,6 +605,25 @@ fn run_with_args(args: &[String]) -> Result<i32, String> {
             project_id.as_deref(),
             project_name.as_deref(),
         ),
+        Some(Commands::Diff {
+            before,
+            after,
+            json,
+            output,
+        }) => run_diff(&before, &after, json, output.as_deref()),
+        Some(Commands::Query {
+            selector,
+            input,
+            json,
+        }) => run_query(&selector, &input, json),
+        Some(Commands::Merge {
+            base,
+            ours,
+            theirs,
+            output,
+            report,
+            strategy,
+        }) => run_merge(&base, &ours, &theirs, &output, report.as_deref(), strategy),
         None => {
             let file = cli
                 .file
@@ -361,12 +664,77 @@ fn execute_parse(file: &Path) -> Result<String, String> {
     let contents =
         fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {e}", file.display()))?;
 
-    let graphs = build_graphs_from_source(&contents);
+    let (graphs, report) = build_graphs_with_report(&contents, Some(&file.display().to_string()));
+    for diagnostic in &report.diagnostics {
+        eprintln!("{diagnostic}");
+    }
     serde_json::to_string_pretty(&graphs).map_err(|e| format!("Failed to serialize graphs: {e}"))
 }
 
-fn run_analyze_file(file: &Path, output: Option<PathBuf>, confidence: f32) -> Result<i32, String> {
-    let report = execute_analyze(file, confidence)?;
+fn run_workspace(
+    root: &Path,
+    output: Option<PathBuf>,
+    cache: Option<&Path>,
+    parallelism: Parallelism<'_>,
+) -> Result<i32, String> {
+    let (json, failures) = execute_workspace(root, cache, parallelism)?;
+    if let Some(path) = output {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create output directory {}: {e}",
+                    parent.display()
+                )
+            })?;
+        }
+        fs::write(&path, &json).map_err(|e| {
+            format!(
+                "Failed to write workspace output to {}: {e}",
+                path.display()
+            )
+        })?;
+    } else {
+        println!("{json}");
+    }
+    Ok(if failures > 0 { 1 } else { 0 })
+}
+
+/// Imports the workspace and returns its JSON with the number of files that
+/// could not be imported.
+fn execute_workspace(
+    root: &Path,
+    cache: Option<&Path>,
+    parallelism: Parallelism<'_>,
+) -> Result<(String, usize), String> {
+    let cache = cache
+        .map(ParseCache::open)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let workspace =
+        import_workspace_with(root, cache.as_ref(), parallelism).map_err(|e| e.to_string())?;
+    for failure in &workspace.failures {
+        eprintln!("Failed to import {}: {}", failure.file, failure.error);
+    }
+    for diagnostic in &workspace.report.diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    if let Some(stats) = workspace.cache {
+        eprintln!(
+            "parse cache: {} hit(s), {} miss(es)",
+            stats.hits, stats.misses
+        );
+    }
+    let json = serde_json::to_string_pretty(&workspace)
+        .map_err(|e| format!("Failed to serialize workspace: {e}"))?;
+    Ok((json, workspace.failures.len()))
+}
+
+fn run_analyze_file(
+    file: &Path,
+    output: Option<PathBuf>,
+    analyzer: Option<SubprocessAnalyzer>,
+) -> Result<i32, String> {
+    let report = execute_analyze(file, analyzer)?;
     let json = serde_json::to_string_pretty(&report)
         .map_err(|e| format!("Failed to serialize analysis report: {e}"))?;
 
@@ -393,7 +761,10 @@ fn run_analyze_file(file: &Path, output: Option<PathBuf>, confidence: f32) -> Re
     }
 }
 
-fn execute_analyze(file: &Path, confidence: f32) -> Result<AnalysisReport, String> {
+fn execute_analyze(
+    file: &Path,
+    analyzer: Option<SubprocessAnalyzer>,
+) -> Result<AnalysisReport, String> {
     if !file.exists() {
         return Err(format!("Input file {} does not exist", file.display()));
     }
@@ -401,17 +772,20 @@ fn execute_analyze(file: &Path, confidence: f32) -> Result<AnalysisReport, Strin
     let contents =
         fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {e}", file.display()))?;
 
-    let graphs = build_graphs_from_source(&contents);
+    let (graphs, report) = build_graphs_with_report(&contents, Some(&file.display().to_string()));
     if graphs.is_empty() {
         return Err("No graphs were generated from the input source.".to_string());
     }
 
-    let service = AnalyzerService::default();
+    let mut service = AnalyzerService::default();
+    if let Some(analyzer) = analyzer {
+        service = service.with_analyzer(analyzer);
+    }
     let mut outcomes = Vec::new();
 
     for graph in graphs {
         let base_graph = graph.clone();
-        let outcome = service.run(&contents, &base_graph, graph, confidence);
+        let outcome = service.run_with_report(&contents, &base_graph, graph, &report);
         outcomes.push(outcome);
     }
 
@@ -427,12 +801,15 @@ fn execute_analyze(file: &Path, confidence: f32) -> Result<AnalysisReport, Strin
 }
 
 fn run_render(
-    file: &Path,
+    files: &[PathBuf],
     framework: &str,
     out_dir: Option<&Path>,
     emit_manifest: bool,
+    jobs: usize,
 ) -> Result<i32, String> {
-    let graph = read_graph(file).map_err(|err| err.to_string())?;
+    if files.is_empty() {
+        return Err("At least one --file argument is required".into());
+    }
 
     let descriptor = get_renderer(framework).ok_or_else(|| {
         format!(
@@ -441,6 +818,20 @@ fn run_render(
         )
     })?;
 
+    // A file that cannot be read or rendered is reported and skipped so the
+    // remaining screens are still written.
+    let mut failures = 0;
+    let mut screens = Vec::with_capacity(files.len());
+    for file in files {
+        match read_graph(file) {
+            Ok(graph) => screens.push(graph),
+            Err(err) => {
+                eprintln!("{err}");
+                failures += 1;
+            }
+        }
+    }
+
     let renderer = descriptor.instantiate();
     let state_adapter = RiverpodAdapter::new();
     let options = RenderOptions {
@@ -449,26 +840,57 @@ fn run_render(
         dialect: descriptor.dialect,
     };
     let ctx = RenderContext::new(0, &state_adapter, &options);
-
-    let unit = renderer
-        .render_tree(&graph.root, &ctx)
-        .map_err(|err| err.message.clone())?;
-
-    let output_code = unit.code;
-    let dependencies = unit.dependencies;
+    let renders = render_screens(
+        renderer.as_ref(),
+        &screens,
+        &ctx,
+        Parallelism::new().with_jobs(jobs),
+    );
 
     if let Some(dir) = out_dir {
         fs::create_dir_all(dir)
             .map_err(|err| format!("Failed to create output directory {}: {err}", dir.display()))?;
-        let output_path = dir.join(format!("main.{}", descriptor.file_extension));
-        fs::write(&output_path, &output_code)
-            .map_err(|err| format!("Failed to write {}: {err}", output_path.display()))?;
-        println!(
-            "Rendered {} source to {}",
-            descriptor.name,
-            output_path.display()
-        );
+    }
+
+    let single = files.len() == 1;
+    let mut dependencies = HashMap::new();
+    for render in renders {
+        let unit = match render.result {
+            Ok(unit) => unit,
+            Err(err) => {
+                eprintln!("Failed to render {}: {err}", render.screen);
+                failures += 1;
+                continue;
+            }
+        };
+        dependencies.extend(unit.dependencies);
 
+        if let Some(dir) = out_dir {
+            // A single screen keeps the historical `main` file name.
+            let stem = if single {
+                "main"
+            } else {
+                render.screen.as_str()
+            };
+            let output_path = dir.join(format!("{stem}.{}", descriptor.file_extension));
+            fs::write(&output_path, &unit.code)
+                .map_err(|err| format!("Failed to write {}: {err}", output_path.display()))?;
+            println!(
+                "Rendered {} source to {}",
+                descriptor.name,
+                output_path.display()
+            );
+        } else if single {
+            println!("{}", unit.code);
+        } else {
+            println!(
+                "--- {} ({}) ---\n{}",
+                render.screen, descriptor.name, unit.code
+            );
+        }
+    }
+
+    if let Some(dir) = out_dir {
         if !dependencies.is_empty() {
             let deps_path = dir.join("dependencies.json");
             let deps_json = serde_json::to_string_pretty(&dependencies)
@@ -502,7 +924,6 @@ fn run_render(
             }
         }
     } else {
-        println!("{}", output_code);
         if !dependencies.is_empty() {
             eprintln!(
                 "\nDependencies required for {}:\n{}\nInstall these packages before running your app.",
@@ -528,7 +949,12 @@ fn run_render(
         }
     }
 
-    Ok(0)
+    if failures > 0 {
+        eprintln!("{failures} screen(s) failed to render");
+        Ok(1)
+    } else {
+        Ok(0)
+    }
 }
 
 fn format_supported_frameworks() -> String {
@@ -620,7 +1046,7 @@ class SimpleScreen extends StatelessWidget {
     }
 
     #[test]
-    fn analyze_reports_conflicts() {
+    fn analyze_scores_quick_parse_and_reports_analyzer_failures() {
         let temp_dir = tempfile::tempdir().unwrap();
         let file_path = temp_dir.path().join("conflict.dart");
         std::fs::write(
@@ -632,16 +1058,32 @@ class SampleScreen extends StatelessWidget {
 
   @override
   Widget build(BuildContext context) {
-    return Text('Hello');
+    return layouts.first;
   }
 }
 "#,
         )
         .unwrap();
 
-        let report = execute_analyze(&file_path, 0.1).unwrap();
+        let report = execute_analyze(&file_path, None).unwrap();
         assert_eq!(report.outcomes.len(), 1);
-        assert_eq!(report.total_conflicts, 1);
+        assert_eq!(report.total_conflicts, 0);
+
+        let outcome = &report.outcomes[0];
+        let breakdown = outcome.decision.breakdown.as_ref().expect("breakdown");
+        assert_eq!(breakdown.raw_nodes, 1);
+        assert_eq!(outcome.decision.native_confidence, 0.5);
+        assert!(!outcome.analyzer_invoked);
+        assert_eq!(outcome.diagnostics.len(), 1);
+
+        let missing = SubprocessAnalyzer::new(temp_dir.path().join("missing-analyzer"));
+        let report = execute_analyze(&file_path, Some(missing)).unwrap();
+        let outcome = &report.outcomes[0];
+        assert!(outcome.analyzer_invoked);
+        assert_eq!(outcome.diagnostics.len(), 2);
+        assert!(outcome.diagnostics[1]
+            .message
+            .starts_with("failed to start analyzer"));
     }
 
     #[test]
@@ -668,4 +1110,213 @@ class SampleScreen extends StatelessWidget {
         assert_eq!(value["screens"].as_array().unwrap().len(), 1);
         assert_eq!(value["forge_schema_version"], "1.0.0");
     }
+
+    #[test]
+    fn workspace_writes_linked_graphs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let lib = temp_dir.path().join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(
+            lib.join("home.dart"),
+            "class HomeScreen extends StatelessWidget {\n  Widget build(BuildContext context) => Badge(3);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            lib.join("badge.dart"),
+            "class Badge extends StatelessWidget {\n  const Badge(this.count);\n  final int count;\n  Widget build(BuildContext context) => Text('$count');\n}\n",
+        )
+        .unwrap();
+        let out_path = temp_dir.path().join("out/workspace.json");
+
+        let args = vec![
+            "cli".to_string(),
+            "workspace".to_string(),
+            "--root".to_string(),
+            temp_dir.path().to_string_lossy().into_owned(),
+            "--output".to_string(),
+            out_path.to_string_lossy().into_owned(),
+        ];
+        assert_eq!(run_with_args(&args).expect("workspace command"), 0);
+
+        let payload = std::fs::read_to_string(&out_path).expect("read workspace output");
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(
+            value["files"],
+            serde_json::json!(["lib/badge.dart", "lib/home.dart"])
+        );
+        let home = &value["graphs"][1]["root"];
+        assert_eq!(home["component"], "lib/badge.dart");
+        assert!(home["props"]["count"].is_object());
+        assert_eq!(value["dependencies"]["edges"][0]["to"], "Badge");
+        assert!(value.get("cache").is_none());
+
+        let cache_dir = temp_dir.path().join("cache");
+        let mut cached_args = args.clone();
+        cached_args.extend([
+            "--cache".to_string(),
+            cache_dir.to_string_lossy().into_owned(),
+        ]);
+        run_with_args(&cached_args).expect("cold cached run");
+        run_with_args(&cached_args).expect("warm cached run");
+        let payload = std::fs::read_to_string(&out_path).expect("read workspace output");
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(
+            value["cache"],
+            serde_json::json!({ "hits": 2, "misses": 0 })
+        );
+    }
+
+    #[test]
+    fn render_writes_each_screen_and_skips_failures() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let out_dir = temp_dir.path().join("out");
+        let args: Vec<String> = [
+            "cli",
+            "render",
+            "--file",
+            "fixtures/ui/react_basic.json",
+            "--file",
+            "fixtures/ui/missing.json",
+            "--file",
+            "fixtures/ui/conditional_list.json",
+            "--framework",
+            "react",
+            "--jobs",
+            "2",
+            "--out-dir",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain([out_dir.to_string_lossy().into_owned()])
+        .collect();
+
+        assert_eq!(run_with_args(&args).expect("render command"), 1);
+        let mut written: Vec<String> = std::fs::read_dir(&out_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        written.sort();
+        let expected: Vec<String> = ["react_basic", "conditional_list"]
+            .iter()
+            .map(|stem| {
+                let graph = read_graph(format!("fixtures/ui/{stem}.json")).unwrap();
+                format!("{}.jsx", graph.id)
+            })
+            .collect();
+        for file in &expected {
+            assert!(written.contains(file), "{file} missing from {written:?}");
+        }
+    }
+
+    #[test]
+    fn merge_writes_document_and_reports_conflicts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let screen = read_graph("fixtures/ui/angular_basic.json").unwrap();
+        let write = |name: &str, project_name: &str| {
+            let path = temp_dir.path().join(name);
+            let document = SchemaWriter::from_screens(
+                SchemaProject::new("app", project_name),
+                vec![screen.clone()],
+            );
+            std::fs::write(&path, document.to_string_pretty().unwrap()).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let base = write("base.json", "App");
+        let ours = write("ours.json", "Ours");
+        let out = temp_dir.path().join("merged.json");
+        let report = temp_dir.path().join("conflicts.json");
+        let args = |theirs: String| -> Vec<String> {
+            [
+                "cli", "merge", "--base", &base, "--ours", &ours, "--theirs", &theirs, "-o",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain([
+                out.to_string_lossy().into_owned(),
+                "--report".to_string(),
+                report.to_string_lossy().into_owned(),
+            ])
+            .collect()
+        };
+
+        assert_eq!(run_with_args(&args(base.clone())).expect("clean merge"), 0);
+        let merged = SchemaDocument::from_json(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(merged.project.name, "Ours");
+        assert!(!report.exists());
+
+        let theirs = write("theirs.json", "Theirs");
+        assert_eq!(run_with_args(&args(theirs)).expect("conflicted merge"), 1);
+        let merged = SchemaDocument::from_json(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(merged.project.name, "Theirs");
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(value["total_conflicts"], 1);
+        assert_eq!(value["conflicts"][0]["path"], "project.name");
+
+        let with_strategy = |strategy: &str| -> Vec<String> {
+            let mut args = args(temp_dir.path().join("theirs.json").to_string_lossy().into());
+            args.extend(["--strategy".to_string(), strategy.to_string()]);
+            args
+        };
+        assert_eq!(run_with_args(&with_strategy("prefer-left")).unwrap(), 1);
+        let merged = SchemaDocument::from_json(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(merged.project.name, "Ours");
+
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(run_with_args(&with_strategy("fail")).unwrap(), 1);
+        assert!(!out.exists());
+        assert!(run_with_args(&with_strategy("ours")).is_err());
+    }
+
+    #[test]
+    fn diff_reports_changes_with_exit_code_three() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let before = read_graph("fixtures/ui/angular_basic.json").unwrap();
+        let mut after = before.clone();
+        after.root.widget = format!("{}Changed", after.root.widget);
+        let write = |name: &str, graph: &forge_engine::ScreenGraph| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, serde_json::to_string(graph).unwrap()).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let (before_path, after_path) =
+            (write("before.json", &before), write("after.json", &after));
+        let report = temp_dir.path().join("diff.json");
+        let args = |after_path: &str| -> Vec<String> {
+            let mut args: Vec<String> = ["cli", "diff", "--before", &before_path, "--after"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect();
+            args.extend([
+                after_path.to_string(),
+                "--output".to_string(),
+                report.to_string_lossy().into_owned(),
+            ]);
+            args
+        };
+
+        assert_eq!(run_with_args(&args(&before_path)).expect("diff"), 0);
+        assert_eq!(run_with_args(&args(&after_path)).expect("diff"), 3);
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(value["ops"][0]["op"], "widget_type_changed");
+        assert_eq!(value["ops"][0]["path"], "screen.root");
+    }
+
+    #[test]
+    fn query_rejects_invalid_selectors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let graph = read_graph("fixtures/ui/angular_basic.json").unwrap();
+        let path = temp_dir.path().join("graph.json");
+        std::fs::write(&path, serde_json::to_string(&graph).unwrap()).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let selector = format!("{} > *", graph.root.widget);
+        let args = ["cli", "query", &selector, "--input", &path, "--json"].map(String::from);
+        assert_eq!(run_with_args(&args).expect("query"), 0);
+
+        let args = ["cli", "query", "Text[data", "--input", &path].map(String::from);
+        let err = run_with_args(&args).unwrap_err();
+        assert!(err.contains("invalid selector at column 10"), "{err}");
+    }
 }
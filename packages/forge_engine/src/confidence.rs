@@ -0,0 +1,188 @@
+use crate::{get_widget, ParseReport, PropValue, ScreenGraph, WidgetNode, RAW_DART_WIDGET};
+use serde::Serialize;
+
+/// Weight of the share of the tree kept as raw Dart.
+const RAW_NODE_WEIGHT: f32 = 0.5;
+/// Weight of the share of modeled widgets that are neither Flutter framework
+/// widgets nor registered.
+const UNKNOWN_WIDGET_WEIGHT: f32 = 0.2;
+/// Weight of the share of props that fell back to a `positionalN` name.
+const POSITIONAL_PROP_WEIGHT: f32 = 0.1;
+/// Weight of the share of bindings without a resolvable provider.
+const UNRESOLVED_BINDING_WEIGHT: f32 = 0.1;
+/// Penalty per widget class in the same file that could not be imported,
+/// capped at [MAX_SKIPPED_CLASS_PENALTY].
+const SKIPPED_CLASS_PENALTY: f32 = 0.05;
+const MAX_SKIPPED_CLASS_PENALTY: f32 = 0.1;
+
+/// How much the quick parser trusts its own result for a screen, with the
+/// counts the score was derived from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfidenceBreakdown {
+    /// Final score between 0.0 and 1.0.
+    pub score: f32,
+    pub nodes: usize,
+    /// Nodes kept as [RAW_DART_WIDGET] because they could not be modeled.
+    pub raw_nodes: usize,
+    /// Modeled widgets that are neither Flutter framework widgets, registered
+    /// nor project components resolved by [crate::import_workspace].
+    pub unknown_widgets: usize,
+    pub props: usize,
+    /// Props named `positionalN` because the widget's positional arguments are unknown.
+    pub positional_props: usize,
+    pub bindings: usize,
+    /// Bindings whose reference does not name a provider.
+    pub unresolved_bindings: usize,
+    /// Widget classes in the same file that could not be imported.
+    pub skipped_classes: usize,
+}
+
+impl ConfidenceBreakdown {
+    /// Scores `graph` from its shape and the report produced while parsing it.
+    pub fn from_parse(graph: &ScreenGraph, report: &ParseReport) -> Self {
+        let mut breakdown = Self {
+            score: 1.0,
+            nodes: 0,
+            raw_nodes: 0,
+            unknown_widgets: 0,
+            props: 0,
+            positional_props: 0,
+            bindings: 0,
+            unresolved_bindings: 0,
+            skipped_classes: report.skipped_classes.len(),
+        };
+        breakdown.visit(&graph.root);
+
+        let modeled = breakdown.nodes - breakdown.raw_nodes;
+        let skipped_penalty = (breakdown.skipped_classes as f32 * SKIPPED_CLASS_PENALTY)
+            .min(MAX_SKIPPED_CLASS_PENALTY);
+        let score = 1.0
+            - RAW_NODE_WEIGHT * ratio(breakdown.raw_nodes, breakdown.nodes)
+            - UNKNOWN_WIDGET_WEIGHT * ratio(breakdown.unknown_widgets, modeled)
+            - POSITIONAL_PROP_WEIGHT * ratio(breakdown.positional_props, breakdown.props)
+            - UNRESOLVED_BINDING_WEIGHT * ratio(breakdown.unresolved_bindings, breakdown.bindings)
+            - skipped_penalty;
+        breakdown.score = score.clamp(0.0, 1.0);
+        breakdown
+    }
+
+    fn visit(&mut self, node: &WidgetNode) {
+        self.nodes += 1;
+        if node.raw.is_some() {
+            self.raw_nodes += 1;
+        } else if !node.is_structural()
+            && node.component.is_none()
+            && !is_known_widget(&node.widget)
+        {
+            self.unknown_widgets += 1;
+        }
+
+        for (name, value) in &node.props {
+            self.props += 1;
+            if is_positional_fallback(name) {
+                self.positional_props += 1;
+            }
+            if let PropValue::Binding { binding, .. } = value {
+                self.bindings += 1;
+                if binding.provider_id.is_none() {
+                    self.unresolved_bindings += 1;
+                }
+            }
+        }
+
+        for child in &node.children {
+            self.visit(child);
+        }
+        for builder in &node.builders {
+            self.visit(&builder.template);
+        }
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f32 / whole as f32
+    }
+}
+
+/// Named constructors such as `Image.network` count as their class.
+fn is_known_widget(widget: &str) -> bool {
+    let class = widget.split('.').next().unwrap_or(widget);
+    widget != RAW_DART_WIDGET && (get_widget(widget).is_some() || get_widget(class).is_some())
+}
+
+fn is_positional_fallback(name: &str) -> bool {
+    name.strip_prefix("positional")
+        .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_graphs_with_report;
+
+    fn breakdown(source: &str) -> ConfidenceBreakdown {
+        let (graphs, report) = build_graphs_with_report(source, None);
+        ConfidenceBreakdown::from_parse(&graphs[0], &report)
+    }
+
+    #[test]
+    fn fully_modeled_screen_scores_one() {
+        let result = breakdown(
+            r#"class Home extends StatelessWidget {
+  Widget build(BuildContext context) {
+    return Column(children: [Text('Hi'), Icon(Icons.add)]);
+  }
+}"#,
+        );
+        assert_eq!(result.nodes, 3);
+        assert_eq!(result.score, 1.0);
+    }
+
+    #[test]
+    fn fallbacks_lower_the_score() {
+        let result = breakdown(
+            r#"class Home extends StatelessWidget {
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        ...extraItems,
+        ProfileCard(user, text: ref.watch(userProvider).name),
+        Text(ref.watch(providers[0])),
+      ],
+    );
+  }
+}
+
+class Orphan extends StatefulWidget {
+  State<Orphan> createState() => _OrphanState();
+}"#,
+        );
+        assert_eq!(result.nodes, 4);
+        assert_eq!(result.raw_nodes, 1);
+        assert_eq!(result.unknown_widgets, 1);
+        assert_eq!(result.props, 3);
+        assert_eq!(result.positional_props, 1);
+        assert_eq!(result.bindings, 2);
+        assert_eq!(result.unresolved_bindings, 1);
+        assert_eq!(result.skipped_classes, 1);
+
+        let expected = 1.0 - 0.5 / 4.0 - 0.2 / 3.0 - 0.1 / 3.0 - 0.1 / 2.0 - 0.05;
+        assert!((result.score - expected).abs() < 1e-6, "{}", result.score);
+    }
+
+    #[test]
+    fn known_widgets_come_from_the_widget_registry() {
+        let source = "class Home extends StatelessWidget {\n  Widget build(BuildContext context) {\n    return Scaffold(body: ConfidenceProbe());\n  }\n}\n";
+        assert_eq!(
+            crate::get_widget("Scaffold").and_then(|widget| widget.category),
+            Some("flutter".to_string())
+        );
+        assert_eq!(breakdown(source).unknown_widgets, 1);
+
+        crate::register_widget(crate::WidgetDescriptor::new("ConfidenceProbe")).unwrap();
+        assert_eq!(breakdown(source).unknown_widgets, 0);
+    }
+}
@@ -0,0 +1,1541 @@
+//! Tokenizer and recursive-descent parser for the subset of Dart found in
+//! Flutter UI code: constructor calls, named arguments, collection literals,
+//! function literals and ordinary operator expressions. Statement blocks are
+//! not parsed; they are kept as spans into the source.
+
+use thiserror::Error;
+
+/// Byte range into the parsed source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the slice of `source` covered by this span.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifiers and keywords alike; keywords are recognized by the parser.
+    Identifier,
+    Number,
+    String {
+        raw: bool,
+        triple: bool,
+        interpolated: bool,
+    },
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DartParseError {
+    #[error("unterminated string literal at offset {0}")]
+    UnterminatedString(usize),
+    #[error("unterminated block comment at offset {0}")]
+    UnterminatedComment(usize),
+    #[error("unexpected character '{ch}' at offset {offset}")]
+    UnexpectedCharacter { ch: char, offset: usize },
+    #[error("unbalanced '{delimiter}' at offset {offset}")]
+    UnbalancedDelimiter { delimiter: char, offset: usize },
+    #[error("unexpected '{found}' at offset {offset}")]
+    UnexpectedToken { found: String, offset: usize },
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+}
+
+pub type DartParseResult<T> = Result<T, DartParseError>;
+
+/// A parsed Dart expression and the source range it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DartExpr {
+    pub kind: DartExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DartExprKind {
+    Null,
+    Bool(bool),
+    /// Numeric literal, kept as written (`16`, `1.5`, `0xFF2196F3`).
+    Number(String),
+    String(DartString),
+    /// Adjacent string literals that Dart concatenates, e.g. `'a' 'b'`.
+    AdjacentStrings(Vec<DartString>),
+    Identifier(String),
+    PropertyAccess {
+        target: Box<DartExpr>,
+        name: String,
+        null_aware: bool,
+    },
+    Index {
+        target: Box<DartExpr>,
+        index: Box<DartExpr>,
+    },
+    /// Function, method or constructor invocation.
+    Call {
+        callee: Box<DartExpr>,
+        type_arguments: Vec<String>,
+        arguments: Vec<Argument>,
+        is_const: bool,
+    },
+    List {
+        type_argument: Option<String>,
+        elements: Vec<CollectionElement>,
+        is_const: bool,
+    },
+    /// `{...}` literal; Dart decides between a set and a map from its elements.
+    SetOrMap {
+        type_arguments: Vec<String>,
+        elements: Vec<CollectionElement>,
+        is_const: bool,
+    },
+    Function {
+        parameters: Vec<String>,
+        body: FunctionBody,
+        is_async: bool,
+    },
+    Unary {
+        operator: &'static str,
+        operand: Box<DartExpr>,
+    },
+    Postfix {
+        operator: &'static str,
+        operand: Box<DartExpr>,
+    },
+    Binary {
+        operator: &'static str,
+        left: Box<DartExpr>,
+        right: Box<DartExpr>,
+    },
+    Conditional {
+        condition: Box<DartExpr>,
+        then_branch: Box<DartExpr>,
+        else_branch: Box<DartExpr>,
+    },
+    Assignment {
+        operator: &'static str,
+        target: Box<DartExpr>,
+        value: Box<DartExpr>,
+    },
+    /// `is`, `is!` and `as` expressions.
+    TypeTest {
+        operator: &'static str,
+        expr: Box<DartExpr>,
+        type_name: String,
+    },
+    /// Cascade sections (`..foo()`) are kept as source spans.
+    Cascade {
+        target: Box<DartExpr>,
+        sections: Vec<Span>,
+    },
+    Parenthesized(Box<DartExpr>),
+    /// Source the parser could not understand. Only produced while recovering
+    /// inside argument lists and collection literals.
+    Opaque,
+}
+
+/// A string literal token. `content` is the source between the quotes with
+/// escapes and interpolations left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DartString {
+    pub content: String,
+    pub raw: bool,
+    pub triple: bool,
+    pub interpolated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    /// Name of a named argument; `None` for positional arguments.
+    pub name: Option<String>,
+    pub value: DartExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollectionElement {
+    Expression(DartExpr),
+    MapEntry {
+        key: DartExpr,
+        value: DartExpr,
+    },
+    Spread {
+        expr: DartExpr,
+        null_aware: bool,
+        span: Span,
+    },
+    If {
+        condition: DartExpr,
+        then_element: Box<CollectionElement>,
+        else_element: Option<Box<CollectionElement>>,
+        span: Span,
+    },
+    /// `for` element. `variable` and `iterable` are set for `for (x in xs)`
+    /// loops; C-style loops only keep the `header` between the parentheses.
+    For {
+        variable: Option<String>,
+        iterable: Option<DartExpr>,
+        header: Span,
+        body: Box<CollectionElement>,
+        span: Span,
+    },
+}
+
+impl CollectionElement {
+    /// Source covered by the element, including any `...`, `if` or `for` prefix.
+    pub fn span(&self) -> Span {
+        match self {
+            CollectionElement::Expression(expr) => expr.span,
+            CollectionElement::MapEntry { key, value } => Span::new(key.span.start, value.span.end),
+            CollectionElement::Spread { span, .. }
+            | CollectionElement::If { span, .. }
+            | CollectionElement::For { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionBody {
+    Expression(Box<DartExpr>),
+    /// Block body, including its braces.
+    Block(Span),
+}
+
+/// Symbols recognized by the lexer, longest first. `>` is always emitted on its
+/// own so that nested type arguments such as `List<List<int>>` close cleanly;
+/// the parser reassembles shift operators from adjacent tokens.
+const SYMBOLS: &[&str] = &[
+    "...?", "...", "?..", "??=", "<<=", "~/=", "&&=", "||=", "..", "?.", "??", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", "~/",
+    "(", ")", "[", "]", "{", "}", ",", ":", ";", ".", "?", "=", "!", "<", ">", "+", "-", "*", "/",
+    "%", "&", "|", "^", "~", "@", "#",
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "~/=", "??=", "&=", "|=", "^=", "<<=", "&&=", "||=",
+];
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+fn is_identifier_part(byte: u8) -> bool {
+    is_identifier_start(byte) || byte.is_ascii_digit()
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            bytes: source.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn lex(&mut self) -> DartParseResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    fn next_token(&mut self) -> DartParseResult<Option<Token>> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let Some(&byte) = self.bytes.get(start) else {
+            return Ok(None);
+        };
+        let next = self.bytes.get(start + 1).copied();
+
+        let kind = match byte {
+            b'r' if matches!(next, Some(b'\'' | b'"')) => {
+                self.pos += 1;
+                self.lex_string(start, true)?
+            }
+            b'\'' | b'"' => self.lex_string(start, false)?,
+            b'0'..=b'9' => self.lex_number(),
+            b'.' if next.is_some_and(|b| b.is_ascii_digit()) => self.lex_number(),
+            _ if is_identifier_start(byte) => {
+                while self
+                    .bytes
+                    .get(self.pos)
+                    .is_some_and(|b| is_identifier_part(*b))
+                {
+                    self.pos += 1;
+                }
+                TokenKind::Identifier
+            }
+            _ => {
+                let rest = &self.source[start..];
+                let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) else {
+                    let ch = rest.chars().next().unwrap_or_default();
+                    return Err(DartParseError::UnexpectedCharacter { ch, offset: start });
+                };
+                self.pos += symbol.len();
+                TokenKind::Symbol(symbol)
+            }
+        };
+
+        Ok(Some(Token {
+            kind,
+            span: Span::new(start, self.pos),
+        }))
+    }
+
+    fn skip_trivia(&mut self) -> DartParseResult<()> {
+        loop {
+            match (self.bytes.get(self.pos), self.bytes.get(self.pos + 1)) {
+                (Some(byte), _) if byte.is_ascii_whitespace() => self.pos += 1,
+                (Some(b'/'), Some(b'/')) => {
+                    while self.bytes.get(self.pos).is_some_and(|b| *b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    let start = self.pos;
+                    let mut depth = 0_usize;
+                    loop {
+                        match (self.bytes.get(self.pos), self.bytes.get(self.pos + 1)) {
+                            (Some(b'/'), Some(b'*')) => {
+                                depth += 1;
+                                self.pos += 2;
+                            }
+                            (Some(b'*'), Some(b'/')) => {
+                                depth -= 1;
+                                self.pos += 2;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            (Some(_), _) => self.pos += 1,
+                            (None, _) => return Err(DartParseError::UnterminatedComment(start)),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Lexes a string literal whose opening quote is at `self.pos`. `start` is
+    /// the token start, which differs for raw strings.
+    fn lex_string(&mut self, start: usize, raw: bool) -> DartParseResult<TokenKind> {
+        let quote = self.bytes[self.pos];
+        let triple = self.bytes.get(self.pos + 1) == Some(&quote)
+            && self.bytes.get(self.pos + 2) == Some(&quote);
+        self.pos += if triple { 3 } else { 1 };
+        let mut interpolated = false;
+
+        loop {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                return Err(DartParseError::UnterminatedString(start));
+            };
+            match byte {
+                b'\\' if !raw => self.pos += 2,
+                b'$' if !raw => {
+                    interpolated = true;
+                    self.pos += 1;
+                    if self.bytes.get(self.pos) == Some(&b'{') {
+                        self.pos += 1;
+                        self.skip_interpolation(start)?;
+                    }
+                }
+                b'\n' if !triple => return Err(DartParseError::UnterminatedString(start)),
+                _ if byte == quote => {
+                    if !triple {
+                        self.pos += 1;
+                        break;
+                    }
+                    if self.bytes.get(self.pos + 1) == Some(&quote)
+                        && self.bytes.get(self.pos + 2) == Some(&quote)
+                    {
+                        self.pos += 3;
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+        }
+
+        Ok(TokenKind::String {
+            raw,
+            triple,
+            interpolated,
+        })
+    }
+
+    /// Skips the expression of a `${...}` interpolation, whose `{` has already
+    /// been consumed, by lexing tokens until the matching `}`.
+    fn skip_interpolation(&mut self, string_start: usize) -> DartParseResult<()> {
+        let mut depth = 1_usize;
+        while depth > 0 {
+            let Some(token) = self.next_token()? else {
+                return Err(DartParseError::UnterminatedString(string_start));
+            };
+            match token.kind {
+                TokenKind::Symbol("{") => depth += 1,
+                TokenKind::Symbol("}") => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn lex_number(&mut self) -> TokenKind {
+        let digits = |lexer: &mut Self| {
+            while lexer.bytes.get(lexer.pos).is_some_and(u8::is_ascii_digit) {
+                lexer.pos += 1;
+            }
+        };
+
+        if self.bytes[self.pos] == b'0' && matches!(self.bytes.get(self.pos + 1), Some(b'x' | b'X'))
+        {
+            self.pos += 2;
+            while self.bytes.get(self.pos).is_some_and(u8::is_ascii_hexdigit) {
+                self.pos += 1;
+            }
+            return TokenKind::Number;
+        }
+
+        digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.')
+            && self.bytes.get(self.pos + 1).is_some_and(u8::is_ascii_digit)
+        {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(self.bytes.get(self.pos + 1), Some(b'+' | b'-')));
+            if self
+                .bytes
+                .get(self.pos + 1 + sign)
+                .is_some_and(u8::is_ascii_digit)
+            {
+                self.pos += 1 + sign;
+                digits(self);
+            }
+        }
+        TokenKind::Number
+    }
+}
+
+/// Tokenized Dart source with precomputed bracket pairs.
+pub struct TokenStream<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    closing: Vec<Option<usize>>,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Tokenizes `source`, failing on malformed literals, unterminated
+    /// comments or unbalanced brackets.
+    pub fn new(source: &'a str) -> DartParseResult<Self> {
+        let tokens = Lexer::new(source).lex()?;
+        let mut closing = vec![None; tokens.len()];
+        let mut stack: Vec<usize> = Vec::new();
+        for (idx, token) in tokens.iter().enumerate() {
+            let TokenKind::Symbol(symbol) = token.kind else {
+                continue;
+            };
+            match symbol {
+                "(" | "[" | "{" => stack.push(idx),
+                ")" | "]" | "}" => {
+                    let expected = match symbol {
+                        ")" => "(",
+                        "]" => "[",
+                        _ => "{",
+                    };
+                    match stack.pop() {
+                        Some(open) if tokens[open].kind == TokenKind::Symbol(expected) => {
+                            closing[open] = Some(idx);
+                        }
+                        _ => {
+                            return Err(DartParseError::UnbalancedDelimiter {
+                                delimiter: symbol.chars().next().unwrap_or_default(),
+                                offset: token.span.start,
+                            })
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(open) = stack.pop() {
+            return Err(DartParseError::UnbalancedDelimiter {
+                delimiter: source[tokens[open].span.start..]
+                    .chars()
+                    .next()
+                    .unwrap_or_default(),
+                offset: tokens[open].span.start,
+            });
+        }
+
+        Ok(Self {
+            source,
+            tokens,
+            closing,
+        })
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Source text of the token at `index`.
+    pub fn text(&self, index: usize) -> &'a str {
+        self.tokens[index].span.text(self.source)
+    }
+
+    pub fn is_symbol(&self, index: usize, symbol: &str) -> bool {
+        matches!(self.tokens.get(index), Some(Token { kind: TokenKind::Symbol(s), .. }) if *s == symbol)
+    }
+
+    pub fn is_identifier(&self, index: usize, name: &str) -> bool {
+        matches!(self.tokens.get(index), Some(token) if token.kind == TokenKind::Identifier && token.span.text(self.source) == name)
+    }
+
+    /// Index of the bracket closing the one at `index`, if `index` opens one.
+    pub fn closing(&self, index: usize) -> Option<usize> {
+        self.closing.get(index).copied().flatten()
+    }
+
+    /// Index of the next token at the same nesting depth as `index`, stepping
+    /// over a whole bracketed group when `index` opens one.
+    pub fn skip(&self, index: usize) -> usize {
+        self.closing(index).map_or(index + 1, |close| close + 1)
+    }
+
+    /// Span from the start of token `first` to the end of token `last`.
+    pub fn span(&self, first: usize, last: usize) -> Span {
+        Span::new(self.tokens[first].span.start, self.tokens[last].span.end)
+    }
+
+    /// Parses one expression starting at token `index`. Returns the expression
+    /// and the index of the first token after it.
+    pub fn parse_expression(&self, index: usize) -> DartParseResult<(DartExpr, usize)> {
+        let mut parser = Parser {
+            stream: self,
+            pos: index,
+        };
+        let expr = parser.parse_expression()?;
+        Ok((expr, parser.pos))
+    }
+}
+
+/// Parses `source` as a single Dart expression. A trailing `;` is allowed.
+pub fn parse_dart_expression(source: &str) -> DartParseResult<DartExpr> {
+    let stream = TokenStream::new(source)?;
+    let (expr, next) = stream.parse_expression(0)?;
+    let end = if stream.is_symbol(stream.len().saturating_sub(1), ";") {
+        stream.len() - 1
+    } else {
+        stream.len()
+    };
+    if next < end {
+        return Err(DartParseError::UnexpectedToken {
+            found: stream.text(next).to_string(),
+            offset: stream.tokens[next].span.start,
+        });
+    }
+    Ok(expr)
+}
+
+struct Parser<'s, 'a> {
+    stream: &'s TokenStream<'a>,
+    pos: usize,
+}
+
+impl Parser<'_, '_> {
+    fn peek(&self) -> Option<&Token> {
+        self.stream.tokens.get(self.pos)
+    }
+
+    fn at_symbol(&self, symbol: &str) -> bool {
+        self.stream.is_symbol(self.pos, symbol)
+    }
+
+    fn at_identifier(&self, name: &str) -> bool {
+        self.stream.is_identifier(self.pos, name)
+    }
+
+    fn unexpected(&self) -> DartParseError {
+        match self.peek() {
+            Some(token) => DartParseError::UnexpectedToken {
+                found: token.span.text(self.stream.source).to_string(),
+                offset: token.span.start,
+            },
+            None => DartParseError::UnexpectedEof,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> DartParseResult<()> {
+        if self.at_symbol(symbol) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_identifier(&mut self) -> DartParseResult<String> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Identifier => {
+                let name = token.span.text(self.stream.source).to_string();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Closing index of the bracket at the current position.
+    fn current_closing(&self) -> DartParseResult<usize> {
+        self.stream
+            .closing(self.pos)
+            .ok_or_else(|| self.unexpected())
+    }
+
+    /// Span from token `start` to the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        self.stream.span(start, self.pos.max(start + 1) - 1)
+    }
+
+    fn node(&self, start: usize, kind: DartExprKind) -> DartExpr {
+        DartExpr {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    /// Whether two tokens touch, with no whitespace or comments between them.
+    fn adjacent(&self, first: usize, second: usize) -> bool {
+        match (
+            self.stream.tokens.get(first),
+            self.stream.tokens.get(second),
+        ) {
+            (Some(a), Some(b)) => a.span.end == b.span.start,
+            _ => false,
+        }
+    }
+
+    fn parse_expression(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        if self.at_identifier("throw") {
+            self.pos += 1;
+            let operand = self.parse_expression()?;
+            return Ok(self.node(
+                start,
+                DartExprKind::Unary {
+                    operator: "throw",
+                    operand: Box::new(operand),
+                },
+            ));
+        }
+
+        let expr = self.parse_conditional()?;
+
+        if let Some(TokenKind::Symbol(symbol)) = self.peek().map(|token| token.kind) {
+            if ASSIGNMENT_OPERATORS.contains(&symbol) {
+                self.pos += 1;
+                let value = self.parse_expression()?;
+                return Ok(self.node(
+                    start,
+                    DartExprKind::Assignment {
+                        operator: symbol,
+                        target: Box::new(expr),
+                        value: Box::new(value),
+                    },
+                ));
+            }
+        }
+
+        if self.at_symbol("..") || self.at_symbol("?..") {
+            let mut sections = Vec::new();
+            while self.at_symbol("..") || self.at_symbol("?..") {
+                let section_start = self.pos;
+                self.pos += 1;
+                while let Some(token) = self.peek() {
+                    let ends_section = matches!(
+                        token.kind,
+                        TokenKind::Symbol(".." | "?.." | "," | ")" | "]" | "}" | ";" | ":")
+                    );
+                    if ends_section {
+                        break;
+                    }
+                    self.pos = self.stream.skip(self.pos);
+                }
+                sections.push(self.span_from(section_start));
+            }
+            return Ok(self.node(
+                start,
+                DartExprKind::Cascade {
+                    target: Box::new(expr),
+                    sections,
+                },
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_conditional(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let condition = self.parse_binary(0)?;
+        if !self.at_symbol("?") {
+            return Ok(condition);
+        }
+        self.pos += 1;
+        let then_branch = self.parse_expression()?;
+        self.expect_symbol(":")?;
+        let else_branch = self.parse_expression()?;
+        Ok(self.node(
+            start,
+            DartExprKind::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            },
+        ))
+    }
+
+    /// Binary operators from loosest to tightest binding:
+    /// `??`, `||`, `&&`, equality, relational, `|`, `^`, `&`, shift, additive,
+    /// multiplicative.
+    fn parse_binary(&mut self, level: usize) -> DartParseResult<DartExpr> {
+        const LEVELS: usize = 11;
+        if level == LEVELS {
+            return self.parse_unary();
+        }
+
+        let start = self.pos;
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some((operator, width)) = self.binary_operator(level) {
+            self.pos += width;
+            if matches!(operator, "is" | "is!" | "as") {
+                let type_name = self.parse_type()?;
+                left = self.node(
+                    start,
+                    DartExprKind::TypeTest {
+                        operator,
+                        expr: Box::new(left),
+                        type_name,
+                    },
+                );
+                continue;
+            }
+            let right = self.parse_binary(level + 1)?;
+            left = self.node(
+                start,
+                DartExprKind::Binary {
+                    operator,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+            );
+        }
+        Ok(left)
+    }
+
+    /// Returns the operator at the current position for `level` and the number
+    /// of tokens it spans.
+    fn binary_operator(&self, level: usize) -> Option<(&'static str, usize)> {
+        let token = self.peek()?;
+        let symbol = match token.kind {
+            TokenKind::Symbol(symbol) => symbol,
+            TokenKind::Identifier => {
+                if level != 4 {
+                    return None;
+                }
+                return match token.span.text(self.stream.source) {
+                    "is" if self.stream.is_symbol(self.pos + 1, "!") => Some(("is!", 2)),
+                    "is" => Some(("is", 1)),
+                    "as" => Some(("as", 1)),
+                    _ => None,
+                };
+            }
+            _ => return None,
+        };
+        let shift_width = if symbol == ">" && self.stream.is_symbol(self.pos + 1, ">") {
+            if self.adjacent(self.pos, self.pos + 1) {
+                if self.stream.is_symbol(self.pos + 2, ">")
+                    && self.adjacent(self.pos + 1, self.pos + 2)
+                {
+                    3
+                } else {
+                    2
+                }
+            } else {
+                1
+            }
+        } else {
+            1
+        };
+
+        let operators: &[&'static str] = match level {
+            0 => &["??"],
+            1 => &["||"],
+            2 => &["&&"],
+            3 => &["==", "!="],
+            4 if shift_width == 1 => &["<", ">", "<=", ">="],
+            5 => &["|"],
+            6 => &["^"],
+            7 => &["&"],
+            8 => {
+                return match (symbol, shift_width) {
+                    ("<<", _) => Some(("<<", 1)),
+                    (">", 2) => Some((">>", 2)),
+                    (">", 3) => Some((">>>", 3)),
+                    _ => None,
+                }
+            }
+            9 => &["+", "-"],
+            10 => &["*", "/", "%", "~/"],
+            _ => &[],
+        };
+        operators
+            .iter()
+            .find(|operator| **operator == symbol)
+            .map(|operator| (*operator, 1))
+    }
+
+    fn parse_unary(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let operator = match self.peek().map(|token| token.kind) {
+            Some(TokenKind::Symbol(symbol @ ("-" | "!" | "~" | "++" | "--"))) => Some(symbol),
+            Some(TokenKind::Identifier) if self.at_identifier("await") => Some("await"),
+            _ => None,
+        };
+        let Some(operator) = operator else {
+            return self.parse_postfix();
+        };
+        self.pos += 1;
+        let operand = self.parse_unary()?;
+        Ok(self.node(
+            start,
+            DartExprKind::Unary {
+                operator,
+                operand: Box::new(operand),
+            },
+        ))
+    }
+
+    fn parse_postfix(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let mut expr = self.parse_primary()?;
+        while let Some(TokenKind::Symbol(symbol)) = self.peek().map(|token| token.kind) {
+            let kind = match symbol {
+                "." | "?." => {
+                    self.pos += 1;
+                    let name = self.expect_identifier()?;
+                    DartExprKind::PropertyAccess {
+                        target: Box::new(expr),
+                        name,
+                        null_aware: symbol == "?.",
+                    }
+                }
+                "(" => {
+                    let arguments = self.parse_arguments()?;
+                    DartExprKind::Call {
+                        callee: Box::new(expr),
+                        type_arguments: Vec::new(),
+                        arguments,
+                        is_const: false,
+                    }
+                }
+                "<" => {
+                    let checkpoint = self.pos;
+                    match self.parse_type_arguments() {
+                        Ok(type_arguments) if self.at_symbol("(") => {
+                            let arguments = self.parse_arguments()?;
+                            DartExprKind::Call {
+                                callee: Box::new(expr),
+                                type_arguments,
+                                arguments,
+                                is_const: false,
+                            }
+                        }
+                        _ => {
+                            self.pos = checkpoint;
+                            break;
+                        }
+                    }
+                }
+                "[" => {
+                    let close = self.current_closing()?;
+                    self.pos += 1;
+                    let index = self.parse_expression()?;
+                    if self.pos != close {
+                        return Err(self.unexpected());
+                    }
+                    self.pos = close + 1;
+                    DartExprKind::Index {
+                        target: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                "!" | "++" | "--" => {
+                    self.pos += 1;
+                    DartExprKind::Postfix {
+                        operator: symbol,
+                        operand: Box::new(expr),
+                    }
+                }
+                _ => break,
+            };
+            expr = self.node(start, kind);
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let Some(token) = self.peek().copied() else {
+            return Err(DartParseError::UnexpectedEof);
+        };
+
+        match token.kind {
+            TokenKind::Number => {
+                self.pos += 1;
+                let lexeme = token.span.text(self.stream.source).to_string();
+                Ok(self.node(start, DartExprKind::Number(lexeme)))
+            }
+            TokenKind::String { .. } => {
+                let mut strings = Vec::new();
+                while let Some(token) = self.peek().copied() {
+                    let TokenKind::String {
+                        raw,
+                        triple,
+                        interpolated,
+                    } = token.kind
+                    else {
+                        break;
+                    };
+                    let quote_len = if triple { 3 } else { 1 };
+                    let content_start = token.span.start + usize::from(raw) + quote_len;
+                    let content_end = token.span.end - quote_len;
+                    strings.push(DartString {
+                        content: self.stream.source[content_start..content_end].to_string(),
+                        raw,
+                        triple,
+                        interpolated,
+                    });
+                    self.pos += 1;
+                }
+                let kind = if strings.len() == 1 {
+                    DartExprKind::String(strings.remove(0))
+                } else {
+                    DartExprKind::AdjacentStrings(strings)
+                };
+                Ok(self.node(start, kind))
+            }
+            TokenKind::Identifier => {
+                let name = token.span.text(self.stream.source);
+                match name {
+                    "null" => {
+                        self.pos += 1;
+                        Ok(self.node(start, DartExprKind::Null))
+                    }
+                    "true" | "false" => {
+                        self.pos += 1;
+                        Ok(self.node(start, DartExprKind::Bool(name == "true")))
+                    }
+                    "const" | "new" => {
+                        self.pos += 1;
+                        let mut expr =
+                            if self.at_symbol("[") || self.at_symbol("{") || self.at_symbol("<") {
+                                self.parse_collection_literal()?
+                            } else {
+                                self.parse_postfix()?
+                            };
+                        if name == "const" {
+                            mark_const(&mut expr);
+                        }
+                        expr.span.start = token.span.start;
+                        Ok(expr)
+                    }
+                    _ => {
+                        self.pos += 1;
+                        Ok(self.node(start, DartExprKind::Identifier(name.to_string())))
+                    }
+                }
+            }
+            TokenKind::Symbol("(") => {
+                let close = self.current_closing()?;
+                let after = close + 1;
+                let is_function = self.stream.is_symbol(after, "=>")
+                    || self.stream.is_symbol(after, "{")
+                    || self.stream.is_identifier(after, "async")
+                    || self.stream.is_identifier(after, "sync");
+                if is_function {
+                    return self.parse_function_literal();
+                }
+                self.pos += 1;
+                let inner = self.parse_expression()?;
+                if self.pos != close {
+                    return Err(self.unexpected());
+                }
+                self.pos = close + 1;
+                Ok(self.node(start, DartExprKind::Parenthesized(Box::new(inner))))
+            }
+            TokenKind::Symbol("[" | "{" | "<") => self.parse_collection_literal(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_function_literal(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let close = self.current_closing()?;
+        let parameters = self.parameter_names(start, close);
+        self.pos = close + 1;
+
+        let mut is_async = false;
+        if self.at_identifier("async") || self.at_identifier("sync") {
+            is_async = self.at_identifier("async");
+            self.pos += 1;
+            if self.at_symbol("*") {
+                self.pos += 1;
+            }
+        }
+
+        let body = if self.at_symbol("=>") {
+            self.pos += 1;
+            FunctionBody::Expression(Box::new(self.parse_expression()?))
+        } else if self.at_symbol("{") {
+            let block_close = self.current_closing()?;
+            let span = self.stream.span(self.pos, block_close);
+            self.pos = block_close + 1;
+            FunctionBody::Block(span)
+        } else {
+            return Err(self.unexpected());
+        };
+
+        Ok(self.node(
+            start,
+            DartExprKind::Function {
+                parameters,
+                body,
+                is_async,
+            },
+        ))
+    }
+
+    /// Names of the parameters declared between the parentheses at `open` and
+    /// `close`, including optional and named parameter groups.
+    fn parameter_names(&self, open: usize, close: usize) -> Vec<String> {
+        let stream = self.stream;
+        let mut names = Vec::new();
+        let mut current: Option<&str> = None;
+        let mut in_default = false;
+        let mut idx = open + 1;
+        let mut group_end = None;
+
+        while idx < close {
+            let token = stream.tokens[idx];
+            let at_group_start = current.is_none()
+                && group_end.is_none()
+                && (stream.is_symbol(idx, "{") || stream.is_symbol(idx, "["));
+            if at_group_start {
+                group_end = stream.closing(idx);
+                idx += 1;
+                continue;
+            }
+            if Some(idx) == group_end || stream.is_symbol(idx, ",") {
+                names.extend(current.take().map(str::to_string));
+                in_default = false;
+                idx += 1;
+                continue;
+            }
+            match token.kind {
+                TokenKind::Symbol("=" | ":") => in_default = true,
+                TokenKind::Identifier if !in_default => current = Some(stream.text(idx)),
+                _ => {}
+            }
+            idx = stream.skip(idx);
+        }
+        names.extend(current.map(str::to_string));
+        names
+    }
+
+    fn parse_collection_literal(&mut self) -> DartParseResult<DartExpr> {
+        let start = self.pos;
+        let type_arguments = if self.at_symbol("<") {
+            self.parse_type_arguments()?
+        } else {
+            Vec::new()
+        };
+
+        let is_list = self.at_symbol("[");
+        if !is_list && !self.at_symbol("{") {
+            return Err(self.unexpected());
+        }
+        let close = self.current_closing()?;
+        self.pos += 1;
+        let elements = self.parse_delimited(close, Self::parse_element, |span| {
+            CollectionElement::Expression(DartExpr {
+                kind: DartExprKind::Opaque,
+                span,
+            })
+        });
+
+        let kind = if is_list {
+            DartExprKind::List {
+                type_argument: type_arguments.into_iter().next(),
+                elements,
+                is_const: false,
+            }
+        } else {
+            DartExprKind::SetOrMap {
+                type_arguments,
+                elements,
+                is_const: false,
+            }
+        };
+        Ok(self.node(start, kind))
+    }
+
+    fn parse_element(&mut self) -> DartParseResult<CollectionElement> {
+        let start = self.pos;
+        if self.at_symbol("...") || self.at_symbol("...?") {
+            let null_aware = self.at_symbol("...?");
+            self.pos += 1;
+            let expr = self.parse_expression()?;
+            return Ok(CollectionElement::Spread {
+                expr,
+                null_aware,
+                span: self.span_from(start),
+            });
+        }
+
+        if self.at_identifier("if") && self.stream.is_symbol(self.pos + 1, "(") {
+            self.pos += 1;
+            let close = self.current_closing()?;
+            self.pos += 1;
+            let condition = self.parse_expression()?;
+            if self.pos != close {
+                return Err(self.unexpected());
+            }
+            self.pos = close + 1;
+            let then_element = Box::new(self.parse_element()?);
+            let else_element = if self.at_identifier("else") {
+                self.pos += 1;
+                Some(Box::new(self.parse_element()?))
+            } else {
+                None
+            };
+            return Ok(CollectionElement::If {
+                condition,
+                then_element,
+                else_element,
+                span: self.span_from(start),
+            });
+        }
+
+        if self.at_identifier("for") && self.stream.is_symbol(self.pos + 1, "(") {
+            self.pos += 1;
+            let open = self.pos;
+            let close = self.current_closing()?;
+            let header = if close > open + 1 {
+                self.stream.span(open + 1, close - 1)
+            } else {
+                Span::new(
+                    self.stream.tokens[open].span.end,
+                    self.stream.tokens[close].span.start,
+                )
+            };
+            let (variable, iterable) = self.parse_for_in(open, close);
+            self.pos = close + 1;
+            let body = Box::new(self.parse_element()?);
+            return Ok(CollectionElement::For {
+                variable,
+                iterable,
+                header,
+                body,
+                span: self.span_from(start),
+            });
+        }
+
+        let expr = self.parse_expression()?;
+        if self.at_symbol(":") {
+            self.pos += 1;
+            let value = self.parse_expression()?;
+            return Ok(CollectionElement::MapEntry { key: expr, value });
+        }
+        Ok(CollectionElement::Expression(expr))
+    }
+
+    /// Splits a `for (<declaration> in <iterable>)` header. Returns `None`s for
+    /// C-style loops or headers that do not parse.
+    fn parse_for_in(&mut self, open: usize, close: usize) -> (Option<String>, Option<DartExpr>) {
+        let mut idx = open + 1;
+        while idx < close && !self.stream.is_identifier(idx, "in") {
+            idx = self.stream.skip(idx);
+        }
+        let variable_idx = idx.checked_sub(1).filter(|candidate| *candidate > open);
+        let Some(variable_idx) = variable_idx else {
+            return (None, None);
+        };
+        if idx >= close || self.stream.tokens[variable_idx].kind != TokenKind::Identifier {
+            return (None, None);
+        }
+
+        self.pos = idx + 1;
+        match self.parse_expression() {
+            Ok(iterable) if self.pos == close => (
+                Some(self.stream.text(variable_idx).to_string()),
+                Some(iterable),
+            ),
+            _ => (None, None),
+        }
+    }
+
+    fn parse_arguments(&mut self) -> DartParseResult<Vec<Argument>> {
+        let close = self.current_closing()?;
+        self.pos += 1;
+        Ok(
+            self.parse_delimited(close, Self::parse_argument, |span| Argument {
+                name: None,
+                value: DartExpr {
+                    kind: DartExprKind::Opaque,
+                    span,
+                },
+                span,
+            }),
+        )
+    }
+
+    fn parse_argument(&mut self) -> DartParseResult<Argument> {
+        let start = self.pos;
+        let is_named = self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::Identifier)
+            && self.stream.is_symbol(self.pos + 1, ":");
+        let name = if is_named {
+            let name = self.stream.text(self.pos).to_string();
+            self.pos += 2;
+            Some(name)
+        } else {
+            None
+        };
+        let value = self.parse_expression()?;
+        Ok(Argument {
+            name,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses comma-separated items up to the bracket at `close` and moves past
+    /// it. Items that fail to parse are replaced by `recover(span)` so that one
+    /// unsupported construct does not discard its siblings.
+    fn parse_delimited<T>(
+        &mut self,
+        close: usize,
+        item: fn(&mut Self) -> DartParseResult<T>,
+        recover: fn(Span) -> T,
+    ) -> Vec<T> {
+        let mut items = Vec::new();
+        while self.pos < close {
+            if self.at_symbol(",") {
+                self.pos += 1;
+                continue;
+            }
+            let start = self.pos;
+            let parsed = match item(self) {
+                Ok(value) if self.pos == close || self.at_symbol(",") => value,
+                _ => {
+                    self.pos = start;
+                    while self.pos < close && !self.at_symbol(",") {
+                        self.pos = self.stream.skip(self.pos);
+                    }
+                    recover(self.span_from(start))
+                }
+            };
+            items.push(parsed);
+        }
+        self.pos = close + 1;
+        items
+    }
+
+    fn parse_type_arguments(&mut self) -> DartParseResult<Vec<String>> {
+        self.expect_symbol("<")?;
+        let mut types = vec![self.parse_type()?];
+        while self.at_symbol(",") {
+            self.pos += 1;
+            types.push(self.parse_type()?);
+        }
+        self.expect_symbol(">")?;
+        Ok(types)
+    }
+
+    /// Parses a type annotation and returns its source text.
+    fn parse_type(&mut self) -> DartParseResult<String> {
+        let start = self.pos;
+        if self.at_symbol("(") {
+            // Record type.
+            self.pos = self.current_closing()? + 1;
+        } else {
+            self.expect_identifier()?;
+            while self.at_symbol(".") {
+                self.pos += 1;
+                self.expect_identifier()?;
+            }
+            if self.at_symbol("<") {
+                self.parse_type_arguments()?;
+            }
+        }
+
+        while self.at_identifier("Function") {
+            self.pos += 1;
+            if self.at_symbol("<") {
+                self.parse_type_arguments()?;
+            }
+            if !self.at_symbol("(") {
+                return Err(self.unexpected());
+            }
+            self.pos = self.current_closing()? + 1;
+        }
+
+        if self.at_symbol("?") && !self.starts_expression(self.pos + 1) {
+            self.pos += 1;
+        }
+        Ok(self.span_from(start).text(self.stream.source).to_string())
+    }
+
+    fn starts_expression(&self, index: usize) -> bool {
+        match self.stream.tokens.get(index).map(|token| token.kind) {
+            Some(TokenKind::Identifier | TokenKind::Number | TokenKind::String { .. }) => true,
+            Some(TokenKind::Symbol(symbol)) => {
+                matches!(
+                    symbol,
+                    "(" | "[" | "{" | "!" | "-" | "<" | "~" | "++" | "--"
+                )
+            }
+            None => false,
+        }
+    }
+}
+
+fn mark_const(expr: &mut DartExpr) {
+    match &mut expr.kind {
+        DartExprKind::Call { is_const, .. }
+        | DartExprKind::List { is_const, .. }
+        | DartExprKind::SetOrMap { is_const, .. } => *is_const = true,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callee_name(expr: &DartExpr) -> String {
+        match &expr.kind {
+            DartExprKind::Identifier(name) => name.clone(),
+            DartExprKind::PropertyAccess { target, name, .. } => {
+                format!("{}.{}", callee_name(target), name)
+            }
+            other => panic!("unexpected callee {other:?}"),
+        }
+    }
+
+    fn call_parts(expr: &DartExpr) -> (String, &[Argument], bool) {
+        match &expr.kind {
+            DartExprKind::Call {
+                callee,
+                arguments,
+                is_const,
+                ..
+            } => (callee_name(callee), arguments, *is_const),
+            other => panic!("expected call, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn tokenizer_skips_comments_and_keeps_strings_whole() {
+        let source = "Text('a (b' /* ) */, r'\\d+', \"${user.name('}')}\") // )";
+        let stream = TokenStream::new(source).expect("tokens");
+        let kinds: Vec<_> = stream.tokens().iter().map(|token| token.kind).collect();
+        assert_eq!(kinds.len(), 8);
+        assert_eq!(stream.text(2), "'a (b'");
+        assert_eq!(stream.text(4), "r'\\d+'");
+        assert_eq!(
+            kinds[6],
+            TokenKind::String {
+                raw: false,
+                triple: false,
+                interpolated: true
+            }
+        );
+        assert_eq!(stream.closing(1), Some(7));
+    }
+
+    #[test]
+    fn tokenizer_reports_unterminated_string() {
+        assert_eq!(
+            TokenStream::new("Text('oops)").err(),
+            Some(DartParseError::UnterminatedString(5))
+        );
+    }
+
+    #[test]
+    fn parses_constructor_call_with_named_arguments_and_lambda() {
+        let expr = parse_dart_expression(
+            "const Padding(padding: EdgeInsets.all(8), child: ElevatedButton(onPressed: () => go(1), child: Text('Go')))",
+        )
+        .expect("expression");
+        let (name, arguments, is_const) = call_parts(&expr);
+        assert_eq!(name, "Padding");
+        assert!(is_const);
+        assert_eq!(arguments.len(), 2);
+        assert_eq!(arguments[0].name.as_deref(), Some("padding"));
+        let (button, button_args, _) = call_parts(&arguments[1].value);
+        assert_eq!(button, "ElevatedButton");
+        match &button_args[0].value.kind {
+            DartExprKind::Function {
+                parameters, body, ..
+            } => {
+                assert!(parameters.is_empty());
+                assert!(matches!(body, FunctionBody::Expression(_)));
+            }
+            other => panic!("expected lambda, found {other:?}"),
+        }
+        assert_eq!(
+            button_args[0].value.span.text(
+                "const Padding(padding: EdgeInsets.all(8), child: ElevatedButton(onPressed: () => go(1), child: Text('Go')))"
+            ),
+            "() => go(1)"
+        );
+    }
+
+    #[test]
+    fn parses_nested_generics_and_typed_collections() {
+        let source =
+            "Foo<Map<String, List<int>>>(items: <Map<String, List<int>>>[], flag: a >> 2 > b)";
+        let expr = parse_dart_expression(source).expect("expression");
+        match &expr.kind {
+            DartExprKind::Call {
+                type_arguments,
+                arguments,
+                ..
+            } => {
+                assert_eq!(type_arguments, &vec!["Map<String, List<int>>".to_string()]);
+                match &arguments[0].value.kind {
+                    DartExprKind::List { type_argument, .. } => {
+                        assert_eq!(type_argument.as_deref(), Some("Map<String, List<int>>"))
+                    }
+                    other => panic!("expected list, found {other:?}"),
+                }
+                match &arguments[1].value.kind {
+                    DartExprKind::Binary { operator, left, .. } => {
+                        assert_eq!(*operator, ">");
+                        assert!(matches!(
+                            left.kind,
+                            DartExprKind::Binary { operator: ">>", .. }
+                        ));
+                    }
+                    other => panic!("expected comparison, found {other:?}"),
+                }
+            }
+            other => panic!("expected call, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_collection_if_and_for_elements() {
+        let source = "Column(children: [if (loading) Spinner() else Text('Done'), for (final item in items) Tile(item), ...extra])";
+        let expr = parse_dart_expression(source).expect("expression");
+        let (_, arguments, _) = call_parts(&expr);
+        let DartExprKind::List { elements, .. } = &arguments[0].value.kind else {
+            panic!("expected list");
+        };
+        assert_eq!(elements.len(), 3);
+        assert!(matches!(
+            &elements[0],
+            CollectionElement::If {
+                else_element: Some(_),
+                ..
+            }
+        ));
+        match &elements[1] {
+            CollectionElement::For {
+                variable, iterable, ..
+            } => {
+                assert_eq!(variable.as_deref(), Some("item"));
+                assert_eq!(
+                    iterable.as_ref().map(|expr| expr.span.text(source)),
+                    Some("items")
+                );
+            }
+            other => panic!("expected for element, found {other:?}"),
+        }
+        assert!(matches!(
+            &elements[2],
+            CollectionElement::Spread {
+                null_aware: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unsupported_argument_is_recovered_as_opaque() {
+        let source = "Text(#sym, style: bold)";
+        let expr = parse_dart_expression(source).expect("expression");
+        let (_, arguments, _) = call_parts(&expr);
+        assert_eq!(arguments.len(), 2);
+        assert_eq!(arguments[0].value.kind, DartExprKind::Opaque);
+        assert_eq!(arguments[0].value.span.text(source), "#sym");
+        assert_eq!(arguments[1].name.as_deref(), Some("style"));
+    }
+
+    #[test]
+    fn function_literal_collects_parameter_names() {
+        let expr =
+            parse_dart_expression("(context, {required int index, List<int> ids = const []}) {}")
+                .expect("expression");
+        match expr.kind {
+            DartExprKind::Function {
+                parameters, body, ..
+            } => {
+                assert_eq!(parameters, vec!["context", "index", "ids"]);
+                assert!(matches!(body, FunctionBody::Block(_)));
+            }
+            other => panic!("expected function, found {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::merge_engine::{merge_keyed, merge_optional, merge_scalar};
+use crate::{
+    merge_logic_graphs, merge_screen_graphs, MergeConflict, MergeOutcome, SchemaDocument,
+    SchemaProject, ScreenGraph,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentMergeOutcome {
+    pub document: SchemaDocument,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Three-way merge of whole schema documents: project metadata, screens
+/// matched by id through [merge_screen_graphs], and logic graphs matched by
+/// position through [merge_logic_graphs]. Conflict paths are rooted at the
+/// document, such as `screens[Home].root.props.title` or
+/// `logic[0].flows[main].nodes[start]`, and keep the right side's value;
+/// [crate::merge_documents_with] settles them another way.
+pub fn merge_documents(
+    base: &SchemaDocument,
+    left: &SchemaDocument,
+    right: &SchemaDocument,
+) -> DocumentMergeOutcome {
+    merge_documents_by(base, left, right, merge_screen_graphs)
+}
+
+/// Like [merge_documents], merging screens present on all sides with
+/// `merge_screen`.
+pub(crate) fn merge_documents_by(
+    base: &SchemaDocument,
+    left: &SchemaDocument,
+    right: &SchemaDocument,
+    merge_screen: impl Fn(&ScreenGraph, &ScreenGraph, &ScreenGraph) -> MergeOutcome,
+) -> DocumentMergeOutcome {
+    let mut conflicts = Vec::new();
+    let version = merge_scalar(
+        "forge_schema_version",
+        &base.version,
+        &left.version,
+        &right.version,
+        None,
+        &mut conflicts,
+    );
+    let project = SchemaProject {
+        id: merge_scalar(
+            "project.id",
+            &base.project.id,
+            &left.project.id,
+            &right.project.id,
+            None,
+            &mut conflicts,
+        ),
+        name: merge_scalar(
+            "project.name",
+            &base.project.name,
+            &left.project.name,
+            &right.project.name,
+            None,
+            &mut conflicts,
+        ),
+        description: merge_optional(
+            "project.description",
+            base.project.description.as_ref(),
+            left.project.description.as_ref(),
+            right.project.description.as_ref(),
+            None,
+            &mut conflicts,
+        ),
+    };
+
+    let screens = merge_keyed(
+        "screens",
+        &base.screens,
+        &left.screens,
+        &right.screens,
+        |screen| screen.id.clone(),
+        |path, base, left, right, conflicts| {
+            let outcome = merge_screen(base, left, right);
+            conflicts.extend(rebase_paths(outcome.conflicts, "screen", path));
+            outcome.screen
+        },
+        &mut conflicts,
+    );
+
+    let graphs = base
+        .logic
+        .len()
+        .max(left.logic.len())
+        .max(right.logic.len());
+    let mut logic = Vec::with_capacity(graphs);
+    for idx in 0..graphs {
+        let path = format!("logic[{idx}]");
+        match (
+            base.logic.get(idx),
+            left.logic.get(idx),
+            right.logic.get(idx),
+        ) {
+            (Some(base), Some(left), Some(right)) => {
+                let outcome = merge_logic_graphs(base, left, right);
+                conflicts.extend(rebase_paths(outcome.conflicts, "logic", &path));
+                logic.push(outcome.logic);
+            }
+            (base, left, right) => logic.extend(merge_optional(
+                &path,
+                base,
+                left,
+                right,
+                None,
+                &mut conflicts,
+            )),
+        }
+    }
+
+    let metadata = merge_optional(
+        "metadata",
+        base.metadata.as_ref(),
+        left.metadata.as_ref(),
+        right.metadata.as_ref(),
+        None,
+        &mut conflicts,
+    );
+
+    DocumentMergeOutcome {
+        document: SchemaDocument {
+            version,
+            project,
+            screens,
+            logic,
+            metadata,
+        },
+        conflicts,
+    }
+}
+
+/// Replaces the `root` prefix of conflict paths with `path`.
+fn rebase_paths(
+    conflicts: Vec<MergeConflict>,
+    root: &str,
+    path: &str,
+) -> impl Iterator<Item = MergeConflict> {
+    let root = root.to_string();
+    let path = path.to_string();
+    conflicts.into_iter().map(move |mut conflict| {
+        if let Some(rest) = conflict.path.strip_prefix(root.as_str()) {
+            conflict.path = format!("{path}{rest}");
+        }
+        conflict
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BindingReference, BindingTarget, LogicGraph, MergeStrategy, PropValue, ProviderAccess,
+        SchemaWriter, WidgetNode,
+    };
+    use serde_json::json;
+
+    fn screen(id: &str, title: &str) -> ScreenGraph {
+        ScreenGraph {
+            id: id.to_string(),
+            root: WidgetNode {
+                id: Some(format!("{id}-root")),
+                widget: "Text".to_string(),
+                props: [(
+                    "data".to_string(),
+                    PropValue::Literal {
+                        value: json!(title),
+                        span: None,
+                    },
+                )]
+                .into(),
+                children: Vec::new(),
+                raw: None,
+                builders: Vec::new(),
+                component: None,
+                span: None,
+            },
+            state: None,
+            consumer: None,
+            statements: None,
+        }
+    }
+
+    fn document(name: &str, screens: Vec<ScreenGraph>) -> SchemaDocument {
+        SchemaWriter::build_document(
+            SchemaProject::new("app", name),
+            screens,
+            vec![LogicGraph {
+                flows: Vec::new(),
+                metadata: None,
+            }],
+            None,
+        )
+    }
+
+    #[test]
+    fn merges_project_screens_and_logic_independently() {
+        let base = document("App", vec![screen("Home", "Hi"), screen("About", "Us")]);
+        let left = document(
+            "Renamed",
+            vec![screen("Home", "Hello"), screen("About", "Us")],
+        );
+        let right = document(
+            "App",
+            vec![
+                screen("Home", "Hi"),
+                screen("About", "Us"),
+                screen("Settings", "Prefs"),
+            ],
+        );
+
+        let outcome = merge_documents(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        assert_eq!(outcome.document.project.name, "Renamed");
+        let ids: Vec<&str> = outcome
+            .document
+            .screens
+            .iter()
+            .map(|screen| screen.id.as_str())
+            .collect();
+        assert_eq!(ids, ["Home", "About", "Settings"]);
+        assert_eq!(outcome.document.screens[0], screen("Home", "Hello"));
+        assert_eq!(outcome.document.logic.len(), 1);
+    }
+
+    #[test]
+    fn conflict_paths_are_rooted_at_the_document() {
+        let base = document("App", vec![screen("Home", "Hi")]);
+        let left = document("Left", vec![screen("Home", "Left")]);
+        let right = document("Right", vec![screen("Home", "Right")]);
+
+        let outcome = merge_documents(&base, &left, &right);
+        let paths: Vec<&str> = outcome
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.path.as_str())
+            .collect();
+        assert_eq!(paths, ["project.name", "screens[Home].root.props.data"]);
+        assert_eq!(outcome.conflicts[1].node_id.as_deref(), Some("Home-root"));
+        assert_eq!(outcome.document.screens[0], screen("Home", "Right"));
+    }
+
+    #[test]
+    fn strategies_settle_document_conflicts() {
+        let base = document("App", vec![screen("Home", "Hi")]);
+        let left = document("Left", vec![screen("Home", "Left")]);
+        let right = document("Right", vec![screen("Home", "Right")]);
+
+        let outcome =
+            crate::merge_documents_with(&base, &left, &right, MergeStrategy::PreferLeft).unwrap();
+        assert_eq!(outcome.document.project.name, "Left");
+        assert_eq!(outcome.document.screens[0], screen("Home", "Left"));
+        assert_eq!(outcome.conflicts.len(), 2);
+        assert_eq!(outcome.conflicts[0].left, Some(json!("Left")));
+
+        let outcome =
+            crate::merge_documents_with(&base, &left, &right, MergeStrategy::KeepBoth).unwrap();
+        assert_eq!(outcome.document.project.name, "Right");
+        assert!(matches!(
+            crate::merge_documents_with(&base, &left, &right, MergeStrategy::Fail),
+            Err(crate::MergeError::Conflicts(conflicts)) if conflicts.len() == 2
+        ));
+    }
+
+    #[test]
+    fn documents_round_trip_through_json() {
+        let mut home = screen("Home", "Hi");
+        home.root.props.insert(
+            "count".to_string(),
+            PropValue::Binding {
+                binding: BindingReference {
+                    target: BindingTarget::Provider,
+                    reference: "counterProvider".to_string(),
+                    provider_id: Some("counter".to_string()),
+                    path: None,
+                    type_hint: Some("int".to_string()),
+                    access: ProviderAccess::Read,
+                    notifier: false,
+                },
+                span: None,
+            },
+        );
+        let document = document("App", vec![home]);
+        let payload = document.to_string_pretty().unwrap();
+        assert_eq!(SchemaDocument::from_json(&payload).unwrap(), document);
+    }
+}
@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
-use serde_json::{Number, Value};
+use serde_json::{Map, Number, Value};
+use std::cmp::Ordering;
 use thiserror::Error;
 
+use crate::function_registry::{get_function, ValueType};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Literal(Value),
@@ -9,6 +12,29 @@ pub enum Expr {
     Unary(UnaryOp, Box<Expr>),
     Binary(Box<Expr>, BinaryOp, Box<Expr>),
     Call(String, Vec<Expr>),
+    /// `condition ? then : otherwise`
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `value ?? fallback`
+    Coalesce(Box<Expr>, Box<Expr>),
+    /// `object.property`, or `object?.property` when `optional`. Plain dotted
+    /// names parse as [Expr::Var]; this covers access on other expressions and
+    /// null-safe access. Like a dotted name, reading a field of null or of a
+    /// non-object yields null, so `optional` only affects the static type.
+    Member {
+        object: Box<Expr>,
+        property: String,
+        optional: bool,
+    },
+    /// `object[index]`, or `object?[index]` when `optional`, as in Dart.
+    /// Indexing null yields null.
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        optional: bool,
+    },
+    /// `item => body` or `(acc, item) => body`; only valid as an argument to
+    /// the list operations such as `map` and `reduce`.
+    Lambda(Vec<String>, Box<Expr>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -43,6 +69,21 @@ pub enum ExprError {
     InvalidLiteral(String),
     #[error("evaluation error: {0}")]
     Evaluation(String),
+    #[error("unknown function {0}")]
+    UnknownFunction(String),
+    #[error("{function}() expects {expected}, got {found}")]
+    Arity {
+        function: String,
+        expected: String,
+        found: usize,
+    },
+    #[error("{function}() argument {position} must be {expected}, got {found}")]
+    ArgumentType {
+        function: String,
+        position: usize,
+        expected: String,
+        found: String,
+    },
 }
 
 pub type ExprResult<T> = Result<T, ExprError>;
@@ -51,6 +92,9 @@ pub type ExprResult<T> = Result<T, ExprError>;
 struct Token {
     kind: TokenKind,
     lexeme: String,
+    /// Character offsets of the token in the source.
+    start: usize,
+    end: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -76,9 +120,17 @@ enum TokenKind {
     Gte,
     LParen,
     RParen,
+    LBracket,
+    RBracket,
     Comma,
     Dot,
-    EOF,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
+    QuestionBracket,
+    Colon,
+    Arrow,
+    Eof,
 }
 
 struct Lexer {
@@ -98,7 +150,7 @@ impl Lexer {
         let mut tokens = Vec::new();
         loop {
             let token = self.next_token()?;
-            let at_end = token.kind == TokenKind::EOF;
+            let at_end = token.kind == TokenKind::Eof;
             tokens.push(token);
             if at_end {
                 break;
@@ -111,13 +163,16 @@ impl Lexer {
         self.skip_whitespace();
         if self.is_at_end() {
             return Ok(Token {
-                kind: TokenKind::EOF,
+                kind: TokenKind::Eof,
                 lexeme: String::new(),
+                start: self.pos,
+                end: self.pos,
             });
         }
 
+        let start = self.pos;
         let ch = self.advance();
-        match ch {
+        let mut token = match ch {
             '0'..='9' => self.lex_number(ch),
             'a'..='z' | 'A'..='Z' | '_' => self.lex_identifier(ch),
             '"' => self.lex_string(),
@@ -149,6 +204,8 @@ impl Lexer {
             '=' => {
                 if self.match_char('=') {
                     Ok(self.simple_token(TokenKind::EqEq, "=="))
+                } else if self.match_char('>') {
+                    Ok(self.simple_token(TokenKind::Arrow, "=>"))
                 } else {
                     Err(ExprError::UnexpectedToken("=".into()))
                 }
@@ -169,27 +226,46 @@ impl Lexer {
             }
             '(' => Ok(self.simple_token(TokenKind::LParen, "(")),
             ')' => Ok(self.simple_token(TokenKind::RParen, ")")),
+            '[' => Ok(self.simple_token(TokenKind::LBracket, "[")),
+            ']' => Ok(self.simple_token(TokenKind::RBracket, "]")),
             ',' => Ok(self.simple_token(TokenKind::Comma, ",")),
             '.' => Ok(self.simple_token(TokenKind::Dot, ".")),
+            ':' => Ok(self.simple_token(TokenKind::Colon, ":")),
+            '?' => {
+                if self.match_char('?') {
+                    Ok(self.simple_token(TokenKind::QuestionQuestion, "??"))
+                } else if self.match_char('.') {
+                    Ok(self.simple_token(TokenKind::QuestionDot, "?."))
+                } else if self.match_char('[') {
+                    Ok(self.simple_token(TokenKind::QuestionBracket, "?["))
+                } else {
+                    Ok(self.simple_token(TokenKind::Question, "?"))
+                }
+            }
             other => Err(ExprError::UnexpectedToken(other.to_string())),
-        }
+        }?;
+        token.start = start;
+        token.end = self.pos;
+        Ok(token)
     }
 
     fn simple_token(&self, kind: TokenKind, lexeme: &str) -> Token {
         Token {
             kind,
             lexeme: lexeme.to_string(),
+            start: 0,
+            end: 0,
         }
     }
 
     fn lex_number(&mut self, first: char) -> ExprResult<Token> {
         let mut literal = String::from(first);
-        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
             literal.push(self.advance());
         }
         if self.peek() == Some('.') {
             literal.push(self.advance());
-            while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
                 literal.push(self.advance());
             }
         }
@@ -199,6 +275,8 @@ impl Lexer {
         Ok(Token {
             kind: TokenKind::Number(value),
             lexeme: literal,
+            start: 0,
+            end: 0,
         })
     }
 
@@ -206,7 +284,7 @@ impl Lexer {
         let mut literal = String::from(first);
         while self
             .peek()
-            .map_or(false, |c| c.is_ascii_alphanumeric() || c == '_')
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
         {
             literal.push(self.advance());
         }
@@ -219,6 +297,8 @@ impl Lexer {
         Ok(Token {
             kind,
             lexeme: literal,
+            start: 0,
+            end: 0,
         })
     }
 
@@ -231,6 +311,8 @@ impl Lexer {
                     return Ok(Token {
                         kind: TokenKind::String,
                         lexeme: literal,
+                        start: 0,
+                        end: 0,
                     })
                 }
                 '\\' => {
@@ -293,55 +375,153 @@ impl Lexer {
 }
 
 pub fn parse_expression(src: &str) -> ExprResult<Expr> {
+    parse_expression_spanned(src).map(|(expr, _)| expr)
+}
+
+/// Character range of an expression within its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ExprSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parses `src` and returns the span of every node in post-order: children
+/// before their parent, in the order of [Expr::children].
+pub(crate) fn parse_expression_spanned(src: &str) -> ExprResult<(Expr, Vec<ExprSpan>)> {
     let mut lexer = Lexer::new(src);
     let tokens = lexer.lex()?;
     let mut parser = Parser::new(tokens);
     let expr = parser.parse_expression()?;
-    parser.expect(TokenKind::EOF)?;
-    Ok(expr)
+    parser.expect(TokenKind::Eof)?;
+    debug_assert_eq!(parser.spans.len(), expr.node_count());
+    Ok((expr, parser.spans))
+}
+
+impl Expr {
+    /// Direct subexpressions, in evaluation order.
+    pub(crate) fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Literal(_) | Expr::Var(_) => Vec::new(),
+            Expr::Unary(_, inner) => vec![inner],
+            Expr::Binary(left, _, right) | Expr::Coalesce(left, right) => vec![left, right],
+            Expr::Call(_, args) => args.iter().collect(),
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                vec![condition, then_branch, else_branch]
+            }
+            Expr::Member { object, .. } => vec![object],
+            Expr::Index { object, index, .. } => vec![object, index],
+            Expr::Lambda(_, body) => vec![body],
+        }
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Expr::node_count)
+            .sum::<usize>()
+    }
 }
 
 struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    spans: Vec<ExprSpan>,
 }
 
 impl Parser {
     fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self {
+            tokens,
+            pos: 0,
+            spans: Vec::new(),
+        }
+    }
+
+    fn start(&self) -> usize {
+        self.peek().start
+    }
+
+    /// Records the span of `expr`, from `start` to the last consumed token.
+    fn finish(&mut self, expr: Expr, start: usize) -> Expr {
+        let end = self.tokens[self.pos - 1].end;
+        self.spans.push(ExprSpan { start, end });
+        expr
     }
 
     fn parse_expression(&mut self) -> ExprResult<Expr> {
-        self.parse_or()
+        self.parse_conditional()
+    }
+
+    fn parse_conditional(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
+        let condition = self.parse_coalesce()?;
+        if !self.match_kind(TokenKind::Question) {
+            return Ok(condition);
+        }
+        let then_branch = self.parse_expression()?;
+        self.expect(TokenKind::Colon)?;
+        let else_branch = self.parse_conditional()?;
+        let expr = Expr::Conditional(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        );
+        Ok(self.finish(expr, start))
+    }
+
+    fn parse_coalesce(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
+        let mut expr = self.parse_or()?;
+        while self.match_kind(TokenKind::QuestionQuestion) {
+            let rhs = self.parse_or()?;
+            expr = self.finish(Expr::Coalesce(Box::new(expr), Box::new(rhs)), start);
+        }
+        Ok(expr)
     }
 
     fn parse_or(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_and()?;
         while self.match_kind(TokenKind::OrOr) {
             let rhs = self.parse_and()?;
-            expr = Expr::Binary(Box::new(expr), BinaryOp::Or, Box::new(rhs));
+            expr = self.finish(
+                Expr::Binary(Box::new(expr), BinaryOp::Or, Box::new(rhs)),
+                start,
+            );
         }
         Ok(expr)
     }
 
     fn parse_and(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_equality()?;
         while self.match_kind(TokenKind::AndAnd) {
             let rhs = self.parse_equality()?;
-            expr = Expr::Binary(Box::new(expr), BinaryOp::And, Box::new(rhs));
+            expr = self.finish(
+                Expr::Binary(Box::new(expr), BinaryOp::And, Box::new(rhs)),
+                start,
+            );
         }
         Ok(expr)
     }
 
     fn parse_equality(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_comparison()?;
         loop {
             if self.match_kind(TokenKind::EqEq) {
                 let rhs = self.parse_comparison()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Eq, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Eq, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::NotEq) {
                 let rhs = self.parse_comparison()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::NotEq, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::NotEq, Box::new(rhs)),
+                    start,
+                );
             } else {
                 break;
             }
@@ -350,20 +530,33 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_term()?;
         loop {
             if self.match_kind(TokenKind::Lt) {
                 let rhs = self.parse_term()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Lt, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Lt, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::Lte) {
                 let rhs = self.parse_term()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Lte, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Lte, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::Gt) {
                 let rhs = self.parse_term()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Gt, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Gt, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::Gte) {
                 let rhs = self.parse_term()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Gte, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Gte, Box::new(rhs)),
+                    start,
+                );
             } else {
                 break;
             }
@@ -372,14 +565,21 @@ impl Parser {
     }
 
     fn parse_term(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_factor()?;
         loop {
             if self.match_kind(TokenKind::Plus) {
                 let rhs = self.parse_factor()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Add, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Add, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::Minus) {
                 let rhs = self.parse_factor()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Sub, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Sub, Box::new(rhs)),
+                    start,
+                );
             } else {
                 break;
             }
@@ -388,14 +588,21 @@ impl Parser {
     }
 
     fn parse_factor(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_unary()?;
         loop {
             if self.match_kind(TokenKind::Star) {
                 let rhs = self.parse_unary()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Mul, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Mul, Box::new(rhs)),
+                    start,
+                );
             } else if self.match_kind(TokenKind::Slash) {
                 let rhs = self.parse_unary()?;
-                expr = Expr::Binary(Box::new(expr), BinaryOp::Div, Box::new(rhs));
+                expr = self.finish(
+                    Expr::Binary(Box::new(expr), BinaryOp::Div, Box::new(rhs)),
+                    start,
+                );
             } else {
                 break;
             }
@@ -404,27 +611,39 @@ impl Parser {
     }
 
     fn parse_unary(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         if self.match_kind(TokenKind::Bang) {
             let expr = self.parse_unary()?;
-            return Ok(Expr::Unary(UnaryOp::Not, Box::new(expr)));
+            return Ok(self.finish(Expr::Unary(UnaryOp::Not, Box::new(expr)), start));
         }
         if self.match_kind(TokenKind::Minus) {
             let expr = self.parse_unary()?;
-            return Ok(Expr::Unary(UnaryOp::Negate, Box::new(expr)));
+            return Ok(self.finish(Expr::Unary(UnaryOp::Negate, Box::new(expr)), start));
         }
         self.parse_call()
     }
 
     fn parse_call(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut expr = self.parse_primary()?;
         loop {
             if self.match_kind(TokenKind::LParen) {
-                let args = self.parse_arguments()?;
-                let callee_name = match expr {
-                    Expr::Var(ref path) if path.len() == 1 => path[0].clone(),
-                    Expr::Var(_) => {
+                let callee_span = self.spans.len() - 1;
+                let mut args = self.parse_arguments()?;
+                // `receiver.name(args)` is sugar for `name(receiver, args)`.
+                let (callee_name, receiver) = match expr {
+                    Expr::Var(mut path) => {
+                        let name = path.pop().expect("variables have a name");
+                        (name, (!path.is_empty()).then_some(Expr::Var(path)))
+                    }
+                    Expr::Member {
+                        object,
+                        property,
+                        optional: false,
+                    } => (property, Some(*object)),
+                    Expr::Member { optional: true, .. } => {
                         return Err(ExprError::UnexpectedToken(
-                            "method-style calls are not supported".into(),
+                            "null-safe method calls are not supported".into(),
                         ))
                     }
                     _ => {
@@ -433,7 +652,43 @@ impl Parser {
                         ))
                     }
                 };
-                expr = Expr::Call(callee_name, args);
+                // The callee's own span goes; a variable receiver keeps it,
+                // minus `.name`, as it already sits ahead of the arguments.
+                match receiver {
+                    Some(receiver @ Expr::Var(_)) => {
+                        let span = &mut self.spans[callee_span];
+                        span.end = span.end.saturating_sub(callee_name.chars().count() + 1);
+                        args.insert(0, receiver);
+                    }
+                    Some(receiver) => {
+                        self.spans.remove(callee_span);
+                        args.insert(0, receiver);
+                    }
+                    None => {
+                        self.spans.remove(callee_span);
+                    }
+                }
+                expr = self.finish(Expr::Call(callee_name, args), start);
+            } else if self.match_kind(TokenKind::Dot) {
+                let property = self.expect(TokenKind::Identifier)?.lexeme;
+                let member = Expr::Member {
+                    object: Box::new(expr),
+                    property,
+                    optional: false,
+                };
+                expr = self.finish(member, start);
+            } else if self.match_kind(TokenKind::LBracket) {
+                expr = self.parse_index(expr, false, start)?;
+            } else if self.match_kind(TokenKind::QuestionBracket) {
+                expr = self.parse_index(expr, true, start)?;
+            } else if self.match_kind(TokenKind::QuestionDot) {
+                let property = self.expect(TokenKind::Identifier)?.lexeme;
+                let member = Expr::Member {
+                    object: Box::new(expr),
+                    property,
+                    optional: true,
+                };
+                expr = self.finish(member, start);
             } else {
                 break;
             }
@@ -441,43 +696,88 @@ impl Parser {
         Ok(expr)
     }
 
+    fn parse_index(&mut self, object: Expr, optional: bool, start: usize) -> ExprResult<Expr> {
+        let index = self.parse_expression()?;
+        self.expect(TokenKind::RBracket)?;
+        let expr = Expr::Index {
+            object: Box::new(object),
+            index: Box::new(index),
+            optional,
+        };
+        Ok(self.finish(expr, start))
+    }
+
     fn parse_primary(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
+        if let Some((params, body_start)) = self.lambda_params() {
+            self.pos = body_start;
+            let body = self.parse_expression()?;
+            return Ok(self.finish(Expr::Lambda(params, Box::new(body)), start));
+        }
         if self.match_kind(TokenKind::LParen) {
             let expr = self.parse_expression()?;
             self.expect(TokenKind::RParen)?;
             return Ok(expr);
         }
 
-        match self.peek_kind().clone() {
+        let literal = match self.peek_kind().clone() {
             TokenKind::Number(value) => {
                 self.advance();
                 let number = Number::from_f64(value)
                     .ok_or_else(|| ExprError::InvalidLiteral(value.to_string()))?;
-                Ok(Expr::Literal(Value::Number(number)))
-            }
-            TokenKind::String => {
-                let lexeme = self.advance().lexeme;
-                Ok(Expr::Literal(Value::String(lexeme)))
+                Value::Number(number)
             }
+            TokenKind::String => Value::String(self.advance().lexeme),
             TokenKind::True => {
                 self.advance();
-                Ok(Expr::Literal(Value::Bool(true)))
+                Value::Bool(true)
             }
             TokenKind::False => {
                 self.advance();
-                Ok(Expr::Literal(Value::Bool(false)))
+                Value::Bool(false)
             }
             TokenKind::Null => {
                 self.advance();
-                Ok(Expr::Literal(Value::Null))
+                Value::Null
             }
-            TokenKind::Identifier => self.parse_variable(),
-            TokenKind::EOF => Err(ExprError::UnexpectedEof),
-            other => Err(ExprError::UnexpectedToken(format!("{other:?}"))),
+            TokenKind::Identifier => return self.parse_variable(),
+            TokenKind::Eof => return Err(ExprError::UnexpectedEof),
+            other => return Err(ExprError::UnexpectedToken(format!("{other:?}"))),
+        };
+        Ok(self.finish(Expr::Literal(literal), start))
+    }
+
+    /// Looks ahead for `name =>` or `(a, b) =>`, returning the parameters and
+    /// the position of the lambda body.
+    fn lambda_params(&self) -> Option<(Vec<String>, usize)> {
+        let kind = |offset: usize| self.tokens.get(self.pos + offset).map(|t| &t.kind);
+        if kind(0) == Some(&TokenKind::Identifier) && kind(1) == Some(&TokenKind::Arrow) {
+            return Some((vec![self.peek().lexeme.clone()], self.pos + 2));
         }
+        if kind(0) != Some(&TokenKind::LParen) {
+            return None;
+        }
+        let mut params = Vec::new();
+        let mut offset = 1;
+        if kind(offset) != Some(&TokenKind::RParen) {
+            loop {
+                if kind(offset) != Some(&TokenKind::Identifier) {
+                    return None;
+                }
+                params.push(self.tokens[self.pos + offset].lexeme.clone());
+                offset += 1;
+                match kind(offset) {
+                    Some(TokenKind::Comma) => offset += 1,
+                    Some(TokenKind::RParen) => break,
+                    _ => return None,
+                }
+            }
+        }
+        (kind(offset + 1) == Some(&TokenKind::Arrow)).then_some((params, self.pos + offset + 2))
     }
 
     fn parse_variable(&mut self) -> ExprResult<Expr> {
+        let start = self.start();
         let mut path = Vec::new();
         let ident = self.advance();
         path.push(ident.lexeme);
@@ -485,7 +785,7 @@ impl Parser {
             let next = self.expect(TokenKind::Identifier)?;
             path.push(next.lexeme);
         }
-        Ok(Expr::Var(path))
+        Ok(self.finish(Expr::Var(path), start))
     }
 
     fn parse_arguments(&mut self) -> ExprResult<Vec<Expr>> {
@@ -542,59 +842,60 @@ impl Parser {
 
 impl TokenKind {
     fn kind_eq(&self, other: &TokenKind) -> bool {
-        use TokenKind::*;
-        match (self, other) {
-            (Identifier, Identifier)
-            | (String, String)
-            | (True, True)
-            | (False, False)
-            | (Null, Null)
-            | (Plus, Plus)
-            | (Minus, Minus)
-            | (Star, Star)
-            | (Slash, Slash)
-            | (Bang, Bang)
-            | (AndAnd, AndAnd)
-            | (OrOr, OrOr)
-            | (EqEq, EqEq)
-            | (NotEq, NotEq)
-            | (Lt, Lt)
-            | (Lte, Lte)
-            | (Gt, Gt)
-            | (Gte, Gte)
-            | (LParen, LParen)
-            | (RParen, RParen)
-            | (Comma, Comma)
-            | (Dot, Dot)
-            | (EOF, EOF) => true,
-            (Number(_), Number(_)) => true,
-            _ => false,
-        }
+        std::mem::discriminant(self) == std::mem::discriminant(other)
     }
 }
 
 pub struct EvalContext<'a> {
     variables: &'a Value,
     now: DateTime<Utc>,
+    /// Lambda parameters bound by this scope; outer scopes are reached
+    /// through `parent`.
+    locals: Vec<(String, Value)>,
+    parent: Option<&'a EvalContext<'a>>,
 }
 
 impl<'a> EvalContext<'a> {
     pub fn new(variables: &'a Value, now: DateTime<Utc>) -> Self {
-        Self { variables, now }
+        Self {
+            variables,
+            now,
+            locals: Vec::new(),
+            parent: None,
+        }
     }
 
     pub fn with_now(variables: &'a Value) -> Self {
-        Self {
-            variables,
-            now: Utc::now(),
+        Self::new(variables, Utc::now())
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    /// A nested scope where `locals` shadow outer names and variables.
+    fn scoped(&self, locals: Vec<(String, Value)>) -> EvalContext<'_> {
+        EvalContext {
+            variables: self.variables,
+            now: self.now,
+            locals,
+            parent: Some(self),
         }
     }
+
+    fn local(&self, name: &str) -> Option<&Value> {
+        self.locals
+            .iter()
+            .find(|(local, _)| local == name)
+            .map(|(_, value)| value)
+            .or_else(|| self.parent?.local(name))
+    }
 }
 
 pub fn eval_expression(expr: &Expr, ctx: &EvalContext<'_>) -> ExprResult<Value> {
     match expr {
         Expr::Literal(v) => Ok(v.clone()),
-        Expr::Var(path) => Ok(lookup_variable(ctx.variables, path)),
+        Expr::Var(path) => Ok(lookup_variable(ctx, path)),
         Expr::Unary(op, inner) => {
             let value = eval_expression(inner, ctx)?;
             match op {
@@ -609,6 +910,56 @@ pub fn eval_expression(expr: &Expr, ctx: &EvalContext<'_>) -> ExprResult<Value>
         }
         Expr::Binary(left, op, right) => eval_binary(left, *op, right, ctx),
         Expr::Call(name, args) => eval_call(name, args, ctx),
+        Expr::Conditional(condition, then_branch, else_branch) => {
+            if truthy(&eval_expression(condition, ctx)?) {
+                eval_expression(then_branch, ctx)
+            } else {
+                eval_expression(else_branch, ctx)
+            }
+        }
+        Expr::Coalesce(value, fallback) => match eval_expression(value, ctx)? {
+            Value::Null => eval_expression(fallback, ctx),
+            value => Ok(value),
+        },
+        Expr::Member {
+            object, property, ..
+        } => match eval_expression(object, ctx)? {
+            Value::Object(map) => Ok(map.get(property).cloned().unwrap_or(Value::Null)),
+            _ => Ok(Value::Null),
+        },
+        Expr::Index { object, index, .. } => {
+            let target = eval_expression(object, ctx)?;
+            let index = eval_expression(index, ctx)?;
+            eval_index(target, index)
+        }
+        Expr::Lambda(..) => Err(ExprError::Evaluation(
+            "a lambda can only be passed to a list operation such as map()".into(),
+        )),
+    }
+}
+
+/// `target[index]`. Indexing null yields null, as reading a field of null
+/// does; any other mismatch between target and index is an error.
+fn eval_index(target: Value, index: Value) -> ExprResult<Value> {
+    match (target, index) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Array(items), Value::Number(position)) => {
+            let position = position
+                .as_f64()
+                .filter(|value| value.fract() == 0.0 && *value >= 0.0)
+                .ok_or_else(|| {
+                    ExprError::Evaluation(format!(
+                        "array index must be a non-negative integer, got {position}"
+                    ))
+                })?;
+            Ok(items.get(position as usize).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Object(map), Value::String(key)) => {
+            Ok(map.get(&key).cloned().unwrap_or(Value::Null))
+        }
+        _ => Err(ExprError::Evaluation(
+            "indexing requires an array with a number or an object with a string".into(),
+        )),
     }
 }
 
@@ -657,7 +1008,7 @@ fn eval_binary(
         BinaryOp::Eq | BinaryOp::NotEq => {
             let lhs = eval_expression(left, ctx)?;
             let rhs = eval_expression(right, ctx)?;
-            let eq = lhs == rhs;
+            let eq = values_equal(&lhs, &rhs);
             Ok(Value::Bool(if op == BinaryOp::Eq { eq } else { !eq }))
         }
         BinaryOp::Lt | BinaryOp::Lte | BinaryOp::Gt | BinaryOp::Gte => {
@@ -699,46 +1050,196 @@ fn compare_values(op: BinaryOp, lhs: &Value, rhs: &Value) -> ExprResult<Value> {
 }
 
 fn eval_call(name: &str, args: &[Expr], ctx: &EvalContext<'_>) -> ExprResult<Value> {
-    let lower = name.to_ascii_lowercase();
-    match lower.as_str() {
-        "concat" => {
-            let mut result = String::new();
-            for arg in args {
-                let value = eval_expression(arg, ctx)?;
-                result.push_str(&value_to_string(&value));
-            }
-            Ok(Value::String(result))
-        }
-        "len" => {
-            if args.len() != 1 {
+    if let Some(operation) = list_operation(name) {
+        return eval_list_operation(operation, args, ctx);
+    }
+    let function =
+        get_function(name).ok_or_else(|| ExprError::UnknownFunction(name.to_string()))?;
+    let values = args
+        .iter()
+        .map(|arg| eval_expression(arg, ctx))
+        .collect::<ExprResult<Vec<_>>>()?;
+    function.call(&values, ctx)
+}
+
+/// List operations take a lambda, which must not be evaluated up front like
+/// the arguments of registry functions, so they are built into the evaluator.
+pub(crate) const LIST_OPERATIONS: &[&str] = &[
+    "map", "filter", "reduce", "sortBy", "groupBy", "any", "all", "find",
+];
+
+pub(crate) fn list_operation(name: &str) -> Option<&'static str> {
+    LIST_OPERATIONS
+        .iter()
+        .copied()
+        .find(|operation| operation.eq_ignore_ascii_case(name))
+}
+
+/// Runs `map`, `filter`, `reduce`, `sortBy`, `groupBy`, `any`, `all` or
+/// `find`. Lambdas receive `(item, index)`, and `reduce` lambdas
+/// `(accumulator, item, index)`; trailing parameters may be omitted.
+fn eval_list_operation(
+    operation: &'static str,
+    args: &[Expr],
+    ctx: &EvalContext<'_>,
+) -> ExprResult<Value> {
+    let reduce = operation == "reduce";
+    let max_args = if reduce { 3 } else { 2 };
+    if args.len() < 2 || args.len() > max_args {
+        return Err(ExprError::Arity {
+            function: operation.to_string(),
+            expected: if reduce {
+                "2 to 3 arguments".into()
+            } else {
+                "2 arguments".into()
+            },
+            found: args.len(),
+        });
+    }
+    let items = match eval_expression(&args[0], ctx)? {
+        Value::Array(items) => items,
+        other => {
+            return Err(ExprError::ArgumentType {
+                function: operation.to_string(),
+                position: 1,
+                expected: "an array".into(),
+                found: ValueType::of(&other).to_string(),
+            })
+        }
+    };
+    let Expr::Lambda(params, body) = &args[1] else {
+        let found = eval_expression(&args[1], ctx)
+            .map(|value| ValueType::of(&value).to_string())
+            .unwrap_or_else(|_| "an invalid expression".into());
+        return Err(ExprError::ArgumentType {
+            function: operation.to_string(),
+            position: 2,
+            expected: "a lambda such as `item => item.name`".into(),
+            found,
+        });
+    };
+    let (min_params, max_params) = if reduce { (2, 3) } else { (1, 2) };
+    if params.len() < min_params || params.len() > max_params {
+        return Err(ExprError::Evaluation(format!(
+            "{operation}() lambda takes {} parameters, got {}",
+            if reduce {
+                "2 to 3 (accumulator, item, index)"
+            } else {
+                "1 to 2 (item, index)"
+            },
+            params.len()
+        )));
+    }
+    let call = |bindings: Vec<Value>| {
+        let scope = ctx.scoped(params.iter().cloned().zip(bindings).collect());
+        eval_expression(body, &scope)
+    };
+    let index = |position: usize| Value::from(position as f64);
+
+    match operation {
+        "map" => items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| call(vec![item, index(i)]))
+            .collect::<ExprResult<Vec<_>>>()
+            .map(Value::Array),
+        "filter" => {
+            let mut kept = Vec::new();
+            for (i, item) in items.into_iter().enumerate() {
+                if truthy(&call(vec![item.clone(), index(i)])?) {
+                    kept.push(item);
+                }
+            }
+            Ok(Value::Array(kept))
+        }
+        "reduce" => {
+            let mut items = items.into_iter().enumerate();
+            let mut accumulator = match args.get(2) {
+                Some(initial) => eval_expression(initial, ctx)?,
+                None => items.next().map(|(_, first)| first).ok_or_else(|| {
+                    ExprError::Evaluation(
+                        "reduce() of an empty array needs an initial value".into(),
+                    )
+                })?,
+            };
+            for (i, item) in items {
+                accumulator = call(vec![accumulator, item, index(i)])?;
+            }
+            Ok(accumulator)
+        }
+        "sortBy" => {
+            let mut keyed = items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| Ok((call(vec![item.clone(), index(i)])?, item)))
+                .collect::<ExprResult<Vec<_>>>()?;
+            let keys: Vec<&Value> = keyed.iter().map(|(key, _)| key).collect();
+            let numbers = keys.iter().all(|key| key.is_number() || key.is_null());
+            let strings = keys.iter().all(|key| key.is_string() || key.is_null());
+            if !numbers && !strings {
                 return Err(ExprError::Evaluation(
-                    "len() expects exactly one argument".into(),
+                    "sortBy() keys must all be numbers or all be strings".into(),
                 ));
             }
-            let value = eval_expression(&args[0], ctx)?;
-            let length = match value {
-                Value::String(s) => s.chars().count() as f64,
-                Value::Array(arr) => arr.len() as f64,
-                _ => {
-                    return Err(ExprError::Evaluation(
-                        "len() supports only string or array arguments".into(),
-                    ))
+            // Stable, with null keys last.
+            keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+                (Value::Null, Value::Null) => Ordering::Equal,
+                (Value::Null, _) => Ordering::Greater,
+                (_, Value::Null) => Ordering::Less,
+                (Value::String(a), Value::String(b)) => a.cmp(b),
+                (a, b) => a
+                    .as_f64()
+                    .partial_cmp(&b.as_f64())
+                    .unwrap_or(Ordering::Equal),
+            });
+            Ok(Value::Array(
+                keyed.into_iter().map(|(_, item)| item).collect(),
+            ))
+        }
+        "groupBy" => {
+            let mut groups = Map::new();
+            for (i, item) in items.into_iter().enumerate() {
+                let key = value_to_string(&call(vec![item.clone(), index(i)])?);
+                match groups
+                    .entry(key)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                {
+                    Value::Array(group) => group.push(item),
+                    _ => unreachable!("groups hold arrays"),
                 }
-            };
-            to_json_number(length)
+            }
+            Ok(Value::Object(groups))
         }
-        "now" => {
-            if !args.is_empty() {
-                return Err(ExprError::Evaluation("now() takes no arguments".into()));
+        "any" | "all" | "find" => {
+            for (i, item) in items.into_iter().enumerate() {
+                let matched = truthy(&call(vec![item.clone(), index(i)])?);
+                match operation {
+                    "any" if matched => return Ok(Value::Bool(true)),
+                    "all" if !matched => return Ok(Value::Bool(false)),
+                    "find" if matched => return Ok(item),
+                    _ => {}
+                }
             }
-            Ok(Value::String(ctx.now.to_rfc3339()))
+            Ok(match operation {
+                "any" => Value::Bool(false),
+                "all" => Value::Bool(true),
+                _ => Value::Null,
+            })
         }
-        _ => Err(ExprError::Evaluation(format!("unknown function {name}"))),
+        _ => unreachable!("unknown list operation {operation}"),
     }
 }
 
-fn lookup_variable(root: &Value, path: &[String]) -> Value {
-    let mut current = root;
+/// Resolves a dotted path, starting from a lambda parameter when the first
+/// segment names one in scope and from the context variables otherwise.
+fn lookup_variable(ctx: &EvalContext<'_>, path: &[String]) -> Value {
+    let (mut current, path) = match path.split_first() {
+        Some((first, rest)) => match ctx.local(first) {
+            Some(value) => (value, rest),
+            None => (ctx.variables, path),
+        },
+        None => (ctx.variables, path),
+    };
     for segment in path {
         match current {
             Value::Object(map) => match map.get(segment) {
@@ -751,6 +1252,23 @@ fn lookup_variable(root: &Value, path: &[String]) -> Value {
     current.clone()
 }
 
+/// Structural equality that compares numbers by value, so the `2` of a
+/// literal (parsed as a float) equals the `2` of a JSON integer.
+pub(crate) fn values_equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64() == r.as_f64(),
+        (Value::Array(l), Value::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| values_equal(l, r))
+        }
+        (Value::Object(l), Value::Object(r)) => {
+            l.len() == r.len()
+                && l.iter()
+                    .all(|(key, l)| r.get(key).is_some_and(|r| values_equal(l, r)))
+        }
+        _ => lhs == rhs,
+    }
+}
+
 fn to_number(value: &Value) -> ExprResult<f64> {
     match value {
         Value::Number(num) => num
@@ -764,23 +1282,38 @@ fn truthy(value: &Value) -> bool {
     match value {
         Value::Bool(b) => *b,
         Value::Null => false,
-        Value::Number(n) => n.as_f64().map_or(false, |v| v != 0.0),
+        Value::Number(n) => n.as_f64().is_some_and(|v| v != 0.0),
         Value::String(s) => !s.is_empty(),
         Value::Array(arr) => !arr.is_empty(),
         Value::Object(obj) => !obj.is_empty(),
     }
 }
 
-fn value_to_string(value: &Value) -> String {
+pub(crate) fn value_to_string(value: &Value) -> String {
     match value {
         Value::Null => "null".into(),
         Value::Bool(b) => b.to_string(),
-        Value::Number(n) => n.to_string(),
+        Value::Number(n) => number_to_string(n),
         Value::String(s) => s.clone(),
         Value::Array(_) | Value::Object(_) => value.to_string(),
     }
 }
 
+/// Formats `number` the way generated code does: every number is a float, and
+/// whole ones print without the `.0`.
+pub(crate) fn number_to_string(number: &Number) -> String {
+    match number.as_f64() {
+        Some(float) if float.fract() == 0.0 && float.abs() < 1e15 => (float as i64).to_string(),
+        _ => number.to_string(),
+    }
+}
+
+fn to_json_number(value: f64) -> ExprResult<Value> {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| ExprError::Evaluation("invalid number value".into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -798,6 +1331,210 @@ mod tests {
         assert_eq!(result, json!(7.0));
     }
 
+    #[test]
+    fn conditional_binds_looser_than_coalesce_and_or() {
+        assert_eq!(
+            parse_expression("a || b ? c ?? d : e").unwrap(),
+            Expr::Conditional(
+                Box::new(Expr::Binary(
+                    Box::new(Expr::Var(vec!["a".into()])),
+                    BinaryOp::Or,
+                    Box::new(Expr::Var(vec!["b".into()])),
+                )),
+                Box::new(Expr::Coalesce(
+                    Box::new(Expr::Var(vec!["c".into()])),
+                    Box::new(Expr::Var(vec!["d".into()])),
+                )),
+                Box::new(Expr::Var(vec!["e".into()])),
+            )
+        );
+        assert_eq!(
+            eval("1 + 1 == 2 ? \"yes\" : \"no\"", Value::Null),
+            json!("yes")
+        );
+        let vars = json!({"score": 42});
+        assert_eq!(
+            eval("score > 90 ? \"A\" : score > 40 ? \"B\" : \"C\"", vars),
+            json!("B")
+        );
+    }
+
+    #[test]
+    fn coalesce_and_optional_chaining() {
+        let guest = json!({"user": null});
+        assert_eq!(
+            eval("user?.name ?? \"Guest\"", guest.clone()),
+            json!("Guest")
+        );
+        assert_eq!(eval("user?.address.city", guest), Value::Null);
+        let vars = json!({"user": {"name": "Ada", "nick": null}});
+        assert_eq!(eval("user?.name ?? \"Guest\"", vars.clone()), json!("Ada"));
+        assert_eq!(eval("user.nick ?? user.name", vars.clone()), json!("Ada"));
+        assert_eq!(eval("false ?? true", vars.clone()), json!(false));
+
+        // Reading through null yields null whether the path is a dotted name
+        // or an access on another expression.
+        assert_eq!(eval("user.nick.foo", vars.clone()), Value::Null);
+        assert_eq!(eval("(user.nick).foo", vars.clone()), Value::Null);
+        assert_eq!(eval("(user.nick)[0]", vars.clone()), Value::Null);
+        assert_eq!(eval("(user.name).foo", vars), Value::Null);
+    }
+
+    #[test]
+    fn indexing_arrays_and_objects() {
+        let vars = json!({
+            "items": [{"price": 5}, {"price": 7}],
+            "prices": {"small": 1},
+            "key": "small",
+        });
+        assert_eq!(
+            eval("items[0].price + items[1].price", vars.clone()),
+            json!(12.0)
+        );
+        assert_eq!(eval("prices[key]", vars.clone()), json!(1));
+        assert_eq!(eval("items[1].price == 7", vars.clone()), json!(true));
+        assert_eq!(eval("items[5]?.price ?? 0", vars.clone()), json!(0.0));
+        assert_eq!(eval("missing?[0]", vars.clone()), Value::Null);
+        assert_eq!(eval("items[5].price", vars.clone()), Value::Null);
+        assert!(parse_expression("missing?.[0]").is_err());
+        assert_eq!(
+            parse_expression("items[len(items) - 1].price").unwrap(),
+            Expr::Member {
+                object: Box::new(Expr::Index {
+                    object: Box::new(Expr::Var(vec!["items".into()])),
+                    index: Box::new(Expr::Binary(
+                        Box::new(Expr::Call(
+                            "len".into(),
+                            vec![Expr::Var(vec!["items".into()])]
+                        )),
+                        BinaryOp::Sub,
+                        Box::new(Expr::Literal(json!(1.0))),
+                    )),
+                    optional: false,
+                }),
+                property: "price".into(),
+                optional: false,
+            }
+        );
+
+        let ctx = EvalContext::with_now(&vars);
+        for src in ["items[0.5]", "prices[0]"] {
+            let err = eval_expression(&parse_expression(src).unwrap(), &ctx).unwrap_err();
+            assert!(matches!(err, ExprError::Evaluation(_)), "{src}");
+        }
+    }
+
+    #[test]
+    fn lambdas_and_method_calls_parse() {
+        assert_eq!(
+            parse_expression("items.filter(i => i.done)").unwrap(),
+            Expr::Call(
+                "filter".into(),
+                vec![
+                    Expr::Var(vec!["items".into()]),
+                    Expr::Lambda(
+                        vec!["i".into()],
+                        Box::new(Expr::Var(vec!["i".into(), "done".into()]))
+                    ),
+                ]
+            )
+        );
+        assert_eq!(
+            parse_expression("reduce(xs, (a, x) => a + x, 0)").unwrap(),
+            Expr::Call(
+                "reduce".into(),
+                vec![
+                    Expr::Var(vec!["xs".into()]),
+                    Expr::Lambda(
+                        vec!["a".into(), "x".into()],
+                        Box::new(Expr::Binary(
+                            Box::new(Expr::Var(vec!["a".into()])),
+                            BinaryOp::Add,
+                            Box::new(Expr::Var(vec!["x".into()])),
+                        ))
+                    ),
+                    Expr::Literal(json!(0.0)),
+                ]
+            )
+        );
+        assert_eq!(eval("(1 + 2) * 3", Value::Null), json!(9.0));
+        assert_eq!(
+            eval("user.name.upper()", json!({"user": {"name": "ada"}})),
+            json!("ADA")
+        );
+    }
+
+    #[test]
+    fn list_operations_with_lambdas() {
+        let vars = json!({
+            "todos": [
+                {"title": "b", "done": true, "prio": 2},
+                {"title": "a", "done": false, "prio": 1},
+                {"title": "c", "done": true, "prio": 1},
+            ],
+            "title": "shadowed",
+        });
+        for (src, expected) in [
+            (
+                "todos.filter(i => i.done).map(i => i.title)",
+                json!(["b", "c"]),
+            ),
+            ("todos.map((t, i) => i)", json!([0.0, 1.0, 2.0])),
+            (
+                "todos.sortBy(t => t.title).map(t => t.title)",
+                json!(["a", "b", "c"]),
+            ),
+            (
+                "todos.sortBy(t => t.prio).map(t => t.title)",
+                json!(["a", "c", "b"]),
+            ),
+            ("todos.any(t => !t.done)", json!(true)),
+            ("todos.all(t => t.done)", json!(false)),
+            ("todos.find(t => t.prio == 1).title", json!("a")),
+            (
+                "todos.find(t => t.prio > 5)?.title ?? \"none\"",
+                json!("none"),
+            ),
+            ("todos.map(t => t.prio).reduce((a, p) => a + p)", json!(4.0)),
+            (
+                "todos.map(t => todos.filter(o => o.prio == t.prio).len())",
+                json!([1.0, 2.0, 2.0]),
+            ),
+            ("todos.map(title => title.title).first()", json!("b")),
+        ] {
+            assert_eq!(eval(src, vars.clone()), expected, "{src}");
+        }
+        let groups = eval("groupBy(todos, t => t.prio)", vars.clone());
+        assert_eq!(groups["1"].as_array().unwrap().len(), 2);
+        assert_eq!(eval("title", vars), json!("shadowed"));
+    }
+
+    #[test]
+    fn list_operation_misuse_errors() {
+        let vars = json!({"items": [1, 2], "user": {}});
+        let ctx = EvalContext::with_now(&vars);
+        let message = |src: &str| {
+            eval_expression(&parse_expression(src).unwrap(), &ctx)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            message("user.map(u => u)"),
+            "map() argument 1 must be an array, got object"
+        );
+        assert_eq!(
+            message("items.filter(1)"),
+            "filter() argument 2 must be a lambda such as `item => item.name`, got number"
+        );
+        assert_eq!(message("items.any()"), "any() expects 2 arguments, got 1");
+        assert_eq!(
+            message("items.reduce(x => x)"),
+            "evaluation error: reduce() lambda takes 2 to 3 (accumulator, item, index) parameters, got 1"
+        );
+        assert!(message("x => x").contains("only be passed to a list operation"));
+        assert!(message("user.filter(x => x).map(x => x)").contains("must be an array"));
+    }
+
     #[test]
     fn boolean_logic() {
         let vars = json!({"user": {"active": true, "age": 21}});
@@ -848,9 +1585,3 @@ mod tests {
         assert!(matches!(err, ExprError::Evaluation(_)));
     }
 }
-
-fn to_json_number(value: f64) -> ExprResult<Value> {
-    Number::from_f64(value)
-        .map(Value::Number)
-        .ok_or_else(|| ExprError::Evaluation("invalid number value".into()))
-}
@@ -0,0 +1,1157 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::expr::{
+    list_operation, number_to_string, parse_expression, BinaryOp, Expr, ExprError, UnaryOp,
+};
+use crate::function_registry::get_function;
+use crate::state_adapter::StateAdapter;
+use crate::{BindingReference, BindingTarget, ProviderAccess};
+
+/// Language an expression is compiled to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodegenTarget {
+    Dart,
+    TypeScript,
+}
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error(transparent)]
+    Expression(#[from] ExprError),
+    #[error("provider '{provider}' is not resolved by the {adapter} state adapter")]
+    UnresolvedProvider {
+        provider: String,
+        adapter: &'static str,
+    },
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+/// Source code for one expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledExpression {
+    pub code: String,
+    /// Import directives the code and its helpers rely on.
+    pub imports: Vec<String>,
+    /// Definitions of the `forge*` helpers the code calls, to be emitted once
+    /// per generated file.
+    pub helpers: Vec<String>,
+}
+
+/// Compiles Forge expressions to Dart or TypeScript that evaluates like
+/// [crate::eval_expression].
+///
+/// Builtins map onto the equivalent library calls where those agree with the
+/// simulator, and onto small `forge*` helpers where they don't (truthiness,
+/// null-safe collection access, stable `sortBy`, `groupBy`, dates).
+/// `providers.<id>.<path>` reads go through the [StateAdapter], so Riverpod
+/// state compiles to `ref.watch(<id>Provider)?.<path>` in Dart; `input` reads
+/// the flow input. Expressions the simulator rejects at runtime, such as
+/// arithmetic on strings, are not guaranteed to fail the same way, so check
+/// them with [crate::check_expression] first.
+pub struct ExprCodegen<'a> {
+    target: CodegenTarget,
+    state_adapter: &'a dyn StateAdapter,
+    ref_name: String,
+    input_name: String,
+}
+
+impl<'a> ExprCodegen<'a> {
+    pub fn new(target: CodegenTarget, state_adapter: &'a dyn StateAdapter) -> Self {
+        Self {
+            target,
+            state_adapter,
+            ref_name: "ref".into(),
+            input_name: "input".into(),
+        }
+    }
+
+    /// Name of the Riverpod `WidgetRef` in Dart output.
+    pub fn with_ref_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.ref_name = name.into();
+        self
+    }
+
+    /// Name of the variable holding the flow input.
+    pub fn with_input_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.input_name = name.into();
+        self
+    }
+
+    pub fn compile(&self, source: &str) -> Result<CompiledExpression, CodegenError> {
+        self.compile_expr(&parse_expression(source)?)
+    }
+
+    pub fn compile_expr(&self, expr: &Expr) -> Result<CompiledExpression, CodegenError> {
+        let mut emitter = Emitter {
+            codegen: self,
+            locals: Vec::new(),
+            helpers: BTreeSet::new(),
+        };
+        let code = emitter.emit(expr)?;
+
+        let helpers: Vec<&Helper> = HELPERS
+            .iter()
+            .filter(|helper| emitter.helpers.contains(helper.name))
+            .collect();
+        let mut imports = BTreeSet::new();
+        for helper in &helpers {
+            imports.extend(match self.target {
+                CodegenTarget::Dart => helper.dart_imports,
+                CodegenTarget::TypeScript => helper.typescript_imports,
+            });
+        }
+        Ok(CompiledExpression {
+            code,
+            imports: imports.into_iter().map(str::to_string).collect(),
+            helpers: helpers
+                .iter()
+                .map(|helper| match self.target {
+                    CodegenTarget::Dart => helper.dart.to_string(),
+                    CodegenTarget::TypeScript => helper.typescript.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+type CodegenResult<T> = Result<T, CodegenError>;
+
+struct Emitter<'c, 'a> {
+    codegen: &'c ExprCodegen<'a>,
+    /// Lambda parameters in scope.
+    locals: Vec<String>,
+    helpers: BTreeSet<&'static str>,
+}
+
+impl Emitter<'_, '_> {
+    fn dart(&self) -> bool {
+        self.codegen.target == CodegenTarget::Dart
+    }
+
+    fn emit(&mut self, expr: &Expr) -> CodegenResult<String> {
+        match expr {
+            Expr::Literal(value) => Ok(self.literal(value)),
+            Expr::Var(path) => self.var(path),
+            Expr::Unary(UnaryOp::Negate, inner) => Ok(format!("-{}", self.receiver(inner)?)),
+            Expr::Unary(UnaryOp::Not, inner) => Ok(format!("!{}", self.condition_operand(inner)?)),
+            Expr::Binary(left, op, right) => self.binary(left, *op, right),
+            Expr::Call(name, args) => self.call(name, args),
+            Expr::Conditional(condition, then_branch, else_branch) => Ok(format!(
+                "{} ? {} : {}",
+                self.condition_operand(condition)?,
+                self.operand(then_branch)?,
+                self.operand(else_branch)?
+            )),
+            Expr::Coalesce(value, fallback) => Ok(format!(
+                "{} ?? {}",
+                self.operand(value)?,
+                self.operand(fallback)?
+            )),
+            // The simulator reads through null, so every access is null-safe
+            // whether or not the expression spells `?.` or `?[`.
+            Expr::Member {
+                object, property, ..
+            } => Ok(format!("{}?.{property}", self.receiver(object)?)),
+            Expr::Index { object, index, .. } => Ok(format!(
+                "{}{}[{}]",
+                self.receiver(object)?,
+                if self.dart() { "?" } else { "?." },
+                self.emit(index)?
+            )),
+            Expr::Lambda(..) => Err(CodegenError::Unsupported(
+                "a lambda can only be passed to a list operation such as map()".into(),
+            )),
+        }
+    }
+
+    /// `expr`, parenthesized when it is an infix operator expression.
+    fn operand(&mut self, expr: &Expr) -> CodegenResult<String> {
+        let code = self.emit(expr)?;
+        Ok(if compound(expr) {
+            format!("({code})")
+        } else {
+            code
+        })
+    }
+
+    /// `expr` as the target of `.` or `[]`, which bind tighter than prefix
+    /// operators too.
+    fn receiver(&mut self, expr: &Expr) -> CodegenResult<String> {
+        if matches!(expr, Expr::Unary(..)) {
+            Ok(format!("({})", self.emit(expr)?))
+        } else {
+            self.operand(expr)
+        }
+    }
+
+    /// `expr` as a boolean, using the simulator's truthiness.
+    fn condition(&mut self, expr: &Expr) -> CodegenResult<String> {
+        if boolean(expr) {
+            self.emit(expr)
+        } else {
+            let code = self.emit(expr)?;
+            Ok(self.helper_call("forgeTruthy", &[code]))
+        }
+    }
+
+    fn condition_operand(&mut self, expr: &Expr) -> CodegenResult<String> {
+        if boolean(expr) {
+            self.operand(expr)
+        } else {
+            self.condition(expr)
+        }
+    }
+
+    fn helper_call(&mut self, name: &'static str, args: &[String]) -> String {
+        self.use_helper(name);
+        format!("{name}({})", args.join(", "))
+    }
+
+    fn use_helper(&mut self, name: &'static str) {
+        if self.helpers.insert(name) {
+            let helper = HELPERS
+                .iter()
+                .find(|helper| helper.name == name)
+                .expect("helpers are declared");
+            for required in helper.requires {
+                self.use_helper(required);
+            }
+        }
+    }
+
+    fn literal(&self, value: &Value) -> String {
+        match value {
+            Value::Number(number) => number_to_string(number),
+            Value::Null | Value::Bool(_) => value.to_string(),
+            // JSON string escapes are valid in both languages; Dart also
+            // interpolates `$`.
+            Value::String(_) if self.dart() => value.to_string().replace('$', "\\$"),
+            Value::String(_) => value.to_string(),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(|item| self.literal(item)).collect();
+                if self.dart() {
+                    format!("<dynamic>[{}]", items.join(", "))
+                } else {
+                    format!("[{}]", items.join(", "))
+                }
+            }
+            Value::Object(map) => {
+                let entries: Vec<String> = map
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            self.literal(&Value::String(key.clone())),
+                            self.literal(value)
+                        )
+                    })
+                    .collect();
+                if self.dart() {
+                    format!("<String, dynamic>{{{}}}", entries.join(", "))
+                } else {
+                    format!("{{{}}}", entries.join(", "))
+                }
+            }
+        }
+    }
+
+    /// Variable paths read missing fields as null, so every step after the
+    /// root is null-safe.
+    fn var(&mut self, path: &[String]) -> CodegenResult<String> {
+        let Some((root, rest)) = path.split_first() else {
+            return Err(CodegenError::Unsupported("empty variable path".into()));
+        };
+        if self.locals.contains(root) {
+            return Ok(field_chain(root.clone(), rest));
+        }
+        match root.as_str() {
+            "input" => Ok(field_chain(self.codegen.input_name.clone(), rest)),
+            "providers" => {
+                let Some((provider, rest)) = rest.split_first() else {
+                    return Err(CodegenError::Unsupported(
+                        "`providers` must be followed by a provider id".into(),
+                    ));
+                };
+                self.provider(provider, rest)
+            }
+            other => Err(CodegenError::Unsupported(format!(
+                "unknown variable '{other}'"
+            ))),
+        }
+    }
+
+    /// Resolves `providers.<id>.<path>` through the state adapter, trying the
+    /// id as written and then with the conventional `Provider` suffix.
+    fn provider(&self, provider: &str, path: &[String]) -> CodegenResult<String> {
+        let adapter = self.codegen.state_adapter;
+        let resolved = [provider.to_string(), format!("{provider}Provider")]
+            .into_iter()
+            .find_map(|reference| {
+                adapter.resolve(&BindingReference {
+                    target: BindingTarget::Provider,
+                    reference: reference.clone(),
+                    provider_id: Some(reference),
+                    path: (!path.is_empty()).then(|| path.join(".")),
+                    type_hint: None,
+                    access: ProviderAccess::Watch,
+                    notifier: false,
+                })
+            })
+            .ok_or_else(|| CodegenError::UnresolvedProvider {
+                provider: provider.to_string(),
+                adapter: adapter.name(),
+            })?;
+
+        let state = if self.dart() {
+            format!(
+                "{}.{}({}{})",
+                self.codegen.ref_name,
+                resolved.access.as_str(),
+                resolved.provider_id,
+                if resolved.notifier { ".notifier" } else { "" }
+            )
+        } else {
+            resolved.provider_id
+        };
+        let path: Vec<String> = resolved
+            .path
+            .iter()
+            .flat_map(|path| path.split('.'))
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(field_chain(state, &path))
+    }
+
+    fn binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr) -> CodegenResult<String> {
+        let symbol = match op {
+            BinaryOp::And | BinaryOp::Or => {
+                return Ok(format!(
+                    "{} {} {}",
+                    self.condition_operand(left)?,
+                    if op == BinaryOp::And { "&&" } else { "||" },
+                    self.condition_operand(right)?
+                ))
+            }
+            BinaryOp::Eq | BinaryOp::NotEq => {
+                let negate = op == BinaryOp::NotEq;
+                // `undefined` stands in for a missing field, so loose
+                // equality is used against null.
+                let against_null = [left, right]
+                    .iter()
+                    .any(|side| matches!(side, Expr::Literal(Value::Null)));
+                match (self.dart() || against_null, negate) {
+                    (true, false) => "==",
+                    (true, true) => "!=",
+                    (false, false) => "===",
+                    (false, true) => "!==",
+                }
+            }
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Lt => "<",
+            BinaryOp::Lte => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Gte => ">=",
+        };
+        // Dart strings have no relational operators.
+        if self.dart()
+            && matches!(
+                op,
+                BinaryOp::Lt | BinaryOp::Lte | BinaryOp::Gt | BinaryOp::Gte
+            )
+        {
+            let (left, right) = (self.receiver(left)?, self.emit(right)?);
+            return Ok(format!("{left}.compareTo({right}) {symbol} 0"));
+        }
+        let (left, right) = (self.operand(left)?, self.operand(right)?);
+        Ok(format!("{left} {symbol} {right}"))
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> CodegenResult<String> {
+        if let Some(operation) = list_operation(name) {
+            return self.list_operation(operation, args);
+        }
+        let function =
+            get_function(name).ok_or_else(|| ExprError::UnknownFunction(name.to_string()))?;
+        function.check_arity(args.len())?;
+        if args.iter().any(|arg| matches!(arg, Expr::Lambda(..))) {
+            return Err(CodegenError::Unsupported(format!(
+                "{}() does not take a lambda",
+                function.name
+            )));
+        }
+        let values = args
+            .iter()
+            .map(|arg| self.emit(arg))
+            .collect::<CodegenResult<Vec<_>>>()?;
+        let receiver = match args.first() {
+            Some(first) => self.receiver(first)?,
+            None => String::new(),
+        };
+        let dart = self.dart();
+        let method = |call: &str| format!("{receiver}.{call}");
+
+        Ok(match (function.name.as_str(), values.as_slice()) {
+            ("concat", []) => if dart { "''" } else { "\"\"" }.to_string(),
+            ("concat", [value]) => self.helper_call("forgeString", std::slice::from_ref(value)),
+            ("concat", values) => {
+                let parts: Vec<String> = values
+                    .iter()
+                    .map(|value| self.helper_call("forgeString", std::slice::from_ref(value)))
+                    .collect();
+                format!("({})", parts.join(" + "))
+            }
+            ("len", _) => method("length"),
+            ("now", _) if dart => "DateTime.now().toUtc().toIso8601String()".into(),
+            ("now", _) => "new Date().toISOString()".into(),
+            ("upper", _) => method("toUpperCase()"),
+            ("lower", _) => method("toLowerCase()"),
+            ("trim", _) => method("trim()"),
+            ("split", [_, separator]) => method(&format!("split({separator})")),
+            ("replace", [_, from, to]) => method(&format!("replaceAll({from}, {to})")),
+            ("startsWith", [_, prefix]) => method(&format!("startsWith({prefix})")),
+            ("contains", values) => self.helper_call("forgeContains", values),
+            ("round", [_]) if dart => method("round()"),
+            ("round", values) => self.helper_call("forgeRound", values),
+            ("floor", _) if dart => method("floor()"),
+            ("floor", [value]) => format!("Math.floor({value})"),
+            ("abs", _) if dart => method("abs()"),
+            ("abs", [value]) => format!("Math.abs({value})"),
+            ("min" | "max", values) => {
+                let helper = if function.name == "min" {
+                    "forgeMin"
+                } else {
+                    "forgeMax"
+                };
+                let values = match values {
+                    [value] => value.clone(),
+                    values => format!("[{}]", values.join(", ")),
+                };
+                self.helper_call(helper, &[values])
+            }
+            ("sum", _) if dart => method("fold<num>(0, (total, value) => total + value)"),
+            ("sum", _) => method("reduce((total: number, value: number) => total + value, 0)"),
+            ("keys", _) if dart => method("keys.toList()"),
+            ("keys", [value]) => format!("Object.keys({value})"),
+            ("values", _) if dart => method("values.toList()"),
+            ("values", [value]) => format!("Object.values({value})"),
+            ("first", values) => self.helper_call("forgeFirst", values),
+            ("last", values) => self.helper_call("forgeLast", values),
+            ("slice", values) if dart => self.helper_call("forgeSlice", values),
+            ("slice", [_, bounds @ ..]) => method(&format!("slice({})", bounds.join(", "))),
+            ("isEmpty", values) => self.helper_call("forgeIsEmpty", values),
+            ("formatDate", values) => self.helper_call("forgeFormatDate", values),
+            ("parseDate", values) => self.helper_call("forgeParseDate", values),
+            ("addDays", values) => self.helper_call("forgeAddDays", values),
+            ("toNumber", values) => self.helper_call("forgeToNumber", values),
+            ("toString", values) => self.helper_call("forgeString", values),
+            ("jsonParse", values) => self.helper_call("forgeJsonParse", values),
+            // Host functions keep their name; the generated code's
+            // environment provides them.
+            (name, values) => format!("{name}({})", values.join(", ")),
+        })
+    }
+
+    fn list_operation(&mut self, operation: &'static str, args: &[Expr]) -> CodegenResult<String> {
+        let reduce = operation == "reduce";
+        let max_args = if reduce { 3 } else { 2 };
+        if args.len() < 2 || args.len() > max_args {
+            return Err(ExprError::Arity {
+                function: operation.to_string(),
+                expected: if reduce {
+                    "2 to 3 arguments".into()
+                } else {
+                    "2 arguments".into()
+                },
+                found: args.len(),
+            }
+            .into());
+        }
+        let Expr::Lambda(params, body) = &args[1] else {
+            return Err(CodegenError::Unsupported(format!(
+                "{operation}() argument 2 must be a lambda such as `item => item.name`"
+            )));
+        };
+        let (min_params, max_params) = if reduce { (2, 3) } else { (1, 2) };
+        if params.len() < min_params || params.len() > max_params {
+            return Err(CodegenError::Unsupported(format!(
+                "{operation}() lambda takes {} parameters, got {}",
+                if reduce {
+                    "2 to 3 (accumulator, item, index)"
+                } else {
+                    "1 to 2 (item, index)"
+                },
+                params.len()
+            )));
+        }
+
+        let list = self.receiver(&args[0])?;
+        let initial = args.get(2).map(|initial| self.emit(initial)).transpose()?;
+        let scope = self.locals.len();
+        self.locals.extend(params.iter().cloned());
+        let body = if matches!(operation, "filter" | "any" | "all" | "find") {
+            self.condition(body)
+        } else {
+            self.emit(body)
+        };
+        self.locals.truncate(scope);
+        let body = body?;
+
+        if self.dart() {
+            self.dart_list_operation(operation, &list, params, &body, initial)
+        } else {
+            Ok(self.typescript_list_operation(operation, &list, params, &body, initial))
+        }
+    }
+
+    fn typescript_list_operation(
+        &mut self,
+        operation: &str,
+        list: &str,
+        params: &[String],
+        body: &str,
+        initial: Option<String>,
+    ) -> String {
+        let params: Vec<String> = params.iter().map(|param| format!("{param}: any")).collect();
+        let lambda = format!("({}) => {body}", params.join(", "));
+        match operation {
+            "map" => format!("{list}.map({lambda})"),
+            "filter" => format!("{list}.filter({lambda})"),
+            "any" => format!("{list}.some({lambda})"),
+            "all" => format!("{list}.every({lambda})"),
+            "find" => format!("({list}.find({lambda}) ?? null)"),
+            "reduce" => match initial {
+                Some(initial) => format!("{list}.reduce({lambda}, {initial})"),
+                None => format!("{list}.reduce({lambda})"),
+            },
+            "sortBy" => self.helper_call("forgeSortBy", &[list.to_string(), lambda]),
+            _ => self.helper_call("forgeGroupBy", &[list.to_string(), lambda]),
+        }
+    }
+
+    /// Dart callbacks take no index, so lambdas using one iterate
+    /// `list.indexed` records instead.
+    fn dart_list_operation(
+        &mut self,
+        operation: &str,
+        list: &str,
+        params: &[String],
+        body: &str,
+        initial: Option<String>,
+    ) -> CodegenResult<String> {
+        if matches!(operation, "sortBy" | "groupBy") {
+            let index = params.get(1).map(String::as_str).unwrap_or("_");
+            let lambda = format!("({}, {index}) => {body}", params[0]);
+            let helper = if operation == "sortBy" {
+                "forgeSortBy"
+            } else {
+                "forgeGroupBy"
+            };
+            return Ok(self.helper_call(helper, &[list.to_string(), lambda]));
+        }
+
+        let (accumulator, params) = if operation == "reduce" {
+            (Some(params[0].as_str()), &params[1..])
+        } else {
+            (None, params)
+        };
+        let item = &params[0];
+        let Some(index) = params.get(1) else {
+            let lambda = match accumulator {
+                Some(accumulator) => format!("({accumulator}, {item}) => {body}"),
+                None => format!("({item}) => {body}"),
+            };
+            return Ok(match (operation, initial) {
+                ("map", _) => format!("{list}.map({lambda}).toList()"),
+                ("filter", _) => format!("{list}.where({lambda}).toList()"),
+                ("any", _) => format!("{list}.any({lambda})"),
+                ("all", _) => format!("{list}.every({lambda})"),
+                ("find", _) => format!("{list}.where({lambda}).firstOrNull"),
+                (_, Some(initial)) => format!("{list}.fold<dynamic>({initial}, {lambda})"),
+                (_, None) => format!("{list}.reduce({lambda})"),
+            });
+        };
+
+        let pattern = format!("final ({index}, {item})");
+        let block = |params: &str| format!("({params}) {{ {pattern} = entry$; return {body}; }}");
+        Ok(match (operation, initial) {
+            ("map", _) => format!("[for ({pattern} in {list}.indexed) {body}]"),
+            ("filter", _) => format!("[for ({pattern} in {list}.indexed) if ({body}) {item}]"),
+            ("any", _) => format!("{list}.indexed.any({})", block("entry$")),
+            ("all", _) => format!("{list}.indexed.every({})", block("entry$")),
+            ("find", _) => format!("{list}.indexed.where({}).firstOrNull?.$2", block("entry$")),
+            (_, Some(initial)) => format!(
+                "{list}.indexed.fold<dynamic>({initial}, {})",
+                block(&format!("{}, entry$", accumulator.unwrap_or_default()))
+            ),
+            (_, None) => {
+                return Err(CodegenError::Unsupported(
+                    "reduce() with an index parameter needs an initial value in Dart".into(),
+                ))
+            }
+        })
+    }
+}
+
+fn field_chain(root: String, path: &[String]) -> String {
+    path.iter()
+        .fold(root, |code, segment| format!("{code}?.{segment}"))
+}
+
+fn compound(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Binary(..) | Expr::Conditional(..) | Expr::Coalesce(..)
+    )
+}
+
+/// Whether `expr` always evaluates to a boolean, so it needs no truthiness
+/// conversion.
+fn boolean(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Value::Bool(_)) | Expr::Unary(UnaryOp::Not, _) => true,
+        Expr::Binary(_, op, _) => !matches!(
+            op,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div
+        ),
+        Expr::Call(name, _) => {
+            list_operation(name).is_some_and(|operation| matches!(operation, "any" | "all"))
+                || get_function(name).is_some_and(|function| {
+                    matches!(
+                        function.name.as_str(),
+                        "contains" | "startsWith" | "isEmpty"
+                    )
+                })
+        }
+        _ => false,
+    }
+}
+
+struct Helper {
+    name: &'static str,
+    requires: &'static [&'static str],
+    dart: &'static str,
+    dart_imports: &'static [&'static str],
+    typescript: &'static str,
+    typescript_imports: &'static [&'static str],
+}
+
+const DART_CONVERT: &str = "import 'dart:convert';";
+const DART_MATH: &str = "import 'dart:math' as math;";
+const DART_INTL: &str = "import 'package:intl/intl.dart';";
+const DATE_FNS: &str = "import * as dateFns from 'date-fns';";
+
+/// Runtime support mirroring the simulator where the target's own library
+/// differs from it.
+const HELPERS: &[Helper] = &[
+    Helper {
+        name: "forgeTruthy",
+        requires: &[],
+        dart: r#"bool forgeTruthy(Object? value) {
+  if (value is bool) return value;
+  if (value is num) return value != 0;
+  if (value is String) return value.isNotEmpty;
+  if (value is Iterable) return value.isNotEmpty;
+  if (value is Map) return value.isNotEmpty;
+  return value != null;
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeTruthy(value: unknown): boolean {
+  if (Array.isArray(value)) return value.length > 0;
+  if (value !== null && typeof value === 'object') return Object.keys(value).length > 0;
+  return Boolean(value);
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeString",
+        requires: &[],
+        dart: r#"String forgeString(Object? value) {
+  if (value is double && value == value.truncateToDouble() && value.abs() < 1e15) {
+    return value.toInt().toString();
+  }
+  return value is Map || value is List ? jsonEncode(value) : '$value';
+}"#,
+        dart_imports: &[DART_CONVERT],
+        typescript: r#"function forgeString(value: unknown): string {
+  if (value === null || value === undefined) return 'null';
+  return typeof value === 'object' ? JSON.stringify(value) : String(value);
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeContains",
+        requires: &[],
+        dart: r#"bool forgeContains(Object? haystack, Object? needle) {
+  if (haystack is String) return haystack.contains(needle as String);
+  if (haystack is Map) return haystack.containsKey(needle);
+  return (haystack as Iterable).contains(needle);
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeContains(haystack: any, needle: any): boolean {
+  if (typeof haystack === 'string' || Array.isArray(haystack)) return haystack.includes(needle);
+  return Object.prototype.hasOwnProperty.call(haystack, needle);
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeRound",
+        requires: &[],
+        dart: r#"num forgeRound(num value, [num digits = 0]) {
+  final scale = math.pow(10, digits);
+  return (value * scale).round() / scale;
+}"#,
+        dart_imports: &[DART_MATH],
+        typescript: r#"function forgeRound(value: number, digits = 0): number {
+  // Math.round rounds halves up; the simulator rounds them away from zero.
+  const scale = 10 ** digits;
+  return (Math.sign(value) * Math.round(Math.abs(value) * scale)) / scale;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeMin",
+        requires: &[],
+        dart: r#"num forgeMin(Object? values) =>
+    values is List ? values.cast<num>().reduce(math.min) : values as num;"#,
+        dart_imports: &[DART_MATH],
+        typescript: r#"function forgeMin(values: number | number[]): number {
+  return Array.isArray(values) ? Math.min(...values) : values;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeMax",
+        requires: &[],
+        dart: r#"num forgeMax(Object? values) =>
+    values is List ? values.cast<num>().reduce(math.max) : values as num;"#,
+        dart_imports: &[DART_MATH],
+        typescript: r#"function forgeMax(values: number | number[]): number {
+  return Array.isArray(values) ? Math.max(...values) : values;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeFirst",
+        requires: &[],
+        dart: r#"Object? forgeFirst(dynamic value) => value.isEmpty ? null : value[0];"#,
+        dart_imports: &[],
+        typescript: r#"function forgeFirst(value: any): any {
+  return value.length > 0 ? value[0] : null;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeLast",
+        requires: &[],
+        dart: r#"Object? forgeLast(dynamic value) =>
+    value.isEmpty ? null : value[value.length - 1];"#,
+        dart_imports: &[],
+        typescript: r#"function forgeLast(value: any): any {
+  return value.length > 0 ? value[value.length - 1] : null;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeSlice",
+        requires: &[],
+        dart: r#"dynamic forgeSlice(dynamic value, num start, [num? end]) {
+  final length = value.length as int;
+  int bound(num index) => (index < 0 ? length + index : index).clamp(0, length).toInt();
+  final from = bound(start);
+  final to = math.max(from, bound(end ?? length));
+  return value is String ? value.substring(from, to) : value.sublist(from, to);
+}"#,
+        dart_imports: &[DART_MATH],
+        // `slice` on TypeScript strings and arrays already matches.
+        typescript: "",
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeIsEmpty",
+        requires: &[],
+        dart: r#"bool forgeIsEmpty(Object? value) {
+  if (value is String) return value.isEmpty;
+  if (value is Iterable) return value.isEmpty;
+  if (value is Map) return value.isEmpty;
+  return value == null;
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeIsEmpty(value: any): boolean {
+  if (value === null || value === undefined) return true;
+  if (typeof value === 'string' || Array.isArray(value)) return value.length === 0;
+  return typeof value === 'object' && Object.keys(value).length === 0;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeFormatDate",
+        requires: &[],
+        dart: r#"String forgeFormatDate(String date, String pattern) =>
+    DateFormat(pattern).format(DateTime.parse(date).toUtc());"#,
+        dart_imports: &[DART_INTL],
+        typescript: r#"function forgeFormatDate(date: string, pattern: string): string {
+  const parsed = dateFns.parseISO(date);
+  // date-fns formats local time; shift so the UTC fields are printed.
+  return dateFns.format(dateFns.addMinutes(parsed, parsed.getTimezoneOffset()), pattern);
+}"#,
+        typescript_imports: &[DATE_FNS],
+    },
+    Helper {
+        name: "forgeParseDate",
+        requires: &[],
+        dart: r#"String? forgeParseDate(String text, [String? pattern]) {
+  final date = pattern == null
+      ? DateTime.tryParse(text)
+      : DateFormat(pattern).tryParseUtc(text);
+  return date?.toUtc().toIso8601String();
+}"#,
+        dart_imports: &[DART_INTL],
+        typescript: r#"function forgeParseDate(text: string, pattern?: string): string | null {
+  const parsed =
+    pattern === undefined ? dateFns.parseISO(text) : dateFns.parse(text, pattern, new Date());
+  return dateFns.isValid(parsed) ? parsed.toISOString() : null;
+}"#,
+        typescript_imports: &[DATE_FNS],
+    },
+    Helper {
+        name: "forgeAddDays",
+        requires: &[],
+        dart: r#"String forgeAddDays(String date, num days) => DateTime.parse(date)
+    .toUtc()
+    .add(Duration(seconds: (days * 86400).round()))
+    .toIso8601String();"#,
+        dart_imports: &[],
+        typescript: r#"function forgeAddDays(date: string, days: number): string {
+  return new Date(Date.parse(date) + Math.round(days * 86400) * 1000).toISOString();
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeToNumber",
+        requires: &[],
+        dart: r#"num? forgeToNumber(Object? value) {
+  if (value is num) return value;
+  if (value is String) return num.tryParse(value.trim());
+  if (value is bool) return value ? 1 : 0;
+  return null;
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeToNumber(value: unknown): number | null {
+  if (typeof value === 'number') return value;
+  if (typeof value === 'boolean') return value ? 1 : 0;
+  if (typeof value !== 'string' || value.trim() === '') return null;
+  const parsed = Number(value.trim());
+  return Number.isNaN(parsed) ? null : parsed;
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeJsonParse",
+        requires: &[],
+        dart: r#"Object? forgeJsonParse(String text) {
+  try {
+    return jsonDecode(text);
+  } on FormatException {
+    return null;
+  }
+}"#,
+        dart_imports: &[DART_CONVERT],
+        typescript: r#"function forgeJsonParse(text: string): unknown {
+  try {
+    return JSON.parse(text);
+  } catch {
+    return null;
+  }
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeSortBy",
+        requires: &[],
+        dart: r#"List<dynamic> forgeSortBy(
+    List<dynamic> items, Object? Function(dynamic, int) key) {
+  final keyed = [
+    for (final (index, item) in items.indexed) (key(item, index), index, item)
+  ];
+  // List.sort is not stable, so ties fall back to the original order.
+  keyed.sort((a, b) {
+    final (keyA, indexA, _) = a;
+    final (keyB, indexB, _) = b;
+    final order = keyA == null || keyB == null
+        ? (keyA == null ? 1 : 0) - (keyB == null ? 1 : 0)
+        : (keyA as Comparable).compareTo(keyB);
+    return order != 0 ? order : indexA.compareTo(indexB);
+  });
+  return [for (final (_, _, item) in keyed) item];
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeSortBy<T>(items: T[], key: (item: T, index: number) => any): T[] {
+  const keyed = items.map((item, index) => ({ item, key: key(item, index) ?? null }));
+  // Array.prototype.sort is stable; null keys sort last.
+  keyed.sort((a, b) => {
+    if (a.key === null || b.key === null) {
+      return (a.key === null ? 1 : 0) - (b.key === null ? 1 : 0);
+    }
+    return a.key < b.key ? -1 : a.key > b.key ? 1 : 0;
+  });
+  return keyed.map((entry) => entry.item);
+}"#,
+        typescript_imports: &[],
+    },
+    Helper {
+        name: "forgeGroupBy",
+        requires: &["forgeString"],
+        dart: r#"Map<String, List<dynamic>> forgeGroupBy(
+    List<dynamic> items, Object? Function(dynamic, int) key) {
+  final groups = <String, List<dynamic>>{};
+  for (final (index, item) in items.indexed) {
+    groups.putIfAbsent(forgeString(key(item, index)), () => []).add(item);
+  }
+  return groups;
+}"#,
+        dart_imports: &[],
+        typescript: r#"function forgeGroupBy<T>(
+  items: T[],
+  key: (item: T, index: number) => unknown,
+): Record<string, T[]> {
+  const groups: Record<string, T[]> = {};
+  items.forEach((item, index) => {
+    (groups[forgeString(key(item, index))] ??= []).push(item);
+  });
+  return groups;
+}"#,
+        typescript_imports: &[],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RiverpodAdapter;
+
+    fn compile(target: CodegenTarget, source: &str) -> CompiledExpression {
+        ExprCodegen::new(target, &RiverpodAdapter)
+            .compile(source)
+            .expect(source)
+    }
+
+    fn code(target: CodegenTarget, source: &str) -> String {
+        compile(target, source).code
+    }
+
+    #[test]
+    fn provider_reads_go_through_the_state_adapter() {
+        assert_eq!(
+            code(CodegenTarget::Dart, "providers.cart.total * 2"),
+            "ref.watch(cartProvider)?.total * 2"
+        );
+        assert_eq!(
+            code(
+                CodegenTarget::TypeScript,
+                "providers.userProvider.name ?? input.name"
+            ),
+            "userProvider?.name ?? input?.name"
+        );
+        let codegen = ExprCodegen::new(CodegenTarget::Dart, &RiverpodAdapter)
+            .with_ref_name("widgetRef")
+            .with_input_name("event");
+        assert_eq!(
+            codegen
+                .compile("providers.cart.items[0].price > input.min")
+                .unwrap()
+                .code,
+            "widgetRef.watch(cartProvider)?.items?[0]?.price.compareTo(event?.min) > 0"
+        );
+        assert!(matches!(
+            ExprCodegen::new(CodegenTarget::Dart, &RiverpodAdapter).compile("session.id"),
+            Err(CodegenError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn builtins_map_onto_library_calls_and_helpers() {
+        for (source, dart, typescript) in [
+            (
+                "upper(trim(input.name))",
+                "input?.name.trim().toUpperCase()",
+                "input?.name.trim().toUpperCase()",
+            ),
+            (
+                "concat(\"$\", round(input.total, 2))",
+                "(forgeString(\"\\$\") + forgeString(forgeRound(input?.total, 2)))",
+                "(forgeString(\"$\") + forgeString(forgeRound(input?.total, 2)))",
+            ),
+            (
+                "input.tags.contains(\"new\") && !input.archived",
+                "forgeContains(input?.tags, \"new\") && !forgeTruthy(input?.archived)",
+                "forgeContains(input?.tags, \"new\") && !forgeTruthy(input?.archived)",
+            ),
+            (
+                "input.count == null ? max(1, input.limit) : len(input.items)",
+                "(input?.count == null) ? forgeMax([1, input?.limit]) : input?.items.length",
+                "(input?.count == null) ? forgeMax([1, input?.limit]) : input?.items.length",
+            ),
+            (
+                "slice(input.name, -3) != \"abc\"",
+                "forgeSlice(input?.name, -3) != \"abc\"",
+                "input?.name.slice(-3) !== \"abc\"",
+            ),
+            (
+                "(input.items)?[0].price ?? 0",
+                "input?.items?[0]?.price ?? 0",
+                "input?.items?.[0]?.price ?? 0",
+            ),
+        ] {
+            assert_eq!(code(CodegenTarget::Dart, source), dart, "{source}");
+            assert_eq!(
+                code(CodegenTarget::TypeScript, source),
+                typescript,
+                "{source}"
+            );
+        }
+
+        let compiled = compile(CodegenTarget::Dart, "formatDate(now(), \"yyyy-MM-dd\")");
+        assert_eq!(
+            compiled.code,
+            "forgeFormatDate(DateTime.now().toUtc().toIso8601String(), \"yyyy-MM-dd\")"
+        );
+        assert_eq!(compiled.imports, ["import 'package:intl/intl.dart';"]);
+        assert_eq!(compiled.helpers.len(), 1);
+        assert!(compiled.helpers[0].starts_with("String forgeFormatDate("));
+    }
+
+    #[test]
+    fn list_operations_compile_to_collection_methods() {
+        let source = "input.items.filter(i => !i.removed).map(i => i.price * i.qty).sum()";
+        assert_eq!(
+            code(CodegenTarget::Dart, source),
+            "input?.items.where((i) => !forgeTruthy(i?.removed)).toList()\
+             .map((i) => i?.price * i?.qty).toList()\
+             .fold<num>(0, (total, value) => total + value)"
+        );
+        assert_eq!(
+            code(CodegenTarget::TypeScript, source),
+            "input?.items.filter((i: any) => !forgeTruthy(i?.removed))\
+             .map((i: any) => i?.price * i?.qty)\
+             .reduce((total: number, value: number) => total + value, 0)"
+        );
+
+        assert_eq!(
+            code(
+                CodegenTarget::Dart,
+                "input.items.map((item, i) => i + item)"
+            ),
+            "[for (final (i, item) in input?.items.indexed) i + item]"
+        );
+        assert_eq!(
+            code(
+                CodegenTarget::Dart,
+                "input.items.reduce((sum, item) => sum + item, 0)"
+            ),
+            "input?.items.fold<dynamic>(0, (sum, item) => sum + item)"
+        );
+        assert_eq!(
+            code(
+                CodegenTarget::TypeScript,
+                "input.items.find(item => item.id == 2)"
+            ),
+            "(input?.items.find((item: any) => item?.id === 2) ?? null)"
+        );
+
+        let grouped = compile(
+            CodegenTarget::TypeScript,
+            "groupBy(input.items, item => item.kind)",
+        );
+        assert_eq!(
+            grouped.code,
+            "forgeGroupBy(input?.items, (item: any) => item?.kind)"
+        );
+        // Helpers come with the helpers they call.
+        assert_eq!(grouped.helpers.len(), 2);
+        assert!(grouped.helpers[0].starts_with("function forgeString("));
+    }
+
+    #[test]
+    fn reports_what_cannot_be_compiled() {
+        let dart = ExprCodegen::new(CodegenTarget::Dart, &RiverpodAdapter);
+        assert_eq!(
+            dart.compile("providers.cart").unwrap().code,
+            "ref.watch(cartProvider)"
+        );
+        assert_eq!(
+            dart.compile("uper(input.name)").unwrap_err().to_string(),
+            "unknown function uper"
+        );
+        assert_eq!(
+            dart.compile("upper(input.a, input.b)")
+                .unwrap_err()
+                .to_string(),
+            "upper() expects 1 argument, got 2"
+        );
+        assert_eq!(
+            dart.compile("input.items.map(1)").unwrap_err().to_string(),
+            "map() argument 2 must be a lambda such as `item => item.name`"
+        );
+        assert_eq!(
+            dart.compile("input.items.reduce((a, b, i) => a + i)")
+                .unwrap_err()
+                .to_string(),
+            "reduce() with an index parameter needs an initial value in Dart"
+        );
+
+        struct NoProviders;
+        impl StateAdapter for NoProviders {
+            fn name(&self) -> &'static str {
+                "none"
+            }
+            fn can_resolve(&self, _: &BindingReference) -> bool {
+                false
+            }
+            fn resolve(&self, _: &BindingReference) -> Option<crate::ResolvedBinding> {
+                None
+            }
+        }
+        assert_eq!(
+            ExprCodegen::new(CodegenTarget::TypeScript, &NoProviders)
+                .compile("providers.cart.total")
+                .unwrap_err()
+                .to_string(),
+            "provider 'cart' is not resolved by the none state adapter"
+        );
+    }
+
+    #[test]
+    fn dart_comparisons_keep_prefix_operators_on_the_receiver() {
+        assert_eq!(
+            code(CodegenTarget::Dart, "-input.x < 3"),
+            "(-input?.x).compareTo(3) < 0"
+        );
+        assert_eq!(
+            code(CodegenTarget::TypeScript, "-input.x < 3"),
+            "-input?.x < 3"
+        );
+    }
+
+    #[test]
+    fn whole_numbers_print_like_the_simulator() {
+        let source = "concat(2, \"x\", 2.5)";
+        let expr = parse_expression(source).unwrap();
+        let vars = serde_json::json!({});
+        let simulated =
+            crate::expr::eval_expression(&expr, &crate::expr::EvalContext::with_now(&vars))
+                .unwrap();
+        assert_eq!(simulated, Value::from("2x2.5"));
+        assert_eq!(
+            code(CodegenTarget::TypeScript, source),
+            "(forgeString(2) + forgeString(\"x\") + forgeString(2.5))"
+        );
+    }
+}
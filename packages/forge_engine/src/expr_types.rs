@@ -0,0 +1,1012 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::expr::{
+    list_operation, parse_expression_spanned, BinaryOp, Expr, ExprSpan, UnaryOp, LIST_OPERATIONS,
+};
+use crate::function_registry::{get_function, list_functions, ValueType};
+use crate::logic_types::{BuiltinLogicNodeKind, Flow, LogicGraph, LogicNode};
+
+/// Static type of an expression value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprType {
+    Any,
+    Null,
+    Bool,
+    Number,
+    String,
+    Array(Box<ExprType>),
+    /// An object with known fields. Reading any other field of a closed
+    /// object is reported as unknown.
+    Object {
+        fields: BTreeMap<String, ExprType>,
+        open: bool,
+    },
+    Nullable(Box<ExprType>),
+}
+
+impl ExprType {
+    /// Converts a JSON-schema-like shape: `type` (a name or a list of names
+    /// including `"null"`), `properties`, `items` and `additionalProperties`.
+    /// Unlike JSON Schema, an object listing `properties` is closed unless
+    /// `additionalProperties` allows more, so typos in field names are caught.
+    pub fn from_schema(schema: &Value) -> ExprType {
+        let Some(schema) = schema.as_object() else {
+            return ExprType::Any;
+        };
+        let names: Vec<&str> = match schema.get("type") {
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ if schema.contains_key("properties") => vec!["object"],
+            _ if schema.contains_key("items") => vec!["array"],
+            _ => return ExprType::Any,
+        };
+        let nullable = names.contains(&"null");
+        let concrete: Vec<&str> = names.into_iter().filter(|name| *name != "null").collect();
+        let ty = match concrete.as_slice() {
+            [] => return ExprType::Null,
+            ["string"] => ExprType::String,
+            ["number" | "integer"] => ExprType::Number,
+            ["boolean"] => ExprType::Bool,
+            ["array"] => ExprType::Array(Box::new(
+                schema
+                    .get("items")
+                    .map(ExprType::from_schema)
+                    .unwrap_or(ExprType::Any),
+            )),
+            ["object"] => {
+                let fields: BTreeMap<String, ExprType> = schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .map(|properties| {
+                        properties
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), ExprType::from_schema(schema)))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let open = !schema.contains_key("properties")
+                    || !matches!(
+                        schema.get("additionalProperties"),
+                        None | Some(Value::Bool(false))
+                    );
+                ExprType::Object { fields, open }
+            }
+            _ => ExprType::Any,
+        };
+        if nullable {
+            ExprType::Nullable(Box::new(ty))
+        } else {
+            ty
+        }
+    }
+
+    fn of_value(value: &Value) -> ExprType {
+        match value {
+            Value::Null => ExprType::Null,
+            Value::Bool(_) => ExprType::Bool,
+            Value::Number(_) => ExprType::Number,
+            Value::String(_) => ExprType::String,
+            Value::Array(_) => ExprType::Array(Box::new(ExprType::Any)),
+            Value::Object(_) => open_object(),
+        }
+    }
+
+    fn of_value_type(ty: &ValueType) -> ExprType {
+        match ty {
+            ValueType::Any => ExprType::Any,
+            ValueType::Null => ExprType::Null,
+            ValueType::Bool => ExprType::Bool,
+            ValueType::Number => ExprType::Number,
+            ValueType::String => ExprType::String,
+            ValueType::Array => ExprType::Array(Box::new(ExprType::Any)),
+            ValueType::Object => open_object(),
+            ValueType::OneOf(types) => types
+                .iter()
+                .map(ExprType::of_value_type)
+                .reduce(|a, b| a.unify(&b))
+                .unwrap_or(ExprType::Any),
+        }
+    }
+
+    fn non_null(&self) -> &ExprType {
+        match self {
+            ExprType::Nullable(inner) => inner,
+            other => other,
+        }
+    }
+
+    fn nullable(self) -> ExprType {
+        match self {
+            ExprType::Any | ExprType::Null | ExprType::Nullable(_) => self,
+            other => ExprType::Nullable(Box::new(other)),
+        }
+    }
+
+    /// The narrowest type covering both, falling back to `Any`.
+    fn unify(&self, other: &ExprType) -> ExprType {
+        match (self, other) {
+            _ if self == other => self.clone(),
+            (ExprType::Any, _) | (_, ExprType::Any) => ExprType::Any,
+            (ExprType::Null, ty) | (ty, ExprType::Null) => ty.clone().nullable(),
+            (ExprType::Nullable(a), b) | (b, ExprType::Nullable(a)) => {
+                a.unify(b.non_null()).nullable()
+            }
+            (ExprType::Array(a), ExprType::Array(b)) => ExprType::Array(Box::new(a.unify(b))),
+            (ExprType::Object { .. }, ExprType::Object { .. }) => open_object(),
+            _ => ExprType::Any,
+        }
+    }
+
+    /// Whether a value of this type may be passed where `expected` is
+    /// declared. Nullable types are accepted where their inner type is.
+    fn fits(&self, expected: &ValueType) -> bool {
+        match (self.non_null(), expected) {
+            (ExprType::Any, _) | (_, ValueType::Any) => true,
+            (ty, ValueType::OneOf(types)) => types.iter().any(|expected| ty.fits(expected)),
+            (ExprType::Null, ValueType::Null)
+            | (ExprType::Bool, ValueType::Bool)
+            | (ExprType::Number, ValueType::Number)
+            | (ExprType::String, ValueType::String)
+            | (ExprType::Array(_), ValueType::Array)
+            | (ExprType::Object { .. }, ValueType::Object) => true,
+            _ => false,
+        }
+    }
+}
+
+fn open_object() -> ExprType {
+    ExprType::Object {
+        fields: BTreeMap::new(),
+        open: true,
+    }
+}
+
+impl fmt::Display for ExprType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprType::Any => f.write_str("any"),
+            ExprType::Null => f.write_str("null"),
+            ExprType::Bool => f.write_str("bool"),
+            ExprType::Number => f.write_str("number"),
+            ExprType::String => f.write_str("string"),
+            ExprType::Array(item) => write!(f, "array<{item}>"),
+            ExprType::Object { .. } => f.write_str("object"),
+            ExprType::Nullable(inner) => write!(f, "{inner}?"),
+        }
+    }
+}
+
+/// Types of the variables an expression may read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeEnv {
+    variables: BTreeMap<String, ExprType>,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_variable<S: Into<String>>(mut self, name: S, ty: ExprType) -> Self {
+        self.variables.insert(name.into(), ty);
+        self
+    }
+
+    /// The variables logic nodes see: `input` and `providers`, typed from the
+    /// `types` entry of the graph's and the flow's metadata:
+    ///
+    /// ```json
+    /// { "types": { "input": { "type": "object", "properties": { … } },
+    ///              "providers": { "cart": { "type": "object", … } } } }
+    /// ```
+    ///
+    /// Providers declared on the flow add to those declared on the graph.
+    /// Once any provider is declared, reading an undeclared one is an error;
+    /// without declarations both variables are untyped.
+    pub fn for_flow(graph: &LogicGraph, flow: &Flow) -> Self {
+        let types = |metadata: &Option<Value>| {
+            metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get("types"))
+                .cloned()
+                .unwrap_or(Value::Null)
+        };
+        let (graph_types, flow_types) = (types(&graph.metadata), types(&flow.metadata));
+
+        let mut providers = BTreeMap::new();
+        for declared in [&graph_types, &flow_types] {
+            if let Some(declared) = declared.get("providers").and_then(Value::as_object) {
+                for (id, schema) in declared {
+                    providers.insert(id.clone(), ExprType::from_schema(schema));
+                }
+            }
+        }
+        let open = providers.is_empty();
+        let input = flow_types
+            .get("input")
+            .map(ExprType::from_schema)
+            .unwrap_or(ExprType::Any);
+        TypeEnv::new().with_variable("input", input).with_variable(
+            "providers",
+            ExprType::Object {
+                fields: providers,
+                open,
+            },
+        )
+    }
+}
+
+/// A type error in an expression.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeDiagnostic {
+    pub message: String,
+    /// Character offsets of the offending part of the expression.
+    pub start: usize,
+    pub end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+}
+
+impl fmt::Display for TypeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(flow), Some(node)) = (&self.flow_id, &self.node_id) {
+            write!(f, "flow '{flow}', node '{node}', ")?;
+        }
+        write!(f, "column {}: {}", self.start + 1, self.message)
+    }
+}
+
+/// Infers the type of `source` under `env`, or reports unknown variables and
+/// fields, operands of the wrong type, and function calls with the wrong
+/// arity or argument types.
+pub fn check_expression(source: &str, env: &TypeEnv) -> Result<ExprType, Vec<TypeDiagnostic>> {
+    let (ty, diagnostics) = infer_source(source, env);
+    if diagnostics.is_empty() {
+        Ok(ty)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Checks the expressions of every flow; see [check_flow].
+pub fn check_logic_graph(graph: &LogicGraph) -> Vec<TypeDiagnostic> {
+    graph
+        .flows
+        .iter()
+        .flat_map(|flow| check_flow(graph, flow))
+        .collect()
+}
+
+/// Checks the expressions of `flow`'s nodes against [TypeEnv::for_flow].
+/// Each node's `input` is typed from what reaches it: the declared input at
+/// entry nodes and the output of upstream nodes elsewhere.
+pub fn check_flow(graph: &LogicGraph, flow: &Flow) -> Vec<TypeDiagnostic> {
+    let env = TypeEnv::for_flow(graph, flow);
+    let inputs = propagate_inputs(flow, &env);
+
+    let mut diagnostics = Vec::new();
+    for node in &flow.nodes {
+        let Some(expression) = node_expression(node) else {
+            continue;
+        };
+        let input = inputs
+            .get(node.id.as_str())
+            .cloned()
+            .unwrap_or(ExprType::Any);
+        let env = env.clone().with_variable("input", input);
+        let (_, found) = infer_source(expression, &env);
+        diagnostics.extend(found.into_iter().map(|diagnostic| TypeDiagnostic {
+            flow_id: Some(flow.id.clone()),
+            node_id: Some(node.id.clone()),
+            ..diagnostic
+        }));
+    }
+    diagnostics
+}
+
+fn node_expression(node: &LogicNode) -> Option<&str> {
+    match node.kind {
+        Some(
+            BuiltinLogicNodeKind::Transform
+            | BuiltinLogicNodeKind::Condition
+            | BuiltinLogicNodeKind::Return
+            | BuiltinLogicNodeKind::ActionSetState,
+        ) => node.props.get("expression").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Flows the declared input through the graph the way the simulator passes
+/// values: transforms emit their expression's value, other nodes pass their
+/// input on. Nodes reached with different types see the unified type.
+fn propagate_inputs<'a>(flow: &'a Flow, env: &TypeEnv) -> HashMap<&'a str, ExprType> {
+    let declared = env.variables.get("input").cloned().unwrap_or(ExprType::Any);
+    let entries: Vec<&str> = if flow.entry_nodes.is_empty() {
+        flow.nodes
+            .iter()
+            .filter(|node| node.kind == Some(BuiltinLogicNodeKind::EventEntry))
+            .map(|node| node.id.as_str())
+            .collect()
+    } else {
+        flow.entry_nodes.iter().map(String::as_str).collect()
+    };
+
+    let mut inputs: HashMap<&str, ExprType> = HashMap::new();
+    let mut queue: VecDeque<&str> = VecDeque::new();
+    for entry in entries {
+        inputs.insert(entry, declared.clone());
+        queue.push_back(entry);
+    }
+    // Types only widen, so this settles; the bound guards against surprises.
+    let mut budget = flow.nodes.len() * 8 + 8;
+    while let Some(id) = queue.pop_front() {
+        budget = budget.saturating_sub(1);
+        if budget == 0 {
+            break;
+        }
+        let Some(node) = flow.nodes.iter().find(|node| node.id == id) else {
+            continue;
+        };
+        let input = inputs[id].clone();
+        let output = match node.kind {
+            Some(BuiltinLogicNodeKind::Return) => continue,
+            Some(BuiltinLogicNodeKind::Transform) => node_expression(node)
+                .map(|expression| {
+                    let env = env.clone().with_variable("input", input.clone());
+                    infer_source(expression, &env).0
+                })
+                .unwrap_or(ExprType::Any),
+            Some(
+                BuiltinLogicNodeKind::EventEntry
+                | BuiltinLogicNodeKind::Condition
+                | BuiltinLogicNodeKind::ActionSetState,
+            ) => input,
+            _ => ExprType::Any,
+        };
+        for edge in flow.edges.iter().filter(|edge| edge.from_node == id) {
+            let target = edge.to_node.as_str();
+            let merged = match inputs.get(target) {
+                Some(existing) => existing.unify(&output),
+                None => output.clone(),
+            };
+            if inputs.get(target) != Some(&merged) {
+                inputs.insert(target, merged);
+                queue.push_back(target);
+            }
+        }
+    }
+    inputs
+}
+
+fn infer_source(source: &str, env: &TypeEnv) -> (ExprType, Vec<TypeDiagnostic>) {
+    match parse_expression_spanned(source) {
+        Ok((expr, spans)) => {
+            let mut checker = Checker {
+                env,
+                spans: &spans,
+                cursor: 0,
+                locals: Vec::new(),
+                diagnostics: Vec::new(),
+            };
+            let (ty, _) = checker.infer(&expr);
+            (ty, checker.diagnostics)
+        }
+        Err(err) => (
+            ExprType::Any,
+            vec![TypeDiagnostic {
+                message: err.to_string(),
+                start: 0,
+                end: source.chars().count(),
+                flow_id: None,
+                node_id: None,
+            }],
+        ),
+    }
+}
+
+struct Checker<'a> {
+    env: &'a TypeEnv,
+    /// Post-order spans from the parser; `cursor` is the next unvisited one.
+    spans: &'a [ExprSpan],
+    cursor: usize,
+    locals: Vec<(String, ExprType)>,
+    diagnostics: Vec<TypeDiagnostic>,
+}
+
+impl Checker<'_> {
+    fn infer(&mut self, expr: &Expr) -> (ExprType, ExprSpan) {
+        let first = self.cursor;
+        let next = first + expr.node_count();
+        let span = self.spans[next - 1];
+        let ty = self.infer_node(expr, span);
+        self.cursor = next;
+        (ty, span)
+    }
+
+    /// Infers `expr` as if its subtree's spans started at `cursor`.
+    fn infer_at(&mut self, expr: &Expr, cursor: usize) -> (ExprType, ExprSpan) {
+        self.cursor = cursor;
+        self.infer(expr)
+    }
+
+    fn report(&mut self, span: ExprSpan, message: String) {
+        self.diagnostics.push(TypeDiagnostic {
+            message,
+            start: span.start,
+            end: span.end,
+            flow_id: None,
+            node_id: None,
+        });
+    }
+
+    fn infer_node(&mut self, expr: &Expr, span: ExprSpan) -> ExprType {
+        match expr {
+            Expr::Literal(value) => ExprType::of_value(value),
+            Expr::Var(path) => self.infer_var(path, span),
+            Expr::Unary(op, inner) => {
+                let (ty, inner_span) = self.infer(inner);
+                match op {
+                    UnaryOp::Negate => {
+                        self.expect_number("-", &ty, inner_span);
+                        ExprType::Number
+                    }
+                    UnaryOp::Not => ExprType::Bool,
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                let (left_ty, left_span) = self.infer(left);
+                let (right_ty, right_span) = self.infer(right);
+                self.infer_binary(*op, (&left_ty, left_span), (&right_ty, right_span), span)
+            }
+            Expr::Call(name, args) => self.infer_call(name, args, span),
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.infer(condition);
+                let (then_ty, _) = self.infer(then_branch);
+                let (else_ty, _) = self.infer(else_branch);
+                then_ty.unify(&else_ty)
+            }
+            Expr::Coalesce(value, fallback) => {
+                let (value_ty, _) = self.infer(value);
+                let (fallback_ty, _) = self.infer(fallback);
+                match value_ty {
+                    ExprType::Null => fallback_ty,
+                    ty => ty.non_null().unify(&fallback_ty),
+                }
+            }
+            Expr::Member {
+                object,
+                property,
+                optional,
+            } => {
+                let (object_ty, _) = self.infer(object);
+                let ty = self.field(&object_ty, property, span);
+                if *optional {
+                    ty.nullable()
+                } else {
+                    ty
+                }
+            }
+            Expr::Index {
+                object,
+                index,
+                optional,
+            } => {
+                let (object_ty, _) = self.infer(object);
+                let (index_ty, index_span) = self.infer(index);
+                let ty = self.infer_index(&object_ty, index, &index_ty, index_span, span);
+                // Indexing null yields null, as reading a field of null does.
+                if *optional || matches!(object_ty, ExprType::Nullable(_)) {
+                    ty.nullable()
+                } else {
+                    ty
+                }
+            }
+            Expr::Lambda(params, body) => {
+                self.infer_lambda(params, vec![ExprType::Any; params.len()], body);
+                self.report(
+                    span,
+                    "a lambda can only be passed to a list operation such as map()".into(),
+                );
+                ExprType::Any
+            }
+        }
+    }
+
+    fn infer_var(&mut self, path: &[String], span: ExprSpan) -> ExprType {
+        let Some((first, rest)) = path.split_first() else {
+            return ExprType::Any;
+        };
+        let found = self
+            .locals
+            .iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, ty)| ty.clone())
+            .or_else(|| self.env.variables.get(first).cloned());
+        let Some(mut ty) = found else {
+            let names = self
+                .locals
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .chain(self.env.variables.keys().map(String::as_str));
+            let hint = suggestion(first, names);
+            self.report(span, format!("unknown variable '{first}'{hint}"));
+            return ExprType::Any;
+        };
+        for segment in rest {
+            ty = self.field(&ty, segment, span);
+        }
+        ty
+    }
+
+    fn field(&mut self, ty: &ExprType, name: &str, span: ExprSpan) -> ExprType {
+        match ty {
+            ExprType::Any | ExprType::Null => ExprType::Any,
+            ExprType::Nullable(inner) => self.field(inner, name, span).nullable(),
+            ExprType::Object { fields, open } => match fields.get(name) {
+                Some(field) => field.clone(),
+                None if *open => ExprType::Any,
+                None => {
+                    let hint = suggestion(name, fields.keys().map(String::as_str));
+                    self.report(span, format!("unknown field '{name}'{hint}"));
+                    ExprType::Any
+                }
+            },
+            other => {
+                self.report(span, format!("cannot read field '{name}' of {other}"));
+                ExprType::Any
+            }
+        }
+    }
+
+    fn infer_index(
+        &mut self,
+        object_ty: &ExprType,
+        index: &Expr,
+        index_ty: &ExprType,
+        index_span: ExprSpan,
+        span: ExprSpan,
+    ) -> ExprType {
+        match object_ty.non_null() {
+            ExprType::Any | ExprType::Null => ExprType::Any,
+            ExprType::Array(item) => {
+                if !index_ty.fits(&ValueType::Number) {
+                    self.report(
+                        index_span,
+                        format!("array index must be a number, got {index_ty}"),
+                    );
+                }
+                (**item).clone()
+            }
+            object @ ExprType::Object { .. } => {
+                if !index_ty.fits(&ValueType::String) {
+                    self.report(
+                        index_span,
+                        format!("object key must be a string, got {index_ty}"),
+                    );
+                }
+                match index {
+                    Expr::Literal(Value::String(key)) => self.field(object, key, index_span),
+                    _ => ExprType::Any,
+                }
+            }
+            other => {
+                self.report(span, format!("cannot index {other}"));
+                ExprType::Any
+            }
+        }
+    }
+
+    fn infer_binary(
+        &mut self,
+        op: BinaryOp,
+        (left, left_span): (&ExprType, ExprSpan),
+        (right, right_span): (&ExprType, ExprSpan),
+        span: ExprSpan,
+    ) -> ExprType {
+        let symbol = match op {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Lt => "<",
+            BinaryOp::Lte => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Gte => ">=",
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::NotEq => return ExprType::Bool,
+        };
+        if matches!(
+            op,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div
+        ) {
+            self.expect_number(symbol, left, left_span);
+            self.expect_number(symbol, right, right_span);
+            return ExprType::Number;
+        }
+
+        let comparable = ValueType::OneOf(vec![ValueType::Number, ValueType::String]);
+        let mut operands_ok = true;
+        for (ty, operand_span) in [(left, left_span), (right, right_span)] {
+            if !ty.fits(&comparable) {
+                operands_ok = false;
+                self.report(
+                    operand_span,
+                    format!("operator '{symbol}' expects numbers or strings, got {ty}"),
+                );
+            }
+        }
+        let (left, right) = (left.non_null(), right.non_null());
+        if operands_ok
+            && !matches!(left, ExprType::Any)
+            && !matches!(right, ExprType::Any)
+            && left != right
+        {
+            self.report(span, format!("cannot compare {left} with {right}"));
+        }
+        ExprType::Bool
+    }
+
+    fn expect_number(&mut self, symbol: &str, ty: &ExprType, span: ExprSpan) {
+        if !ty.fits(&ValueType::Number) {
+            self.report(
+                span,
+                format!("operator '{symbol}' expects a number, got {ty}"),
+            );
+        }
+    }
+
+    fn infer_call(&mut self, name: &str, args: &[Expr], span: ExprSpan) -> ExprType {
+        if let Some(operation) = list_operation(name) {
+            return self.infer_list_operation(operation, args, span);
+        }
+        let arg_types: Vec<(ExprType, ExprSpan)> = args.iter().map(|arg| self.infer(arg)).collect();
+        let Some(function) = get_function(name) else {
+            let functions = list_functions();
+            let names = functions
+                .iter()
+                .map(|function| function.name.as_str())
+                .chain(LIST_OPERATIONS.iter().copied());
+            let hint = suggestion(name, names);
+            self.report(span, format!("unknown function {name}{hint}"));
+            return ExprType::Any;
+        };
+        if let Err(err) = function.check_arity(args.len()) {
+            self.report(span, err.to_string());
+        }
+        for (position, (ty, arg_span)) in arg_types.iter().enumerate() {
+            let Some(expected) = function.param_type(position) else {
+                continue;
+            };
+            if !ty.fits(expected) {
+                self.report(
+                    *arg_span,
+                    format!(
+                        "{}() argument {} must be {}, got {ty}",
+                        function.name,
+                        position + 1,
+                        expected.described()
+                    ),
+                );
+            }
+        }
+        // Collection accessors keep the element type.
+        match (function.name.as_str(), arg_types.first()) {
+            ("first" | "last", Some((ExprType::Array(item), _))) => (**item).clone().nullable(),
+            ("slice", Some((ty @ (ExprType::Array(_) | ExprType::String), _))) => ty.clone(),
+            _ => ExprType::of_value_type(&function.returns),
+        }
+    }
+
+    fn infer_list_operation(
+        &mut self,
+        operation: &'static str,
+        args: &[Expr],
+        span: ExprSpan,
+    ) -> ExprType {
+        let reduce = operation == "reduce";
+        let max_args = if reduce { 3 } else { 2 };
+        if args.len() < 2 || args.len() > max_args {
+            self.report(
+                span,
+                format!(
+                    "{operation}() expects {}, got {}",
+                    if reduce {
+                        "2 to 3 arguments"
+                    } else {
+                        "2 arguments"
+                    },
+                    args.len()
+                ),
+            );
+            for arg in args {
+                self.infer(arg);
+            }
+            return ExprType::Any;
+        }
+
+        let (list_ty, list_span) = self.infer(&args[0]);
+        let item = match list_ty.non_null() {
+            ExprType::Array(item) => (**item).clone(),
+            ExprType::Any => ExprType::Any,
+            other => {
+                self.report(
+                    list_span,
+                    format!("{operation}() argument 1 must be an array, got {other}"),
+                );
+                ExprType::Any
+            }
+        };
+
+        // `reduce`'s initial value comes after the lambda but types its
+        // accumulator, so it is checked first.
+        let lambda_start = self.cursor;
+        let initial = args.get(2).map(|initial| {
+            let (ty, _) = self.infer_at(initial, lambda_start + args[1].node_count());
+            ty
+        });
+        let end = self.cursor;
+        self.cursor = lambda_start;
+
+        let Expr::Lambda(params, body) = &args[1] else {
+            let (ty, arg_span) = self.infer(&args[1]);
+            self.report(
+                arg_span,
+                format!(
+                    "{operation}() argument 2 must be a lambda such as `item => item.name`, got {ty}"
+                ),
+            );
+            self.cursor = self.cursor.max(end);
+            return ExprType::Any;
+        };
+        let (min_params, max_params) = if reduce { (2, 3) } else { (1, 2) };
+        let lambda_span = self.spans[lambda_start + args[1].node_count() - 1];
+        if params.len() < min_params || params.len() > max_params {
+            self.report(
+                lambda_span,
+                format!(
+                    "{operation}() lambda takes {} parameters, got {}",
+                    if reduce {
+                        "2 to 3 (accumulator, item, index)"
+                    } else {
+                        "1 to 2 (item, index)"
+                    },
+                    params.len()
+                ),
+            );
+        }
+        let accumulator = initial.clone().unwrap_or_else(|| item.clone());
+        let bound = if reduce {
+            vec![accumulator.clone(), item.clone(), ExprType::Number]
+        } else {
+            vec![item.clone(), ExprType::Number]
+        };
+        let body_ty = self.infer_lambda(params, bound, body);
+        self.cursor = self.cursor.max(end);
+
+        match operation {
+            "map" => ExprType::Array(Box::new(body_ty)),
+            "filter" => ExprType::Array(Box::new(item)),
+            "sortBy" => {
+                let key = ValueType::OneOf(vec![ValueType::Number, ValueType::String]);
+                if !body_ty.fits(&key) {
+                    self.report(
+                        lambda_span,
+                        format!("sortBy() keys must be numbers or strings, got {body_ty}"),
+                    );
+                }
+                ExprType::Array(Box::new(item))
+            }
+            "reduce" => accumulator.unify(&body_ty),
+            "groupBy" => open_object(),
+            "find" => item.nullable(),
+            _ => ExprType::Bool,
+        }
+    }
+
+    /// Checks a lambda's body with `types` bound to its parameters and
+    /// consumes the lambda's own span.
+    fn infer_lambda(&mut self, params: &[String], types: Vec<ExprType>, body: &Expr) -> ExprType {
+        let scope = self.locals.len();
+        self.locals.extend(
+            params
+                .iter()
+                .cloned()
+                .zip(types.into_iter().chain(std::iter::repeat(ExprType::Any))),
+        );
+        let (ty, _) = self.infer(body);
+        self.locals.truncate(scope);
+        self.cursor += 1;
+        ty
+    }
+}
+
+/// `"; did you mean 'x'?"` for the candidate closest to `name`, if any is
+/// close enough to be a likely typo.
+fn suggestion<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2 && *distance < candidate.len())
+        .min()
+        .map(|(_, candidate)| format!("; did you mean '{candidate}'?"))
+        .unwrap_or_default()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn env() -> TypeEnv {
+        TypeEnv::new()
+            .with_variable(
+                "user",
+                ExprType::from_schema(&json!({
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "age": {"type": ["integer", "null"]},
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                    },
+                })),
+            )
+            .with_variable(
+                "items",
+                ExprType::from_schema(&json!({
+                    "type": "array",
+                    "items": {"type": "object", "properties": {"price": {"type": "number"}}},
+                })),
+            )
+    }
+
+    fn errors(source: &str) -> Vec<(String, usize, usize)> {
+        check_expression(source, &env())
+            .expect_err(source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.start, diagnostic.end))
+            .collect()
+    }
+
+    #[test]
+    fn infers_types_of_well_typed_expressions() {
+        for (source, expected) in [
+            ("user.name", ExprType::String),
+            ("user.age ?? 0", ExprType::Number),
+            (
+                "concat(upper(user.name), \"!\") == \"ADA!\"",
+                ExprType::Bool,
+            ),
+            (
+                "items.map(i => i.price * 2)",
+                ExprType::Array(Box::new(ExprType::Number)),
+            ),
+            (
+                "items.reduce((sum, i) => sum + i.price, 0)",
+                ExprType::Number,
+            ),
+            (
+                "user.tags.first()",
+                ExprType::Nullable(Box::new(ExprType::String)),
+            ),
+            (
+                "user.tags[0].len() > 2 ? user.name : null",
+                ExprType::Nullable(Box::new(ExprType::String)),
+            ),
+        ] {
+            assert_eq!(check_expression(source, &env()), Ok(expected), "{source}");
+        }
+    }
+
+    #[test]
+    fn reports_unknown_names_with_positions() {
+        assert_eq!(
+            errors("user.nmae"),
+            [("unknown field 'nmae'; did you mean 'name'?".into(), 0, 9)]
+        );
+        assert_eq!(
+            errors("1 + usr.name"),
+            [("unknown variable 'usr'; did you mean 'user'?".into(), 4, 12)]
+        );
+        assert_eq!(
+            errors("items.filter(i => i.prcie > 1)"),
+            [(
+                "unknown field 'prcie'; did you mean 'price'?".into(),
+                18,
+                25
+            )]
+        );
+        assert_eq!(
+            errors("uper(user.name)"),
+            [("unknown function uper; did you mean 'upper'?".into(), 0, 15)]
+        );
+    }
+
+    #[test]
+    fn reports_operand_and_argument_errors() {
+        assert_eq!(
+            errors("user.name - 1"),
+            [("operator '-' expects a number, got string".into(), 0, 9)]
+        );
+        assert_eq!(
+            errors("user.age < \"ten\""),
+            [("cannot compare number with string".into(), 0, 16)]
+        );
+        assert_eq!(
+            errors("round(user.name)"),
+            [(
+                "round() argument 1 must be a number, got string".into(),
+                6,
+                15
+            )]
+        );
+        assert_eq!(
+            errors("upper(user.name, 1)"),
+            [("upper() expects 1 argument, got 2".into(), 0, 19)]
+        );
+        assert_eq!(
+            errors("user.map(u => u)"),
+            [("map() argument 1 must be an array, got object".into(), 0, 4)]
+        );
+        assert_eq!(
+            errors("user.name.first"),
+            [("cannot read field 'first' of string".into(), 0, 15)]
+        );
+    }
+
+    #[test]
+    fn flows_type_inputs_from_declarations_and_upstream_nodes() {
+        let graph: LogicGraph = serde_json::from_value(json!({
+            "metadata": {"types": {"providers": {
+                "cart": {"type": "object", "properties": {"total": {"type": "number"}}}
+            }}},
+            "flows": [{
+                "id": "checkout",
+                "metadata": {"types": {"input": {
+                    "type": "object", "properties": {"coupon": {"type": "string"}}
+                }}},
+                "entry_nodes": ["start"],
+                "nodes": [
+                    {"id": "start", "kind": "eventEntry"},
+                    {"id": "discount", "kind": "transform",
+                     "props": {"expression": "providers.cart.total - len(input.coupon)"}},
+                    {"id": "check", "kind": "condition",
+                     "props": {"expression": "input.amount > 0 && providers.user"}},
+                ],
+                "edges": [
+                    {"from_node": "start", "to_node": "discount"},
+                    {"from_node": "discount", "to_node": "check"},
+                ],
+            }],
+        }))
+        .unwrap();
+
+        let diagnostics = check_logic_graph(&graph);
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "flow 'checkout', node 'check', column 1: cannot read field 'amount' of number",
+                "flow 'checkout', node 'check', column 21: unknown field 'user'",
+            ]
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use crate::renderer_adapter::{
-    RenderContext, RenderDialect, RenderResult, RenderUnit, RendererAdapter,
+use crate::expr_codegen::CodegenTarget;
+use crate::renderer_adapter::{RenderContext, RenderResult, RenderUnit, RendererAdapter};
+use crate::{
+    BindingReference, BindingTarget, PropValue, ScreenGraph, ScreenState, StateField,
+    WidgetBuilder, WidgetNode, CONDITIONAL_WIDGET, FOR_EACH_WIDGET,
 };
-use crate::{BindingReference, BindingTarget, PropValue, ScreenGraph, WidgetNode};
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
 
@@ -15,7 +17,7 @@ impl RendererAdapter for FlutterRenderer {
     }
 
     fn render_node(&self, node: &WidgetNode, ctx: &RenderContext<'_>) -> RenderResult {
-        let code = render_widget(node, ctx.indent);
+        let code = render_widget(node, ctx.indent, DEFAULT_REF_NAME);
         let unit = RenderUnit::new(code).with_dependencies(self.dependencies());
         Ok(unit)
     }
@@ -23,57 +25,330 @@ impl RendererAdapter for FlutterRenderer {
     fn dependencies(&self) -> HashMap<String, String> {
         HashMap::from([(String::from("flutter"), String::from("sdk: flutter"))])
     }
+
+    fn codegen_target(&self) -> Option<CodegenTarget> {
+        Some(CodegenTarget::Dart)
+    }
 }
 
-/// Generates Dart code for a stateless widget from a [ScreenGraph].
+/// Name of the Riverpod `WidgetRef` assumed when a graph does not record one.
+const DEFAULT_REF_NAME: &str = "ref";
+
+/// Generates Dart code for a stateless widget from a [ScreenGraph]. Screens
+/// with a [ConsumerRef](crate::ConsumerRef) are emitted as a `ConsumerWidget`.
 pub fn generate_stateless_widget(screen: &ScreenGraph) -> String {
-    let body = render_widget(&screen.root, 6);
+    let (base, ref_param) = match &screen.consumer {
+        Some(consumer) => ("ConsumerWidget", Some(consumer.name.as_str())),
+        None => ("StatelessWidget", None),
+    };
     format!(
-        "class {} extends StatelessWidget {{\n  const {}({{ super.key }});\n\n  @override\n  Widget build(BuildContext context) {{\n    return {}\n  }}\n}}\n",
-        screen.id, screen.id, body
+        "class {} extends {} {{\n  const {}({{ super.key }});\n\n{}}}\n",
+        screen.id,
+        base,
+        screen.id,
+        render_build_method(screen, ref_param)
     )
 }
 
+/// Generates Dart code for a `StatefulWidget` and its `State` subclass from a
+/// [ScreenGraph]. Captured [ScreenState] members are re-emitted around the
+/// rendered `build` method. Screens with a [ConsumerRef](crate::ConsumerRef)
+/// are emitted as a `ConsumerStatefulWidget` with a `ConsumerState`.
+pub fn generate_stateful_widget(screen: &ScreenGraph) -> String {
+    let state = screen
+        .state
+        .clone()
+        .unwrap_or_else(|| ScreenState::new(format!("_{}State", screen.id)));
+
+    let (widget_base, state_base) = if screen.consumer.is_some() {
+        ("ConsumerStatefulWidget", "ConsumerState")
+    } else {
+        ("StatefulWidget", "State")
+    };
+
+    let mut buffer = format!("class {} extends {widget_base} {{\n", screen.id);
+    for field in &state.widget_fields {
+        buffer.push_str("  ");
+        buffer.push_str(&render_state_field(field));
+        buffer.push('\n');
+    }
+    if !state.widget_fields.is_empty() {
+        buffer.push('\n');
+    }
+    if state.widget_constructors.is_empty() {
+        buffer.push_str(&format!("  const {}({{ super.key }});\n", screen.id));
+    }
+    for constructor in &state.widget_constructors {
+        buffer.push_str(&indent_block(constructor, 2));
+    }
+    buffer.push_str(&format!(
+        "\n  @override\n  {state_base}<{id}> createState() => {state}();\n}}\n\n",
+        id = screen.id,
+        state = state.class_name
+    ));
+    buffer.push_str(&format!(
+        "class {} extends {}<{}> {{\n",
+        state.class_name, state_base, screen.id
+    ));
+
+    for field in &state.fields {
+        buffer.push_str("  ");
+        buffer.push_str(&render_state_field(field));
+        buffer.push('\n');
+    }
+    if !state.fields.is_empty() {
+        buffer.push('\n');
+    }
+
+    let lifecycle = [
+        ("initState", &state.init_state),
+        ("dispose", &state.dispose),
+    ];
+    for (name, body) in lifecycle {
+        if let Some(body) = body {
+            buffer.push_str(&format!("  @override\n  void {name}() {{\n"));
+            buffer.push_str(&indent_block(body, 4));
+            buffer.push_str("  }\n\n");
+        }
+    }
+
+    for method in &state.methods {
+        buffer.push_str(&indent_block(&method.source, 2));
+        buffer.push('\n');
+    }
+
+    buffer.push_str(&render_build_method(screen, None));
+    buffer.push_str("}\n");
+    buffer
+}
+
 /// Generates a Dart module containing all provided [ScreenGraph] widgets.
 pub fn generate_dart_module(graphs: &[ScreenGraph]) -> String {
     let mut buffer = String::new();
-    buffer.push_str("import 'package:flutter/widgets.dart';\n\n");
+    buffer.push_str("import 'package:flutter/widgets.dart';\n");
+    if graphs.iter().any(|graph| graph.consumer.is_some()) {
+        buffer.push_str("import 'package:flutter_riverpod/flutter_riverpod.dart';\n");
+    }
+    buffer.push('\n');
     for graph in graphs {
-        buffer.push_str(&generate_stateless_widget(graph));
+        if graph.state.is_some() {
+            buffer.push_str(&generate_stateful_widget(graph));
+        } else {
+            buffer.push_str(&generate_stateless_widget(graph));
+        }
         buffer.push('\n');
     }
     buffer
 }
 
-fn render_widget(node: &WidgetNode, indent: usize) -> String {
+/// Renders `build`. `ref_param` adds a `WidgetRef` parameter, as required by
+/// `ConsumerWidget`; `ConsumerState` exposes `ref` as a member instead.
+fn render_build_method(screen: &ScreenGraph, ref_param: Option<&str>) -> String {
+    let ref_name = screen
+        .consumer
+        .as_ref()
+        .map_or(DEFAULT_REF_NAME, |consumer| consumer.name.as_str());
+    let params = match ref_param {
+        Some(name) => format!("BuildContext context, WidgetRef {name}"),
+        None => String::from("BuildContext context"),
+    };
+    let statements = screen
+        .statements
+        .as_deref()
+        .map(|statements| indent_block(statements, 4))
+        .unwrap_or_default();
+    format!(
+        "  @override\n  Widget build({params}) {{\n{statements}    return {};\n  }}\n",
+        render_widget(&screen.root, 4, ref_name).trim_start()
+    )
+}
+
+fn render_state_field(field: &StateField) -> String {
+    let mut parts: Vec<&str> = field.modifiers.iter().map(String::as_str).collect();
+    if let Some(ty) = &field.ty {
+        parts.push(ty);
+    }
+    parts.push(&field.name);
+    let mut rendered = parts.join(" ");
+    if let Some(initializer) = &field.initializer {
+        rendered.push_str(" = ");
+        rendered.push_str(initializer);
+    }
+    rendered.push(';');
+    rendered
+}
+
+fn indent_block(text: &str, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut rendered = String::new();
+    for line in text.lines() {
+        if !line.trim().is_empty() {
+            rendered.push_str(&pad);
+            rendered.push_str(line);
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn render_widget(node: &WidgetNode, indent: usize, ref_name: &str) -> String {
+    if let Some(raw) = &node.raw {
+        return render_raw_dart(raw, indent);
+    }
+    if node.is_structural() {
+        return render_structural_expression(node, indent, ref_name);
+    }
+
     let mut buffer = String::new();
     buffer.push_str(&" ".repeat(indent));
     buffer.push_str(&node.widget);
     buffer.push_str("(\n");
 
-    let props = render_props(&node.props, indent + 2);
+    let mut props = render_props(&node.props, indent + 2, ref_name);
+    for builder in &node.builders {
+        if !props.is_empty() {
+            props.push_str(",\n");
+        }
+        props.push_str(&render_builder(builder, indent + 2, ref_name));
+    }
     if !props.is_empty() {
         buffer.push_str(&props);
         if !props.ends_with('\n') {
             buffer.push_str(",\n");
         } else {
-            buffer.push_str(",");
+            buffer.push(',');
         }
         buffer.push('\n');
     }
 
-    let children = render_children(&node.children, indent + 2);
+    let children = render_children(&node.children, indent + 2, ref_name);
     if !children.is_empty() {
         buffer.push_str(&children);
         buffer.push('\n');
     }
 
     buffer.push_str(&" ".repeat(indent));
-    buffer.push_str(")");
+    buffer.push(')');
     buffer
 }
 
-fn render_children(children: &[WidgetNode], indent: usize) -> String {
+/// Re-emits Dart the importer could not model. Continuation lines were
+/// dedented on import and are re-indented relative to `indent`.
+fn render_raw_dart(raw: &str, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    raw.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{pad}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders a builder callback argument. Callbacks without statements use an
+/// arrow body.
+fn render_builder(builder: &WidgetBuilder, indent: usize, ref_name: &str) -> String {
+    let pad = " ".repeat(indent);
+    let signature = format!(
+        "{pad}{}: ({})",
+        builder.argument,
+        builder.parameters.join(", ")
+    );
+    match &builder.statements {
+        None => format!(
+            "{signature} => {}",
+            render_widget(&builder.template, indent, ref_name).trim_start()
+        ),
+        Some(statements) => format!(
+            "{signature} {{\n{}{pad}  return {};\n{pad}}}",
+            indent_block(statements, indent + 2),
+            render_widget(&builder.template, indent + 2, ref_name).trim_start()
+        ),
+    }
+}
+
+/// Renders a structural node where Dart expects an expression: conditionals
+/// become ternaries and loops are wrapped in a `Column`.
+fn render_structural_expression(node: &WidgetNode, indent: usize, ref_name: &str) -> String {
+    let pad = " ".repeat(indent);
+    if node.widget == FOR_EACH_WIDGET {
+        return format!(
+            "{pad}Column(
+{pad}  children: [
+{},
+{pad}  ]
+{pad})",
+            render_element(node, indent + 4, ref_name)
+        );
+    }
+
+    let condition = render_condition(node, ref_name);
+    let then = render_branch(node.children.first(), indent, ref_name);
+    let otherwise = render_branch(node.children.get(1), indent, ref_name);
+    format!("{pad}{condition} ? {then} : {otherwise}")
+}
+
+fn render_branch(node: Option<&WidgetNode>, indent: usize, ref_name: &str) -> String {
+    match node {
+        Some(node) => render_widget(node, indent, ref_name)
+            .trim_start()
+            .to_string(),
+        None => String::from("const SizedBox.shrink()"),
+    }
+}
+
+fn render_condition(node: &WidgetNode, ref_name: &str) -> String {
+    node.condition().map_or_else(
+        || String::from("false"),
+        |value| render_prop_value(value, ref_name),
+    )
+}
+
+/// Renders an element of a `children` list, where structural nodes map onto
+/// collection `if` and `for` elements.
+fn render_element(node: &WidgetNode, indent: usize, ref_name: &str) -> String {
+    if !node.is_structural() {
+        return render_widget(node, indent, ref_name);
+    }
+
+    let pad = " ".repeat(indent);
+    let branch = |child: Option<&WidgetNode>| match child {
+        Some(child) => render_element(child, indent, ref_name)
+            .trim_start()
+            .to_string(),
+        None => String::from("const SizedBox.shrink()"),
+    };
+    match node.widget.as_str() {
+        CONDITIONAL_WIDGET => {
+            let mut rendered = format!(
+                "{pad}if ({}) {}",
+                render_condition(node, ref_name),
+                branch(node.children.first())
+            );
+            if let Some(otherwise) = node.children.get(1) {
+                rendered.push_str(" else ");
+                rendered.push_str(&branch(Some(otherwise)));
+            }
+            rendered
+        }
+        _ => {
+            let iterable = node.iterable().map_or_else(
+                || String::from("const []"),
+                |value| render_prop_value(value, ref_name),
+            );
+            format!(
+                "{pad}for (final {} in {iterable}) {}",
+                node.item_name(),
+                branch(node.children.first())
+            )
+        }
+    }
+}
+
+fn render_children(children: &[WidgetNode], indent: usize, ref_name: &str) -> String {
     if children.is_empty() {
         return String::new();
     }
@@ -82,15 +357,15 @@ fn render_children(children: &[WidgetNode], indent: usize) -> String {
     rendered.push_str(&" ".repeat(indent));
     rendered.push_str("children: [\n");
     for child in children {
-        rendered.push_str(&render_widget(child, indent + 2));
+        rendered.push_str(&render_element(child, indent + 2, ref_name));
         rendered.push_str(",\n");
     }
     rendered.push_str(&" ".repeat(indent));
-    rendered.push_str("]");
+    rendered.push(']');
     rendered
 }
 
-fn render_props(props: &BTreeMap<String, PropValue>, indent: usize) -> String {
+fn render_props(props: &BTreeMap<String, PropValue>, indent: usize, ref_name: &str) -> String {
     if props.is_empty() {
         return String::new();
     }
@@ -103,14 +378,14 @@ fn render_props(props: &BTreeMap<String, PropValue>, indent: usize) -> String {
         rendered.push_str(&" ".repeat(indent));
         rendered.push_str(key);
         rendered.push_str(": ");
-        rendered.push_str(&render_prop_value(value));
+        rendered.push_str(&render_prop_value(value, ref_name));
     }
     rendered
 }
 
-fn render_prop_value(value: &PropValue) -> String {
+fn render_prop_value(value: &PropValue, ref_name: &str) -> String {
     match value {
-        PropValue::Literal { value } => match value {
+        PropValue::Literal { value, .. } => match value {
             Value::String(s) => format!("\"{}\"", s),
             Value::Bool(true) => "true".to_string(),
             Value::Bool(false) => "false".to_string(),
@@ -118,17 +393,22 @@ fn render_prop_value(value: &PropValue) -> String {
             Value::Number(num) => num.to_string(),
             other => serde_json::to_string(other).unwrap_or_default(),
         },
-        PropValue::Expression { expression } => expression.clone(),
-        PropValue::Binding { binding } => render_binding(binding),
+        PropValue::Expression { expression, .. } => expression.clone(),
+        PropValue::Binding { binding, .. } => render_binding(binding, ref_name),
     }
 }
 
-fn render_binding(binding: &BindingReference) -> String {
+fn render_binding(binding: &BindingReference, ref_name: &str) -> String {
     if binding.target != BindingTarget::Provider {
         return String::from("<unsupported binding>");
     }
 
-    let mut rendered = format!("ref.watch({})", binding.reference);
+    let mut rendered = format!(
+        "{ref_name}.{}({}{})",
+        binding.access.as_str(),
+        binding.reference,
+        if binding.notifier { ".notifier" } else { "" }
+    );
     if let Some(path) = &binding.path {
         rendered.push('.');
         rendered.push_str(path);
@@ -139,19 +419,28 @@ fn render_binding(binding: &BindingReference) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{renderer_adapter::RenderOptions, state_adapter::RiverpodAdapter};
+    use crate::{
+        renderer_adapter::{RenderDialect, RenderOptions},
+        state_adapter::RiverpodAdapter,
+    };
 
     #[test]
     fn renderer_emits_widget_invocation() {
         let node = WidgetNode {
+            id: None,
             widget: "Text".to_string(),
             props: BTreeMap::from([(
                 String::from("data"),
                 PropValue::Literal {
                     value: Value::String("Hello".into()),
+                    span: None,
                 },
             )]),
             children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         };
         let renderer = FlutterRenderer;
         let adapter = RiverpodAdapter::new();
@@ -165,4 +454,28 @@ mod tests {
         assert!(unit.code.contains("Text"));
         assert!(unit.code.contains("\"Hello\""));
     }
+
+    #[test]
+    fn renderer_compiles_logic_expressions_to_dart() {
+        let renderer = FlutterRenderer;
+        let adapter = RiverpodAdapter::new();
+        let options = RenderOptions::default();
+        let ctx = RenderContext::new(0, &adapter, &options);
+
+        let unit = renderer
+            .render_expression("providers.cart.total * 2 > input.limit", &ctx)
+            .expect("compiled expression");
+        assert_eq!(
+            unit.code,
+            "(ref.watch(cartProvider)?.total * 2).compareTo(input?.limit) > 0"
+        );
+        let unit = renderer
+            .render_expression("input.items.sortBy(i => i.price)", &ctx)
+            .expect("compiled expression");
+        assert!(unit.code.starts_with("forgeSortBy("), "{}", unit.code);
+        assert_eq!(unit.helpers.len(), 1);
+
+        let err = renderer.render_expression("1 +", &ctx).unwrap_err();
+        assert!(err.message.starts_with("cannot compile `1 +`"), "{err}");
+    }
 }
@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+
+use crate::expr::{
+    list_operation, value_to_string, values_equal, EvalContext, ExprError, ExprResult,
+};
+
+/// The JSON type of an expression value, as declared by function signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueType {
+    Any,
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+    OneOf(Vec<ValueType>),
+}
+
+impl ValueType {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Number(_) => ValueType::Number,
+            Value::String(_) => ValueType::String,
+            Value::Array(_) => ValueType::Array,
+            Value::Object(_) => ValueType::Object,
+        }
+    }
+
+    /// The type with an article, for messages: `a string`, `an array or null`.
+    pub fn described(&self) -> String {
+        match self {
+            ValueType::Null => self.to_string(),
+            ValueType::OneOf(types) => match types.split_first() {
+                Some((first, rest)) => std::iter::once(first.described())
+                    .chain(rest.iter().map(ToString::to_string))
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                None => self.to_string(),
+            },
+            ValueType::Array | ValueType::Object | ValueType::Any => format!("an {self}"),
+            _ => format!("a {self}"),
+        }
+    }
+
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self {
+            ValueType::Any => true,
+            ValueType::OneOf(types) => types.iter().any(|ty| ty.accepts(value)),
+            ty => *ty == ValueType::of(value),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::Any => f.write_str("any"),
+            ValueType::Null => f.write_str("null"),
+            ValueType::Bool => f.write_str("bool"),
+            ValueType::Number => f.write_str("number"),
+            ValueType::String => f.write_str("string"),
+            ValueType::Array => f.write_str("array"),
+            ValueType::Object => f.write_str("object"),
+            ValueType::OneOf(types) => {
+                let names: Vec<String> = types.iter().map(ToString::to_string).collect();
+                f.write_str(&names.join(" or "))
+            }
+        }
+    }
+}
+
+pub type NativeFunction =
+    Arc<dyn Fn(&[Value], &EvalContext<'_>) -> ExprResult<Value> + Send + Sync>;
+
+/// Signature and implementation of a function callable from expressions.
+///
+/// Required parameters come first, then optional ones, then an optional
+/// variadic tail. Arguments are checked against the signature before the
+/// implementation runs, so implementations may assume the declared types.
+#[derive(Clone)]
+pub struct FunctionDescriptor {
+    pub name: String,
+    pub params: Vec<ValueType>,
+    pub required: usize,
+    pub variadic: Option<ValueType>,
+    pub returns: ValueType,
+    implementation: NativeFunction,
+}
+
+impl FunctionDescriptor {
+    pub fn new<S, F>(name: S, implementation: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&[Value], &EvalContext<'_>) -> ExprResult<Value> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            params: Vec::new(),
+            required: 0,
+            variadic: None,
+            returns: ValueType::Any,
+            implementation: Arc::new(implementation),
+        }
+    }
+
+    pub fn param(mut self, ty: ValueType) -> Self {
+        debug_assert_eq!(
+            self.required,
+            self.params.len(),
+            "required parameters must precede optional ones"
+        );
+        self.params.push(ty);
+        self.required += 1;
+        self
+    }
+
+    pub fn optional(mut self, ty: ValueType) -> Self {
+        self.params.push(ty);
+        self
+    }
+
+    pub fn variadic(mut self, ty: ValueType) -> Self {
+        self.variadic = Some(ty);
+        self
+    }
+
+    pub fn returns(mut self, ty: ValueType) -> Self {
+        self.returns = ty;
+        self
+    }
+
+    /// The declared type of argument `index`, if the function takes one there.
+    pub fn param_type(&self, index: usize) -> Option<&ValueType> {
+        self.params.get(index).or(self.variadic.as_ref())
+    }
+
+    /// Checks `count` against the signature's arity.
+    pub fn check_arity(&self, count: usize) -> ExprResult<()> {
+        let max = self.variadic.is_none().then_some(self.params.len());
+        if count >= self.required && max.is_none_or(|max| count <= max) {
+            return Ok(());
+        }
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        let expected = match max {
+            Some(max) if max == self.required => format!("{max} {}", plural(max)),
+            Some(max) => format!("{} to {max} {}", self.required, plural(max)),
+            None => format!("at least {} {}", self.required, plural(self.required)),
+        };
+        Err(ExprError::Arity {
+            function: self.name.clone(),
+            expected,
+            found: count,
+        })
+    }
+
+    /// Checks arity and argument types, then runs the function.
+    pub fn call(&self, args: &[Value], ctx: &EvalContext<'_>) -> ExprResult<Value> {
+        self.check_arity(args.len())?;
+        for (index, arg) in args.iter().enumerate() {
+            let expected = self.param_type(index).expect("arity was checked");
+            if !expected.accepts(arg) {
+                return Err(ExprError::ArgumentType {
+                    function: self.name.clone(),
+                    position: index + 1,
+                    expected: expected.described(),
+                    found: ValueType::of(arg).to_string(),
+                });
+            }
+        }
+        (self.implementation)(args, ctx)
+    }
+}
+
+impl fmt::Debug for FunctionDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionDescriptor")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("required", &self.required)
+            .field("variadic", &self.variadic)
+            .field("returns", &self.returns)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FunctionRegistryError {
+    #[error("function '{0}' is already registered")]
+    AlreadyRegistered(String),
+}
+
+/// Functions are looked up case-insensitively, so they are keyed in lowercase.
+static BUILTINS: Lazy<HashMap<String, FunctionDescriptor>> = Lazy::new(|| {
+    builtins()
+        .into_iter()
+        .map(|function| (function.name.to_ascii_lowercase(), function))
+        .collect()
+});
+
+static REGISTRY: Lazy<RwLock<HashMap<String, FunctionDescriptor>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Makes a host function callable from expressions. Builtins and the list
+/// operations such as `map` cannot be replaced.
+pub fn register_function(descriptor: FunctionDescriptor) -> Result<(), FunctionRegistryError> {
+    let key = descriptor.name.to_ascii_lowercase();
+    let mut registry = REGISTRY.write().unwrap();
+    if BUILTINS.contains_key(&key) || list_operation(&key).is_some() || registry.contains_key(&key)
+    {
+        return Err(FunctionRegistryError::AlreadyRegistered(descriptor.name));
+    }
+    registry.insert(key, descriptor);
+    Ok(())
+}
+
+/// Returns a builtin or host function by case-insensitive name.
+pub fn get_function(name: &str) -> Option<FunctionDescriptor> {
+    let key = name.to_ascii_lowercase();
+    if let Some(function) = BUILTINS.get(&key) {
+        return Some(function.clone());
+    }
+    REGISTRY.read().unwrap().get(&key).cloned()
+}
+
+/// Lists builtin and host functions.
+pub fn list_functions() -> Vec<FunctionDescriptor> {
+    let registry = REGISTRY.read().unwrap();
+    let mut functions: Vec<_> = BUILTINS
+        .values()
+        .chain(registry.values())
+        .cloned()
+        .collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    functions
+}
+
+/// Removes host functions. Intended for tests and should not be used at runtime.
+pub fn clear_functions() {
+    REGISTRY.write().unwrap().clear();
+}
+
+/// The builtin library. Conversions (`toNumber`, `parseDate`, `jsonParse`)
+/// return null for input they cannot convert so flows can fall back with `??`.
+/// Dates are RFC 3339 strings in UTC, as returned by `now()`.
+fn builtins() -> Vec<FunctionDescriptor> {
+    use ValueType::{Any, Array, Bool, Number, Object, String as Str};
+    let text = || ValueType::OneOf(vec![Str, Array]);
+
+    vec![
+        FunctionDescriptor::new("concat", |args, _| {
+            Ok(Value::String(args.iter().map(value_to_string).collect()))
+        })
+        .variadic(Any)
+        .returns(Str),
+        FunctionDescriptor::new("len", |args, _| {
+            number(match &args[0] {
+                Value::String(s) => s.chars().count() as f64,
+                other => as_array(other).len() as f64,
+            })
+        })
+        .param(text())
+        .returns(Number),
+        FunctionDescriptor::new("now", |_, ctx| Ok(Value::String(ctx.now().to_rfc3339())))
+            .returns(Str),
+        // Strings
+        FunctionDescriptor::new("upper", |args, _| {
+            Ok(Value::String(as_str(&args[0]).to_uppercase()))
+        })
+        .param(Str)
+        .returns(Str),
+        FunctionDescriptor::new("lower", |args, _| {
+            Ok(Value::String(as_str(&args[0]).to_lowercase()))
+        })
+        .param(Str)
+        .returns(Str),
+        FunctionDescriptor::new("trim", |args, _| {
+            Ok(Value::String(as_str(&args[0]).trim().to_string()))
+        })
+        .param(Str)
+        .returns(Str),
+        FunctionDescriptor::new("split", |args, _| {
+            let (text, separator) = (as_str(&args[0]), as_str(&args[1]));
+            let parts: Vec<Value> = if separator.is_empty() {
+                text.chars().map(|c| Value::String(c.to_string())).collect()
+            } else {
+                text.split(separator)
+                    .map(|part| Value::String(part.to_string()))
+                    .collect()
+            };
+            Ok(Value::Array(parts))
+        })
+        .param(Str)
+        .param(Str)
+        .returns(Array),
+        FunctionDescriptor::new("replace", |args, _| {
+            let (text, from, to) = (as_str(&args[0]), as_str(&args[1]), as_str(&args[2]));
+            if from.is_empty() {
+                return Err(ExprError::Evaluation(
+                    "replace() cannot replace an empty string".into(),
+                ));
+            }
+            Ok(Value::String(text.replace(from, to)))
+        })
+        .param(Str)
+        .param(Str)
+        .param(Str)
+        .returns(Str),
+        FunctionDescriptor::new("contains", |args, _| {
+            let found = match (&args[0], &args[1]) {
+                (Value::String(text), Value::String(needle)) => text.contains(needle.as_str()),
+                (Value::Array(items), needle) => {
+                    items.iter().any(|item| values_equal(item, needle))
+                }
+                (Value::Object(map), Value::String(key)) => map.contains_key(key),
+                // Strings and object keys are searched by string.
+                (_, other) => {
+                    return Err(ExprError::ArgumentType {
+                        function: "contains".into(),
+                        position: 2,
+                        expected: "a string".into(),
+                        found: ValueType::of(other).to_string(),
+                    })
+                }
+            };
+            Ok(Value::Bool(found))
+        })
+        .param(ValueType::OneOf(vec![Str, Array, Object]))
+        .param(Any)
+        .returns(Bool),
+        FunctionDescriptor::new("startsWith", |args, _| {
+            Ok(Value::Bool(as_str(&args[0]).starts_with(as_str(&args[1]))))
+        })
+        .param(Str)
+        .param(Str)
+        .returns(Bool),
+        // Math
+        FunctionDescriptor::new("round", |args, _| {
+            let value = as_f64(&args[0]);
+            match args.get(1) {
+                None => number(value.round()),
+                Some(digits) => {
+                    let digits = integer("round", 2, digits)?;
+                    let scale = 10f64.powi(digits as i32);
+                    number((value * scale).round() / scale)
+                }
+            }
+        })
+        .param(Number)
+        .optional(Number)
+        .returns(Number),
+        FunctionDescriptor::new("floor", |args, _| number(as_f64(&args[0]).floor()))
+            .param(Number)
+            .returns(Number),
+        FunctionDescriptor::new("abs", |args, _| number(as_f64(&args[0]).abs()))
+            .param(Number)
+            .returns(Number),
+        FunctionDescriptor::new("min", |args, _| extremum("min", args, f64::min))
+            .param(ValueType::OneOf(vec![Number, Array]))
+            .variadic(Number)
+            .returns(Number),
+        FunctionDescriptor::new("max", |args, _| extremum("max", args, f64::max))
+            .param(ValueType::OneOf(vec![Number, Array]))
+            .variadic(Number)
+            .returns(Number),
+        FunctionDescriptor::new("sum", |args, _| {
+            let values = numbers("sum", as_array(&args[0]))?;
+            number(values.into_iter().sum())
+        })
+        .param(Array)
+        .returns(Number),
+        // Collections
+        FunctionDescriptor::new("keys", |args, _| {
+            Ok(Value::Array(
+                as_object(&args[0])
+                    .keys()
+                    .cloned()
+                    .map(Value::String)
+                    .collect(),
+            ))
+        })
+        .param(Object)
+        .returns(Array),
+        FunctionDescriptor::new("values", |args, _| {
+            Ok(Value::Array(
+                as_object(&args[0]).values().cloned().collect(),
+            ))
+        })
+        .param(Object)
+        .returns(Array),
+        FunctionDescriptor::new("first", |args, _| Ok(edge(&args[0], true)))
+            .param(text())
+            .returns(Any),
+        FunctionDescriptor::new("last", |args, _| Ok(edge(&args[0], false)))
+            .param(text())
+            .returns(Any),
+        FunctionDescriptor::new("slice", |args, _| {
+            let len = match &args[0] {
+                Value::String(s) => s.chars().count(),
+                other => as_array(other).len(),
+            };
+            let start = slice_bound(&args[1], len, 2)?;
+            let end = match args.get(2) {
+                Some(end) => slice_bound(end, len, 3)?,
+                None => len,
+            }
+            .max(start);
+            Ok(match &args[0] {
+                Value::String(s) => {
+                    Value::String(s.chars().skip(start).take(end - start).collect())
+                }
+                other => Value::Array(as_array(other)[start..end].to_vec()),
+            })
+        })
+        .param(text())
+        .param(Number)
+        .optional(Number)
+        .returns(text()),
+        FunctionDescriptor::new("isEmpty", |args, _| {
+            Ok(Value::Bool(match &args[0] {
+                Value::Null => true,
+                Value::String(s) => s.is_empty(),
+                Value::Array(items) => items.is_empty(),
+                Value::Object(map) => map.is_empty(),
+                Value::Bool(_) | Value::Number(_) => false,
+            }))
+        })
+        .param(Any)
+        .returns(Bool),
+        // Dates
+        FunctionDescriptor::new("formatDate", |args, _| {
+            let date = parse_date(as_str(&args[0]), None).ok_or_else(|| {
+                ExprError::Evaluation(format!(
+                    "formatDate() argument 1 is not a date: {}",
+                    args[0]
+                ))
+            })?;
+            let format = date_format(as_str(&args[1]))?;
+            Ok(Value::String(date.format(&format).to_string()))
+        })
+        .param(Str)
+        .param(Str)
+        .returns(Str),
+        FunctionDescriptor::new("parseDate", |args, _| {
+            let format = args.get(1).map(|f| date_format(as_str(f))).transpose()?;
+            Ok(parse_date(as_str(&args[0]), format.as_deref())
+                .map(|date| Value::String(date.to_rfc3339()))
+                .unwrap_or(Value::Null))
+        })
+        .param(Str)
+        .optional(Str)
+        .returns(ValueType::OneOf(vec![Str, ValueType::Null])),
+        FunctionDescriptor::new("addDays", |args, _| {
+            let date = parse_date(as_str(&args[0]), None).ok_or_else(|| {
+                ExprError::Evaluation(format!("addDays() argument 1 is not a date: {}", args[0]))
+            })?;
+            let days = as_f64(&args[1]);
+            let out_of_range =
+                || ExprError::Evaluation(format!("addDays() cannot add {days} days to {date}"));
+            let seconds = (days * 86_400.0).round();
+            // Casting saturates, so bound the float before converting.
+            if !seconds.is_finite() || seconds.abs() >= i64::MAX as f64 {
+                return Err(out_of_range());
+            }
+            let shifted = Duration::try_seconds(seconds as i64)
+                .and_then(|duration| date.checked_add_signed(duration))
+                .ok_or_else(out_of_range)?;
+            Ok(Value::String(shifted.to_rfc3339()))
+        })
+        .param(Str)
+        .param(Number)
+        .returns(Str),
+        // Conversions
+        FunctionDescriptor::new("toNumber", |args, _| {
+            Ok(match &args[0] {
+                Value::Number(n) => Value::Number(n.clone()),
+                Value::String(s) => s
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|n| number(n).ok())
+                    .unwrap_or(Value::Null),
+                Value::Bool(b) => number(if *b { 1.0 } else { 0.0 })?,
+                _ => Value::Null,
+            })
+        })
+        .param(Any)
+        .returns(ValueType::OneOf(vec![Number, ValueType::Null])),
+        FunctionDescriptor::new("toString", |args, _| {
+            Ok(Value::String(value_to_string(&args[0])))
+        })
+        .param(Any)
+        .returns(Str),
+        FunctionDescriptor::new("jsonParse", |args, _| {
+            Ok(serde_json::from_str(as_str(&args[0])).unwrap_or(Value::Null))
+        })
+        .param(Str)
+        .returns(Any),
+    ]
+}
+
+fn number(value: f64) -> ExprResult<Value> {
+    serde_json::Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| ExprError::Evaluation("invalid number value".into()))
+}
+
+// The accessors below rely on the signature check done in `call`.
+
+fn as_str(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+fn as_f64(value: &Value) -> f64 {
+    value.as_f64().unwrap_or_default()
+}
+
+fn as_array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn as_object(value: &Value) -> &Map<String, Value> {
+    static EMPTY: Lazy<Map<String, Value>> = Lazy::new(Map::new);
+    value.as_object().unwrap_or(&EMPTY)
+}
+
+fn integer(function: &str, position: usize, value: &Value) -> ExprResult<i64> {
+    let n = as_f64(value);
+    if n.fract() != 0.0 {
+        return Err(ExprError::Evaluation(format!(
+            "{function}() argument {position} must be an integer, got {n}"
+        )));
+    }
+    Ok(n as i64)
+}
+
+fn numbers(function: &str, values: &[Value]) -> ExprResult<Vec<f64>> {
+    values
+        .iter()
+        .map(|value| {
+            value.as_f64().ok_or_else(|| {
+                ExprError::Evaluation(format!(
+                    "{function}() expects numbers, got {}",
+                    ValueType::of(value)
+                ))
+            })
+        })
+        .collect()
+}
+
+/// `min`/`max` over numeric arguments or a single array of numbers.
+fn extremum(function: &str, args: &[Value], pick: fn(f64, f64) -> f64) -> ExprResult<Value> {
+    let values = match args {
+        [Value::Array(items)] => numbers(function, items)?,
+        _ => numbers(function, args)?,
+    };
+    match values.into_iter().reduce(pick) {
+        Some(result) => number(result),
+        None => Err(ExprError::Evaluation(format!(
+            "{function}() of an empty array"
+        ))),
+    }
+}
+
+fn edge(value: &Value, first: bool) -> Value {
+    match value {
+        Value::String(s) => {
+            let c = if first {
+                s.chars().next()
+            } else {
+                s.chars().last()
+            };
+            c.map(|c| Value::String(c.to_string()))
+                .unwrap_or(Value::Null)
+        }
+        other => {
+            let items = as_array(other);
+            let item = if first { items.first() } else { items.last() };
+            item.cloned().unwrap_or(Value::Null)
+        }
+    }
+}
+
+/// A `slice` bound; negative bounds count from the end and bounds are clamped.
+fn slice_bound(value: &Value, len: usize, position: usize) -> ExprResult<usize> {
+    let bound = integer("slice", position, value)?;
+    let len = len as i64;
+    Ok(if bound < 0 { len + bound } else { bound }.clamp(0, len) as usize)
+}
+
+/// Parses RFC 3339 and plain ISO dates, or `format` when given.
+fn parse_date(text: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    let naive = |format: &str| {
+        NaiveDateTime::parse_from_str(text, format)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(text, format)
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .map(|naive| naive.and_utc())
+    };
+    match format {
+        Some(format) => naive(format),
+        None => DateTime::parse_from_rfc3339(text)
+            .map(|date| date.with_timezone(&Utc))
+            .ok()
+            .or_else(|| naive("%Y-%m-%dT%H:%M:%S"))
+            .or_else(|| naive("%Y-%m-%d %H:%M:%S"))
+            .or_else(|| naive("%Y-%m-%d")),
+    }
+}
+
+/// Translates a date pattern in the notation of Dart's `DateFormat`
+/// (`yyyy-MM-dd HH:mm`, `'quoted'` literals) into a chrono format string.
+fn date_format(pattern: &str) -> ExprResult<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut format = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&next| next == c).count();
+        if c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&next| next == '\'')
+                .ok_or_else(|| {
+                    ExprError::Evaluation(format!("unterminated quote in date pattern '{pattern}'"))
+                })?;
+            if end == 0 {
+                format.push('\'');
+            }
+            for &literal in &chars[i + 1..i + 1 + end] {
+                if literal == '%' {
+                    format.push_str("%%");
+                } else {
+                    format.push(literal);
+                }
+            }
+            i += end + 2;
+            continue;
+        }
+        if !c.is_ascii_alphabetic() {
+            if c == '%' {
+                format.push_str("%%");
+            } else {
+                format.push(c);
+            }
+            i += 1;
+            continue;
+        }
+        let spec = match (c, run) {
+            ('y', 2) => "%y",
+            ('y', _) => "%Y",
+            ('M', 1) => "%-m",
+            ('M', 2) => "%m",
+            ('M', 3) => "%b",
+            ('M', _) => "%B",
+            ('d', 1) => "%-d",
+            ('d', _) => "%d",
+            ('E', 1..=3) => "%a",
+            ('E', _) => "%A",
+            ('H', 1) => "%-H",
+            ('H', _) => "%H",
+            ('h', 1) => "%-I",
+            ('h', _) => "%I",
+            ('m', 1) => "%-M",
+            ('m', _) => "%M",
+            ('s', 1) => "%-S",
+            ('s', _) => "%S",
+            ('a', _) => "%p",
+            _ => {
+                return Err(ExprError::Evaluation(format!(
+                    "unsupported date pattern letter '{c}' in '{pattern}'"
+                )))
+            }
+        };
+        format.push_str(spec);
+        i += run;
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{eval_expression, parse_expression};
+    use serde_json::json;
+
+    fn eval(src: &str) -> ExprResult<Value> {
+        let vars = json!({
+            "name": "  Ada Lovelace ",
+            "items": [3, 1, 2],
+            "user": {"id": 7, "role": "admin"},
+            "day": "2024-02-28T10:30:00+00:00",
+        });
+        let ctx = EvalContext::with_now(&vars);
+        eval_expression(&parse_expression(src)?, &ctx)
+    }
+
+    #[test]
+    fn string_math_and_collection_builtins() {
+        for (src, expected) in [
+            ("upper(trim(name))", json!("ADA LOVELACE")),
+            ("split(trim(name), \" \")", json!(["Ada", "Lovelace"])),
+            ("replace(\"a-b-c\", \"-\", \"+\")", json!("a+b+c")),
+            ("contains(name, \"Love\")", json!(true)),
+            ("contains(items, 2)", json!(true)),
+            ("contains(user, \"role\")", json!(true)),
+            ("startsWith(lower(trim(name)), \"ada\")", json!(true)),
+            ("round(2.345, 2)", json!(2.35)),
+            ("floor(-1.5) + abs(-4)", json!(2.0)),
+            ("min(items) + max(4, 9, 5)", json!(10.0)),
+            ("sum(items)", json!(6.0)),
+            ("keys(user)", json!(["id", "role"])),
+            ("first(items) + last(items)", json!(5.0)),
+            ("slice(items, -2)", json!([1, 2])),
+            ("slice(\"forge\", 1, 3)", json!("or")),
+            ("isEmpty(missing) && !isEmpty(items)", json!(true)),
+            ("toNumber(\" 42 \") + toNumber(true)", json!(43.0)),
+            ("toNumber(\"abc\") ?? 0", json!(0.0)),
+            ("toString(user.id)", json!("7")),
+            ("jsonParse(\"{\\\"a\\\": [1]}\").a[0]", json!(1)),
+        ] {
+            assert_eq!(eval(src).unwrap(), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn date_builtins_use_dart_style_patterns() {
+        assert_eq!(
+            eval("formatDate(addDays(day, 2), \"EEE d MMM yyyy, HH:mm\")").unwrap(),
+            json!("Fri 1 Mar 2024, 10:30")
+        );
+        assert_eq!(
+            eval("parseDate(\"28/02/2024\", \"dd/MM/yyyy\")").unwrap(),
+            json!("2024-02-28T00:00:00+00:00")
+        );
+        assert_eq!(eval("parseDate(\"not a date\")").unwrap(), Value::Null);
+        assert_eq!(
+            eval("formatDate(day, \"'at' h a\")").unwrap(),
+            json!("at 10 AM")
+        );
+        assert_eq!(eval("formatDate(day, \"'100%'\")").unwrap(), json!("100%"));
+        assert_eq!(
+            eval("formatDate(day, \"yyyy'%d'\")").unwrap(),
+            json!("2024%d")
+        );
+    }
+
+    #[test]
+    fn add_days_rejects_out_of_range_results() {
+        for src in [
+            "addDays(day, 100000000000000)",
+            "addDays(day, -100000000000000)",
+            "addDays(day, 100000000000000 * 100000000000000 * 100000000000000)",
+        ] {
+            assert!(matches!(eval(src), Err(ExprError::Evaluation(_))), "{src}");
+        }
+    }
+
+    #[test]
+    fn misuse_reports_one_clear_error() {
+        let message = |src: &str| eval(src).unwrap_err().to_string();
+        assert_eq!(message("upper()"), "upper() expects 1 argument, got 0");
+        assert_eq!(
+            message("slice(items)"),
+            "slice() expects 2 to 3 arguments, got 1"
+        );
+        assert_eq!(message("min()"), "min() expects at least 1 argument, got 0");
+        assert_eq!(
+            message("upper(user.id)"),
+            "upper() argument 1 must be a string, got number"
+        );
+        assert_eq!(
+            message("len(user)"),
+            "len() argument 1 must be a string or array, got object"
+        );
+        assert_eq!(
+            message("contains(user, 7)"),
+            "contains() argument 2 must be a string, got number"
+        );
+        assert_eq!(message("nope(1)"), "unknown function nope");
+        assert_eq!(
+            message("round(1.5, 0.5)"),
+            "evaluation error: round() argument 2 must be an integer, got 0.5"
+        );
+    }
+
+    #[test]
+    fn host_functions_can_be_registered() {
+        let greet = FunctionDescriptor::new("greetHost", |args, _| {
+            Ok(Value::String(format!("Hello, {}", as_str(&args[0]))))
+        })
+        .param(ValueType::String)
+        .returns(ValueType::String);
+        register_function(greet.clone()).unwrap();
+        assert!(matches!(
+            register_function(greet),
+            Err(FunctionRegistryError::AlreadyRegistered(_))
+        ));
+        assert!(
+            register_function(FunctionDescriptor::new("UPPER", |_, _| Ok(Value::Null))).is_err()
+        );
+
+        assert_eq!(eval("greetHost(\"Ada\")").unwrap(), json!("Hello, Ada"));
+        assert!(list_functions().iter().any(|f| f.name == "greetHost"));
+        assert_eq!(get_function("formatdate").unwrap().name, "formatDate");
+    }
+}
@@ -0,0 +1,877 @@
+use std::{collections::BTreeSet, fmt};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::logic_merge::edge_key;
+use crate::merge_engine::match_children;
+use crate::{
+    Flow, LogicGraph, LogicNode, PropValue, SchemaDocument, ScreenGraph, WidgetBuilder, WidgetNode,
+};
+
+/// One change between two versions of a graph. Paths use the notation of
+/// [crate::MergeConflict]: those of added, moved and changed nodes point into
+/// the new version, those of removed nodes into the old one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DiffOp {
+    ScreenAdded {
+        screen: String,
+    },
+    ScreenRemoved {
+        screen: String,
+    },
+    NodeAdded {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        widget: String,
+    },
+    NodeRemoved {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        widget: String,
+    },
+    NodeMoved {
+        from_path: String,
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        widget: String,
+    },
+    WidgetTypeChanged {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        before: String,
+        after: String,
+    },
+    PropChanged {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        prop: String,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+    /// A prop that is or becomes a provider, widget or logic binding.
+    BindingChanged {
+        path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node_id: Option<String>,
+        prop: String,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+    LogicNodeAdded {
+        path: String,
+        node_id: String,
+    },
+    LogicNodeRemoved {
+        path: String,
+        node_id: String,
+    },
+    EdgeAdded {
+        path: String,
+    },
+    EdgeRemoved {
+        path: String,
+    },
+    /// Any other field, such as project metadata, a screen's state or a
+    /// logic node's props.
+    ValueChanged {
+        path: String,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+}
+
+/// Changes between two graphs, in document order.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct GraphDiff {
+    pub ops: Vec<DiffOp>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// One line per change, for terminals and review comments.
+    pub fn summary(&self) -> String {
+        if self.ops.is_empty() {
+            return "No differences.".to_string();
+        }
+        let mut summary = format!("{} change(s):", self.ops.len());
+        for op in &self.ops {
+            summary.push_str(&format!("\n  • {op}"));
+        }
+        summary
+    }
+}
+
+impl fmt::Display for DiffOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffOp::ScreenAdded { screen } => write!(f, "screen {screen} added"),
+            DiffOp::ScreenRemoved { screen } => write!(f, "screen {screen} removed"),
+            DiffOp::NodeAdded { path, widget, .. } => write!(f, "{path}: {widget} added"),
+            DiffOp::NodeRemoved { path, widget, .. } => write!(f, "{path}: {widget} removed"),
+            DiffOp::NodeMoved {
+                from_path,
+                path,
+                widget,
+                ..
+            } => write!(f, "{path}: {widget} moved from {from_path}"),
+            DiffOp::WidgetTypeChanged {
+                path,
+                before,
+                after,
+                ..
+            } => write!(f, "{path}: widget {before} → {after}"),
+            DiffOp::PropChanged {
+                path,
+                prop,
+                before,
+                after,
+                ..
+            } => write!(
+                f,
+                "{path}: prop \"{prop}\" {} → {}",
+                show(before),
+                show(after)
+            ),
+            DiffOp::BindingChanged {
+                path,
+                prop,
+                before,
+                after,
+                ..
+            } => write!(
+                f,
+                "{path}: binding \"{prop}\" {} → {}",
+                show(before),
+                show(after)
+            ),
+            DiffOp::LogicNodeAdded { path, .. } => write!(f, "{path}: logic node added"),
+            DiffOp::LogicNodeRemoved { path, .. } => write!(f, "{path}: logic node removed"),
+            DiffOp::EdgeAdded { path } => write!(f, "{path}: edge added"),
+            DiffOp::EdgeRemoved { path } => write!(f, "{path}: edge removed"),
+            DiffOp::ValueChanged {
+                path,
+                before,
+                after,
+            } => write!(f, "{path}: {} → {}", show(before), show(after)),
+        }
+    }
+}
+
+fn show(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "(none)".to_string(), Value::to_string)
+}
+
+/// Structural diff of two versions of a screen. Children are matched the way
+/// [crate::merge_screen_graphs] matches them, by node id and similarity, so
+/// an insertion is reported as one added node rather than a shifted list.
+pub fn diff_screen_graphs(before: &ScreenGraph, after: &ScreenGraph) -> GraphDiff {
+    let mut differ = Differ::default();
+    differ.screen("screen", before, after);
+    differ.finish()
+}
+
+/// Structural diff of two schema documents: project metadata, screens
+/// matched by id and logic graphs matched by position, with flows, nodes and
+/// edges matched as in [crate::merge_logic_graphs].
+pub fn diff_documents(before: &SchemaDocument, after: &SchemaDocument) -> GraphDiff {
+    let mut differ = Differ::default();
+    differ.value(
+        "forge_schema_version",
+        Some(&before.version),
+        Some(&after.version),
+    );
+    differ.value(
+        "project.id",
+        Some(&before.project.id),
+        Some(&after.project.id),
+    );
+    differ.value(
+        "project.name",
+        Some(&before.project.name),
+        Some(&after.project.name),
+    );
+    differ.value(
+        "project.description",
+        before.project.description.as_ref(),
+        after.project.description.as_ref(),
+    );
+
+    for screen in &before.screens {
+        match after.screens.iter().find(|other| other.id == screen.id) {
+            Some(other) => differ.screen(&format!("screens[{}]", screen.id), screen, other),
+            None => differ.ops.push(DiffOp::ScreenRemoved {
+                screen: screen.id.clone(),
+            }),
+        }
+    }
+    for screen in &after.screens {
+        if !before.screens.iter().any(|other| other.id == screen.id) {
+            differ.ops.push(DiffOp::ScreenAdded {
+                screen: screen.id.clone(),
+            });
+        }
+    }
+
+    let graphs = before.logic.len().max(after.logic.len());
+    for idx in 0..graphs {
+        let path = format!("logic[{idx}]");
+        match (before.logic.get(idx), after.logic.get(idx)) {
+            (Some(old), Some(new)) => differ.logic(&path, old, new),
+            (old, new) => differ.value(&path, old, new),
+        }
+    }
+    differ.value(
+        "metadata",
+        before.metadata.as_ref(),
+        after.metadata.as_ref(),
+    );
+    differ.finish()
+}
+
+#[derive(Default)]
+struct Differ<'a> {
+    ops: Vec<DiffOp>,
+    /// Nodes reported removed or added, by op index, so a node that changed
+    /// parent can be reported as a move once the whole tree was seen.
+    removed: Vec<(usize, &'a WidgetNode)>,
+    added: Vec<(usize, &'a WidgetNode)>,
+}
+
+impl<'a> Differ<'a> {
+    fn finish(mut self) -> GraphDiff {
+        let mut dropped = BTreeSet::new();
+        let mut moved = Vec::new();
+        for (removed_at, old) in std::mem::take(&mut self.removed) {
+            let Some(id) = &old.id else {
+                continue;
+            };
+            let Some(&(added_at, new)) = self
+                .added
+                .iter()
+                .find(|(at, node)| node.id.as_ref() == Some(id) && !dropped.contains(at))
+            else {
+                continue;
+            };
+            let (
+                DiffOp::NodeRemoved {
+                    path: from_path, ..
+                },
+                DiffOp::NodeAdded { path, .. },
+            ) = (&self.ops[removed_at], &self.ops[added_at])
+            else {
+                continue;
+            };
+            moved.push((from_path.clone(), path.clone(), old, new));
+            self.ops[removed_at] = DiffOp::NodeMoved {
+                from_path: from_path.clone(),
+                path: path.clone(),
+                node_id: Some(id.clone()),
+                widget: new.widget.clone(),
+            };
+            dropped.insert(added_at);
+        }
+
+        let mut idx = 0;
+        self.ops.retain(|_| {
+            idx += 1;
+            !dropped.contains(&(idx - 1))
+        });
+        for (from_path, path, old, new) in moved {
+            self.node(&from_path, &path, old, new);
+        }
+        GraphDiff { ops: self.ops }
+    }
+
+    fn value<T: Serialize + PartialEq>(
+        &mut self,
+        path: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        if before != after {
+            self.ops.push(DiffOp::ValueChanged {
+                path: path.to_string(),
+                before: before.and_then(|value| serde_json::to_value(value).ok()),
+                after: after.and_then(|value| serde_json::to_value(value).ok()),
+            });
+        }
+    }
+
+    fn screen(&mut self, path: &str, before: &'a ScreenGraph, after: &'a ScreenGraph) {
+        self.value(&format!("{path}.id"), Some(&before.id), Some(&after.id));
+        let root = format!("{path}.root");
+        self.node(&root, &root, &before.root, &after.root);
+        self.value(
+            &format!("{path}.state"),
+            before.state.as_ref(),
+            after.state.as_ref(),
+        );
+        self.value(
+            &format!("{path}.consumer"),
+            before.consumer.as_ref(),
+            after.consumer.as_ref(),
+        );
+        self.value(
+            &format!("{path}.statements"),
+            before.statements.as_ref(),
+            after.statements.as_ref(),
+        );
+    }
+
+    fn node(
+        &mut self,
+        before_path: &str,
+        after_path: &str,
+        before: &'a WidgetNode,
+        after: &'a WidgetNode,
+    ) {
+        let node_id = after.id.clone().or_else(|| before.id.clone());
+        if before.widget != after.widget {
+            self.ops.push(DiffOp::WidgetTypeChanged {
+                path: after_path.to_string(),
+                node_id: node_id.clone(),
+                before: before.widget.clone(),
+                after: after.widget.clone(),
+            });
+        }
+
+        let keys: BTreeSet<&String> = before.props.keys().chain(after.props.keys()).collect();
+        for key in keys {
+            let (old, new) = (before.props.get(key), after.props.get(key));
+            if old == new {
+                continue;
+            }
+            let is_binding =
+                |value: Option<&PropValue>| matches!(value, Some(PropValue::Binding { .. }));
+            let (prop, before, after) = (key.clone(), old.map(prop_json), new.map(prop_json));
+            let path = after_path.to_string();
+            let node_id = node_id.clone();
+            self.ops.push(if is_binding(old) || is_binding(new) {
+                DiffOp::BindingChanged {
+                    path,
+                    node_id,
+                    prop,
+                    before,
+                    after,
+                }
+            } else {
+                DiffOp::PropChanged {
+                    path,
+                    node_id,
+                    prop,
+                    before,
+                    after,
+                }
+            });
+        }
+
+        self.value(
+            &format!("{after_path}.raw"),
+            before.raw.as_ref(),
+            after.raw.as_ref(),
+        );
+        self.value(
+            &format!("{after_path}.component"),
+            before.component.as_ref(),
+            after.component.as_ref(),
+        );
+        self.children(
+            &format!("{before_path}.children"),
+            &format!("{after_path}.children"),
+            &before.children,
+            &after.children,
+        );
+        self.builders(before_path, after_path, &before.builders, &after.builders);
+    }
+
+    fn children(
+        &mut self,
+        before_path: &str,
+        after_path: &str,
+        before: &'a [WidgetNode],
+        after: &'a [WidgetNode],
+    ) {
+        let pairs = match_children(before, after);
+        let in_order = longest_ordered(&pairs);
+
+        for (idx, node) in before.iter().enumerate() {
+            if !pairs.iter().any(|(old, _)| *old == idx) {
+                self.removed.push((self.ops.len(), node));
+                self.ops.push(DiffOp::NodeRemoved {
+                    path: format!("{before_path}[{idx}]"),
+                    node_id: node.id.clone(),
+                    widget: node.widget.clone(),
+                });
+            }
+        }
+        for (idx, node) in after.iter().enumerate() {
+            let path = format!("{after_path}[{idx}]");
+            let Some(&(old, _)) = pairs.iter().find(|(_, new)| *new == idx) else {
+                self.added.push((self.ops.len(), node));
+                self.ops.push(DiffOp::NodeAdded {
+                    path,
+                    node_id: node.id.clone(),
+                    widget: node.widget.clone(),
+                });
+                continue;
+            };
+            let from_path = format!("{before_path}[{old}]");
+            if !in_order.contains(&(old, idx)) {
+                self.ops.push(DiffOp::NodeMoved {
+                    from_path: from_path.clone(),
+                    path: path.clone(),
+                    node_id: node.id.clone().or_else(|| before[old].id.clone()),
+                    widget: node.widget.clone(),
+                });
+            }
+            self.node(&from_path, &path, &before[old], node);
+        }
+    }
+
+    fn builders(
+        &mut self,
+        before_path: &str,
+        after_path: &str,
+        before: &'a [WidgetBuilder],
+        after: &'a [WidgetBuilder],
+    ) {
+        for builder in before {
+            let old_path = format!("{before_path}.builders.{}", builder.argument);
+            match after
+                .iter()
+                .find(|other| other.argument == builder.argument)
+            {
+                Some(other) => {
+                    let new_path = format!("{after_path}.builders.{}", builder.argument);
+                    self.value(
+                        &format!("{new_path}.parameters"),
+                        Some(&builder.parameters),
+                        Some(&other.parameters),
+                    );
+                    self.value(
+                        &format!("{new_path}.statements"),
+                        builder.statements.as_ref(),
+                        other.statements.as_ref(),
+                    );
+                    self.node(
+                        &format!("{old_path}.template"),
+                        &format!("{new_path}.template"),
+                        &builder.template,
+                        &other.template,
+                    );
+                }
+                None => self.value(&old_path, Some(builder), None),
+            }
+        }
+        for builder in after {
+            if !before
+                .iter()
+                .any(|other| other.argument == builder.argument)
+            {
+                let path = format!("{after_path}.builders.{}", builder.argument);
+                self.value(&path, None, Some(builder));
+            }
+        }
+    }
+
+    fn logic(&mut self, path: &str, before: &LogicGraph, after: &LogicGraph) {
+        for flow in &before.flows {
+            let flow_path = format!("{path}.flows[{}]", flow.id);
+            match after.flows.iter().find(|other| other.id == flow.id) {
+                Some(other) => self.flow(&flow_path, flow, other),
+                None => self.value(&flow_path, Some(flow), None),
+            }
+        }
+        for flow in &after.flows {
+            if !before.flows.iter().any(|other| other.id == flow.id) {
+                self.value(&format!("{path}.flows[{}]", flow.id), None, Some(flow));
+            }
+        }
+        self.value(
+            &format!("{path}.metadata"),
+            before.metadata.as_ref(),
+            after.metadata.as_ref(),
+        );
+    }
+
+    fn flow(&mut self, path: &str, before: &Flow, after: &Flow) {
+        self.value(
+            &format!("{path}.name"),
+            before.name.as_ref(),
+            after.name.as_ref(),
+        );
+        for node in &before.nodes {
+            let node_path = format!("{path}.nodes[{}]", node.id);
+            match after.nodes.iter().find(|other| other.id == node.id) {
+                Some(other) => self.logic_node(&node_path, node, other),
+                None => self.ops.push(DiffOp::LogicNodeRemoved {
+                    path: node_path,
+                    node_id: node.id.clone(),
+                }),
+            }
+        }
+        for node in &after.nodes {
+            if !before.nodes.iter().any(|other| other.id == node.id) {
+                self.ops.push(DiffOp::LogicNodeAdded {
+                    path: format!("{path}.nodes[{}]", node.id),
+                    node_id: node.id.clone(),
+                });
+            }
+        }
+
+        let before_edges: Vec<String> = before.edges.iter().map(edge_key).collect();
+        let after_edges: Vec<String> = after.edges.iter().map(edge_key).collect();
+        for key in &before_edges {
+            if !after_edges.contains(key) {
+                self.ops.push(DiffOp::EdgeRemoved {
+                    path: format!("{path}.edges[{key}]"),
+                });
+            }
+        }
+        for (key, edge) in after_edges.iter().zip(&after.edges) {
+            match before_edges.iter().position(|other| other == key) {
+                Some(at) => self.value(
+                    &format!("{path}.edges[{key}].metadata"),
+                    before.edges[at].metadata.as_ref(),
+                    edge.metadata.as_ref(),
+                ),
+                None => self.ops.push(DiffOp::EdgeAdded {
+                    path: format!("{path}.edges[{key}]"),
+                }),
+            }
+        }
+
+        self.value(
+            &format!("{path}.entry_nodes"),
+            Some(&before.entry_nodes),
+            Some(&after.entry_nodes),
+        );
+        self.value(
+            &format!("{path}.metadata"),
+            before.metadata.as_ref(),
+            after.metadata.as_ref(),
+        );
+    }
+
+    fn logic_node(&mut self, path: &str, before: &LogicNode, after: &LogicNode) {
+        self.value(
+            &format!("{path}.name"),
+            before.name.as_ref(),
+            after.name.as_ref(),
+        );
+        self.value(
+            &format!("{path}.kind"),
+            before.kind.as_ref(),
+            after.kind.as_ref(),
+        );
+        self.value(
+            &format!("{path}.custom_kind"),
+            before.custom_kind.as_ref(),
+            after.custom_kind.as_ref(),
+        );
+        match (&before.props, &after.props) {
+            (Value::Object(old), Value::Object(new)) => {
+                let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+                for key in keys {
+                    self.value(&format!("{path}.props.{key}"), old.get(key), new.get(key));
+                }
+            }
+            (old, new) => self.value(&format!("{path}.props"), Some(old), Some(new)),
+        }
+        self.value(
+            &format!("{path}.inputs"),
+            Some(&before.inputs),
+            Some(&after.inputs),
+        );
+        self.value(
+            &format!("{path}.outputs"),
+            Some(&before.outputs),
+            Some(&after.outputs),
+        );
+        self.value(
+            &format!("{path}.metadata"),
+            before.metadata.as_ref(),
+            after.metadata.as_ref(),
+        );
+    }
+}
+
+/// JSON of a prop without its source span, which is not part of the value.
+fn prop_json(value: &PropValue) -> Value {
+    let mut json = serde_json::to_value(value).unwrap_or(Value::Null);
+    if let Value::Object(object) = &mut json {
+        object.remove("span");
+    }
+    json
+}
+
+/// The largest set of matched pairs that kept their relative order; every
+/// other pair moved.
+fn longest_ordered(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut sorted = pairs.to_vec();
+    sorted.sort();
+    let mut best: Vec<usize> = vec![1; sorted.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; sorted.len()];
+    for i in 0..sorted.len() {
+        for j in 0..i {
+            if sorted[j].1 < sorted[i].1 && best[j] + 1 > best[i] {
+                best[i] = best[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut chain = Vec::new();
+    let mut cursor = (0..sorted.len()).max_by_key(|&i| (best[i], std::cmp::Reverse(i)));
+    while let Some(i) = cursor {
+        chain.push(sorted[i]);
+        cursor = previous[i];
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BindingReference, BindingTarget, LogicEdge, ProviderAccess, SchemaProject, SchemaWriter,
+    };
+    use serde_json::json;
+
+    fn node(widget: &str, id: &str, props: &[(&str, PropValue)]) -> WidgetNode {
+        WidgetNode {
+            id: Some(id.to_string()),
+            widget: widget.to_string(),
+            props: props
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
+        }
+    }
+
+    fn literal(value: &str) -> PropValue {
+        PropValue::Literal {
+            value: json!(value),
+            span: None,
+        }
+    }
+
+    fn screen(children: Vec<WidgetNode>) -> ScreenGraph {
+        let mut root = node("Column", "root", &[]);
+        root.children = children;
+        ScreenGraph {
+            id: "Home".to_string(),
+            root,
+            state: None,
+            consumer: None,
+            statements: None,
+        }
+    }
+
+    #[test]
+    fn identical_graphs_have_no_differences() {
+        let graph = screen(vec![node("Text", "a", &[("data", literal("Hi"))])]);
+        let diff = diff_screen_graphs(&graph, &graph);
+        assert!(diff.is_empty());
+        assert_eq!(diff.summary(), "No differences.");
+    }
+
+    #[test]
+    fn reports_typed_node_and_prop_changes() {
+        let before = screen(vec![
+            node("Text", "a", &[("data", literal("Hi"))]),
+            node("Icon", "b", &[]),
+            node("Spacer", "c", &[]),
+        ]);
+        let counter = PropValue::Binding {
+            binding: BindingReference {
+                target: BindingTarget::Provider,
+                reference: "counterProvider".to_string(),
+                provider_id: None,
+                path: None,
+                type_hint: None,
+                access: ProviderAccess::Watch,
+                notifier: false,
+            },
+            span: None,
+        };
+        let after = screen(vec![
+            node("Divider", "new", &[]),
+            node("SelectableText", "a", &[("data", counter)]),
+            node("Icon", "b", &[("size", literal("24"))]),
+        ]);
+
+        let diff = diff_screen_graphs(&before, &after);
+        let ops: Vec<&str> = diff
+            .ops
+            .iter()
+            .map(|op| match op {
+                DiffOp::NodeAdded { .. } => "added",
+                DiffOp::NodeRemoved { .. } => "removed",
+                DiffOp::WidgetTypeChanged { .. } => "type",
+                DiffOp::BindingChanged { .. } => "binding",
+                DiffOp::PropChanged { .. } => "prop",
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(ops, ["removed", "added", "type", "binding", "prop"]);
+        assert_eq!(
+            diff.ops[0],
+            DiffOp::NodeRemoved {
+                path: "screen.root.children[2]".to_string(),
+                node_id: Some("c".to_string()),
+                widget: "Spacer".to_string(),
+            }
+        );
+        assert_eq!(
+            diff.ops[4],
+            DiffOp::PropChanged {
+                path: "screen.root.children[2]".to_string(),
+                node_id: Some("b".to_string()),
+                prop: "size".to_string(),
+                before: None,
+                after: Some(json!({ "type": "literal", "value": "24" })),
+            }
+        );
+        assert!(diff
+            .summary()
+            .contains("screen.root.children[1]: widget Text → SelectableText"));
+    }
+
+    #[test]
+    fn detects_moves_within_and_across_parents() {
+        let mut card = node("Card", "card", &[]);
+        card.children = vec![node("Text", "title", &[("data", literal("Hi"))])];
+        let before = screen(vec![
+            node("Icon", "icon", &[]),
+            card.clone(),
+            node("Spacer", "gap", &[]),
+        ]);
+
+        let mut emptied = card.clone();
+        emptied.children.clear();
+        let after = screen(vec![
+            node("Spacer", "gap", &[]),
+            node("Text", "title", &[("data", literal("Hello"))]),
+            node("Icon", "icon", &[]),
+            emptied,
+        ]);
+
+        let diff = diff_screen_graphs(&before, &after);
+        let moves: Vec<(&str, &str)> = diff
+            .ops
+            .iter()
+            .filter_map(|op| match op {
+                DiffOp::NodeMoved {
+                    from_path, path, ..
+                } => Some((from_path.as_str(), path.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                ("screen.root.children[2]", "screen.root.children[0]"),
+                (
+                    "screen.root.children[1].children[0]",
+                    "screen.root.children[1]"
+                ),
+            ]
+        );
+        assert!(diff.ops.iter().any(|op| matches!(
+            op,
+            DiffOp::PropChanged { node_id: Some(id), prop, .. } if id == "title" && prop == "data"
+        )));
+        assert!(!diff
+            .ops
+            .iter()
+            .any(|op| matches!(op, DiffOp::NodeAdded { .. } | DiffOp::NodeRemoved { .. })));
+    }
+
+    #[test]
+    fn documents_diff_screens_and_logic() {
+        let flow = |nodes: &[&str], edges: &[(&str, &str)]| LogicGraph {
+            flows: vec![Flow {
+                id: "main".to_string(),
+                name: None,
+                nodes: nodes
+                    .iter()
+                    .map(|id| LogicNode {
+                        id: id.to_string(),
+                        name: None,
+                        kind: None,
+                        custom_kind: None,
+                        props: json!({}),
+                        inputs: Vec::new(),
+                        outputs: Vec::new(),
+                        metadata: None,
+                    })
+                    .collect(),
+                edges: edges
+                    .iter()
+                    .map(|(from, to)| LogicEdge {
+                        from_node: from.to_string(),
+                        from_port: None,
+                        to_node: to.to_string(),
+                        to_port: None,
+                        metadata: None,
+                    })
+                    .collect(),
+                entry_nodes: Vec::new(),
+                metadata: None,
+            }],
+            metadata: None,
+        };
+        let mut other = screen(Vec::new());
+        other.id = "About".to_string();
+        let before = SchemaWriter::build_document(
+            SchemaProject::new("app", "App"),
+            vec![screen(Vec::new()), other],
+            vec![flow(&["a", "b"], &[("a", "b")])],
+            None,
+        );
+        let after = SchemaWriter::build_document(
+            SchemaProject::new("app", "Renamed"),
+            vec![screen(vec![node("Text", "t", &[])])],
+            vec![flow(&["a", "c"], &[("a", "c")])],
+            None,
+        );
+
+        let diff = diff_documents(&before, &after);
+        let lines: Vec<String> = diff.ops.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "project.name: \"App\" → \"Renamed\"",
+                "screens[Home].root.children[0]: Text added",
+                "screen About removed",
+                "logic[0].flows[main].nodes[b]: logic node removed",
+                "logic[0].flows[main].nodes[c]: logic node added",
+                "logic[0].flows[main].edges[a->b]: edge removed",
+                "logic[0].flows[main].edges[a->c]: edge added",
+            ]
+        );
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(
+            json["ops"][2],
+            json!({ "op": "screen_removed", "screen": "About" })
+        );
+    }
+}
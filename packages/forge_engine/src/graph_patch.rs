@@ -0,0 +1,756 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{PropValue, ScreenGraph, WidgetNode};
+
+/// The node a patch op acts on: a node id, or a path in the notation of
+/// [crate::MergeConflict] such as `screen.root.children[1]` or
+/// `screen.root.builders.itemBuilder.template`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRef {
+    Id(String),
+    Path(String),
+}
+
+impl fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeRef::Id(id) => write!(f, "node {id}"),
+            NodeRef::Path(path) => f.write_str(path),
+        }
+    }
+}
+
+/// One edit of a screen graph. References are resolved against the graph as
+/// it is when the op applies, after the ops before it in a patch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOp {
+    /// Inserts `node` as child `index` of `parent`. Ids in `node` must not
+    /// be used in the graph yet; clear them when pasting a copy.
+    InsertNode {
+        parent: NodeRef,
+        index: usize,
+        node: WidgetNode,
+    },
+    RemoveNode {
+        target: NodeRef,
+    },
+    /// Moves `target` to child `index` of `parent`, counted after `target`
+    /// left its old place.
+    MoveNode {
+        target: NodeRef,
+        parent: NodeRef,
+        index: usize,
+    },
+    SetProp {
+        target: NodeRef,
+        prop: String,
+        value: PropValue,
+    },
+    RemoveProp {
+        target: NodeRef,
+        prop: String,
+    },
+    /// Puts `wrapper`, which must have no children and no id used in the
+    /// graph, in the place of `target` and makes `target` its only child.
+    Wrap {
+        target: NodeRef,
+        wrapper: WidgetNode,
+    },
+    /// Replaces `target`, which must have exactly one child, with that child.
+    Unwrap {
+        target: NodeRef,
+    },
+    ReplaceType {
+        target: NodeRef,
+        widget: String,
+    },
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PatchError {
+    #[error("{0} was not found")]
+    NodeNotFound(NodeRef),
+    #[error("{0} is not a node path")]
+    InvalidPath(String),
+    #[error("index {index} is out of range for the {len} children of {parent}")]
+    IndexOutOfRange {
+        parent: NodeRef,
+        index: usize,
+        len: usize,
+    },
+    #[error("{0} is not a child and cannot be removed or moved")]
+    NotAChild(NodeRef),
+    #[error("{0} cannot be moved into itself")]
+    MoveIntoSelf(NodeRef),
+    #[error("{target} has no prop {prop}")]
+    PropNotFound { target: NodeRef, prop: String },
+    #[error("{target} has {children} children; only a node with one child can be unwrapped")]
+    CannotUnwrap { target: NodeRef, children: usize },
+    #[error("the wrapper for {0} must not have children")]
+    WrapperHasChildren(NodeRef),
+    #[error("node id {0} is already used in the graph")]
+    DuplicateId(String),
+    /// A node marked while applying an op could not be found again; a bug in
+    /// the patch engine rather than in the patch.
+    #[error("internal error: marked node {0} left the graph")]
+    MarkerLost(String),
+    #[error("op {index}: {source}")]
+    AtOp {
+        index: usize,
+        #[source]
+        source: Box<PatchError>,
+    },
+}
+
+/// Applies `patch` to `graph`. The patch applies as a whole: when an op
+/// fails, `graph` is left unchanged and the error names the op.
+pub fn apply_patch(graph: &mut ScreenGraph, patch: &[PatchOp]) -> Result<(), PatchError> {
+    let mut root = graph.root.clone();
+    for (index, op) in patch.iter().enumerate() {
+        apply_op(&mut root, op).map_err(|source| PatchError::AtOp {
+            index,
+            source: Box::new(source),
+        })?;
+    }
+    graph.root = root;
+    Ok(())
+}
+
+/// The patch that undoes `patch` once it was applied to `graph`. `graph` is
+/// the state before `patch`; it is not modified.
+pub fn invert_patch(graph: &ScreenGraph, patch: &[PatchOp]) -> Result<Vec<PatchOp>, PatchError> {
+    let mut root = graph.root.clone();
+    let mut inverse = Vec::with_capacity(patch.len());
+    for (index, op) in patch.iter().enumerate() {
+        inverse.push(apply_op(&mut root, op).map_err(|source| PatchError::AtOp {
+            index,
+            source: Box::new(source),
+        })?);
+    }
+    inverse.reverse();
+    Ok(inverse)
+}
+
+/// One step from a node to a node below it.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Child(usize),
+    Template(String),
+}
+
+/// Id given to nodes without one while an op runs, so the inverse can find
+/// them afterwards. Never left in a graph.
+const MARKER_PREFIX: &str = "\u{0}patch-marker-";
+
+/// Applies `op` and returns the op that undoes it.
+fn apply_op(root: &mut WidgetNode, op: &PatchOp) -> Result<PatchOp, PatchError> {
+    match op {
+        PatchOp::InsertNode {
+            parent,
+            index,
+            node,
+        } => {
+            check_unused_ids(root, node)?;
+            let parent_at = locate(root, parent)?;
+            let children = &mut node_mut(root, &parent_at, parent)?.children;
+            if *index > children.len() {
+                return Err(PatchError::IndexOutOfRange {
+                    parent: parent.clone(),
+                    index: *index,
+                    len: children.len(),
+                });
+            }
+            let mut node = node.clone();
+            let marker = mark(&mut node, 0);
+            children.insert(*index, node);
+            let target = take_reference(root, marker)?;
+            Ok(PatchOp::RemoveNode { target })
+        }
+        PatchOp::RemoveNode { target } => {
+            let at = locate(root, target)?;
+            let (parent_at, index) = split_child(&at, target)?;
+            let parent = node_mut(root, &parent_at, target)?;
+            let removed = parent.children.remove(index);
+            let marker = mark(parent, 0);
+            Ok(PatchOp::InsertNode {
+                parent: take_reference(root, marker)?,
+                index,
+                node: removed,
+            })
+        }
+        PatchOp::MoveNode {
+            target,
+            parent,
+            index,
+        } => {
+            let at = locate(root, target)?;
+            let (old_parent_at, old_index) = split_child(&at, target)?;
+            let mut new_parent_at = locate(root, parent)?;
+            if new_parent_at.starts_with(&at) {
+                return Err(PatchError::MoveIntoSelf(target.clone()));
+            }
+            // Later siblings of the moved node move up by one.
+            if new_parent_at.len() > old_parent_at.len()
+                && new_parent_at.starts_with(&old_parent_at)
+            {
+                if let Step::Child(sibling) = &mut new_parent_at[old_parent_at.len()] {
+                    if *sibling > old_index {
+                        *sibling -= 1;
+                    }
+                }
+            }
+
+            let len = node_mut(root, &new_parent_at, parent)?.children.len()
+                - usize::from(new_parent_at == old_parent_at);
+            if *index > len {
+                return Err(PatchError::IndexOutOfRange {
+                    parent: parent.clone(),
+                    index: *index,
+                    len,
+                });
+            }
+            let old_parent = node_mut(root, &old_parent_at, target)?;
+            let old_parent_marker = mark(old_parent, 0);
+            let mut node = old_parent.children.remove(old_index);
+            let node_marker = mark(&mut node, 1);
+            node_mut(root, &new_parent_at, parent)?
+                .children
+                .insert(*index, node);
+            Ok(PatchOp::MoveNode {
+                target: take_reference(root, node_marker)?,
+                parent: take_reference(root, old_parent_marker)?,
+                index: old_index,
+            })
+        }
+        PatchOp::SetProp {
+            target,
+            prop,
+            value,
+        } => {
+            let at = locate(root, target)?;
+            let node = node_mut(root, &at, target)?;
+            let previous = node.props.insert(prop.clone(), value.clone());
+            let marker = mark(node, 0);
+            let target = take_reference(root, marker)?;
+            Ok(match previous {
+                Some(value) => PatchOp::SetProp {
+                    target,
+                    prop: prop.clone(),
+                    value,
+                },
+                None => PatchOp::RemoveProp {
+                    target,
+                    prop: prop.clone(),
+                },
+            })
+        }
+        PatchOp::RemoveProp { target, prop } => {
+            let at = locate(root, target)?;
+            let node = node_mut(root, &at, target)?;
+            let value = node
+                .props
+                .remove(prop)
+                .ok_or_else(|| PatchError::PropNotFound {
+                    target: target.clone(),
+                    prop: prop.clone(),
+                })?;
+            let marker = mark(node, 0);
+            Ok(PatchOp::SetProp {
+                target: take_reference(root, marker)?,
+                prop: prop.clone(),
+                value,
+            })
+        }
+        PatchOp::Wrap { target, wrapper } => {
+            if !wrapper.children.is_empty() {
+                return Err(PatchError::WrapperHasChildren(target.clone()));
+            }
+            check_unused_ids(root, wrapper)?;
+            let at = locate(root, target)?;
+            let slot = node_mut(root, &at, target)?;
+            let mut wrapper = wrapper.clone();
+            let marker = mark(&mut wrapper, 0);
+            let inner = std::mem::replace(slot, wrapper);
+            slot.children.push(inner);
+            Ok(PatchOp::Unwrap {
+                target: take_reference(root, marker)?,
+            })
+        }
+        PatchOp::Unwrap { target } => {
+            let at = locate(root, target)?;
+            let slot = node_mut(root, &at, target)?;
+            if slot.children.len() != 1 {
+                return Err(PatchError::CannotUnwrap {
+                    target: target.clone(),
+                    children: slot.children.len(),
+                });
+            }
+            let mut child = slot.children.remove(0);
+            let marker = mark(&mut child, 0);
+            let wrapper = std::mem::replace(slot, child);
+            Ok(PatchOp::Wrap {
+                target: take_reference(root, marker)?,
+                wrapper,
+            })
+        }
+        PatchOp::ReplaceType { target, widget } => {
+            let at = locate(root, target)?;
+            let node = node_mut(root, &at, target)?;
+            let previous = std::mem::replace(&mut node.widget, widget.clone());
+            let marker = mark(node, 0);
+            Ok(PatchOp::ReplaceType {
+                target: take_reference(root, marker)?,
+                widget: previous,
+            })
+        }
+    }
+}
+
+/// Fails when a node in `node` carries an id that `root` or `node` already
+/// uses. Ids must stay unique for [NodeRef::Id] and the inverse to find the
+/// right node.
+fn check_unused_ids(root: &WidgetNode, node: &WidgetNode) -> Result<(), PatchError> {
+    let mut ids = Vec::new();
+    collect_ids(node, &mut ids);
+    for (index, id) in ids.iter().enumerate() {
+        if ids[..index].contains(id) || find(root, id).is_some() {
+            return Err(PatchError::DuplicateId(id.to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn collect_ids<'a>(node: &'a WidgetNode, ids: &mut Vec<&'a str>) {
+    ids.extend(node.id.as_deref());
+    for child in &node.children {
+        collect_ids(child, ids);
+    }
+    for builder in &node.builders {
+        collect_ids(&builder.template, ids);
+    }
+}
+
+/// Gives `node` a marker id if it has no id. Returns the id to look it up by
+/// and whether it is a marker.
+fn mark(node: &mut WidgetNode, slot: usize) -> (String, bool) {
+    match &node.id {
+        Some(id) => (id.clone(), false),
+        None => {
+            let marker = format!("{MARKER_PREFIX}{slot}");
+            node.id = Some(marker.clone());
+            (marker, true)
+        }
+    }
+}
+
+/// A reference to the node `mark` returned, by id when it has a real one and
+/// by path otherwise; removes the marker.
+fn take_reference(
+    root: &mut WidgetNode,
+    (id, is_marker): (String, bool),
+) -> Result<NodeRef, PatchError> {
+    if !is_marker {
+        return Ok(NodeRef::Id(id));
+    }
+    let at = find(root, &id).ok_or_else(|| PatchError::MarkerLost(id.clone()))?;
+    let path = path_of(root, &at);
+    node_mut(root, &at, &NodeRef::Id(id))?.id = None;
+    Ok(NodeRef::Path(path))
+}
+
+fn locate(root: &WidgetNode, node: &NodeRef) -> Result<Vec<Step>, PatchError> {
+    match node {
+        NodeRef::Id(id) => find(root, id).ok_or_else(|| PatchError::NodeNotFound(node.clone())),
+        NodeRef::Path(path) => {
+            let steps = parse_path(path).ok_or_else(|| PatchError::InvalidPath(path.clone()))?;
+            node_at(root, &steps)
+                .map(|_| steps)
+                .ok_or_else(|| PatchError::NodeNotFound(node.clone()))
+        }
+    }
+}
+
+fn split_child(at: &[Step], node: &NodeRef) -> Result<(Vec<Step>, usize), PatchError> {
+    match at.split_last() {
+        Some((Step::Child(index), parent)) => Ok((parent.to_vec(), *index)),
+        _ => Err(PatchError::NotAChild(node.clone())),
+    }
+}
+
+/// Parses `screen.root` followed by `.children[i]` and
+/// `.builders.<argument>.template` steps.
+fn parse_path(path: &str) -> Option<Vec<Step>> {
+    let mut rest = path.strip_prefix("screen.root")?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix(".children[") {
+            let end = index.find(']')?;
+            steps.push(Step::Child(index[..end].parse().ok()?));
+            rest = &index[end + 1..];
+        } else if let Some(builder) = rest.strip_prefix(".builders.") {
+            let end = builder.find(".template")?;
+            steps.push(Step::Template(builder[..end].to_string()));
+            rest = &builder[end + ".template".len()..];
+        } else {
+            return None;
+        }
+    }
+    Some(steps)
+}
+
+fn path_of(root: &WidgetNode, at: &[Step]) -> String {
+    let mut path = "screen.root".to_string();
+    for step in at {
+        match step {
+            Step::Child(index) => path.push_str(&format!(".children[{index}]")),
+            Step::Template(argument) => path.push_str(&format!(".builders.{argument}.template")),
+        }
+    }
+    debug_assert!(node_at(root, at).is_some());
+    path
+}
+
+fn find(node: &WidgetNode, id: &str) -> Option<Vec<Step>> {
+    if node.id.as_deref() == Some(id) {
+        return Some(Vec::new());
+    }
+    let children = node
+        .children
+        .iter()
+        .enumerate()
+        .map(|(index, child)| (Step::Child(index), child));
+    let templates = node.builders.iter().map(|builder| {
+        (
+            Step::Template(builder.argument.clone()),
+            builder.template.as_ref(),
+        )
+    });
+    children.chain(templates).find_map(|(step, child)| {
+        let mut at = find(child, id)?;
+        at.insert(0, step);
+        Some(at)
+    })
+}
+
+fn node_at<'a>(node: &'a WidgetNode, at: &[Step]) -> Option<&'a WidgetNode> {
+    at.iter().try_fold(node, |node, step| match step {
+        Step::Child(index) => node.children.get(*index),
+        Step::Template(argument) => node
+            .builders
+            .iter()
+            .find(|builder| &builder.argument == argument)
+            .map(|builder| builder.template.as_ref()),
+    })
+}
+
+fn node_mut<'a>(
+    node: &'a mut WidgetNode,
+    at: &[Step],
+    reference: &NodeRef,
+) -> Result<&'a mut WidgetNode, PatchError> {
+    at.iter()
+        .try_fold(node, |node, step| match step {
+            Step::Child(index) => node.children.get_mut(*index),
+            Step::Template(argument) => node
+                .builders
+                .iter_mut()
+                .find(|builder| &builder.argument == argument)
+                .map(|builder| builder.template.as_mut()),
+        })
+        .ok_or_else(|| PatchError::NodeNotFound(reference.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_graphs_from_source;
+    use serde_json::json;
+
+    fn graph() -> ScreenGraph {
+        let source = "class Home extends StatelessWidget {\n  Widget build(BuildContext context) {\n    return Column(children: [Text('a'), Icon(Icons.add), ListView.builder(itemBuilder: (context, index) => Text('row'))]);\n  }\n}\n";
+        build_graphs_from_source(source).remove(0)
+    }
+
+    fn id_of(graph: &ScreenGraph, path: &str) -> NodeRef {
+        let at = parse_path(path).unwrap();
+        NodeRef::Id(node_at(&graph.root, &at).unwrap().id.clone().unwrap())
+    }
+
+    fn literal(value: &str) -> PropValue {
+        PropValue::Literal {
+            value: json!(value),
+            span: None,
+        }
+    }
+
+    fn bare(widget: &str) -> WidgetNode {
+        WidgetNode {
+            id: None,
+            widget: widget.to_string(),
+            props: Default::default(),
+            children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
+        }
+    }
+
+    /// Applies `patch`, then its inverse, and checks the round trip.
+    fn round_trip(original: &ScreenGraph, patch: &[PatchOp]) -> ScreenGraph {
+        let inverse = invert_patch(original, patch).expect("invertible patch");
+        let mut patched = original.clone();
+        apply_patch(&mut patched, patch).expect("patch applies");
+        let mut undone = patched.clone();
+        apply_patch(&mut undone, &inverse).expect("inverse applies");
+        assert_eq!(&undone, original);
+        patched
+    }
+
+    #[test]
+    fn every_op_applies_and_inverts() {
+        let original = graph();
+        let text = id_of(&original, "screen.root.children[0]");
+        let icon = id_of(&original, "screen.root.children[1]");
+        let row = NodeRef::Path("screen.root.children[2].builders.itemBuilder.template".into());
+        let patch = vec![
+            PatchOp::SetProp {
+                target: text.clone(),
+                prop: "data".to_string(),
+                value: literal("changed"),
+            },
+            PatchOp::SetProp {
+                target: row.clone(),
+                prop: "maxLines".to_string(),
+                value: literal("1"),
+            },
+            PatchOp::RemoveProp {
+                target: icon.clone(),
+                prop: "icon".to_string(),
+            },
+            PatchOp::ReplaceType {
+                target: text.clone(),
+                widget: "SelectableText".to_string(),
+            },
+            PatchOp::InsertNode {
+                parent: NodeRef::Path("screen.root".into()),
+                index: 0,
+                node: bare("Spacer"),
+            },
+            PatchOp::Wrap {
+                target: icon.clone(),
+                wrapper: bare("Padding"),
+            },
+            PatchOp::MoveNode {
+                target: text.clone(),
+                parent: NodeRef::Path("screen.root.children[2]".into()),
+                index: 0,
+            },
+            PatchOp::RemoveNode {
+                target: NodeRef::Path("screen.root.children[0]".into()),
+            },
+        ];
+
+        let patched = round_trip(&original, &patch);
+        let root = &patched.root;
+        let widgets: Vec<&str> = root.children.iter().map(|c| c.widget.as_str()).collect();
+        assert_eq!(widgets, ["Padding", "ListView.builder"]);
+        assert_eq!(root.children[0].children[0].widget, "SelectableText");
+        assert_eq!(root.children[0].children[1].widget, "Icon");
+        assert!(root.children[0].children[1].props.is_empty());
+        assert_eq!(
+            root.children[1].builders[0].template.props["maxLines"],
+            literal("1")
+        );
+        assert!(patched.find_node("\u{0}patch-marker-0").is_none());
+    }
+
+    #[test]
+    fn unwrap_and_root_wraps_invert() {
+        let original = graph();
+        let padded = round_trip(
+            &original,
+            &[PatchOp::Wrap {
+                target: NodeRef::Path("screen.root".into()),
+                wrapper: bare("SafeArea"),
+            }],
+        );
+        assert_eq!(padded.root.widget, "SafeArea");
+        let unwrapped = round_trip(
+            &padded,
+            &[PatchOp::Unwrap {
+                target: NodeRef::Path("screen.root".into()),
+            }],
+        );
+        assert_eq!(unwrapped, original);
+    }
+
+    #[test]
+    fn moves_within_the_same_parent_count_after_removal() {
+        let original = graph();
+        let text = id_of(&original, "screen.root.children[0]");
+        let patched = round_trip(
+            &original,
+            &[PatchOp::MoveNode {
+                target: text,
+                parent: NodeRef::Path("screen.root".into()),
+                index: 2,
+            }],
+        );
+        let widgets: Vec<&str> = patched
+            .root
+            .children
+            .iter()
+            .map(|c| c.widget.as_str())
+            .collect();
+        assert_eq!(widgets, ["Icon", "ListView.builder", "Text"]);
+    }
+
+    #[test]
+    fn failing_ops_leave_the_graph_unchanged() {
+        let original = graph();
+        let mut patched = original.clone();
+        let icon = id_of(&original, "screen.root.children[1]");
+        let err = apply_patch(
+            &mut patched,
+            &[
+                PatchOp::RemoveProp {
+                    target: icon.clone(),
+                    prop: "icon".to_string(),
+                },
+                PatchOp::Unwrap {
+                    target: icon.clone(),
+                },
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            PatchError::AtOp {
+                index: 1,
+                source: Box::new(PatchError::CannotUnwrap {
+                    target: icon,
+                    children: 0
+                }),
+            }
+        );
+        assert_eq!(patched, original);
+
+        let root = NodeRef::Path("screen.root".into());
+        for (op, expected) in [
+            (
+                PatchOp::RemoveNode {
+                    target: root.clone(),
+                },
+                PatchError::NotAChild(root.clone()),
+            ),
+            (
+                PatchOp::MoveNode {
+                    target: NodeRef::Path("screen.root.children[2]".into()),
+                    parent: NodeRef::Path(
+                        "screen.root.children[2].builders.itemBuilder.template".into(),
+                    ),
+                    index: 0,
+                },
+                PatchError::MoveIntoSelf(NodeRef::Path("screen.root.children[2]".into())),
+            ),
+            (
+                PatchOp::InsertNode {
+                    parent: root.clone(),
+                    index: 9,
+                    node: bare("Text"),
+                },
+                PatchError::IndexOutOfRange {
+                    parent: root.clone(),
+                    index: 9,
+                    len: 3,
+                },
+            ),
+            (
+                PatchOp::RemoveNode {
+                    target: NodeRef::Path("screen.root.kids[0]".into()),
+                },
+                PatchError::InvalidPath("screen.root.kids[0]".into()),
+            ),
+        ] {
+            let err = apply_patch(&mut patched, &[op]).unwrap_err();
+            assert_eq!(
+                err,
+                PatchError::AtOp {
+                    index: 0,
+                    source: Box::new(expected)
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn pasting_a_copy_requires_fresh_ids() {
+        let original = graph();
+        let root = NodeRef::Path("screen.root".into());
+        let copy = original.root.children[0].clone();
+        let copy_id = copy.id.clone().unwrap();
+
+        let mut patched = original.clone();
+        let insert = PatchOp::InsertNode {
+            parent: root.clone(),
+            index: 2,
+            node: copy.clone(),
+        };
+        let err = apply_patch(&mut patched, &[insert]).unwrap_err();
+        assert_eq!(
+            err,
+            PatchError::AtOp {
+                index: 0,
+                source: Box::new(PatchError::DuplicateId(copy_id.clone())),
+            }
+        );
+        let wrap = PatchOp::Wrap {
+            target: id_of(&original, "screen.root.children[1]"),
+            wrapper: WidgetNode {
+                children: Vec::new(),
+                ..copy.clone()
+            },
+        };
+        assert!(apply_patch(&mut patched, &[wrap]).is_err());
+        assert_eq!(patched, original);
+
+        // Without its id the copy inserts, and undo removes the copy rather
+        // than the original.
+        let patched = round_trip(
+            &original,
+            &[PatchOp::InsertNode {
+                parent: root,
+                index: 2,
+                node: WidgetNode { id: None, ..copy },
+            }],
+        );
+        let widgets: Vec<_> = patched
+            .root
+            .children
+            .iter()
+            .map(|c| c.widget.as_str())
+            .collect();
+        assert_eq!(widgets, ["Text", "Icon", "Text", "ListView.builder"]);
+        assert_eq!(patched.root.children[0].id, Some(copy_id));
+    }
+
+    #[test]
+    fn ops_serialize_with_an_op_tag() {
+        let op = PatchOp::ReplaceType {
+            target: NodeRef::Id("abc123".into()),
+            widget: "Card".into(),
+        };
+        let json = serde_json::to_value(&op).unwrap();
+        assert_eq!(
+            json,
+            json!({ "op": "replace_type", "target": { "id": "abc123" }, "widget": "Card" })
+        );
+        assert_eq!(serde_json::from_value::<PatchOp>(json).unwrap(), op);
+    }
+}
@@ -0,0 +1,579 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{PropValue, SchemaDocument, ScreenGraph, WidgetNode};
+
+/// A parsed CSS-like selector over widget trees.
+///
+/// A selector is a comma-separated list of alternatives. Each alternative is a
+/// chain of compounds joined by whitespace (descendant) or `>` (child). A
+/// compound is a widget type or `*`, an optional `#node-id`, and attribute
+/// filters:
+///
+/// - `[prop]` — the node sets `prop`.
+/// - `[prop=v]` — the prop's text is `v`: a literal string, the JSON of other
+///   literals, an expression's source, or a binding's reference.
+/// - `[prop.ref=v]` — a field of the prop's binding (`type`, `ref`,
+///   `provider_id`, `path`, `type_hint`, `access`, `notifier`).
+/// - `[@any=v]` and `[@binding.ref=v]` — any prop or any binding prop.
+/// - `[@id=v]`, `[@widget=v]` — the node's id or widget type.
+///
+/// The `@` keeps these apart from props of the same name, so `[value=true]`
+/// tests a `value:` argument.
+///
+/// Besides `=`, filters take `!=`, `^=` (prefix), `$=` (suffix) and `*=`
+/// (contains). Values are bare words or quoted strings. Builder templates count
+/// as children of the node owning the builder.
+///
+/// ```
+/// use forge_engine::Selector;
+///
+/// let selector: Selector = r#"Scaffold > Column Text[data^="Hello"]"#.parse().unwrap();
+/// # let _ = selector;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    source: String,
+    alternatives: Vec<Vec<(Combinator, Compound)>>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error("invalid selector at column {column}: {message}")]
+    Syntax { column: usize, message: String },
+}
+
+/// A node a selector matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryMatch {
+    pub screen: String,
+    /// Path of the node, `screen.root.children[0]` style for a single graph
+    /// and `screens[Home].root.children[0]` style within a document.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    pub widget: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Compound {
+    widget: Option<String>,
+    id: Option<String>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    key: String,
+    test: Option<(Operator, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equals,
+    NotEquals,
+    Prefix,
+    Suffix,
+    Contains,
+}
+
+impl Selector {
+    pub fn parse(source: &str) -> Result<Self, QueryError> {
+        let alternatives = Parser {
+            chars: source.char_indices().collect(),
+            pos: 0,
+        }
+        .selector()?;
+        Ok(Selector {
+            source: source.to_string(),
+            alternatives,
+        })
+    }
+
+    /// Whether `ancestors`' last node matches, given the nodes above it from
+    /// the root down.
+    fn matches(&self, ancestors: &[&WidgetNode]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|chain| matches_chain(chain, ancestors))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = QueryError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Selector::parse(source)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Nodes of `graph` matching `selector`, in document order.
+pub fn query_screen_graph(graph: &ScreenGraph, selector: &Selector) -> Vec<QueryMatch> {
+    let mut matches = Vec::new();
+    walk(
+        &graph.id,
+        &graph.root,
+        "screen.root".to_string(),
+        &mut Vec::new(),
+        selector,
+        &mut matches,
+    );
+    matches
+}
+
+/// Nodes of every screen in `document` matching `selector`.
+pub fn query_document(document: &SchemaDocument, selector: &Selector) -> Vec<QueryMatch> {
+    let mut matches = Vec::new();
+    for screen in &document.screens {
+        walk(
+            &screen.id,
+            &screen.root,
+            format!("screens[{}].root", screen.id),
+            &mut Vec::new(),
+            selector,
+            &mut matches,
+        );
+    }
+    matches
+}
+
+fn walk<'a>(
+    screen: &str,
+    node: &'a WidgetNode,
+    path: String,
+    ancestors: &mut Vec<&'a WidgetNode>,
+    selector: &Selector,
+    matches: &mut Vec<QueryMatch>,
+) {
+    ancestors.push(node);
+    if selector.matches(ancestors) {
+        matches.push(QueryMatch {
+            screen: screen.to_string(),
+            path: path.clone(),
+            node_id: node.id.clone(),
+            widget: node.widget.clone(),
+        });
+    }
+    for (index, child) in node.children.iter().enumerate() {
+        let path = format!("{path}.children[{index}]");
+        walk(screen, child, path, ancestors, selector, matches);
+    }
+    for builder in &node.builders {
+        let path = format!("{path}.builders.{}.template", builder.argument);
+        walk(
+            screen,
+            &builder.template,
+            path,
+            ancestors,
+            selector,
+            matches,
+        );
+    }
+    ancestors.pop();
+}
+
+/// Matches the chain right to left, backtracking over descendant combinators.
+fn matches_chain(chain: &[(Combinator, Compound)], ancestors: &[&WidgetNode]) -> bool {
+    let Some(((combinator, compound), rest)) = chain.split_last() else {
+        return true;
+    };
+    let Some((node, above)) = ancestors.split_last() else {
+        return false;
+    };
+    if !compound.matches(node) {
+        return false;
+    }
+    if rest.is_empty() {
+        return true;
+    }
+    match combinator {
+        Combinator::Child => matches_chain(rest, above),
+        Combinator::Descendant => (1..=above.len()).any(|end| matches_chain(rest, &above[..end])),
+    }
+}
+
+impl Compound {
+    fn matches(&self, node: &WidgetNode) -> bool {
+        self.widget.as_ref().is_none_or(|w| *w == node.widget)
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| node.id.as_ref() == Some(id))
+            && self.filters.iter().all(|filter| filter.matches(node))
+    }
+}
+
+impl Filter {
+    fn matches(&self, node: &WidgetNode) -> bool {
+        let candidates = self.candidates(node);
+        match &self.test {
+            None => !candidates.is_empty(),
+            Some((Operator::NotEquals, expected)) => {
+                !candidates.iter().any(|text| text == expected)
+            }
+            Some((operator, expected)) => candidates.iter().any(|text| match operator {
+                Operator::Equals => text == expected,
+                Operator::Prefix => text.starts_with(expected.as_str()),
+                Operator::Suffix => text.ends_with(expected.as_str()),
+                Operator::Contains => text.contains(expected.as_str()),
+                Operator::NotEquals => unreachable!(),
+            }),
+        }
+    }
+
+    /// The texts the filter's key selects on `node`.
+    fn candidates(&self, node: &WidgetNode) -> Vec<String> {
+        match self.key.as_str() {
+            "@id" => node.id.iter().cloned().collect(),
+            "@widget" => vec![node.widget.clone()],
+            "@any" => node.props.values().map(prop_text).collect(),
+            key => {
+                let (props, field): (Vec<&PropValue>, _) = match key.strip_prefix("@binding.") {
+                    Some(field) => (node.props.values().collect(), Some(field)),
+                    None => {
+                        let (prop, field) = match key.split_once('.') {
+                            Some((prop, field)) => (prop, Some(field)),
+                            None => (key, None),
+                        };
+                        (node.props.get(prop).into_iter().collect(), field)
+                    }
+                };
+                props
+                    .into_iter()
+                    .filter_map(|value| match field {
+                        None => Some(prop_text(value)),
+                        Some(field) => binding_field(value, field),
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn prop_text(value: &PropValue) -> String {
+    match value {
+        PropValue::Literal { value, .. } => json_text(value),
+        PropValue::Expression { expression, .. } => expression.clone(),
+        PropValue::Binding { binding, .. } => binding.reference.clone(),
+    }
+}
+
+fn binding_field(value: &PropValue, field: &str) -> Option<String> {
+    let PropValue::Binding { binding, .. } = value else {
+        return None;
+    };
+    let binding = serde_json::to_value(binding).ok()?;
+    binding.get(field).map(json_text)
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct Parser {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn selector(&mut self) -> Result<Vec<Vec<(Combinator, Compound)>>, QueryError> {
+        let mut alternatives = vec![self.chain()?];
+        while self.eat(',') {
+            alternatives.push(self.chain()?);
+        }
+        match self.peek() {
+            None => Ok(alternatives),
+            Some(c) => Err(self.error(format!("unexpected `{c}`"))),
+        }
+    }
+
+    fn chain(&mut self) -> Result<Vec<(Combinator, Compound)>, QueryError> {
+        self.skip_whitespace();
+        let mut chain = vec![(Combinator::Descendant, self.compound()?)];
+        loop {
+            let spaced = self.skip_whitespace();
+            let combinator = if self.eat('>') {
+                self.skip_whitespace();
+                Combinator::Child
+            } else if spaced && !matches!(self.peek(), None | Some(',')) {
+                Combinator::Descendant
+            } else {
+                return Ok(chain);
+            };
+            chain.push((combinator, self.compound()?));
+        }
+    }
+
+    fn compound(&mut self) -> Result<Compound, QueryError> {
+        let mut compound = Compound::default();
+        let mut empty = true;
+        if self.eat('*') {
+            empty = false;
+        } else if let Some(widget) =
+            self.word(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        {
+            compound.widget = Some(widget);
+            empty = false;
+        }
+        if self.eat('#') {
+            let id = self
+                .word(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                .ok_or_else(|| self.error("expected a node id after `#`"))?;
+            compound.id = Some(id);
+            empty = false;
+        }
+        while self.eat('[') {
+            compound.filters.push(self.filter()?);
+            empty = false;
+        }
+        if empty {
+            return Err(match self.peek() {
+                Some(c) => self.error(format!("expected a widget, `*`, `#` or `[`, found `{c}`")),
+                None => self.error("expected a widget, `*`, `#` or `[`"),
+            });
+        }
+        Ok(compound)
+    }
+
+    fn filter(&mut self) -> Result<Filter, QueryError> {
+        self.skip_whitespace();
+        let pseudo = self.eat('@');
+        let key = self
+            .word(|c| c.is_alphanumeric() || c == '_' || c == '.')
+            .ok_or_else(|| self.error("expected an attribute name"))?;
+        let key = if pseudo { format!("@{key}") } else { key };
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Filter { key, test: None });
+        }
+        let operator = match self.peek() {
+            Some('=') => Operator::Equals,
+            Some('!') => Operator::NotEquals,
+            Some('^') => Operator::Prefix,
+            Some('$') => Operator::Suffix,
+            Some('*') => Operator::Contains,
+            _ => return Err(self.error("expected `]` or an operator")),
+        };
+        if operator != Operator::Equals {
+            self.pos += 1;
+        }
+        if !self.eat('=') {
+            return Err(self.error("expected `=`"));
+        }
+        self.skip_whitespace();
+        let value = self.value()?;
+        self.skip_whitespace();
+        if !self.eat(']') {
+            return Err(self.error("expected `]`"));
+        }
+        Ok(Filter {
+            key,
+            test: Some((operator, value)),
+        })
+    }
+
+    fn value(&mut self) -> Result<String, QueryError> {
+        let Some(quote) = self.peek().filter(|c| *c == '"' || *c == '\'') else {
+            return self
+                .word(|c| !c.is_whitespace() && c != ']')
+                .ok_or_else(|| self.error("expected a value"));
+        };
+        let start = self.column();
+        self.pos += 1;
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    if let Some(escaped) = self.peek() {
+                        value.push(escaped);
+                        self.pos += 1;
+                    }
+                }
+                c if c == quote => return Ok(value),
+                c => value.push(c),
+            }
+        }
+        Err(QueryError::Syntax {
+            column: start,
+            message: "unterminated string".to_string(),
+        })
+    }
+
+    fn word(&mut self, allowed: impl Fn(char) -> bool) -> Option<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(&allowed) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| self.chars[start..self.pos].iter().map(|(_, c)| c).collect())
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, c)| *c)
+    }
+
+    fn column(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError::Syntax {
+            column: self.column(),
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_graphs_from_source;
+
+    const SOURCE: &str = r#"
+class Home extends ConsumerWidget {
+  Widget build(BuildContext context, WidgetRef ref) {
+    return Scaffold(
+      body: Column(children: [
+        Text('Hello there', style: ref.watch(themeProvider)),
+        Text('Goodbye'),
+        Row(children: [Text(ref.watch(userProvider))]),
+        ListView.builder(itemBuilder: (context, index) => Text('row')),
+      ]),
+    );
+  }
+}
+"#;
+
+    fn paths(selector: &str) -> Vec<String> {
+        let graph = build_graphs_from_source(SOURCE).remove(0);
+        query_screen_graph(&graph, &selector.parse().unwrap())
+            .into_iter()
+            .map(|found| found.path)
+            .collect()
+    }
+
+    #[test]
+    fn combinators_follow_css_semantics() {
+        let column = "screen.root.children[0]";
+        assert_eq!(
+            paths("Column > Text"),
+            [
+                format!("{column}.children[0]"),
+                format!("{column}.children[1]")
+            ]
+        );
+        assert_eq!(paths("Scaffold Text").len(), 4);
+        assert_eq!(
+            paths("Row > *, ListView.builder > Text"),
+            [
+                format!("{column}.children[2].children[0]"),
+                format!("{column}.children[3].builders.itemBuilder.template"),
+            ]
+        );
+        assert!(paths("Row > Column").is_empty());
+    }
+
+    #[test]
+    fn attribute_filters_match_literals_and_bindings() {
+        let first = "screen.root.children[0].children[0]";
+        assert_eq!(paths(r#"Text[data^="Hello"]"#), [first]);
+        assert_eq!(paths("Text[style.ref=themeProvider]"), [first]);
+        assert_eq!(paths("Text[style]"), [first]);
+        assert_eq!(
+            paths("*[@binding.ref=userProvider]"),
+            ["screen.root.children[0].children[2].children[0]"]
+        );
+        assert_eq!(paths(r#"*[@any*="bye"]"#).len(), 1);
+        assert_eq!(paths("Text[data!=row]").len(), 3);
+    }
+
+    #[test]
+    fn pseudo_attributes_leave_same_named_props_alone() {
+        let graph = crate::parse_widget_tree(
+            "Column(children: [Switch(value: true, onChanged: null), Checkbox(value: false, onChanged: null), Text('true')])",
+        )
+        .map(|root| ScreenGraph {
+            id: "Settings".to_string(),
+            root,
+            state: None,
+            consumer: None,
+            statements: None,
+        })
+        .unwrap();
+        let paths = |selector: &str| -> Vec<String> {
+            query_screen_graph(&graph, &selector.parse().unwrap())
+                .into_iter()
+                .map(|found| found.path)
+                .collect()
+        };
+        assert_eq!(paths("*[value=true]"), ["screen.root.children[0]"]);
+        assert_eq!(paths("Checkbox[value=false]"), ["screen.root.children[1]"]);
+        assert_eq!(
+            paths("*[@any=true]"),
+            ["screen.root.children[0]", "screen.root.children[2]"]
+        );
+        assert_eq!(paths("*[@widget=Text]"), ["screen.root.children[2]"]);
+    }
+
+    #[test]
+    fn documents_report_screen_rooted_paths() {
+        let graph = build_graphs_from_source(SOURCE).remove(0);
+        let document = crate::SchemaWriter::build_document(
+            crate::SchemaProject::new("app", "App"),
+            vec![graph],
+            Vec::new(),
+            None,
+        );
+        let found = query_document(&document, &"Scaffold".parse().unwrap());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "screens[Home].root");
+        assert_eq!(found[0].screen, "Home");
+        assert!(found[0].node_id.is_some());
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_columns() {
+        let error = |source: &str| match Selector::parse(source).unwrap_err() {
+            QueryError::Syntax { column, .. } => column,
+        };
+        assert_eq!(error("Text >"), 7);
+        assert_eq!(error("Text[data"), 10);
+        assert_eq!(error(r#"Text[data="open]"#), 11);
+        assert_eq!(error("Text ]"), 6);
+    }
+}
@@ -1,197 +1,228 @@
+mod analyzer_backend;
 mod analyzer_service;
 mod angular_renderer;
+mod confidence;
+mod dart_parser;
+mod document_merge;
 mod expr;
+mod expr_codegen;
+mod expr_types;
 mod flutter_renderer;
+mod function_registry;
+mod graph_diff;
+mod graph_patch;
+mod graph_query;
 mod logic_engine;
+mod logic_merge;
 mod logic_types;
 mod manifest;
 mod merge_engine;
+mod merge_resolution;
+mod node_id;
+mod parallel;
+mod parse_cache;
 mod plugin_registry;
 mod plugin_sandbox;
 mod react_renderer;
 mod renderer_adapter;
 mod renderer_registry;
+mod runtime_context;
 mod schema_writer;
 mod state_adapter;
-mod runtime_context;
-mod widget_registry;
 mod theme_registry;
+mod widget_registry;
+mod workspace;
 
 /// Semantic version for the analysis report JSON contract emitted by the CLI.
 pub const ANALYSIS_REPORT_VERSION: &str = "1.0.0";
 
+pub use analyzer_backend::{
+    AnalyzerBackend, AnalyzerError, AnalyzerRequest, AnalyzerResponse, SubprocessAnalyzer,
+    ANALYZER_PROTOCOL_VERSION,
+};
 pub use analyzer_service::{
     AnalysisDecision, AnalysisOutcome, AnalysisStrategy, AnalyzerInvocation, AnalyzerService,
 };
 pub use angular_renderer::AngularRenderer;
+pub use confidence::ConfidenceBreakdown;
+pub use dart_parser::{
+    parse_dart_expression, Argument, CollectionElement, DartExpr, DartExprKind, DartParseError,
+    DartParseResult, DartString, FunctionBody, Span, Token, TokenKind, TokenStream,
+};
+pub use document_merge::{merge_documents, DocumentMergeOutcome};
 pub use expr::{
-    eval_expression, parse_expression, BinaryOp, Expr, ExprError, ExprResult, UnaryOp,
+    eval_expression, parse_expression, BinaryOp, EvalContext, Expr, ExprError, ExprResult, UnaryOp,
+};
+pub use expr_codegen::{CodegenError, CodegenTarget, CompiledExpression, ExprCodegen};
+pub use expr_types::{
+    check_expression, check_flow, check_logic_graph, ExprType, TypeDiagnostic, TypeEnv,
+};
+pub use flutter_renderer::{
+    generate_dart_module, generate_stateful_widget, generate_stateless_widget, FlutterRenderer,
 };
-pub use flutter_renderer::{generate_dart_module, generate_stateless_widget, FlutterRenderer};
+pub use function_registry::{
+    clear_functions, get_function, list_functions, register_function, FunctionDescriptor,
+    FunctionRegistryError, NativeFunction, ValueType,
+};
+pub use graph_diff::{diff_documents, diff_screen_graphs, DiffOp, GraphDiff};
+pub use graph_patch::{apply_patch, invert_patch, NodeRef, PatchError, PatchOp};
+pub use graph_query::{query_document, query_screen_graph, QueryError, QueryMatch, Selector};
+use jsonschema::JSONSchema;
 pub use logic_engine::{simulate_flow, EvalConfig, LogicError};
+pub use logic_merge::{merge_logic_graphs, LogicMergeOutcome};
 pub use logic_types::{
     BuiltinLogicNodeKind, EvalResult, EvalTraceEntry, ExprValue, Flow, LogicEdge, LogicGraph,
     LogicNode,
 };
 pub use manifest::{generate_manifest, Manifest, ManifestKind};
 pub use merge_engine::{merge_screen_graphs, MergeConflict, MergeOutcome};
+pub use merge_resolution::{
+    merge_documents_with, merge_logic_graphs_with, merge_screen_graphs_with, MergeError,
+    MergeStrategy, Resolution,
+};
+pub use node_id::assign_node_ids;
+pub use parallel::{Parallelism, ProgressCallback, TaskProgress};
+pub use parse_cache::{CacheStats, CachedParse, ParseCache, ENGINE_VERSION};
 pub use plugin_registry::{PluginDescriptor, PluginRegistry, PluginRegistryError};
 pub use plugin_sandbox::{PluginSandbox, SandboxError};
 pub use react_renderer::ReactRenderer;
-pub use renderer_adapter::{RenderContext, RenderDialect, RenderOptions, RendererAdapter};
+pub use renderer_adapter::{
+    render_screens, RenderContext, RenderDialect, RenderError, RenderOptions, RenderSeverity,
+    RendererAdapter, ScreenRender,
+};
 pub use renderer_registry::{all_renderers, get_renderer, renderer_names, RendererDescriptor};
-pub use schema_writer::{ForgeGraph, SchemaDocument, SchemaProject, SchemaWriter};
 pub use runtime_context::{RuntimeContext, ThemeHandle};
+pub use schema_writer::{ForgeGraph, SchemaDocument, SchemaProject, SchemaWriter};
+pub use state_adapter::{ResolvedBinding, RiverpodAdapter, StateAdapter};
 pub use theme_registry::{
-    clear_themes,
-    get_theme,
-    list_themes,
-    register_theme,
-    ThemeData,
-    ThemeRegistryError,
+    clear_themes, get_theme, list_themes, register_theme, ThemeData, ThemeRegistryError,
 };
 pub use widget_registry::{
-    clear_registry as clear_widget_registry,
-    get_widget,
-    list_widgets,
-    register_widget,
-    PropDescriptor,
-    WidgetDescriptor,
-    WidgetRegistryError,
+    clear_registry as clear_widget_registry, get_widget, list_widgets, register_widget,
+    PropDescriptor, WidgetDescriptor, WidgetRegistryError,
+};
+pub use workspace::{
+    import_workspace, import_workspace_cached, import_workspace_with, ComponentRole,
+    ComponentSymbol, ConstructorParameter, DependencyEdge, DependencyGraph, DependencyNode,
+    FileFailure, SymbolIndex, WorkspaceImport,
 };
-use jsonschema::JSONSchema;
-pub use state_adapter::{ResolvedBinding, RiverpodAdapter, StateAdapter};
 
-use once_cell::sync::Lazy;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, HashSet},
-    fs,
+    fmt, fs,
     path::Path,
 };
 use thiserror::Error;
 use walkdir::WalkDir;
 
-fn skip_generic_block(input: &str) -> Option<&str> {
-    let mut depth = 0_i32;
-    let mut chars = input.char_indices();
-    let (_, first) = chars.next()?;
-    if first != '<' {
-        return None;
-    }
-
-/// Validates a serialized schema document against the Forge schema.
-pub fn validate_graph_schema(payload: &str) -> Result<(), String> {
-    static VALIDATOR: once_cell::sync::OnceCell<JSONSchema> = once_cell::sync::OnceCell::new();
-    let validator = VALIDATOR.get_or_try_init(|| {
-        let schema_src = include_str!("../../../forge_spec/graph_schema.json");
-        let json: serde_json::Value = serde_json::from_str(schema_src)
-            .map_err(|err| format!("Invalid graph schema JSON: {err}"))?;
-        JSONSchema::compile(&json)
-            .map_err(|err| format!("Failed to compile graph schema: {err}"))
-    })?;
-
-    let value: serde_json::Value = serde_json::from_str(payload)
-        .map_err(|err| format!("Export did not produce valid JSON: {err}"))?;
+/// Location in Dart source that a widget or prop was imported from.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct SourceSpan {
+    /// Path of the imported file, when the source came from disk.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file: Option<String>,
+    /// Byte offset from the start of the source.
+    pub offset: usize,
+    /// One-based line number.
+    pub line: usize,
+    /// One-based column, counted in characters.
+    pub column: usize,
+}
 
-    if let Err(errors) = validator.validate(&value) {
-        let messages: Vec<String> = errors
-            .map(|err| format!("{} at {}", err, err.instance_path))
-            .collect();
-        return Err(messages.join("\n"));
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
     }
+}
 
-    Ok(())
+/// Converts byte offsets within a source into [SourceSpan]s.
+struct SourceMap<'a> {
+    source: &'a str,
+    file: Option<&'a str>,
+    line_starts: Vec<usize>,
 }
-    depth += 1;
 
-    while let Some((idx, ch)) = chars.next() {
-        match ch {
-            '<' => depth += 1,
-            '>' => {
-                depth -= 1;
-                if depth == 0 {
-                    return Some(&input[idx + 1..]);
-                }
-            }
-            _ => {}
+impl<'a> SourceMap<'a> {
+    fn new(source: &'a str, file: Option<&'a str>) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            source,
+            file,
+            line_starts,
         }
     }
-    None
-}
 
-fn extract_parenthesized_block(input: &str) -> Option<(String, &str)> {
-    let mut depth = 0_i32;
-    let mut result = String::new();
-    let mut chars = input.char_indices();
-    let (_, first) = chars.next()?;
-    if first != '(' {
-        return None;
-    }
-    depth += 1;
-    while let Some((idx, ch)) = chars.next() {
-        match ch {
-            '(' => depth += 1,
-            ')' => {
-                depth -= 1;
-                if depth == 0 {
-                    return Some((result, &input[idx + 1..]));
-                }
-            }
-            _ => {}
+    fn span(&self, offset: usize) -> SourceSpan {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self
+            .source
+            .get(line_start..offset)
+            .map_or(offset - line_start, |prefix| prefix.chars().count());
+        SourceSpan {
+            file: self.file.map(str::to_string),
+            offset,
+            line,
+            column: column + 1,
         }
-        result.push(ch);
     }
-    None
 }
 
-fn split_widget_signature(input: &str) -> Option<(&str, &str)> {
-    let mut chars = input.chars().enumerate();
-    let mut end_idx = None;
-    while let Some((idx, ch)) = chars.next() {
-        if ch == '(' {
-            end_idx = Some(idx);
-            break;
+/// Value of a widget prop. The optional `span` records where the argument was
+/// imported from and is ignored when comparing values.
+#[derive(Debug, Serialize, Deserialize, Eq, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PropValue {
+    Literal {
+        value: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        span: Option<SourceSpan>,
+    },
+    Expression {
+        expression: String,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        span: Option<SourceSpan>,
+    },
+    Binding {
+        binding: BindingReference,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        span: Option<SourceSpan>,
+    },
+}
+
+impl PropValue {
+    /// Source location the value was imported from, if known.
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match self {
+            PropValue::Literal { span, .. }
+            | PropValue::Expression { span, .. }
+            | PropValue::Binding { span, .. } => span.as_ref(),
         }
     }
-    let idx = end_idx?;
-    let widget_part = input[..idx].trim();
-    let widget = widget_part.split_whitespace().last()?;
-    let rest = &input[idx..];
-    Some((widget, rest))
-}
-
-fn render_prop_value(value: &PropValue) -> String {
-    match value {
-        PropValue::Literal { value } => match value {
-            serde_json::Value::String(s) => format!("\"{}\"", s),
-            serde_json::Value::Bool(true) => "true".to_string(),
-            serde_json::Value::Bool(false) => "false".to_string(),
-            serde_json::Value::Null => "null".to_string(),
-            serde_json::Value::Number(num) => num.to_string(),
-            other => serde_json::to_string(other).unwrap_or_default(),
-        },
-        PropValue::Expression { expression } => expression.clone(),
-        PropValue::Binding { binding } => {
-            let mut rendered = format!("ref.watch({})", binding.reference);
-            if let Some(path) = &binding.path {
-                rendered.push('.');
-                rendered.push_str(path);
+}
+
+impl PartialEq for PropValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PropValue::Literal { value: a, .. }, PropValue::Literal { value: b, .. }) => a == b,
+            (
+                PropValue::Expression { expression: a, .. },
+                PropValue::Expression { expression: b, .. },
+            ) => a == b,
+            (PropValue::Binding { binding: a, .. }, PropValue::Binding { binding: b, .. }) => {
+                a == b
             }
-            rendered
+            _ => false,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
-#[serde(tag = "type", rename_all = "lowercase")]
-pub enum PropValue {
-    Literal { value: serde_json::Value },
-    Expression { expression: String },
-    Binding { binding: BindingReference },
-}
-
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum BindingTarget {
@@ -216,16 +247,265 @@ pub struct BindingReference {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub type_hint: Option<String>,
+    /// How a provider binding is read through the Riverpod `WidgetRef`.
+    #[serde(skip_serializing_if = "ProviderAccess::is_watch", default)]
+    pub access: ProviderAccess,
+    /// Whether the binding targets the provider's `.notifier` rather than its state.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub notifier: bool,
+}
+
+/// `WidgetRef` method used to access a provider.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderAccess {
+    #[default]
+    Watch,
+    Read,
+    Listen,
+}
+
+impl ProviderAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderAccess::Watch => "watch",
+            ProviderAccess::Read => "read",
+            ProviderAccess::Listen => "listen",
+        }
+    }
+
+    fn from_method(method: &str) -> Option<Self> {
+        match method {
+            "watch" => Some(ProviderAccess::Watch),
+            "read" => Some(ProviderAccess::Read),
+            "listen" => Some(ProviderAccess::Listen),
+            _ => None,
+        }
+    }
+
+    fn is_watch(&self) -> bool {
+        *self == ProviderAccess::Watch
+    }
 }
 
 /// Represents a widget tree captured by the Forge graph format.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Eq, Clone)]
 pub struct WidgetNode {
+    /// Stable identifier, unique within the screen. Assigned on import by
+    /// [assign_node_ids] and kept through serialization, so merges, renderer
+    /// errors and logic bindings can address the node wherever it moves.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
     pub widget: String,
     #[serde(default)]
     pub props: BTreeMap<String, PropValue>,
     #[serde(default)]
     pub children: Vec<WidgetNode>,
+    /// Verbatim Dart for a subtree the parser could not model, such as a
+    /// collection `for` element. Set only on [RAW_DART_WIDGET] nodes, which
+    /// renderers re-emit as-is instead of building a widget call.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub raw: Option<String>,
+    /// Builder callbacks such as `ListView.builder`'s `itemBuilder`, whose
+    /// returned widget is kept as a template subtree.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub builders: Vec<WidgetBuilder>,
+    /// Workspace-relative path of the file declaring the widget class, set by
+    /// [import_workspace] when the widget is a project component.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub component: Option<String>,
+    /// Where the widget's constructor call was imported from. Ignored when
+    /// comparing nodes, so re-imported or merged trees compare by content.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub span: Option<SourceSpan>,
+}
+
+/// A builder callback argument, e.g. `itemBuilder: (context, index) => ...`.
+/// The widget the callback returns is parsed into `template`, which is built
+/// once per item, snapshot or layout pass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct WidgetBuilder {
+    /// Named argument the callback is passed as.
+    pub argument: String,
+    pub kind: BuilderKind,
+    /// Callback parameter names, such as `["context", "index"]`.
+    #[serde(default)]
+    pub parameters: Vec<String>,
+    /// Statements of a block body preceding the final `return`, kept verbatim.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub statements: Option<String>,
+    pub template: Box<WidgetNode>,
+}
+
+/// What a builder callback is invoked for. The item count or data source is
+/// read from the owning node's props, see [WidgetNode::builder_source].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum BuilderKind {
+    /// Called per index, bounded by the `itemCount` prop when present.
+    Indexed,
+    /// `FutureBuilder`, fed by the `future` prop.
+    Future,
+    /// `StreamBuilder`, fed by the `stream` prop.
+    Stream,
+    /// `LayoutBuilder`, called with the parent's constraints.
+    Layout,
+    /// Any other widget-returning callback.
+    Callback,
+}
+
+impl BuilderKind {
+    /// Infers the kind from the widget and the argument the callback is
+    /// passed as.
+    fn classify(widget: &str, argument: &str) -> Self {
+        match widget {
+            "FutureBuilder" => BuilderKind::Future,
+            "StreamBuilder" => BuilderKind::Stream,
+            "LayoutBuilder" => BuilderKind::Layout,
+            _ if argument == "itemBuilder" || argument == "separatorBuilder" => {
+                BuilderKind::Indexed
+            }
+            _ => BuilderKind::Callback,
+        }
+    }
+
+    /// Prop holding the item count or data source for this kind.
+    pub fn source_prop(&self) -> Option<&'static str> {
+        match self {
+            BuilderKind::Indexed => Some("itemCount"),
+            BuilderKind::Future => Some("future"),
+            BuilderKind::Stream => Some("stream"),
+            BuilderKind::Layout | BuilderKind::Callback => None,
+        }
+    }
+}
+
+/// `widget` name of nodes that carry [WidgetNode::raw] Dart source.
+pub const RAW_DART_WIDGET: &str = "RawDart";
+
+/// `widget` name of structural nodes that show their first child when the
+/// `condition` prop holds and their optional second child otherwise.
+pub const CONDITIONAL_WIDGET: &str = "Conditional";
+
+/// `widget` name of structural nodes that repeat their single child for every
+/// element of the `iterable` prop, naming the element after the `item` prop.
+pub const FOR_EACH_WIDGET: &str = "ForEach";
+
+impl WidgetNode {
+    /// Creates an opaque node holding Dart source the parser could not model.
+    pub fn raw_dart<S: Into<String>>(source: S, span: Option<SourceSpan>) -> Self {
+        Self {
+            id: None,
+            widget: RAW_DART_WIDGET.to_string(),
+            props: BTreeMap::new(),
+            children: Vec::new(),
+            raw: Some(source.into()),
+            builders: Vec::new(),
+            component: None,
+            span,
+        }
+    }
+
+    /// Creates a [CONDITIONAL_WIDGET] node choosing between `then` and
+    /// `otherwise`.
+    pub fn conditional(
+        condition: PropValue,
+        then: WidgetNode,
+        otherwise: Option<WidgetNode>,
+        span: Option<SourceSpan>,
+    ) -> Self {
+        let mut children = vec![then];
+        children.extend(otherwise);
+        Self {
+            id: None,
+            widget: CONDITIONAL_WIDGET.to_string(),
+            props: BTreeMap::from([("condition".to_string(), condition)]),
+            children,
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span,
+        }
+    }
+
+    /// Creates a [FOR_EACH_WIDGET] node repeating `template` for every `item`
+    /// of `iterable`.
+    pub fn for_each(
+        iterable: PropValue,
+        item: &str,
+        template: WidgetNode,
+        span: Option<SourceSpan>,
+    ) -> Self {
+        Self {
+            id: None,
+            widget: FOR_EACH_WIDGET.to_string(),
+            props: BTreeMap::from([
+                ("iterable".to_string(), iterable),
+                (
+                    "item".to_string(),
+                    PropValue::Literal {
+                        value: serde_json::Value::String(item.to_string()),
+                        span: None,
+                    },
+                ),
+            ]),
+            children: vec![template],
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span,
+        }
+    }
+
+    /// Whether the node is a [CONDITIONAL_WIDGET] or [FOR_EACH_WIDGET] that
+    /// only controls which of its children are built.
+    pub fn is_structural(&self) -> bool {
+        self.raw.is_none() && (self.widget == CONDITIONAL_WIDGET || self.widget == FOR_EACH_WIDGET)
+    }
+
+    /// Condition of a [CONDITIONAL_WIDGET] node.
+    pub fn condition(&self) -> Option<&PropValue> {
+        (self.widget == CONDITIONAL_WIDGET)
+            .then(|| self.props.get("condition"))
+            .flatten()
+    }
+
+    /// Iterable of a [FOR_EACH_WIDGET] node.
+    pub fn iterable(&self) -> Option<&PropValue> {
+        (self.widget == FOR_EACH_WIDGET)
+            .then(|| self.props.get("iterable"))
+            .flatten()
+    }
+
+    /// Item count or data source feeding the node's first builder, such as
+    /// `itemCount` for `ListView.builder` or `future` for `FutureBuilder`.
+    pub fn builder_source(&self) -> Option<&PropValue> {
+        let prop = self.builders.first()?.kind.source_prop()?;
+        self.props.get(prop)
+    }
+
+    /// Loop variable of a [FOR_EACH_WIDGET] node, `item` when not recorded.
+    pub fn item_name(&self) -> &str {
+        match self.props.get("item") {
+            Some(PropValue::Literal {
+                value: serde_json::Value::String(name),
+                ..
+            }) => name,
+            _ => "item",
+        }
+    }
+}
+
+impl PartialEq for WidgetNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.widget == other.widget
+            && self.props == other.props
+            && self.children == other.children
+            && self.raw == other.raw
+            && self.builders == other.builders
+            && self.component == other.component
+    }
 }
 
 /// Top-level graph describing a Flutter screen.
@@ -233,6 +513,103 @@ pub struct WidgetNode {
 pub struct ScreenGraph {
     pub id: String,
     pub root: WidgetNode,
+    /// State owned by a `StatefulWidget` screen. `None` for stateless screens.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub state: Option<ScreenState>,
+    /// Riverpod `WidgetRef` of a `ConsumerWidget` or `ConsumerStatefulWidget`
+    /// screen. `None` for plain Flutter widgets.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub consumer: Option<ConsumerRef>,
+    /// Statements of `build` preceding its final `return`, such as local
+    /// variables and `ref.listen` calls, kept verbatim.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub statements: Option<String>,
+}
+
+/// The `WidgetRef` available to a Riverpod consumer screen.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ConsumerRef {
+    /// Name the `WidgetRef` is referred to by, usually `ref`.
+    pub name: String,
+    /// Every distinct provider access made through the ref, in source order.
+    /// Includes accesses outside widget props such as `ref.listen` calls and
+    /// `ref.read` inside callbacks.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub bindings: Vec<BindingReference>,
+}
+
+/// Members of the `State<T>` subclass paired with a `StatefulWidget` screen.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ScreenState {
+    /// Name of the `State` subclass, e.g. `_CounterState`.
+    pub class_name: String,
+    #[serde(default)]
+    pub fields: Vec<StateField>,
+    /// Body of `initState`, without the enclosing braces.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub init_state: Option<String>,
+    /// Body of `dispose`, without the enclosing braces.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub dispose: Option<String>,
+    /// Remaining methods and getters, kept verbatim so export can re-emit them.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub methods: Vec<StateMethod>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub set_state_calls: Vec<SetStateCall>,
+    /// Fields declared on the `StatefulWidget` class itself, such as the
+    /// configuration read through `widget.title`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub widget_fields: Vec<StateField>,
+    /// Constructors declared on the `StatefulWidget` class, kept verbatim and
+    /// dedented. Export falls back to `const Name({ super.key })` when empty.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub widget_constructors: Vec<String>,
+}
+
+impl ScreenState {
+    pub fn new<S: Into<String>>(class_name: S) -> Self {
+        Self {
+            class_name: class_name.into(),
+            fields: Vec::new(),
+            init_state: None,
+            dispose: None,
+            methods: Vec::new(),
+            set_state_calls: Vec::new(),
+            widget_fields: Vec::new(),
+            widget_constructors: Vec::new(),
+        }
+    }
+}
+
+/// Instance field declared on a `State` subclass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct StateField {
+    pub name: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub ty: Option<String>,
+    /// Declaration modifiers such as `late`, `final` or `static`, in source order.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub modifiers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub initializer: Option<String>,
+}
+
+/// Method or getter declared on a `State` subclass other than `build`,
+/// `initState` and `dispose`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct StateMethod {
+    pub name: String,
+    /// Full declaration including annotations, dedented to column zero.
+    pub source: String,
+}
+
+/// A `setState` call site discovered inside a `State` subclass.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct SetStateCall {
+    /// Name of the enclosing method (`build` for inline widget callbacks).
+    pub method: String,
+    /// Source of the closure passed to `setState`.
+    pub callback: String,
 }
 
 /// Minimal logical representation of a Flutter screen discovered in Dart code.
@@ -240,6 +617,103 @@ pub struct ScreenGraph {
 pub struct ParsedScreen {
     pub name: String,
     pub body: String,
+    /// Byte offset of `body` within the parsed source.
+    pub body_offset: usize,
+    /// Captured `State` members when the screen is a `StatefulWidget`.
+    pub state: Option<ScreenState>,
+    /// Captured `WidgetRef` when the screen is a Riverpod consumer.
+    pub consumer: Option<ConsumerRef>,
+}
+
+/// Warnings and errors collected while importing Dart source.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct ParseReport {
+    #[serde(default)]
+    pub diagnostics: Vec<ParseDiagnostic>,
+    /// Widget classes that were recognized but could not be imported as screens.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub skipped_classes: Vec<String>,
+}
+
+impl ParseReport {
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ParseDiagnostic> {
+        self.with_severity(DiagnosticSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ParseDiagnostic> {
+        self.with_severity(DiagnosticSeverity::Warning)
+    }
+
+    /// Diagnostics raised while importing the screen named `screen`.
+    pub fn for_screen<'a>(&'a self, screen: &'a str) -> impl Iterator<Item = &'a ParseDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| diagnostic.screen.as_deref() == Some(screen))
+    }
+
+    fn skip_class(&mut self, name: &str, reason: &str, span: SourceSpan) {
+        self.skipped_classes.push(name.to_string());
+        self.diagnostics.push(ParseDiagnostic {
+            severity: DiagnosticSeverity::Warning,
+            message: format!("`{name}` is not imported because {reason}"),
+            screen: Some(name.to_string()),
+            span: Some(span),
+        });
+    }
+
+    fn with_severity(
+        &self,
+        severity: DiagnosticSeverity,
+    ) -> impl Iterator<Item = &ParseDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| diagnostic.severity == severity)
+    }
+}
+
+/// A single problem found while importing Dart source.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ParseDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// Screen being imported when the problem was found.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub screen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub span: Option<SourceSpan>,
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{span}: ")?;
+        }
+        write!(f, "{}: {}", self.severity.as_str(), self.message)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Warning,
+    Error,
+}
+
+impl DiagnosticSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Error => "error",
+        }
+    }
 }
 
 /// Errors that can occur when interacting with the engine.
@@ -279,7 +753,7 @@ pub fn discover_dart_files<P: AsRef<Path>>(workspace_root: P) -> Result<Vec<Stri
             Err(err) => {
                 return Err(EngineError::IoError {
                     path: root.display().to_string(),
-                    source: std::io::Error::new(std::io::ErrorKind::Other, err),
+                    source: std::io::Error::other(err),
                 });
             }
         };
@@ -326,416 +800,1140 @@ pub fn read_graph<P: AsRef<Path>>(path: P) -> Result<ScreenGraph, EngineError> {
     Ok(graph)
 }
 
+/// Validates a serialized schema document against the Forge schema.
+pub fn validate_graph_schema(payload: &str) -> Result<(), String> {
+    static VALIDATOR: once_cell::sync::OnceCell<JSONSchema> = once_cell::sync::OnceCell::new();
+    let validator = VALIDATOR.get_or_try_init(|| {
+        let schema_src = include_str!("../../../forge_spec/graph_schema.json");
+        let json: serde_json::Value = serde_json::from_str(schema_src)
+            .map_err(|err| format!("Invalid graph schema JSON: {err}"))?;
+        JSONSchema::compile(&json).map_err(|err| format!("Failed to compile graph schema: {err}"))
+    })?;
+
+    let value: serde_json::Value = serde_json::from_str(payload)
+        .map_err(|err| format!("Export did not produce valid JSON: {err}"))?;
+
+    if let Err(errors) = validator.validate(&value) {
+        let messages: Vec<String> = errors
+            .map(|err| format!("{} at {}", err, err.instance_path))
+            .collect();
+        return Err(messages.join("\n"));
+    }
+
+    Ok(())
+}
+
 const SINGLE_CHILD_PROPS: &[&str] = &["child", "body", "appBar", "floatingActionButton"];
 
-static CLASS_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?s)class\s+(?P<name>[A-Za-z0-9_]+)\s+extends\s+StatelessWidget\s*\{.*?Widget\s+build\s*\([^)]*\)\s*\{(?P<body>.*?)\n\s*\}\s*\}")
-        .expect("stateless regex")
-});
+const STATE_FIELD_MODIFIERS: &[&str] = &["static", "late", "final", "const", "var", "covariant"];
+
+/// Class declaration located in Dart source, with its body between the braces.
+struct DartClass<'a> {
+    name: &'a str,
+    base: &'a str,
+    type_arg: Option<&'a str>,
+    body: &'a str,
+}
+
+/// A member declared inside a class body.
+enum ClassMember<'a> {
+    Field(StateField),
+    Method {
+        name: &'a str,
+        body: MethodBody<'a>,
+        source: &'a str,
+    },
+}
+
+enum MethodBody<'a> {
+    Block(&'a str),
+    Arrow(&'a str),
+    Abstract,
+}
 
 /// Parses Dart source and returns simple [ParsedScreen] representations of
 /// stateless widgets.
 pub fn parse_stateless_screens(source: &str) -> Vec<ParsedScreen> {
-    CLASS_REGEX
-        .captures_iter(source)
-        .map(|caps| ParsedScreen {
-            name: caps["name"].to_string(),
-            body: caps["body"].to_string(),
-        })
+    parse_screens(source)
+        .into_iter()
+        .filter(|screen| screen.state.is_none())
         .collect()
 }
 
-/// Converts the body of a `build` method into a [WidgetNode]. This parser is
-/// intentionally conservative and only supports nested constructor expressions
-/// with `children` or inline widget instances.
-pub fn parse_widget_tree(body: &str) -> Option<WidgetNode> {
-    let mut trimmed = body
-        .trim()
-        .trim_end_matches(';')
-        .trim_end_matches(',')
-        .trim();
-    if let Some(stripped) = trimmed.strip_prefix("return") {
-        trimmed = stripped.trim_start();
-    }
-    if trimmed.is_empty() {
-        return None;
-    }
-
-    let (widget, rest) = split_widget_signature(trimmed)?;
-    let (inner, _) = extract_parenthesized_block(rest)?;
-    let inner = inner.trim();
+/// Parses Dart source and returns a [ParsedScreen] for every stateless widget
+/// and every stateful widget whose `State` subclass is declared in the same
+/// source. Riverpod `ConsumerWidget` and `ConsumerStatefulWidget` screens are
+/// recognized as well and carry their [ConsumerRef]. Screens are returned in
+/// declaration order.
+pub fn parse_screens(source: &str) -> Vec<ParsedScreen> {
+    collect_screens(source, &mut Vec::new())
+}
 
-    let mut props: BTreeMap<String, PropValue> = BTreeMap::new();
-    let mut children = Vec::new();
+/// A widget class [parse_screens] could not turn into a screen.
+struct SkippedClass<'a> {
+    name: &'a str,
+    reason: &'static str,
+}
 
-    if let Some(raw_children) = extract_children_block(inner) {
-        for child_src in split_children(raw_children) {
-            if let Some(child_node) = parse_widget_tree(child_src) {
-                children.push(child_node);
+fn collect_screens<'a>(source: &'a str, skipped: &mut Vec<SkippedClass<'a>>) -> Vec<ParsedScreen> {
+    let classes = scan_dart_classes(source);
+    let mut screens = Vec::new();
+    let mut skip = |name, reason| skipped.push(SkippedClass { name, reason });
+
+    for class in &classes {
+        match class.base {
+            "StatelessWidget" | "ConsumerWidget" => {
+                let members = split_class_members(class.body);
+                let Some(body) = find_build_body(&members) else {
+                    skip(class.name, "it has no `build` method");
+                    continue;
+                };
+                let consumer = (class.base == "ConsumerWidget").then(|| {
+                    let name = find_widget_ref_parameter(&members).unwrap_or("ref");
+                    collect_consumer_ref(name, &[body])
+                });
+                screens.push(ParsedScreen {
+                    name: class.name.to_string(),
+                    body: body.to_string(),
+                    body_offset: offset_within(source, body),
+                    state: None,
+                    consumer,
+                });
             }
-        }
-    } else {
-        for child_expr in extract_single_child_expressions(inner, SINGLE_CHILD_PROPS) {
-            if let Some(child_node) = parse_widget_tree(child_expr) {
-                children.push(child_node);
+            "StatefulWidget" | "ConsumerStatefulWidget" => {
+                let state_base = if class.base == "StatefulWidget" {
+                    "State"
+                } else {
+                    "ConsumerState"
+                };
+                let Some(state_class) = classes.iter().find(|candidate| {
+                    candidate.base == state_base && candidate.type_arg == Some(class.name)
+                }) else {
+                    skip(
+                        class.name,
+                        "its `State` class is not declared in the same file",
+                    );
+                    continue;
+                };
+                let members = split_class_members(state_class.body);
+                let Some(body) = find_build_body(&members) else {
+                    skip(class.name, "its `State` class has no `build` method");
+                    continue;
+                };
+                let mut state = collect_screen_state(state_class.name, &members);
+                collect_widget_members(class.name, class.body, &mut state);
+                // `ConsumerState` exposes the ref as a member, so it is always `ref`.
+                let consumer = (state_base == "ConsumerState").then(|| {
+                    let mut scopes: Vec<&str> = Vec::new();
+                    scopes.extend(state.init_state.as_deref());
+                    scopes.extend(state.dispose.as_deref());
+                    scopes.extend(state.methods.iter().map(|method| method.source.as_str()));
+                    scopes.push(body);
+                    collect_consumer_ref("ref", &scopes)
+                });
+                screens.push(ParsedScreen {
+                    name: class.name.to_string(),
+                    body: body.to_string(),
+                    body_offset: offset_within(source, body),
+                    state: Some(state),
+                    consumer,
+                });
             }
+            _ => {}
         }
     }
 
-    let mut positional_index = 0;
-    for entry in split_top_level_entries(inner) {
-        if let Some((key, value)) = split_key_value(entry) {
-            if key == "children" || SINGLE_CHILD_PROPS.iter().any(|candidate| candidate == &key) {
+    screens
+}
+
+fn scan_dart_classes(source: &str) -> Vec<DartClass<'_>> {
+    let Ok(stream) = TokenStream::new(source) else {
+        return Vec::new();
+    };
+    let mut classes = Vec::new();
+    let mut idx = 0;
+    while idx < stream.len() {
+        if stream.is_identifier(idx, "class") {
+            if let Some((class, next)) = read_class_declaration(&stream, idx + 1) {
+                classes.push(class);
+                idx = next;
                 continue;
             }
-            props.insert(key.to_string(), parse_prop_value(value.trim()));
-        } else {
-            if let Some(name) = map_positional_prop(widget, positional_index) {
-                props.insert(name.to_string(), parse_prop_value(entry.trim()));
-            } else if !entry.trim().is_empty() {
-                let fallback = format!("positional{positional_index}");
-                props.insert(fallback, parse_prop_value(entry.trim()));
+        }
+        idx = stream.skip(idx);
+    }
+    classes
+}
+
+/// Reads `Name<T> extends Base<Arg> with M implements I { ... }` starting just
+/// after the `class` keyword. Returns the class and the index past its body.
+fn read_class_declaration<'a>(
+    stream: &TokenStream<'a>,
+    mut idx: usize,
+) -> Option<(DartClass<'a>, usize)> {
+    let name = identifier_at(stream, idx)?;
+    idx = skip_type_parameters(stream, idx + 1);
+    if !stream.is_identifier(idx, "extends") {
+        return None;
+    }
+    idx += 1;
+
+    let base_start = idx;
+    identifier_at(stream, idx)?;
+    idx += 1;
+    while stream.is_symbol(idx, ".") && identifier_at(stream, idx + 1).is_some() {
+        idx += 2;
+    }
+    let base = stream.span(base_start, idx - 1).text(stream.source());
+    let type_arg = if stream.is_symbol(idx, "<") {
+        let arg = identifier_at(stream, idx + 1);
+        idx = skip_type_parameters(stream, idx);
+        arg
+    } else {
+        None
+    };
+
+    while idx < stream.len() && !stream.is_symbol(idx, "{") {
+        if stream.is_symbol(idx, ";") {
+            return None;
+        }
+        idx = stream.skip(idx);
+    }
+    let close = stream.closing(idx)?;
+    let body = token_gap(stream, idx, close);
+    Some((
+        DartClass {
+            name,
+            base,
+            type_arg,
+            body,
+        },
+        close + 1,
+    ))
+}
+
+fn identifier_at<'a>(stream: &TokenStream<'a>, idx: usize) -> Option<&'a str> {
+    stream
+        .tokens()
+        .get(idx)
+        .filter(|token| token.kind == TokenKind::Identifier)
+        .map(|token| token.span.text(stream.source()))
+}
+
+/// Skips a `<...>` type parameter or argument list starting at `idx`.
+fn skip_type_parameters(stream: &TokenStream<'_>, mut idx: usize) -> usize {
+    if !stream.is_symbol(idx, "<") {
+        return idx;
+    }
+    let mut depth = 0_usize;
+    while idx < stream.len() {
+        if stream.is_symbol(idx, "<") {
+            depth += 1;
+        } else if stream.is_symbol(idx, ">") {
+            depth -= 1;
+            if depth == 0 {
+                return idx + 1;
             }
-            positional_index += 1;
         }
+        idx = stream.skip(idx);
     }
+    idx
+}
 
-    Some(WidgetNode {
-        widget: widget.to_string(),
-        props,
-        children,
+/// Source strictly between the tokens at `open` and `close`.
+fn token_gap<'a>(stream: &TokenStream<'a>, open: usize, close: usize) -> &'a str {
+    let tokens = stream.tokens();
+    Span::new(tokens[open].span.end, tokens[close].span.start).text(stream.source())
+}
+
+fn find_build_body<'a>(members: &[ClassMember<'a>]) -> Option<&'a str> {
+    members.iter().find_map(|member| match member {
+        ClassMember::Method {
+            name: "build",
+            body: MethodBody::Block(body) | MethodBody::Arrow(body),
+            ..
+        } => Some(*body),
+        _ => None,
     })
 }
 
-fn extract_children_block(inner: &str) -> Option<&str> {
-    let mut chars = inner.char_indices();
-    while let Some((idx, ch)) = chars.next() {
-        if ch == 'c' && inner[idx..].starts_with("children") {
-            let rest = &inner[idx + "children".len()..];
-            let mut rest = rest.trim_start();
-            if !rest.starts_with(':') {
-                continue;
-            }
-            rest = rest[1..].trim_start();
-            if let Some(stripped) = rest.strip_prefix("const") {
-                rest = stripped.trim_start();
-            }
-            if rest.starts_with('<') {
-                if let Some(after_generic) = skip_generic_block(rest) {
-                    rest = after_generic.trim_start();
-                } else {
-                    continue;
-                }
-            }
-            if !rest.starts_with('[') {
-                continue;
+/// Byte offset of `inner` within `outer`. Class members are parsed as slices
+/// of the original source, so this recovers their position without threading
+/// offsets through every helper.
+fn offset_within(outer: &str, inner: &str) -> usize {
+    let offset = (inner.as_ptr() as usize).wrapping_sub(outer.as_ptr() as usize);
+    debug_assert!(offset + inner.len() <= outer.len());
+    offset
+}
+
+/// Returns the name of the `WidgetRef` parameter declared by `build`.
+fn find_widget_ref_parameter<'a>(members: &[ClassMember<'a>]) -> Option<&'a str> {
+    let source = members.iter().find_map(|member| match member {
+        ClassMember::Method {
+            name: "build",
+            source,
+            ..
+        } => Some(*source),
+        _ => None,
+    })?;
+    let stream = TokenStream::new(source).ok()?;
+    let mut open = skip_annotations(&stream, 0);
+    while open < stream.len() && !stream.is_symbol(open, "(") {
+        open = stream.skip(open);
+    }
+    let close = stream.closing(open)?;
+    (open + 1..close).find_map(|idx| {
+        stream
+            .is_identifier(idx, "WidgetRef")
+            .then(|| identifier_at(&stream, idx + 1))
+            .flatten()
+    })
+}
+
+fn collect_consumer_ref(name: &str, scopes: &[&str]) -> ConsumerRef {
+    let mut bindings: Vec<BindingReference> = Vec::new();
+    for scope in scopes {
+        for binding in find_provider_accesses(scope, name) {
+            if !bindings.contains(&binding) {
+                bindings.push(binding);
             }
-            return extract_bracket_block(rest);
         }
     }
-    None
+    ConsumerRef {
+        name: name.to_string(),
+        bindings,
+    }
 }
 
-fn extract_single_child_expressions<'a>(inner: &'a str, keys: &[&str]) -> Vec<&'a str> {
+/// Returns a binding for every `ref.watch`, `ref.read` and `ref.listen` call in
+/// `src`, where `ref` is `ref_name`.
+fn find_provider_accesses(src: &str, ref_name: &str) -> Vec<BindingReference> {
+    let Ok(stream) = TokenStream::new(src) else {
+        return Vec::new();
+    };
     let mut results = Vec::new();
-    let mut index = 0;
-    while index < inner.len() {
-        if let Some((expr, consumed)) = match_single_child_prop(inner, index, keys) {
-            results.push(expr);
-            index += consumed;
-        } else {
-            index += 1;
+    for idx in 0..stream.len() {
+        let is_member =
+            idx > 0 && (stream.is_symbol(idx - 1, ".") || stream.is_symbol(idx - 1, "?."));
+        if is_member || !stream.is_identifier(idx, ref_name) || !stream.is_symbol(idx + 1, ".") {
+            continue;
+        }
+        let access = identifier_at(&stream, idx + 2).and_then(ProviderAccess::from_method);
+        let (Some(access), true) = (access, stream.is_symbol(idx + 3, "(")) else {
+            continue;
+        };
+        if let Ok((provider, _)) = stream.parse_expression(idx + 4) {
+            results.extend(provider_binding(&provider, src, access));
         }
     }
     results
 }
 
-fn match_single_child_prop<'a>(
-    inner: &'a str,
-    start: usize,
-    keys: &[&str],
-) -> Option<(&'a str, usize)> {
-    let slice = &inner[start..];
-    for key in keys {
-        if !slice.starts_with(key) {
-            continue;
+/// Builds a binding from the provider passed to a `WidgetRef` method. A
+/// trailing `.notifier` is recorded on the binding rather than in its reference.
+fn provider_binding(
+    provider: &DartExpr,
+    source: &str,
+    access: ProviderAccess,
+) -> Option<BindingReference> {
+    let (provider, notifier) = match &provider.kind {
+        DartExprKind::PropertyAccess {
+            target,
+            name,
+            null_aware: false,
+        } if name == "notifier" => (target.as_ref(), true),
+        _ => (provider, false),
+    };
+    let reference = provider.span.text(source).trim();
+    if reference.is_empty() {
+        return None;
+    }
+    Some(BindingReference {
+        target: BindingTarget::Provider,
+        reference: reference.to_string(),
+        provider_id: extract_provider_identifier(reference),
+        path: None,
+        type_hint: None,
+        access,
+        notifier,
+    })
+}
+
+fn collect_screen_state(class_name: &str, members: &[ClassMember<'_>]) -> ScreenState {
+    let mut state = ScreenState::new(class_name);
+    let mut build_body = None;
+
+    for member in members {
+        match member {
+            ClassMember::Field(field) => state.fields.push(field.clone()),
+            ClassMember::Method { name, body, source } => match (*name, body) {
+                ("initState", MethodBody::Block(body)) => {
+                    state.init_state = Some(dedent_block(body))
+                }
+                ("dispose", MethodBody::Block(body)) => state.dispose = Some(dedent_block(body)),
+                ("build", MethodBody::Block(body) | MethodBody::Arrow(body)) => {
+                    build_body = Some(*body)
+                }
+                _ => state.methods.push(StateMethod {
+                    name: name.to_string(),
+                    source: dedent_block(source),
+                }),
+            },
         }
-        // Ensure we are matching the property name boundary.
-        if slice[key.len()..]
-            .chars()
-            .next()
-            .map(|c| c.is_alphanumeric() || c == '_')
-            == Some(true)
-        {
-            continue;
+    }
+
+    // Call sites are gathered in the order export re-emits the members so that
+    // a regenerated class yields the same sequence.
+    let mut scopes: Vec<(&str, &str)> = Vec::new();
+    if let Some(body) = &state.init_state {
+        scopes.push(("initState", body));
+    }
+    if let Some(body) = &state.dispose {
+        scopes.push(("dispose", body));
+    }
+    for method in &state.methods {
+        scopes.push((&method.name, &method.source));
+    }
+    if let Some(body) = build_body {
+        scopes.push(("build", body));
+    }
+
+    let mut calls = Vec::new();
+    for (method, body) in scopes {
+        for callback in find_call_arguments(body, "setState") {
+            calls.push(SetStateCall {
+                method: method.to_string(),
+                callback: callback.to_string(),
+            });
         }
-        if start > 0 {
-            if let Some(prev) = inner[..start].chars().rev().find(|c| !c.is_whitespace()) {
-                if prev.is_alphanumeric() || prev == '_' {
-                    continue;
-                }
+    }
+    state.set_state_calls = calls;
+    state
+}
+
+/// Captures the fields and constructors of the `StatefulWidget` class named
+/// `class_name` so export does not drop the widget's configuration.
+fn collect_widget_members(class_name: &str, body: &str, state: &mut ScreenState) {
+    for member in split_class_members(body) {
+        match member {
+            ClassMember::Field(field) => state.widget_fields.push(field),
+            ClassMember::Method { name, source, .. }
+                if name == class_name
+                    || name
+                        .strip_prefix(class_name)
+                        .is_some_and(|rest| rest.starts_with('.')) =>
+            {
+                state.widget_constructors.push(dedent_block(source));
             }
+            ClassMember::Method { .. } => {}
         }
+    }
+}
+
+/// Splits a class body into member declarations. Comments between members are
+/// dropped; each returned slice starts at the beginning of its first line.
+fn split_class_members(body: &str) -> Vec<ClassMember<'_>> {
+    let Ok(stream) = TokenStream::new(body) else {
+        return Vec::new();
+    };
+    let tokens = stream.tokens();
+    let mut members = Vec::new();
+    let mut start = None;
+    let mut has_initializer = false;
+    let mut idx = 0;
+
+    while idx < stream.len() {
+        let first = *start.get_or_insert(idx);
+        let end = if stream.is_symbol(idx, ";") {
+            Some(idx)
+        } else if stream.is_symbol(idx, "{") && !has_initializer {
+            stream.closing(idx)
+        } else {
+            if stream.is_symbol(idx, "=") || stream.is_symbol(idx, "=>") {
+                has_initializer = true;
+            }
+            None
+        };
 
-        let after_key = &slice[key.len()..];
-        let trimmed_after_key = after_key.trim_start();
-        let mut offset = key.len() + (after_key.len() - trimmed_after_key.len());
-        if !trimmed_after_key.starts_with(':') {
+        let Some(last) = end else {
+            idx = stream.skip(idx);
             continue;
+        };
+        let member_start = tokens[first].span.start;
+        let line_start = body[..member_start].rfind('\n').map_or(0, |pos| pos + 1);
+        let from = if body[line_start..member_start].trim().is_empty() {
+            line_start
+        } else {
+            member_start
+        };
+        if let Some(member) = classify_member(&body[from..tokens[last].span.end]) {
+            members.push(member);
         }
-        offset += 1; // consume ':'
-        let after_colon = &trimmed_after_key[1..];
-        let trimmed_after_colon = after_colon.trim_start();
-        offset += after_colon.len() - trimmed_after_colon.len();
-
-        let mut expr_slice = trimmed_after_colon;
-        let mut consumed = offset;
-        if expr_slice.starts_with("const")
-            && expr_slice
-                .chars()
-                .nth("const".len())
-                .map(|c| c.is_alphanumeric() || c == '_')
-                != Some(true)
+        start = None;
+        has_initializer = false;
+        idx = last + 1;
+    }
+
+    members
+}
+
+fn classify_member(source: &str) -> Option<ClassMember<'_>> {
+    let stream = TokenStream::new(source).ok()?;
+    let first = skip_annotations(&stream, 0);
+    if first >= stream.len() || stream.is_symbol(first, ";") {
+        return None;
+    }
+
+    let mut paren = None;
+    let mut assign = None;
+    let mut arrow = None;
+    let mut block = None;
+    let mut idx = first;
+    while idx < stream.len() {
+        // `void Function(int) callback;` declares a field, not a method.
+        let is_function_type = idx > 0 && stream.is_identifier(idx - 1, "Function");
+        if stream.is_symbol(idx, "(") && paren.is_none() && assign.is_none() && !is_function_type {
+            paren = Some(idx);
+        } else if stream.is_symbol(idx, "{")
+            && block.is_none()
+            && arrow.is_none()
+            && assign.is_none()
         {
-            expr_slice = &expr_slice["const".len()..];
-            let trimmed_after_const = expr_slice.trim_start();
-            consumed += "const".len() + (expr_slice.len() - trimmed_after_const.len());
-            expr_slice = trimmed_after_const;
+            block = Some(idx);
+        } else if stream.is_symbol(idx, "=>") && arrow.is_none() && assign.is_none() {
+            arrow = Some(idx);
+        } else if stream.is_symbol(idx, "=") && assign.is_none() && arrow.is_none() {
+            assign = Some(idx);
         }
+        idx = stream.skip(idx);
+    }
 
-        if let Some((expr, expr_len)) = take_widget_expression(expr_slice) {
-            consumed += expr_len;
-            return Some((expr, consumed));
+    if paren.is_none() && arrow.is_none() && block.is_none() {
+        return parse_field_declaration(&stream, first).map(ClassMember::Field);
+    }
+
+    let header_end = [paren, arrow, block].into_iter().flatten().min()?;
+    let name = member_name(&stream, first, header_end)?;
+    let body = match (block, arrow) {
+        (Some(open), _) => MethodBody::Block(token_gap(&stream, open, stream.closing(open)?)),
+        (None, Some(arrow)) => {
+            let after = &source[stream.tokens()[arrow].span.end..];
+            MethodBody::Arrow(after.trim().trim_end_matches(';').trim_end())
+        }
+        (None, None) => MethodBody::Abstract,
+    };
+
+    Some(ClassMember::Method { name, body, source })
+}
+
+/// Skips `@annotation` and `@Annotation(...)` tokens starting at `idx`.
+fn skip_annotations(stream: &TokenStream<'_>, mut idx: usize) -> usize {
+    while stream.is_symbol(idx, "@") {
+        idx += 1;
+        if identifier_at(stream, idx).is_some() {
+            idx += 1;
+        }
+        while stream.is_symbol(idx, ".") && identifier_at(stream, idx + 1).is_some() {
+            idx += 2;
+        }
+        if stream.is_symbol(idx, "(") {
+            idx = stream.skip(idx);
         }
     }
-    None
+    idx
 }
 
-fn extract_bracket_block(input: &str) -> Option<&str> {
-    let mut depth = 0_i32;
-    let mut start_content = None;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '[' => {
+/// Name declared just before the token at `header_end`, skipping generic
+/// method type parameters. Named constructors keep their dotted name.
+fn member_name<'a>(stream: &TokenStream<'a>, first: usize, header_end: usize) -> Option<&'a str> {
+    let mut last = header_end.checked_sub(1)?;
+    if stream.is_symbol(last, ">") {
+        let mut depth = 0_usize;
+        loop {
+            if stream.is_symbol(last, ">") {
                 depth += 1;
-                if depth == 1 {
-                    start_content = Some(idx + ch.len_utf8());
-                }
-            }
-            ']' => {
+            } else if stream.is_symbol(last, "<") {
                 depth -= 1;
                 if depth == 0 {
-                    let start = start_content?;
-                    return Some(input[start..idx].trim());
+                    break;
                 }
             }
-            _ => {}
+            last = last.checked_sub(1).filter(|idx| *idx >= first)?;
         }
+        last = last.checked_sub(1)?;
     }
-    None
+    identifier_at(stream, last)?;
+    let mut start = last;
+    while start >= first + 2
+        && stream.is_symbol(start - 1, ".")
+        && identifier_at(stream, start - 2).is_some()
+    {
+        start -= 2;
+    }
+    (start >= first).then(|| stream.span(start, last).text(stream.source()))
 }
 
-fn take_widget_expression(input: &str) -> Option<(&str, usize)> {
-    let (_, rest) = split_widget_signature(input)?;
-    let (_, remaining) = extract_parenthesized_block(rest)?;
-    let consumed = input.len() - remaining.len();
-    let expr = input[..consumed].trim_end_matches(',').trim();
-    if expr.is_empty() {
-        None
-    } else {
-        Some((expr, consumed))
-    }
-}
-
-fn split_top_level_entries(input: &str) -> Vec<&str> {
-    let mut entries = Vec::new();
-    let mut paren = 0_i32;
-    let mut bracket = 0_i32;
-    let mut brace = 0_i32;
-    let mut angle = 0_i32;
-    let mut start = 0;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '(' => paren += 1,
-            ')' => paren -= 1,
-            '[' => bracket += 1,
-            ']' => bracket -= 1,
-            '{' => brace += 1,
-            '}' => brace -= 1,
-            '<' => angle += 1,
-            '>' => {
-                if angle > 0 {
-                    angle -= 1;
-                }
-            }
-            ',' if paren == 0 && bracket == 0 && brace == 0 && angle == 0 => {
-                let segment = input[start..idx].trim();
-                if !segment.is_empty() {
-                    entries.push(segment);
-                }
-                start = idx + 1;
+fn parse_field_declaration(stream: &TokenStream<'_>, first: usize) -> Option<StateField> {
+    let mut end = stream.len();
+    if stream.is_symbol(end - 1, ";") {
+        end -= 1;
+    }
+    let mut assign = None;
+    let mut idx = first;
+    while idx < end {
+        if stream.is_symbol(idx, "=") {
+            assign = Some(idx);
+            break;
+        }
+        idx = stream.skip(idx);
+    }
+
+    let declarator_end = assign.unwrap_or(end);
+    let name_idx = declarator_end.checked_sub(1).filter(|idx| *idx >= first)?;
+    let name = identifier_at(stream, name_idx)?;
+    let initializer = assign.filter(|assign| assign + 1 < end).map(|assign| {
+        stream
+            .span(assign + 1, end - 1)
+            .text(stream.source())
+            .to_string()
+    });
+
+    let mut modifiers = Vec::new();
+    let mut ty_start = first;
+    while ty_start < name_idx && STATE_FIELD_MODIFIERS.contains(&stream.text(ty_start)) {
+        modifiers.push(stream.text(ty_start).to_string());
+        ty_start += 1;
+    }
+    let ty = (ty_start < name_idx).then(|| {
+        stream
+            .span(ty_start, name_idx - 1)
+            .text(stream.source())
+            .to_string()
+    });
+
+    Some(StateField {
+        name: name.to_string(),
+        ty,
+        modifiers,
+        initializer,
+    })
+}
+
+/// Returns the argument source of every call to `callee` in `src`.
+fn find_call_arguments<'a>(src: &'a str, callee: &str) -> Vec<&'a str> {
+    let Ok(stream) = TokenStream::new(src) else {
+        return Vec::new();
+    };
+    let mut results = Vec::new();
+    let mut idx = 0;
+    while idx < stream.len() {
+        if stream.is_identifier(idx, callee) && stream.is_symbol(idx + 1, "(") {
+            if let Some(close) = stream.closing(idx + 1) {
+                results.push(token_gap(&stream, idx + 1, close).trim());
+                idx = close + 1;
+                continue;
             }
-            _ => {}
         }
+        idx += 1;
+    }
+    results
+}
+
+/// Removes blank leading/trailing lines and the common indentation of a block.
+fn dedent_block(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let first = lines.iter().position(|line| !line.trim().is_empty());
+    let last = lines.iter().rposition(|line| !line.trim().is_empty());
+    let (Some(first), Some(last)) = (first, last) else {
+        return String::new();
+    };
+    let lines = &lines[first..=last];
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Converts the body of a `build` method into a [WidgetNode]. The body is
+/// tokenized and parsed into a [DartExpr]; constructor calls become widgets,
+/// `children` lists and single-child slots become child nodes and every other
+/// argument becomes a prop. Statements preceding the final top-level `return`
+/// are not part of the tree; [build_graphs_with_report] keeps them on
+/// [ScreenGraph::statements]. Anything that is not a widget call is kept as a
+/// [RAW_DART_WIDGET] node; use [parse_widget_tree_with_report] to find out why.
+pub fn parse_widget_tree(body: &str) -> Option<WidgetNode> {
+    parse_widget_tree_with_report(body).0
+}
+
+/// Like [parse_widget_tree], also returning the diagnostics raised while
+/// parsing. Spans are relative to `body`.
+pub fn parse_widget_tree_with_report(body: &str) -> (Option<WidgetNode>, ParseReport) {
+    let map = SourceMap::new(body, None);
+    let mut report = ParseReport::default();
+    let root = parse_widget_node(
+        &BuildBody {
+            text: body,
+            offset: 0,
+            map: &map,
+            ref_name: "ref",
+            screen: None,
+        },
+        &mut report,
+    );
+    (root.map(|(root, _)| root), report)
+}
+
+/// A `build` body being converted into a widget tree.
+struct BuildBody<'a> {
+    text: &'a str,
+    /// Byte offset of `text` within the source mapped by `map`.
+    offset: usize,
+    map: &'a SourceMap<'a>,
+    ref_name: &'a str,
+    screen: Option<&'a str>,
+}
+
+impl BuildBody<'_> {
+    fn span(&self, span: Span) -> SourceSpan {
+        self.map.span(self.offset + span.start)
+    }
+
+    fn report(
+        &self,
+        report: &mut ParseReport,
+        severity: DiagnosticSeverity,
+        message: String,
+        span: Span,
+    ) {
+        report.diagnostics.push(ParseDiagnostic {
+            severity,
+            message,
+            screen: self.screen.map(str::to_string),
+            span: Some(self.span(span)),
+        });
     }
-    if start < input.len() {
-        let segment = input[start..].trim();
-        if !segment.is_empty() {
-            entries.push(segment);
+
+    /// Keeps `span` as a [RAW_DART_WIDGET] node and warns that `what` could
+    /// not be modeled. Continuation lines are dedented by the indentation of
+    /// the line the snippet starts on, so renderers can re-indent it.
+    fn raw_node(&self, span: Span, what: &str, report: &mut ParseReport) -> WidgetNode {
+        self.report(
+            report,
+            DiagnosticSeverity::Warning,
+            format!("{what} is kept as raw Dart"),
+            span,
+        );
+        let line_start = self.text[..span.start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line = &self.text[line_start..];
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let mut lines = span.text(self.text).lines();
+        let mut raw = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            raw.push('\n');
+            let strip = line.len() - line.trim_start_matches([' ', '\t']).len();
+            raw.push_str(&line[strip.min(indent)..]);
         }
+        WidgetNode::raw_dart(raw, Some(self.span(span)))
     }
-    entries
 }
 
-fn split_key_value(entry: &str) -> Option<(&str, &str)> {
-    let mut paren = 0_i32;
-    let mut bracket = 0_i32;
-    let mut brace = 0_i32;
-    let mut angle = 0_i32;
-    let mut single_quote = false;
-    let mut double_quote = false;
-    let mut escape = false;
-    for (idx, ch) in entry.char_indices() {
-        if escape {
-            escape = false;
-            continue;
+/// Converts a `build` body into its widget tree and the statements preceding
+/// the final top-level `return`.
+fn parse_widget_node(
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> Option<(WidgetNode, Option<String>)> {
+    let stream = match TokenStream::new(body.text) {
+        Ok(stream) => stream,
+        Err(err) => {
+            let span = Span::new(0, body.text.len());
+            body.report(report, DiagnosticSeverity::Error, err.to_string(), span);
+            return (!body.text.trim().is_empty()).then(|| {
+                let raw = WidgetNode::raw_dart(body.text.trim(), Some(body.span(span)));
+                (raw, None)
+            });
         }
-
-        match ch {
-            '\\' if single_quote || double_quote => {
-                escape = true;
-            }
-            '\'' if !double_quote => {
-                single_quote = !single_quote;
-            }
-            '"' if !single_quote => {
-                double_quote = !double_quote;
-            }
-            '(' if !single_quote && !double_quote => paren += 1,
-            ')' if !single_quote && !double_quote => paren -= 1,
-            '[' if !single_quote && !double_quote => bracket += 1,
-            ']' if !single_quote && !double_quote => bracket -= 1,
-            '{' if !single_quote && !double_quote => brace += 1,
-            '}' if !single_quote && !double_quote => brace -= 1,
-            '<' if !single_quote && !double_quote => angle += 1,
-            '>' if !single_quote && !double_quote => {
-                if angle > 0 {
-                    angle -= 1;
-                }
+    };
+    let return_idx = find_return_statement(&stream);
+    let start = return_idx.map_or(0, |idx| idx + 1);
+    if start >= stream.len() {
+        return None;
+    }
+    let statements = return_idx
+        .map(|idx| dedent_block(&body.text[..stream.span(idx, idx).start]))
+        .filter(|statements| !statements.is_empty());
+    match stream.parse_expression(start) {
+        Ok((expr, next)) => {
+            let trailing = if stream.is_symbol(next, ";") {
+                next + 1
+            } else {
+                next
+            };
+            if trailing < stream.len() {
+                let span = stream.span(trailing, stream.len() - 1);
+                body.report(
+                    report,
+                    DiagnosticSeverity::Warning,
+                    String::from("statements after the final `return` are dropped"),
+                    span,
+                );
             }
-            ':' if !single_quote
-                && !double_quote
-                && paren == 0
-                && bracket == 0
-                && brace == 0
-                && angle == 0 =>
-            {
-                let key = entry[..idx].trim();
-                if key.is_empty() {
-                    return None;
-                }
-                let value = entry[idx + 1..].trim();
-                return Some((key, value));
+            Some((widget_or_raw(&expr, body, report), statements))
+        }
+        Err(err) => {
+            let mut end = start;
+            while end < stream.len() && !stream.is_symbol(end, ";") {
+                end = stream.skip(end);
             }
-            _ => {}
+            let span = stream.span(start, end.min(stream.len()) - 1);
+            body.report(report, DiagnosticSeverity::Error, err.to_string(), span);
+            let raw = WidgetNode::raw_dart(span.text(body.text), Some(body.span(span)));
+            Some((raw, statements))
         }
     }
-    None
 }
 
-fn parse_prop_value(raw: &str) -> PropValue {
-    if let Some(binding) = parse_binding(raw) {
-        return binding;
-    }
-    if let Some(literal) = parse_literal(raw) {
-        return literal;
+/// Index of the last top-level `return` keyword in a `build` block body.
+/// Earlier returns belong to branches such as `if (loading) return ...;` and
+/// stay part of the preceding statements.
+fn find_return_statement(stream: &TokenStream<'_>) -> Option<usize> {
+    let mut idx = 0;
+    let mut last = None;
+    while idx < stream.len() {
+        if stream.is_identifier(idx, "return") {
+            last = Some(idx);
+        }
+        idx = stream.skip(idx);
     }
-    PropValue::Expression {
-        expression: raw.to_string(),
+    last
+}
+
+/// Converts `expr` into a widget, falling back to a [RAW_DART_WIDGET] node.
+fn widget_or_raw(expr: &DartExpr, body: &BuildBody<'_>, report: &mut ParseReport) -> WidgetNode {
+    widget_from_expr(expr, body, report).unwrap_or_else(|| {
+        let what = match expr.kind {
+            DartExprKind::Opaque => "unparseable expression",
+            _ => "expression that is not a widget constructor call",
+        };
+        body.raw_node(expr.span, what, report)
+    })
+}
+
+fn widget_from_expr(
+    expr: &DartExpr,
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> Option<WidgetNode> {
+    let (callee, arguments) = match &expr.kind {
+        DartExprKind::Call {
+            callee, arguments, ..
+        } => (callee, arguments),
+        DartExprKind::Parenthesized(inner) => return widget_from_expr(inner, body, report),
+        DartExprKind::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            if !is_widget_expr(then_branch)
+                || !(is_widget_expr(else_branch) || else_branch.kind == DartExprKind::Null)
+            {
+                return None;
+            }
+            let then = widget_from_expr(then_branch, body, report)?;
+            let otherwise = widget_from_expr(else_branch, body, report);
+            return Some(WidgetNode::conditional(
+                parse_expr_value(condition, condition.span, body, report),
+                then,
+                otherwise,
+                Some(body.span(expr.span)),
+            ));
+        }
+        _ => return None,
+    };
+    let widget = callee_path(callee)?;
+
+    let mut props = BTreeMap::new();
+    let mut children = Vec::new();
+    let mut builders = Vec::new();
+    let positional = arguments.iter().filter(|argument| argument.name.is_none());
+    for (index, argument) in positional.enumerate() {
+        let name = map_positional_prop(&widget, index)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("positional{index}"));
+        props.insert(name, parse_prop_value(argument, body, report));
+    }
+
+    for argument in arguments {
+        let value = &argument.value;
+        let Some(key) = argument.name.as_deref() else {
+            continue;
+        };
+
+        if key == "children" {
+            if let DartExprKind::List { elements, .. } = &value.kind {
+                children.extend(
+                    elements
+                        .iter()
+                        .map(|element| widget_from_element(element, body, report)),
+                );
+                continue;
+            }
+        }
+        if SINGLE_CHILD_PROPS.contains(&key) {
+            if let Some(child) = widget_from_expr(value, body, report) {
+                children.push(child);
+                continue;
+            }
+        }
+        if key == "builder" || key.ends_with("Builder") {
+            if let Some(builder) = builder_from_expr(&widget, key, value, body, report) {
+                builders.push(builder);
+                continue;
+            }
+        }
+        props.insert(key.to_string(), parse_prop_value(argument, body, report));
     }
+
+    Some(WidgetNode {
+        id: None,
+        widget,
+        props,
+        children,
+        raw: None,
+        builders,
+        component: None,
+        span: Some(body.span(expr.span)),
+    })
 }
 
-fn parse_binding(raw: &str) -> Option<PropValue> {
-    let trimmed = raw.trim();
-    const PREFIX: &str = "ref.watch(";
-    if !trimmed.starts_with(PREFIX) {
+/// Parses a builder callback such as `(context, index) => ItemTile(index)`.
+/// Block bodies use their final top-level `return`; the statements before it
+/// are kept verbatim. Callbacks that do not return a widget stay props.
+fn builder_from_expr(
+    widget: &str,
+    argument: &str,
+    value: &DartExpr,
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> Option<WidgetBuilder> {
+    let DartExprKind::Function {
+        parameters,
+        body: function_body,
+        ..
+    } = &value.kind
+    else {
         return None;
-    }
+    };
 
-    let after_prefix = &trimmed[PREFIX.len()..];
-    let mut depth = 1_i32;
-    let mut closing_rel = None;
-    for (idx, ch) in after_prefix.char_indices() {
-        match ch {
-            '(' => depth += 1,
-            ')' => {
-                depth -= 1;
-                if depth == 0 {
-                    closing_rel = Some(idx);
-                    break;
+    let (template, statements) = match function_body {
+        FunctionBody::Expression(expr) => {
+            if !is_widget_expr(expr) {
+                return None;
+            }
+            (widget_from_expr(expr, body, report)?, None)
+        }
+        FunctionBody::Block(span) => {
+            // Parse the block without its braces, so `return` is top-level.
+            let inner = Span::new(span.start + 1, span.end - 1);
+            let block = BuildBody {
+                text: inner.text(body.text),
+                offset: body.offset + inner.start,
+                map: body.map,
+                ref_name: body.ref_name,
+                screen: body.screen,
+            };
+            let stream = TokenStream::new(block.text).ok()?;
+            let mut idx = 0;
+            let mut last_return = None;
+            while idx < stream.len() {
+                if stream.is_identifier(idx, "return") {
+                    last_return = Some(idx);
                 }
+                idx = stream.skip(idx);
             }
-            _ => {}
+            let return_idx = last_return?;
+            let (expr, _) = stream.parse_expression(return_idx + 1).ok()?;
+            if !is_widget_expr(&expr) {
+                return None;
+            }
+            let template = widget_from_expr(&expr, &block, report)?;
+            let preceding = &block.text[..stream.span(return_idx, return_idx).start];
+            let statements = dedent_block(preceding);
+            (template, (!statements.is_empty()).then_some(statements))
+        }
+    };
+
+    Some(WidgetBuilder {
+        argument: argument.to_string(),
+        kind: BuilderKind::classify(widget, argument),
+        parameters: parameters.clone(),
+        statements,
+        template: Box::new(template),
+    })
+}
+
+/// Converts an element of a `children` list. Collection `if` and `for`
+/// elements become structural nodes; other unmodeled elements are kept raw.
+fn widget_from_element(
+    element: &CollectionElement,
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> WidgetNode {
+    match element {
+        CollectionElement::Expression(expr) => widget_or_raw(expr, body, report),
+        CollectionElement::If {
+            condition,
+            then_element,
+            else_element,
+            span,
+        } => WidgetNode::conditional(
+            parse_expr_value(condition, condition.span, body, report),
+            widget_from_element(then_element, body, report),
+            else_element
+                .as_deref()
+                .map(|element| widget_from_element(element, body, report)),
+            Some(body.span(*span)),
+        ),
+        CollectionElement::For {
+            variable: Some(variable),
+            iterable: Some(iterable),
+            body: template,
+            span,
+            ..
+        } => WidgetNode::for_each(
+            parse_expr_value(iterable, iterable.span, body, report),
+            variable,
+            widget_from_element(template, body, report),
+            Some(body.span(*span)),
+        ),
+        CollectionElement::For { .. } => {
+            body.raw_node(element.span(), "C-style collection `for` element", report)
         }
+        CollectionElement::Spread { .. } => body.raw_node(element.span(), "spread element", report),
+        CollectionElement::MapEntry { .. } => body.raw_node(element.span(), "map entry", report),
     }
+}
 
-    let closing_rel = closing_rel?;
-    let provider_expr = after_prefix[..closing_rel].trim();
-    if provider_expr.is_empty() {
-        return None;
+/// Whether [widget_from_expr] accepts `expr`, checked up front so that
+/// ternaries choosing between non-widget values stay props.
+fn is_widget_expr(expr: &DartExpr) -> bool {
+    match &expr.kind {
+        DartExprKind::Call { callee, .. } => callee_path(callee).is_some(),
+        DartExprKind::Parenthesized(inner) => is_widget_expr(inner),
+        DartExprKind::Conditional {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            is_widget_expr(then_branch)
+                && (is_widget_expr(else_branch) || else_branch.kind == DartExprKind::Null)
+        }
+        _ => false,
     }
+}
 
-    let mut remainder = after_prefix[closing_rel + 1..].trim();
-    let mut path: Option<String> = None;
+/// Dotted name of a constructor or function callee such as `Image.network`.
+fn callee_path(expr: &DartExpr) -> Option<String> {
+    match &expr.kind {
+        DartExprKind::Identifier(name) => Some(name.clone()),
+        DartExprKind::PropertyAccess {
+            target,
+            name,
+            null_aware: false,
+        } => Some(format!("{}.{}", callee_path(target)?, name)),
+        _ => None,
+    }
+}
 
-    if remainder.starts_with('.') {
-        let mut idx = 1; // skip the dot
-        while idx < remainder.len() {
-            let ch = remainder[idx..].chars().next().unwrap();
-            if ch.is_alphanumeric() || ch == '_' || ch == '.' {
-                idx += ch.len_utf8();
-            } else {
-                break;
-            }
-        }
+fn parse_prop_value(
+    argument: &Argument,
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> PropValue {
+    parse_expr_value(&argument.value, argument.span, body, report)
+}
 
-        let candidate = &remainder[1..idx];
-        if candidate.is_empty()
-            || candidate.ends_with('.')
-            || candidate.split('.').any(|segment| {
-                segment.is_empty()
-                    || !segment.chars().enumerate().all(|(i, c)| {
-                        if i == 0 {
-                            c.is_alphabetic() || c == '_'
-                        } else {
-                            c.is_alphanumeric() || c == '_'
-                        }
-                    })
-            })
-        {
-            return None;
-        }
+/// Converts `expr` into a binding, literal or raw expression. `span` is the
+/// location recorded on the value, such as the whole named argument.
+fn parse_expr_value(
+    expr: &DartExpr,
+    span: Span,
+    body: &BuildBody<'_>,
+    report: &mut ParseReport,
+) -> PropValue {
+    if expr.kind == DartExprKind::Opaque {
+        body.report(
+            report,
+            DiagnosticSeverity::Warning,
+            "unparseable argument is kept as a raw expression".to_string(),
+            span,
+        );
+    }
+    let span = Some(body.span(span));
+    if let Some(binding) = parse_binding(expr, body.text, body.ref_name) {
+        return PropValue::Binding { binding, span };
+    }
+    if let Some(value) = parse_literal(expr) {
+        return PropValue::Literal { value, span };
+    }
+    PropValue::Expression {
+        expression: expr.span.text(body.text).to_string(),
+        span,
+    }
+}
 
-        path = Some(candidate.to_string());
-        remainder = remainder[idx..].trim();
+/// Recognizes `ref.watch(provider)`, `ref.read(provider)` and
+/// `ref.listen(provider, ...)`, optionally followed by a property path.
+fn parse_binding(expr: &DartExpr, source: &str, ref_name: &str) -> Option<BindingReference> {
+    let mut path = Vec::new();
+    let mut current = expr;
+    while let DartExprKind::PropertyAccess {
+        target,
+        name,
+        null_aware: false,
+    } = &current.kind
+    {
+        path.push(name.as_str());
+        current = target;
     }
 
-    if !remainder.is_empty() {
+    let DartExprKind::Call {
+        callee, arguments, ..
+    } = &current.kind
+    else {
+        return None;
+    };
+    let DartExprKind::PropertyAccess {
+        target,
+        name: method,
+        null_aware: false,
+    } = &callee.kind
+    else {
+        return None;
+    };
+    if !matches!(&target.kind, DartExprKind::Identifier(name) if name == ref_name) {
         return None;
     }
+    let access = ProviderAccess::from_method(method)?;
+    let provider = arguments
+        .first()
+        .filter(|argument| argument.name.is_none())?;
 
-    let reference = provider_expr.to_string();
-    let provider_id = extract_provider_identifier(provider_expr);
-
-    Some(PropValue::Binding {
-        binding: BindingReference {
-            target: BindingTarget::Provider,
-            reference,
-            provider_id,
-            path,
-            type_hint: None,
-        },
-    })
+    let mut binding = provider_binding(&provider.value, source, access)?;
+    if !path.is_empty() {
+        path.reverse();
+        binding.path = Some(path.join("."));
+    }
+    Some(binding)
 }
 
 fn extract_provider_identifier(reference: &str) -> Option<String> {
@@ -753,48 +1951,31 @@ fn extract_provider_identifier(reference: &str) -> Option<String> {
     }
 }
 
-fn parse_literal(raw: &str) -> Option<PropValue> {
-    let trimmed = raw.trim();
-    if trimmed.len() >= 2
-        && ((trimmed.starts_with('"') && trimmed.ends_with('"'))
-            || (trimmed.starts_with('\'') && trimmed.ends_with('\'')))
-    {
-        let inner = &trimmed[1..trimmed.len() - 1];
-        return Some(PropValue::Literal {
-            value: serde_json::Value::String(inner.to_string()),
-        });
-    }
-
-    if trimmed.eq("true") {
-        return Some(PropValue::Literal {
-            value: serde_json::Value::Bool(true),
-        });
-    }
-    if trimmed.eq("false") {
-        return Some(PropValue::Literal {
-            value: serde_json::Value::Bool(false),
-        });
-    }
-    if trimmed.eq("null") {
-        return Some(PropValue::Literal {
-            value: serde_json::Value::Null,
-        });
-    }
-
-    if let Ok(int_val) = trimmed.parse::<i64>() {
-        return Some(PropValue::Literal {
-            value: serde_json::Value::Number(int_val.into()),
-        });
-    }
-    if let Ok(float_val) = trimmed.parse::<f64>() {
-        if let Some(num) = serde_json::Number::from_f64(float_val) {
-            return Some(PropValue::Literal {
-                value: serde_json::Value::Number(num),
-            });
+fn parse_literal(expr: &DartExpr) -> Option<serde_json::Value> {
+    match &expr.kind {
+        DartExprKind::String(string) if !string.raw && !string.triple => {
+            Some(serde_json::Value::String(string.content.clone()))
         }
+        DartExprKind::Bool(value) => Some(serde_json::Value::Bool(*value)),
+        DartExprKind::Null => Some(serde_json::Value::Null),
+        DartExprKind::Number(lexeme) => parse_number(lexeme),
+        DartExprKind::Unary {
+            operator: "-",
+            operand,
+        } => match &operand.kind {
+            DartExprKind::Number(lexeme) => parse_number(&format!("-{lexeme}")),
+            _ => None,
+        },
+        _ => None,
     }
+}
 
-    None
+fn parse_number(lexeme: &str) -> Option<serde_json::Value> {
+    if let Ok(int_val) = lexeme.parse::<i64>() {
+        return Some(serde_json::Value::Number(int_val.into()));
+    }
+    let float_val = lexeme.parse::<f64>().ok()?;
+    serde_json::Number::from_f64(float_val).map(serde_json::Value::Number)
 }
 
 fn map_positional_prop(widget: &str, index: usize) -> Option<&'static str> {
@@ -815,40 +1996,68 @@ fn map_positional_prop(widget: &str, index: usize) -> Option<&'static str> {
     }
 }
 
-fn split_children(src: &str) -> Vec<&str> {
-    let mut segments = Vec::new();
-    let mut depth = 0_i32;
-    let mut start = 0;
-    let bytes = src.as_bytes();
-    for (idx, ch) in bytes.iter().enumerate() {
-        match ch {
-            b'(' | b'[' | b'{' => depth += 1,
-            b')' | b']' | b'}' => depth -= 1,
-            b',' if depth == 0 => {
-                segments.push(src[start..idx].trim());
-                start = idx + 1;
-            }
-            _ => {}
-        }
-    }
-    if start < bytes.len() {
-        segments.push(src[start..].trim());
+/// Parses Dart source and returns [ScreenGraph] entries for each stateless or
+/// stateful widget discovered. Only the top-level widget returned by `build`
+/// is considered; for stateful widgets the `State` members are captured in
+/// [ScreenGraph::state].
+pub fn build_graphs_from_source(source: &str) -> Vec<ScreenGraph> {
+    build_graphs_with_report(source, None).0
+}
+
+/// Like [build_graphs_from_source], but records `file` on the [SourceSpan] of
+/// every imported widget and prop.
+pub fn build_graphs_from_file_source(file: &str, source: &str) -> Vec<ScreenGraph> {
+    build_graphs_with_report(source, Some(file)).0
+}
+
+/// Parses Dart source into [ScreenGraph]s and reports everything that could
+/// not be modeled. `file`, when given, is recorded on every [SourceSpan].
+pub fn build_graphs_with_report(
+    source: &str,
+    file: Option<&str>,
+) -> (Vec<ScreenGraph>, ParseReport) {
+    let map = SourceMap::new(source, file);
+    let mut report = ParseReport::default();
+    let mut skipped = Vec::new();
+    let screens = collect_screens(source, &mut skipped);
+    for class in skipped {
+        report.skip_class(
+            class.name,
+            class.reason,
+            map.span(offset_within(source, class.name)),
+        );
     }
-    segments.into_iter().filter(|s| !s.is_empty()).collect()
-}
 
-/// Parses Dart source and returns [ScreenGraph] entries for each stateless
-/// widget discovered. Only the top-level widget returned by `build` is
-/// considered.
-pub fn build_graphs_from_source(source: &str) -> Vec<ScreenGraph> {
-    parse_stateless_screens(source)
+    let graphs = screens
         .into_iter()
-        .filter_map(|screen| parse_widget_tree(&screen.body).map(|root| (screen, root)))
-        .map(|(screen, root)| ScreenGraph {
-            id: screen.name,
-            root,
+        .filter_map(|screen| {
+            let body = BuildBody {
+                text: &screen.body,
+                offset: screen.body_offset,
+                map: &map,
+                ref_name: screen.consumer.as_ref().map_or("ref", |c| c.name.as_str()),
+                screen: Some(&screen.name),
+            };
+            let parsed = parse_widget_node(&body, &mut report);
+            if parsed.is_none() {
+                let span = map.span(screen.body_offset);
+                report.skip_class(&screen.name, "its `build` method returns nothing", span);
+            }
+            parsed.map(|(root, statements)| (screen, root, statements))
         })
-        .collect()
+        .map(|(screen, root, statements)| {
+            let mut graph = ScreenGraph {
+                id: screen.name,
+                root,
+                state: screen.state,
+                consumer: screen.consumer,
+                statements,
+            };
+            assign_node_ids(&mut graph);
+            graph
+        })
+        .collect();
+    (graphs, report)
 }
 
 #[cfg(test)]
@@ -862,13 +2071,13 @@ mod tests {
             .get(key)
             .unwrap_or_else(|| panic!("missing prop {key}"))
         {
-            PropValue::Literal { value } => match value {
+            PropValue::Literal { value, .. } => match value {
                 Value::String(s) => assert_eq!(s, expected),
                 Value::Bool(b) => assert_eq!(expected, if *b { "true" } else { "false" }),
                 Value::Number(n) => assert_eq!(n.to_string(), expected),
                 other => panic!("unexpected literal {:?}", other),
             },
-            PropValue::Expression { expression } => assert_eq!(expression, expected),
+            PropValue::Expression { expression, .. } => assert_eq!(expression, expected),
             other => panic!("unexpected prop variant {:?}", other),
         }
     }
@@ -879,7 +2088,7 @@ mod tests {
             .get(key)
             .unwrap_or_else(|| panic!("missing prop {key}"))
         {
-            PropValue::Binding { binding } => {
+            PropValue::Binding { binding, .. } => {
                 assert_eq!(binding.target, BindingTarget::Provider);
                 assert_eq!(binding.reference, provider);
                 assert_eq!(binding.provider_id.as_deref(), Some(provider));
@@ -976,8 +2185,82 @@ class HomeScreen extends StatelessWidget {
         let graph = &graphs[0];
         assert_eq!(graph.id, "HomeScreen");
         assert_eq!(graph.root.widget, "Scaffold");
+        // Scaffold's children are its `appBar` and `body` slots. The old
+        // scanner matched the first `children:` anywhere in the arguments and
+        // hoisted the nested Column's Text and ElevatedButton here instead.
         assert_eq!(graph.root.children.len(), 2);
-        assert_eq!(graph.root.children[0].widget, "Text");
+        assert_eq!(graph.root.children[0].widget, "AppBar");
+        assert_eq!(graph.root.children[1].widget, "Column");
+        assert_eq!(graph.root.children[1].children.len(), 2);
+    }
+
+    #[test]
+    fn parse_widget_tree_ignores_delimiters_in_strings_and_comments() {
+        let source = r#"Column(
+      // Closing ) and ] in comments must not end the call.
+      children: [
+        Text('Tap (here)]'),
+        /* nested /* block ) */ comment */
+        Text(r'C:\path\(raw)'),
+        Text("Count: ${items.where((i) => i.done).length}"),
+        SizedBox<Map<String, List<int>>>(width: 8),
+      ],
+    )"#;
+
+        let tree = parse_widget_tree(source).expect("tree");
+        assert_eq!(tree.widget, "Column");
+        let widgets: Vec<_> = tree.children.iter().map(|c| c.widget.as_str()).collect();
+        assert_eq!(widgets, ["Text", "Text", "Text", "SizedBox"]);
+        assert_eq!(
+            tree.children[0].props.get("data"),
+            Some(&PropValue::Literal {
+                value: serde_json::json!("Tap (here)]"),
+                span: None,
+            })
+        );
+        assert_eq!(
+            tree.children[1].props.get("data"),
+            Some(&PropValue::Expression {
+                expression: r"r'C:\path\(raw)'".to_string(),
+                span: None,
+            })
+        );
+        assert_eq!(
+            tree.children[3].props.get("width"),
+            Some(&PropValue::Literal {
+                value: serde_json::json!(8),
+                span: None,
+            })
+        );
+    }
+
+    #[test]
+    fn build_graphs_records_source_spans() {
+        let source = "// é\nclass Home extends StatelessWidget {\n  @override\n  Widget build(BuildContext context) {\n    return Column(\n      children: [Text('Hi', style: bodyStyle)],\n    );\n  }\n}\n";
+
+        let graph = &build_graphs_from_file_source("lib/home.dart", source)[0];
+        let root = graph.root.span.as_ref().expect("root span");
+        assert_eq!(root.file.as_deref(), Some("lib/home.dart"));
+        assert_eq!((root.line, root.column), (5, 12));
+        assert_eq!(&source[root.offset..root.offset + 6], "Column");
+
+        let text = &graph.root.children[0];
+        let span = text.span.as_ref().expect("child span");
+        assert_eq!((span.line, span.column), (6, 18));
+        let style = text.props["style"].span().expect("prop span");
+        assert_eq!((style.line, style.column), (6, 29));
+        assert_eq!(style.to_string(), "lib/home.dart:6:29");
+
+        // Columns count characters, so the two-byte `é` advances by one.
+        let first_line = SourceMap::new(source, None).span(5);
+        assert_eq!((first_line.line, first_line.column), (1, 5));
+        assert!(build_graphs_from_source(source)[0]
+            .root
+            .span
+            .as_ref()
+            .unwrap()
+            .file
+            .is_none());
     }
 
     #[test]
@@ -1113,7 +2396,7 @@ class HomeScreen extends StatelessWidget {
         let node = parse_widget_tree(source).expect("button node");
         assert_eq!(node.widget, "ElevatedButton");
         match node.props.get("onPressed").expect("onPressed") {
-            PropValue::Expression { expression } => assert_eq!(expression, "() {}"),
+            PropValue::Expression { expression, .. } => assert_eq!(expression, "() {}"),
             other => panic!("unexpected prop: {:?}", other),
         }
         assert_eq!(node.children.len(), 1);
@@ -1328,4 +2611,625 @@ class GreetingScreen extends StatelessWidget {
         let regenerated = build_graphs_from_source(&dart);
         assert_eq!(graphs, regenerated);
     }
+
+    const COUNTER_SOURCE: &str = r#"
+import 'package:flutter/material.dart';
+
+class CounterScreen extends StatefulWidget {
+  const CounterScreen({super.key});
+
+  @override
+  State<CounterScreen> createState() => _CounterScreenState();
+}
+
+class _CounterScreenState extends State<CounterScreen> {
+  // Current tap count.
+  int _count = 0;
+  late final TextEditingController _controller;
+  final Map<String, List<int>> _history = {};
+
+  @override
+  void initState() {
+    super.initState();
+    _controller = TextEditingController(text: '{not a brace}');
+  }
+
+  @override
+  void dispose() {
+    _controller.dispose();
+    super.dispose();
+  }
+
+  void _increment() {
+    setState(() {
+      _count++;
+    });
+  }
+
+  int get doubled => _count * 2;
+
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        Text('Count: $_count'),
+        ElevatedButton(
+          onPressed: () => setState(() => _count = 0),
+          child: const Text('Reset'),
+        ),
+      ],
+    );
+  }
+}
+"#;
+
+    #[test]
+    fn parse_stateful_widget_pairs_state_class() {
+        let graphs = build_graphs_from_source(COUNTER_SOURCE);
+        assert_eq!(graphs.len(), 1);
+        let graph = &graphs[0];
+        assert_eq!(graph.id, "CounterScreen");
+        assert_eq!(graph.root.widget, "Column");
+        assert_eq!(graph.root.children.len(), 2);
+
+        let state = graph.state.as_ref().expect("stateful screen state");
+        assert_eq!(state.class_name, "_CounterScreenState");
+        assert_eq!(
+            state.fields,
+            vec![
+                StateField {
+                    name: "_count".into(),
+                    ty: Some("int".into()),
+                    modifiers: vec![],
+                    initializer: Some("0".into()),
+                },
+                StateField {
+                    name: "_controller".into(),
+                    ty: Some("TextEditingController".into()),
+                    modifiers: vec!["late".into(), "final".into()],
+                    initializer: None,
+                },
+                StateField {
+                    name: "_history".into(),
+                    ty: Some("Map<String, List<int>>".into()),
+                    modifiers: vec!["final".into()],
+                    initializer: Some("{}".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            state.init_state.as_deref(),
+            Some("super.initState();\n_controller = TextEditingController(text: '{not a brace}');")
+        );
+        assert_eq!(
+            state.dispose.as_deref(),
+            Some("_controller.dispose();\nsuper.dispose();")
+        );
+        let method_names: Vec<_> = state.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(method_names, vec!["_increment", "doubled"]);
+        assert_eq!(
+            state.set_state_calls,
+            vec![
+                SetStateCall {
+                    method: "_increment".into(),
+                    callback: "() {\n    _count++;\n  }".into(),
+                },
+                SetStateCall {
+                    method: "build".into(),
+                    callback: "() => _count = 0".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_stateful_widget_without_state_class_is_skipped() {
+        let source = r#"
+class Orphan extends StatefulWidget {
+  @override
+  State<Orphan> createState() => _OrphanState();
+}
+"#;
+        assert!(build_graphs_from_source(source).is_empty());
+    }
+
+    #[test]
+    fn parse_stateless_screens_excludes_stateful_widgets() {
+        let screens = parse_stateless_screens(COUNTER_SOURCE);
+        assert!(screens.is_empty());
+        assert_eq!(parse_screens(COUNTER_SOURCE).len(), 1);
+    }
+
+    #[test]
+    fn round_trip_preserves_stateful_widget_state() {
+        let graphs = build_graphs_from_source(COUNTER_SOURCE);
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("class CounterScreen extends StatefulWidget"));
+        assert!(dart.contains("class _CounterScreenState extends State<CounterScreen>"));
+        let regenerated = build_graphs_from_source(&dart);
+        assert_eq!(graphs, regenerated);
+    }
+
+    #[test]
+    fn round_trip_preserves_stateful_widget_configuration() {
+        let source = r#"
+class GreetingPage extends StatefulWidget {
+  const GreetingPage({super.key, required this.title, this.subtitle});
+
+  final String title;
+  final String? subtitle;
+
+  @override
+  State<GreetingPage> createState() => _GreetingPageState();
+}
+
+class _GreetingPageState extends State<GreetingPage> {
+  @override
+  Widget build(BuildContext context) {
+    return Text(widget.title);
+  }
+}
+"#;
+        let graphs = build_graphs_from_source(source);
+        let state = graphs[0].state.as_ref().expect("stateful screen state");
+        let field_names: Vec<_> = state
+            .widget_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(field_names, vec!["title", "subtitle"]);
+        assert_eq!(
+            state.widget_constructors,
+            vec!["const GreetingPage({super.key, required this.title, this.subtitle});"]
+        );
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("  final String title;\n  final String? subtitle;\n"));
+        assert!(dart.contains("required this.title"));
+        assert!(!dart.contains("const GreetingPage({ super.key });"));
+        assert!(dart.contains("widget.title"));
+        let regenerated = build_graphs_from_source(&dart);
+        assert_eq!(graphs, regenerated);
+    }
+
+    #[test]
+    fn build_statements_before_return_are_kept_on_export() {
+        let source = r#"
+class Profile extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    final name = user.displayName;
+    if (name.isEmpty) {
+      return const Text('Anonymous');
+    }
+    return Text(name);
+  }
+}
+"#;
+        let (graphs, report) = build_graphs_with_report(source, None);
+        assert!(report.is_empty(), "{report:?}");
+        assert_eq!(
+            graphs[0].statements.as_deref(),
+            Some("final name = user.displayName;\nif (name.isEmpty) {\n  return const Text('Anonymous');\n}")
+        );
+        assert_eq!(graphs[0].root.widget, "Text");
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains(
+            "    final name = user.displayName;\n    if (name.isEmpty) {\n      return const Text('Anonymous');\n    }\n    return Text("
+        ));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+    }
+
+    #[test]
+    fn statements_after_final_return_are_reported() {
+        let source = r#"
+class Dead extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    return Text('a');
+    print('unreachable');
+  }
+}
+"#;
+        let (graphs, report) = build_graphs_with_report(source, None);
+        assert_eq!(graphs.len(), 1);
+        let warnings: Vec<_> = report.warnings().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            warnings,
+            vec!["statements after the final `return` are dropped"]
+        );
+    }
+
+    fn provider_access(
+        reference: &str,
+        access: ProviderAccess,
+        notifier: bool,
+    ) -> BindingReference {
+        BindingReference {
+            target: BindingTarget::Provider,
+            reference: reference.into(),
+            provider_id: Some(reference.into()),
+            path: None,
+            type_hint: None,
+            access,
+            notifier,
+        }
+    }
+
+    #[test]
+    fn parse_consumer_widget_tracks_ref_parameter() {
+        let source = r#"
+class ProfileScreen extends ConsumerWidget {
+  const ProfileScreen({super.key});
+
+  @override
+  Widget build(BuildContext context, WidgetRef widgetRef) {
+    final count = widgetRef.watch(counterProvider);
+    widgetRef.listen(authProvider, (previous, next) {
+      debugPrint('ref.read(ignoredProvider)');
+    });
+    return Column(
+      children: [
+        Text(widgetRef.watch(userProvider).name),
+        Text(widgetRef.read(counterProvider.notifier).label),
+        ElevatedButton(
+          onPressed: () => widgetRef.read(counterProvider.notifier).increment(),
+          child: const Text('Add'),
+        ),
+      ],
+    );
+  }
+}
+"#;
+        let graphs = build_graphs_from_source(source);
+        assert_eq!(graphs.len(), 1);
+        let graph = &graphs[0];
+        assert_eq!(graph.id, "ProfileScreen");
+        assert!(graph.state.is_none());
+
+        let consumer = graph.consumer.as_ref().expect("consumer ref");
+        assert_eq!(consumer.name, "widgetRef");
+        assert_eq!(
+            consumer.bindings,
+            vec![
+                provider_access("counterProvider", ProviderAccess::Watch, false),
+                provider_access("authProvider", ProviderAccess::Listen, false),
+                provider_access("userProvider", ProviderAccess::Watch, false),
+                provider_access("counterProvider", ProviderAccess::Read, true),
+            ]
+        );
+
+        assert_eq!(graph.root.widget, "Column");
+        assert_prop_binding(
+            &graph.root.children[0],
+            "data",
+            "userProvider",
+            Some("name"),
+        );
+        match graph.root.children[1].props.get("data") {
+            Some(PropValue::Binding { binding, .. }) => {
+                assert_eq!(binding.reference, "counterProvider");
+                assert_eq!(binding.access, ProviderAccess::Read);
+                assert!(binding.notifier);
+                assert_eq!(binding.path.as_deref(), Some("label"));
+            }
+            other => panic!("expected binding, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_consumer_stateful_widget_pairs_consumer_state() {
+        let source = r#"
+class CartScreen extends ConsumerStatefulWidget {
+  const CartScreen({super.key});
+
+  @override
+  ConsumerState<CartScreen> createState() => _CartScreenState();
+}
+
+class _CartScreenState extends ConsumerState<CartScreen> {
+  @override
+  void initState() {
+    super.initState();
+    ref.read(cartProvider.notifier).load();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    return Text(ref.watch(cartProvider).summary);
+  }
+}
+"#;
+        let graphs = build_graphs_from_source(source);
+        assert_eq!(graphs.len(), 1);
+        let graph = &graphs[0];
+        let state = graph.state.as_ref().expect("consumer state");
+        assert_eq!(state.class_name, "_CartScreenState");
+        let consumer = graph.consumer.as_ref().expect("consumer ref");
+        assert_eq!(consumer.name, "ref");
+        assert_eq!(
+            consumer.bindings,
+            vec![
+                provider_access("cartProvider", ProviderAccess::Read, true),
+                provider_access("cartProvider", ProviderAccess::Watch, false),
+            ]
+        );
+        assert_prop_binding(&graph.root, "data", "cartProvider", Some("summary"));
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("import 'package:flutter_riverpod/flutter_riverpod.dart';"));
+        assert!(dart.contains("class CartScreen extends ConsumerStatefulWidget"));
+        assert!(dart.contains("class _CartScreenState extends ConsumerState<CartScreen>"));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+    }
+
+    #[test]
+    fn round_trip_preserves_consumer_widget_ref_name() {
+        let source = r#"
+class BalanceScreen extends ConsumerWidget {
+  const BalanceScreen({super.key});
+
+  @override
+  Widget build(BuildContext context, WidgetRef watcher) {
+    return Center(
+      child: Text(watcher.watch(balanceProvider).formatted),
+    );
+  }
+}
+"#;
+        let graphs = build_graphs_from_source(source);
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("class BalanceScreen extends ConsumerWidget"));
+        assert!(dart.contains("Widget build(BuildContext context, WidgetRef watcher)"));
+        assert!(dart.contains("watcher.watch(balanceProvider).formatted"));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+    }
+
+    #[test]
+    fn unmodeled_children_are_kept_as_raw_dart_and_reported() {
+        let source = r#"
+class FeedScreen extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        header,
+        for (var i = 0; i < count; i++)
+          ListTile(
+            title: Text('Row $i'),
+          ),
+        ...footer,
+        Text('Done'),
+      ],
+    );
+  }
+}
+"#;
+        let (graphs, report) = build_graphs_with_report(source, Some("lib/feed.dart"));
+        let children = &graphs[0].root.children;
+        let widgets: Vec<_> = children.iter().map(|c| c.widget.as_str()).collect();
+        assert_eq!(
+            widgets,
+            [RAW_DART_WIDGET, RAW_DART_WIDGET, RAW_DART_WIDGET, "Text"]
+        );
+        assert_eq!(children[0].raw.as_deref(), Some("header"));
+        assert_eq!(
+            children[1].raw.as_deref(),
+            Some("for (var i = 0; i < count; i++)\n  ListTile(\n    title: Text('Row $i'),\n  )")
+        );
+
+        assert!(!report.has_errors());
+        let warnings: Vec<_> = report.warnings().map(ToString::to_string).collect();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(
+            warnings[1],
+            "lib/feed.dart:8:9: warning: C-style collection `for` element is kept as raw Dart"
+        );
+        assert_eq!(report.for_screen("FeedScreen").count(), 3);
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("        for (var i = 0; i < count; i++)\n          ListTile(\n"));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+    }
+
+    #[test]
+    fn conditional_and_repeated_children_become_structural_nodes() {
+        let source = r#"
+class FeedScreen extends ConsumerWidget {
+  @override
+  Widget build(BuildContext context, WidgetRef ref) {
+    return Scaffold(
+      body: Column(
+        children: [
+          if (ref.watch(loadingProvider)) CircularProgressIndicator(),
+          if (items.isEmpty) Text('Nothing here') else Text('Items'),
+          for (final item in items) ItemTile(item, color: item.done ? Colors.green : Colors.grey),
+        ],
+      ),
+      floatingActionButton: editing ? SaveButton() : null,
+    );
+  }
+}
+"#;
+        let (graphs, report) = build_graphs_with_report(source, None);
+        assert!(report.is_empty(), "{:?}", report.diagnostics);
+        let root = &graphs[0].root;
+        let column = &root.children[0];
+
+        let loading = &column.children[0];
+        assert_eq!(loading.widget, CONDITIONAL_WIDGET);
+        assert!(matches!(
+            loading.condition(),
+            Some(PropValue::Binding { binding, .. })
+                if binding.provider_id.as_deref() == Some("loadingProvider")
+        ));
+        assert_eq!(loading.children.len(), 1);
+        assert_eq!(loading.children[0].widget, "CircularProgressIndicator");
+
+        let empty = &column.children[1];
+        assert_eq!(empty.widget, CONDITIONAL_WIDGET);
+        assert!(matches!(
+            empty.condition(),
+            Some(PropValue::Expression { expression, .. }) if expression == "items.isEmpty"
+        ));
+        assert_eq!(empty.children.len(), 2);
+
+        let repeated = &column.children[2];
+        assert_eq!(repeated.widget, FOR_EACH_WIDGET);
+        assert_eq!(repeated.item_name(), "item");
+        assert!(matches!(
+            repeated.iterable(),
+            Some(PropValue::Expression { expression, .. }) if expression == "items"
+        ));
+        let tile = &repeated.children[0];
+        assert_eq!(tile.widget, "ItemTile");
+        assert!(matches!(
+            tile.props.get("color"),
+            Some(PropValue::Expression { expression, .. })
+                if expression == "item.done ? Colors.green : Colors.grey"
+        ));
+
+        let fab = &root.children[1];
+        assert_eq!(fab.widget, CONDITIONAL_WIDGET);
+        assert_eq!(fab.children.len(), 1);
+        assert_eq!(fab.children[0].widget, "SaveButton");
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("if (ref.watch(loadingProvider)) CircularProgressIndicator("));
+        assert!(dart.contains("if (items.isEmpty) Text("));
+        assert!(dart.contains(") else Text("));
+        assert!(dart.contains("for (final item in items) ItemTile("));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+
+        let (root, _) = parse_widget_tree_with_report("return wide ? Row() : Column();");
+        let root = root.expect("conditional root");
+        assert_eq!(root.widget, CONDITIONAL_WIDGET);
+        let dart = generate_stateless_widget(&ScreenGraph {
+            id: "Layout".to_string(),
+            root,
+            state: None,
+            consumer: None,
+            statements: None,
+        });
+        assert!(
+            dart.contains("return wide ? Row(\n    ) : Column(\n    );"),
+            "{dart}"
+        );
+    }
+
+    #[test]
+    fn builder_callbacks_are_parsed_as_template_subtrees() {
+        let source = r#"
+class OrdersScreen extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        ListView.builder(
+          itemCount: orders.length,
+          itemBuilder: (context, index) => OrderTile(orders[index]),
+        ),
+        FutureBuilder<User>(
+          future: loadUser(),
+          builder: (BuildContext context, AsyncSnapshot<User> snapshot) {
+            if (!snapshot.hasData) return CircularProgressIndicator();
+            final user = snapshot.data!;
+            return Text(user.name);
+          },
+        ),
+        LayoutBuilder(
+          builder: (context, constraints) =>
+              constraints.maxWidth > 600 ? WideLayout() : NarrowLayout(),
+        ),
+        ListView.separated(
+          itemCount: 3,
+          itemBuilder: buildItem,
+          separatorBuilder: (_, __) => Divider(),
+        ),
+      ],
+    );
+  }
+}
+"#;
+        let (graphs, report) = build_graphs_with_report(source, None);
+        assert!(report.is_empty(), "{:?}", report.diagnostics);
+        let children = &graphs[0].root.children;
+
+        let list = &children[0];
+        assert_eq!(list.widget, "ListView.builder");
+        assert_eq!(list.builders.len(), 1);
+        let item = &list.builders[0];
+        assert_eq!(item.argument, "itemBuilder");
+        assert_eq!(item.kind, BuilderKind::Indexed);
+        assert_eq!(item.parameters, ["context", "index"]);
+        assert_eq!(item.template.widget, "OrderTile");
+        assert!(matches!(
+            list.builder_source(),
+            Some(PropValue::Expression { expression, .. }) if expression == "orders.length"
+        ));
+
+        let future = &children[1];
+        let builder = &future.builders[0];
+        assert_eq!(builder.kind, BuilderKind::Future);
+        assert_eq!(builder.parameters, ["context", "snapshot"]);
+        assert_eq!(
+            builder.statements.as_deref(),
+            Some("if (!snapshot.hasData) return CircularProgressIndicator();\nfinal user = snapshot.data!;")
+        );
+        assert_eq!(builder.template.widget, "Text");
+        assert!(future.builder_source().is_some());
+
+        let layout = &children[2].builders[0];
+        assert_eq!(layout.kind, BuilderKind::Layout);
+        assert_eq!(layout.template.widget, CONDITIONAL_WIDGET);
+
+        // Callbacks passed by name stay props.
+        let separated = &children[3];
+        assert_eq!(separated.builders.len(), 1);
+        assert_eq!(separated.builders[0].argument, "separatorBuilder");
+        assert!(matches!(
+            separated.props.get("itemBuilder"),
+            Some(PropValue::Expression { expression, .. }) if expression == "buildItem"
+        ));
+
+        let dart = generate_dart_module(&graphs);
+        assert!(dart.contains("itemBuilder: (context, index) => OrderTile("));
+        assert!(dart.contains(
+            "builder: (context, snapshot) {\n            if (!snapshot.hasData) return CircularProgressIndicator();\n"
+        ));
+        assert_eq!(build_graphs_from_source(&dart), graphs);
+
+        let mut edited = graphs[0].clone();
+        edited.root.children[0].builders[0].template.widget = "OrderCard".to_string();
+        let merged = merge_screen_graphs(&graphs[0], &edited, &graphs[0]);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.screen.root.children[0].builders[0].template.widget,
+            "OrderCard"
+        );
+
+        let document =
+            SchemaWriter::build_document(SchemaProject::new("p", "P"), graphs, Vec::new(), None);
+        validate_graph_schema(&document.to_string_pretty().unwrap()).expect("schema");
+    }
+
+    #[test]
+    fn unparseable_build_body_is_kept_as_raw_dart() {
+        let (root, report) = parse_widget_tree_with_report("return Text('unterminated);");
+        let root = root.expect("raw root");
+        assert_eq!(root.widget, RAW_DART_WIDGET);
+        assert_eq!(root.raw.as_deref(), Some("return Text('unterminated);"));
+        let errors: Vec<_> = report.errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span.as_ref().map(|span| span.column), Some(1));
+
+        let (root, report) =
+            parse_widget_tree_with_report("return loading ? spinner : Text('Hi');");
+        assert_eq!(
+            root.and_then(|root| root.raw),
+            Some("loading ? spinner : Text('Hi')".to_string())
+        );
+        assert_eq!(report.warnings().count(), 1);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::expr::{eval_expression, parse_expression, EvalContext};
+use crate::expr_types::{check_flow, TypeDiagnostic};
 use crate::logic_types::{
     BuiltinLogicNodeKind, EvalResult, EvalTraceEntry, Flow, LogicEdge, LogicGraph, LogicNode,
     LogicNodeId,
@@ -38,6 +39,19 @@ pub enum LogicError {
     MaxStepsExceeded(usize),
     #[error("expression error in node '{node_id}': {message}")]
     ExpressionError { node_id: String, message: String },
+    #[error("type errors in flow '{flow_id}': {}", join_diagnostics(.diagnostics))]
+    TypeCheck {
+        flow_id: String,
+        diagnostics: Vec<TypeDiagnostic>,
+    },
+}
+
+fn join_diagnostics(diagnostics: &[TypeDiagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 struct Activation {
@@ -74,6 +88,14 @@ pub fn simulate_flow(
         .find(|flow| flow.id == flow_id)
         .ok_or_else(|| LogicError::FlowNotFound(flow_id.to_string()))?;
 
+    let type_errors = check_flow(graph, flow);
+    if !type_errors.is_empty() {
+        return Err(LogicError::TypeCheck {
+            flow_id: flow_id.to_string(),
+            diagnostics: type_errors,
+        });
+    }
+
     let entry_nodes = resolve_entry_nodes(flow, entry)?;
 
     let mut queue: VecDeque<Activation> = entry_nodes
@@ -366,7 +388,7 @@ fn truthy(value: &Value) -> bool {
     match value {
         Value::Bool(b) => *b,
         Value::Null => false,
-        Value::Number(n) => n.as_f64().map_or(false, |v| v != 0.0),
+        Value::Number(n) => n.as_f64().is_some_and(|v| v != 0.0),
         Value::String(s) => !s.is_empty(),
         Value::Array(arr) => !arr.is_empty(),
         Value::Object(obj) => !obj.is_empty(),
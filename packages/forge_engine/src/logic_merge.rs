@@ -0,0 +1,506 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::merge_engine::{merge_keyed, merge_optional, merge_scalar, record_conflict};
+use crate::{Flow, LogicEdge, LogicGraph, LogicNode, MergeConflict};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogicMergeOutcome {
+    pub logic: LogicGraph,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Three-way merge of logic graphs. Flows and nodes are matched by id and
+/// edges by their endpoints, so edits to different nodes or wires merge
+/// cleanly. Conflicts use the paths `logic.flows[<flow>].nodes[<node>]` and
+/// `logic.flows[<flow>].edges[<from>-><to>]`, where an endpoint is
+/// `node.port` or just `node` without a port. Like [crate::merge_screen_graphs],
+/// a conflict keeps the right side's value; [crate::merge_logic_graphs_with]
+/// settles it another way.
+pub fn merge_logic_graphs(
+    base: &LogicGraph,
+    left: &LogicGraph,
+    right: &LogicGraph,
+) -> LogicMergeOutcome {
+    let mut conflicts = Vec::new();
+    let flows = merge_keyed(
+        "logic.flows",
+        &base.flows,
+        &left.flows,
+        &right.flows,
+        |flow| flow.id.clone(),
+        merge_flow,
+        &mut conflicts,
+    );
+    let metadata = merge_optional(
+        "logic.metadata",
+        base.metadata.as_ref(),
+        left.metadata.as_ref(),
+        right.metadata.as_ref(),
+        None,
+        &mut conflicts,
+    );
+
+    LogicMergeOutcome {
+        logic: LogicGraph { flows, metadata },
+        conflicts,
+    }
+}
+
+fn merge_flow(
+    path: &str,
+    base: &Flow,
+    left: &Flow,
+    right: &Flow,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Flow {
+    let nodes_path = format!("{path}.nodes");
+    let mut nodes = merge_keyed(
+        &nodes_path,
+        &base.nodes,
+        &left.nodes,
+        &right.nodes,
+        |node| node.id.clone(),
+        merge_logic_node,
+        conflicts,
+    );
+    let mut edges = merge_edges(&format!("{path}.edges"), base, left, right, conflicts);
+
+    // A node deleted on one side while the other side wired a new edge to it
+    // is restored, so the merged flow never holds a dangling edge the other
+    // side meant to keep.
+    for edge in edges.iter().filter(|edge| !base.edges.contains(edge)) {
+        for endpoint in [&edge.from_node, &edge.to_node] {
+            if nodes.iter().any(|node| &node.id == endpoint) {
+                continue;
+            }
+            let (base_node, left_node, right_node) = (
+                find_node(base, endpoint),
+                find_node(left, endpoint),
+                find_node(right, endpoint),
+            );
+            let Some(kept) = right_node.or(left_node) else {
+                continue;
+            };
+            record_conflict(
+                &format!("{nodes_path}[{endpoint}]"),
+                base_node,
+                left_node,
+                right_node,
+                None,
+                conflicts,
+            );
+            nodes.push(kept.clone());
+        }
+    }
+
+    let exists = |id: &String| nodes.iter().any(|node| &node.id == id);
+    edges.retain(|edge| exists(&edge.from_node) && exists(&edge.to_node));
+    let entry_nodes = merge_set(&base.entry_nodes, &left.entry_nodes, &right.entry_nodes)
+        .into_iter()
+        .filter(exists)
+        .collect();
+
+    Flow {
+        id: right.id.clone(),
+        name: merge_optional(
+            &format!("{path}.name"),
+            base.name.as_ref(),
+            left.name.as_ref(),
+            right.name.as_ref(),
+            None,
+            conflicts,
+        ),
+        nodes,
+        edges,
+        entry_nodes,
+        metadata: merge_optional(
+            &format!("{path}.metadata"),
+            base.metadata.as_ref(),
+            left.metadata.as_ref(),
+            right.metadata.as_ref(),
+            None,
+            conflicts,
+        ),
+    }
+}
+
+fn merge_logic_node(
+    path: &str,
+    base: &LogicNode,
+    left: &LogicNode,
+    right: &LogicNode,
+    conflicts: &mut Vec<MergeConflict>,
+) -> LogicNode {
+    LogicNode {
+        id: right.id.clone(),
+        name: merge_optional(
+            &format!("{path}.name"),
+            base.name.as_ref(),
+            left.name.as_ref(),
+            right.name.as_ref(),
+            None,
+            conflicts,
+        ),
+        kind: merge_optional(
+            &format!("{path}.kind"),
+            base.kind.as_ref(),
+            left.kind.as_ref(),
+            right.kind.as_ref(),
+            None,
+            conflicts,
+        ),
+        custom_kind: merge_optional(
+            &format!("{path}.custom_kind"),
+            base.custom_kind.as_ref(),
+            left.custom_kind.as_ref(),
+            right.custom_kind.as_ref(),
+            None,
+            conflicts,
+        ),
+        props: merge_json(
+            &format!("{path}.props"),
+            &base.props,
+            &left.props,
+            &right.props,
+            conflicts,
+        ),
+        inputs: merge_scalar(
+            &format!("{path}.inputs"),
+            &base.inputs,
+            &left.inputs,
+            &right.inputs,
+            None,
+            conflicts,
+        ),
+        outputs: merge_scalar(
+            &format!("{path}.outputs"),
+            &base.outputs,
+            &left.outputs,
+            &right.outputs,
+            None,
+            conflicts,
+        ),
+        metadata: merge_optional(
+            &format!("{path}.metadata"),
+            base.metadata.as_ref(),
+            left.metadata.as_ref(),
+            right.metadata.as_ref(),
+            None,
+            conflicts,
+        ),
+    }
+}
+
+/// Merges edges keyed by endpoints. An edge both sides rewired away from the
+/// same base edge, to different new endpoints, conflicts; the left rewiring
+/// is dropped in favour of the right.
+fn merge_edges(
+    path: &str,
+    base: &Flow,
+    left: &Flow,
+    right: &Flow,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<LogicEdge> {
+    let mut merged = merge_keyed(
+        path,
+        &base.edges,
+        &left.edges,
+        &right.edges,
+        edge_key,
+        |path, base, left, right, conflicts| LogicEdge {
+            metadata: merge_optional(
+                &format!("{path}.metadata"),
+                base.metadata.as_ref(),
+                left.metadata.as_ref(),
+                right.metadata.as_ref(),
+                None,
+                conflicts,
+            ),
+            ..right.clone()
+        },
+        conflicts,
+    );
+
+    let has = |edges: &[LogicEdge], key: &str| edges.iter().any(|edge| edge_key(edge) == key);
+    let replacement = |side: &Flow, removed: &LogicEdge| -> Option<LogicEdge> {
+        side.edges
+            .iter()
+            .find(|edge| !has(&base.edges, &edge_key(edge)) && shares_endpoint(edge, removed))
+            .cloned()
+    };
+    for removed in &base.edges {
+        let key = edge_key(removed);
+        if has(&left.edges, &key) || has(&right.edges, &key) {
+            continue;
+        }
+        let (Some(left_edge), Some(right_edge)) =
+            (replacement(left, removed), replacement(right, removed))
+        else {
+            continue;
+        };
+        let left_key = edge_key(&left_edge);
+        if left_key == edge_key(&right_edge) || has(&right.edges, &left_key) {
+            continue;
+        }
+        record_conflict(
+            &format!("{path}[{key}]"),
+            Some(removed),
+            Some(&left_edge),
+            Some(&right_edge),
+            None,
+            conflicts,
+        );
+        merged.retain(|edge| edge_key(edge) != left_key);
+    }
+    merged
+}
+
+fn find_node<'a>(flow: &'a Flow, id: &str) -> Option<&'a LogicNode> {
+    flow.nodes.iter().find(|node| node.id == id)
+}
+
+/// `from->to`, each endpoint written `node.port` or `node`.
+pub(crate) fn edge_key(edge: &LogicEdge) -> String {
+    let endpoint = |node: &str, port: &Option<String>| match port {
+        Some(port) => format!("{node}.{port}"),
+        None => node.to_string(),
+    };
+    format!(
+        "{}->{}",
+        endpoint(&edge.from_node, &edge.from_port),
+        endpoint(&edge.to_node, &edge.to_port)
+    )
+}
+
+fn shares_endpoint(a: &LogicEdge, b: &LogicEdge) -> bool {
+    (a.from_node == b.from_node && a.from_port == b.from_port)
+        || (a.to_node == b.to_node && a.to_port == b.to_port)
+}
+
+/// Merges JSON props key by key when every version is an object, and as a
+/// single value otherwise.
+fn merge_json(
+    path: &str,
+    base: &Value,
+    left: &Value,
+    right: &Value,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Value {
+    let (Value::Object(base_map), Value::Object(left_map), Value::Object(right_map)) =
+        (base, left, right)
+    else {
+        return merge_scalar(path, base, left, right, None, conflicts);
+    };
+
+    let keys: BTreeSet<&String> = base_map
+        .keys()
+        .chain(left_map.keys())
+        .chain(right_map.keys())
+        .collect();
+    let mut merged = Map::new();
+    for key in keys {
+        if let Some(value) = merge_optional(
+            &format!("{path}.{key}"),
+            base_map.get(key),
+            left_map.get(key),
+            right_map.get(key),
+            None,
+            conflicts,
+        ) {
+            merged.insert(key.clone(), value);
+        }
+    }
+    Value::Object(merged)
+}
+
+/// Entries both sides kept or either side added, in left order.
+fn merge_set(base: &[String], left: &[String], right: &[String]) -> Vec<String> {
+    let mut merged: Vec<String> = left
+        .iter()
+        .filter(|id| right.contains(id) || !base.contains(id))
+        .cloned()
+        .collect();
+    for id in right {
+        if !base.contains(id) && !merged.contains(id) {
+            merged.push(id.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuiltinLogicNodeKind;
+    use serde_json::json;
+
+    fn node(id: &str, props: Value) -> LogicNode {
+        LogicNode {
+            id: id.to_string(),
+            name: None,
+            kind: Some(BuiltinLogicNodeKind::Return),
+            custom_kind: None,
+            props,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    fn edge(from: &str, to: &str) -> LogicEdge {
+        LogicEdge {
+            from_node: from.to_string(),
+            from_port: Some("then".to_string()),
+            to_node: to.to_string(),
+            to_port: None,
+            metadata: None,
+        }
+    }
+
+    fn graph(nodes: Vec<LogicNode>, edges: Vec<LogicEdge>) -> LogicGraph {
+        LogicGraph {
+            flows: vec![Flow {
+                id: "main".to_string(),
+                name: None,
+                entry_nodes: vec![nodes[0].id.clone()],
+                nodes,
+                edges,
+                metadata: None,
+            }],
+            metadata: None,
+        }
+    }
+
+    fn base() -> LogicGraph {
+        graph(
+            vec![
+                node("start", json!({})),
+                node("a", json!({ "value": 1, "label": "a" })),
+                node("b", json!({ "value": 2 })),
+            ],
+            vec![edge("start", "a"), edge("a", "b")],
+        )
+    }
+
+    #[test]
+    fn disjoint_prop_edits_and_new_nodes_merge_cleanly() {
+        let base = base();
+        let mut left = base.clone();
+        left.flows[0].nodes[1].props["value"] = json!(10);
+        left.flows[0].nodes.push(node("c", json!({})));
+        left.flows[0].edges.push(edge("b", "c"));
+        let mut right = base.clone();
+        right.flows[0].nodes[1].props["label"] = json!("renamed");
+
+        let outcome = merge_logic_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        let flow = &outcome.logic.flows[0];
+        assert_eq!(
+            flow.nodes[1].props,
+            json!({ "value": 10, "label": "renamed" })
+        );
+        assert_eq!(flow.nodes.len(), 4);
+        assert!(flow.edges.contains(&edge("b", "c")));
+    }
+
+    #[test]
+    fn same_prop_edit_conflicts_at_node_path() {
+        let base = base();
+        let mut left = base.clone();
+        left.flows[0].nodes[2].props["value"] = json!(3);
+        let mut right = base.clone();
+        right.flows[0].nodes[2].props["value"] = json!(4);
+
+        let outcome = merge_logic_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(
+            outcome.conflicts[0].path,
+            "logic.flows[main].nodes[b].props.value"
+        );
+        assert_eq!(outcome.logic.flows[0].nodes[2].props["value"], json!(4));
+    }
+
+    #[test]
+    fn strategies_settle_logic_conflicts() {
+        let base = base();
+        let mut left = base.clone();
+        left.flows[0].nodes[2].props["value"] = json!(3);
+        let mut right = base.clone();
+        right.flows[0].nodes[2].props["value"] = json!(4);
+
+        let outcome =
+            crate::merge_logic_graphs_with(&base, &left, &right, crate::MergeStrategy::PreferLeft)
+                .unwrap();
+        assert_eq!(outcome.logic.flows[0].nodes[2].props["value"], json!(3));
+        assert_eq!(outcome.conflicts[0].left, Some(json!(3)));
+        assert_eq!(outcome.conflicts[0].right, Some(json!(4)));
+        assert!(matches!(
+            crate::merge_logic_graphs_with(&base, &left, &right, crate::MergeStrategy::Fail),
+            Err(crate::MergeError::Conflicts(conflicts)) if conflicts.len() == 1
+        ));
+    }
+
+    #[test]
+    fn rewiring_on_one_side_merges_and_on_both_sides_conflicts() {
+        let mut base = base();
+        base.flows[0].nodes.push(node("c", json!({})));
+        base.flows[0].nodes.push(node("d", json!({})));
+        let mut left = base.clone();
+        left.flows[0].edges[1] = edge("a", "c");
+
+        let clean = merge_logic_graphs(&base, &left, &base);
+        assert!(clean.conflicts.is_empty());
+        assert_eq!(
+            clean.logic.flows[0].edges,
+            vec![edge("start", "a"), edge("a", "c")]
+        );
+
+        let mut right = base.clone();
+        right.flows[0].edges[1] = edge("a", "d");
+        let outcome = merge_logic_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(
+            outcome.conflicts[0].path,
+            "logic.flows[main].edges[a.then->b]"
+        );
+        assert_eq!(
+            outcome.logic.flows[0].edges,
+            vec![edge("start", "a"), edge("a", "d")]
+        );
+    }
+
+    #[test]
+    fn deleting_a_node_the_other_side_wires_to_conflicts_and_keeps_it() {
+        let base = base();
+        let mut left = base.clone();
+        left.flows[0].nodes.retain(|node| node.id != "b");
+        left.flows[0].edges.retain(|edge| edge.to_node != "b");
+        let mut right = base.clone();
+        right.flows[0].edges.push(edge("start", "b"));
+
+        let outcome = merge_logic_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].path, "logic.flows[main].nodes[b]");
+        assert_eq!(outcome.conflicts[0].left, None);
+        let flow = &outcome.logic.flows[0];
+        assert!(flow.nodes.iter().any(|node| node.id == "b"));
+        assert!(flow.edges.contains(&edge("start", "b")));
+        assert!(!flow.edges.contains(&edge("a", "b")));
+    }
+
+    #[test]
+    fn clean_deletion_drops_dangling_edges_and_entries() {
+        let base = base();
+        let mut left = base.clone();
+        left.flows[0].nodes.retain(|node| node.id != "start");
+
+        let outcome = merge_logic_graphs(&base, &left, &base);
+        assert!(outcome.conflicts.is_empty());
+        let flow = &outcome.logic.flows[0];
+        assert_eq!(flow.edges, vec![edge("a", "b")]);
+        assert!(flow.entry_nodes.is_empty());
+    }
+}
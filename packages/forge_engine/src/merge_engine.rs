@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use serde::Serialize;
 
-use crate::{PropValue, ScreenGraph, WidgetNode};
+use crate::{PropValue, ScreenGraph, SourceSpan, WidgetBuilder, WidgetNode};
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MergeConflict {
@@ -10,6 +10,14 @@ pub struct MergeConflict {
     pub base: Option<serde_json::Value>,
     pub left: Option<serde_json::Value>,
     pub right: Option<serde_json::Value>,
+    /// Source location of the conflicting widget or prop, taken from the left
+    /// side when it has one, then the right, then the base.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    /// Id of the widget node the conflict is about, which unlike `path` does
+    /// not change when siblings are inserted or removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -18,13 +26,23 @@ pub struct MergeOutcome {
     pub conflicts: Vec<MergeConflict>,
 }
 
+/// Three-way merge of screen graphs. Each conflict is recorded and keeps the
+/// right side's value; [crate::merge_screen_graphs_with] settles conflicts
+/// with another [crate::MergeStrategy].
 pub fn merge_screen_graphs(
     base: &ScreenGraph,
     left: &ScreenGraph,
     right: &ScreenGraph,
 ) -> MergeOutcome {
     let mut conflicts = Vec::new();
-    let merged_id = merge_scalar("screen.id", &base.id, &left.id, &right.id, &mut conflicts);
+    let merged_id = merge_scalar(
+        "screen.id",
+        &base.id,
+        &left.id,
+        &right.id,
+        None,
+        &mut conflicts,
+    );
 
     if left.id != right.id && (left.id == base.id || right.id == base.id) {
         record_conflict(
@@ -32,6 +50,7 @@ pub fn merge_screen_graphs(
             Some(&base.id),
             Some(&left.id),
             Some(&right.id),
+            None,
             &mut conflicts,
         );
     }
@@ -44,10 +63,40 @@ pub fn merge_screen_graphs(
         &mut conflicts,
     );
 
+    let merged_state = merge_optional(
+        "screen.state",
+        base.state.as_ref(),
+        left.state.as_ref(),
+        right.state.as_ref(),
+        None,
+        &mut conflicts,
+    );
+
+    let merged_consumer = merge_optional(
+        "screen.consumer",
+        base.consumer.as_ref(),
+        left.consumer.as_ref(),
+        right.consumer.as_ref(),
+        None,
+        &mut conflicts,
+    );
+
+    let merged_statements = merge_optional(
+        "screen.statements",
+        base.statements.as_ref(),
+        left.statements.as_ref(),
+        right.statements.as_ref(),
+        None,
+        &mut conflicts,
+    );
+
     MergeOutcome {
         screen: ScreenGraph {
             id: merged_id,
             root: merged_root,
+            state: merged_state,
+            consumer: merged_consumer,
+            statements: merged_statements,
         },
         conflicts,
     }
@@ -60,11 +109,21 @@ fn merge_widget_node(
     right: &WidgetNode,
     conflicts: &mut Vec<MergeConflict>,
 ) -> WidgetNode {
+    let first_conflict = conflicts.len();
+    let span = first_span([&left.span, &right.span, &base.span]);
+    // The nodes were already matched, so their id is not merged as a field:
+    // the base id wins, or left's for a node the base does not have.
+    let id = base
+        .id
+        .clone()
+        .or_else(|| left.id.clone())
+        .or_else(|| right.id.clone());
     let widget = merge_scalar(
         &format!("{path}.widget"),
         &base.widget,
         &left.widget,
         &right.widget,
+        span,
         conflicts,
     );
 
@@ -73,6 +132,7 @@ fn merge_widget_node(
         &base.props,
         &left.props,
         &right.props,
+        span,
         conflicts,
     );
 
@@ -84,18 +144,143 @@ fn merge_widget_node(
         conflicts,
     );
 
+    let raw = merge_optional(
+        &format!("{path}.raw"),
+        base.raw.as_ref(),
+        left.raw.as_ref(),
+        right.raw.as_ref(),
+        span,
+        conflicts,
+    );
+
+    let component = merge_optional(
+        &format!("{path}.component"),
+        base.component.as_ref(),
+        left.component.as_ref(),
+        right.component.as_ref(),
+        span,
+        conflicts,
+    );
+
+    let builders = merge_builders(
+        &format!("{path}.builders"),
+        &base.builders,
+        &left.builders,
+        &right.builders,
+        span,
+        conflicts,
+    );
+
+    // Conflicts on this node's own fields are tagged with its id; those of
+    // descendants were already tagged with theirs.
+    for conflict in &mut conflicts[first_conflict..] {
+        if conflict.node_id.is_none() {
+            conflict.node_id = id.clone();
+        }
+    }
+
     WidgetNode {
+        id,
         widget,
         props,
         children,
+        raw,
+        builders,
+        component,
+        span: span.cloned(),
     }
 }
 
+/// Merges builder callbacks matched by argument name. Templates present on all
+/// sides are merged node by node.
+fn merge_builders(
+    path: &str,
+    base: &[WidgetBuilder],
+    left: &[WidgetBuilder],
+    right: &[WidgetBuilder],
+    span: Option<&SourceSpan>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<WidgetBuilder> {
+    if left == right {
+        return left.to_vec();
+    }
+    if left == base {
+        return right.to_vec();
+    }
+    if right == base {
+        return left.to_vec();
+    }
+
+    let mut arguments: Vec<&str> = Vec::new();
+    for builder in left.iter().chain(right).chain(base) {
+        if !arguments.contains(&builder.argument.as_str()) {
+            arguments.push(&builder.argument);
+        }
+    }
+
+    let mut merged = Vec::with_capacity(arguments.len());
+    for argument in arguments {
+        let find = |builders: &'_ [WidgetBuilder]| {
+            builders
+                .iter()
+                .find(|builder| builder.argument == argument)
+                .cloned()
+        };
+        let builder_path = format!("{path}.{argument}");
+        match (find(base), find(left), find(right)) {
+            (Some(base), Some(left), Some(right)) => merged.push(WidgetBuilder {
+                argument: argument.to_string(),
+                kind: merge_scalar(
+                    &format!("{builder_path}.kind"),
+                    &base.kind,
+                    &left.kind,
+                    &right.kind,
+                    span,
+                    conflicts,
+                ),
+                parameters: merge_scalar(
+                    &format!("{builder_path}.parameters"),
+                    &base.parameters,
+                    &left.parameters,
+                    &right.parameters,
+                    span,
+                    conflicts,
+                ),
+                statements: merge_optional(
+                    &format!("{builder_path}.statements"),
+                    base.statements.as_ref(),
+                    left.statements.as_ref(),
+                    right.statements.as_ref(),
+                    span,
+                    conflicts,
+                ),
+                template: Box::new(merge_widget_node(
+                    &format!("{builder_path}.template"),
+                    &base.template,
+                    &left.template,
+                    &right.template,
+                    conflicts,
+                )),
+            }),
+            (base, left, right) => merged.extend(merge_optional(
+                &builder_path,
+                base.as_ref(),
+                left.as_ref(),
+                right.as_ref(),
+                span,
+                conflicts,
+            )),
+        }
+    }
+    merged
+}
+
 fn merge_props(
     path: &str,
     base: &BTreeMap<String, PropValue>,
     left: &BTreeMap<String, PropValue>,
     right: &BTreeMap<String, PropValue>,
+    node_span: Option<&SourceSpan>,
     conflicts: &mut Vec<MergeConflict>,
 ) -> BTreeMap<String, PropValue> {
     let mut keys = BTreeSet::new();
@@ -108,12 +293,18 @@ fn merge_props(
         let base_val = base.get(key);
         let left_val = left.get(key);
         let right_val = right.get(key);
+        let span = [left_val, right_val, base_val]
+            .into_iter()
+            .flatten()
+            .find_map(PropValue::span)
+            .or(node_span);
 
         let merged_val = merge_optional(
             &format!("{path}.{key}"),
             base_val,
             left_val,
             right_val,
+            span,
             conflicts,
         );
 
@@ -125,6 +316,28 @@ fn merge_props(
     merged
 }
 
+/// Score of a match between two children with identical content. Partial
+/// matches of the same widget score between `SIMILAR_SCORE` and this.
+const IDENTICAL_SCORE: u32 = 1500;
+const SIMILAR_SCORE: u32 = 500;
+/// Added when both children carry the same node id. Generated ids come from
+/// a node's `key` or its content, never its position, and ids kept from an
+/// earlier import follow a node through edits, so a shared id marks the same
+/// child.
+const SAME_ID_SCORE: u32 = 300;
+
+/// One child across the three versions, by index into each list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChildSlot {
+    base: Option<usize>,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// Merges child lists by matching children rather than indices: base children
+/// are aligned with each side by node id and widget similarity, so inserts,
+/// deletes and moves on either side merge cleanly and only edits to the same
+/// child, or a child edited on one side and deleted on the other, conflict.
 fn merge_children(
     path: &str,
     base: &[WidgetNode],
@@ -142,14 +355,94 @@ fn merge_children(
         return left.to_vec();
     }
 
-    let max_len = base.len().max(left.len()).max(right.len());
-    let mut merged = Vec::with_capacity(max_len);
+    let left_base = side_to_base(match_children(base, left), left.len());
+    let right_base = side_to_base(match_children(base, right), right.len());
 
-    for idx in 0..max_len {
-        let child_path = format!("{path}[{idx}]");
-        let base_child = base.get(idx);
-        let left_child = left.get(idx);
-        let right_child = right.get(idx);
+    let mut slots: Vec<ChildSlot> = (0..base.len())
+        .map(|idx| ChildSlot {
+            base: Some(idx),
+            left: left_base.iter().position(|b| *b == Some(idx)),
+            right: right_base.iter().position(|b| *b == Some(idx)),
+        })
+        .collect();
+
+    // Children added on both sides after the same base child are the same
+    // insertion when they share an id, or a widget type without conflicting
+    // ids; everything else added is independent.
+    let mut paired_right = BTreeSet::new();
+    for (l, _) in left_base.iter().enumerate().filter(|(_, b)| b.is_none()) {
+        let anchor = insertion_anchor(&left_base, l);
+        let partner = (0..right.len()).find(|&r| {
+            right_base[r].is_none()
+                && !paired_right.contains(&r)
+                && insertion_anchor(&right_base, r) == anchor
+                && !different_ids(&left[l], &right[r])
+                && (left[l].widget == right[r].widget || same_id(&left[l], &right[r]))
+        });
+        if let Some(r) = partner {
+            paired_right.insert(r);
+        }
+        slots.push(ChildSlot {
+            base: None,
+            left: Some(l),
+            right: partner,
+        });
+    }
+    for r in (0..right.len()).filter(|r| right_base[*r].is_none() && !paired_right.contains(r)) {
+        slots.push(ChildSlot {
+            base: None,
+            left: None,
+            right: Some(r),
+        });
+    }
+
+    let left_order = side_order(&slots, |slot| slot.left);
+    let right_order = side_order(&slots, |slot| slot.right);
+    let left_moved = reordered(&left_order);
+    let right_moved = reordered(&right_order);
+    if left_moved && right_moved && !same_relative_order(&left_order, &right_order) {
+        let labels = |children: &[WidgetNode]| -> Vec<String> {
+            children
+                .iter()
+                .map(|child| child.id.clone().unwrap_or_else(|| child.widget.clone()))
+                .collect()
+        };
+        record_conflict(
+            path,
+            Some(&labels(base)),
+            Some(&labels(left)),
+            Some(&labels(right)),
+            None,
+            conflicts,
+        );
+    }
+
+    // Follow the side that reordered its children, the right when both or
+    // neither did, and place the other side's children after their
+    // predecessor there.
+    let (mut order, other) = if left_moved && !right_moved {
+        (left_order, right_order)
+    } else {
+        (right_order, left_order)
+    };
+    for (pos, slot) in other.iter().enumerate() {
+        if order.contains(slot) {
+            continue;
+        }
+        let at = other[..pos]
+            .iter()
+            .rev()
+            .find_map(|prev| order.iter().position(|placed| placed == prev))
+            .map_or(0, |placed| placed + 1);
+        order.insert(at, *slot);
+    }
+
+    let mut merged = Vec::with_capacity(order.len());
+    for slot in order {
+        let child_path = format!("{path}[{}]", merged.len());
+        let base_child = slot.base.map(|idx| &base[idx]);
+        let left_child = slot.left.map(|idx| &left[idx]);
+        let right_child = slot.right.map(|idx| &right[idx]);
 
         match (base_child, left_child, right_child) {
             (Some(b), Some(l), Some(r)) => {
@@ -159,23 +452,30 @@ fn merge_children(
                 if l == r {
                     merged.push(l.clone());
                 } else {
-                    record_conflict(&child_path, None, Some(l), Some(r), conflicts);
+                    let span = first_span([&l.span, &r.span]);
+                    record_conflict(&child_path, None, Some(l), Some(r), span, conflicts);
+                    tag_last_conflict(conflicts, [l, r]);
                     merged.push(r.clone());
                 }
             }
             (Some(b), Some(l), None) => {
-                record_conflict(&child_path, Some(b), Some(l), None, conflicts);
+                if l != b {
+                    let span = first_span([&l.span, &b.span]);
+                    record_conflict(&child_path, Some(b), Some(l), None, span, conflicts);
+                    tag_last_conflict(conflicts, [l, b]);
+                }
                 // analyzer deleted the child; keep analyzer decision by skipping
             }
             (Some(b), None, Some(r)) => {
-                record_conflict(&child_path, Some(b), None, Some(r), conflicts);
-                merged.push(r.clone());
-            }
-            (None, Some(l), None) => {
-                merged.push(l.clone());
+                if r != b {
+                    let span = first_span([&r.span, &b.span]);
+                    record_conflict(&child_path, Some(b), None, Some(r), span, conflicts);
+                    tag_last_conflict(conflicts, [r, b]);
+                    merged.push(r.clone());
+                }
             }
-            (None, None, Some(r)) => {
-                merged.push(r.clone());
+            (None, Some(child), None) | (None, None, Some(child)) => {
+                merged.push(child.clone());
             }
             (Some(_), None, None) | (None, None, None) => {
                 // node absent in merge; nothing to push
@@ -186,11 +486,220 @@ fn merge_children(
     merged
 }
 
-fn merge_scalar<T>(
+/// Pairs of `(base index, side index)` for the children of `side` that are
+/// versions of a base child. A longest common subsequence weighted by
+/// [match_score] aligns the lists in order; children left over are then
+/// paired when they share an id or are identical, which catches moves.
+pub(crate) fn match_children(base: &[WidgetNode], side: &[WidgetNode]) -> Vec<(usize, usize)> {
+    let (rows, cols) = (base.len(), side.len());
+    let mut table = vec![vec![0u32; cols + 1]; rows + 1];
+    for i in 0..rows {
+        for j in 0..cols {
+            let mut best = table[i][j + 1].max(table[i + 1][j]);
+            if let Some(score) = match_score(&base[i], &side[j]) {
+                best = best.max(table[i][j] + score);
+            }
+            table[i + 1][j + 1] = best;
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (rows, cols);
+    while i > 0 && j > 0 {
+        let diagonal = match_score(&base[i - 1], &side[j - 1])
+            .is_some_and(|score| table[i][j] == table[i - 1][j - 1] + score);
+        if diagonal {
+            pairs.push((i - 1, j - 1));
+            i -= 1;
+            j -= 1;
+        } else if table[i - 1][j] >= table[i][j - 1] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    pairs.reverse();
+
+    for (b, base_child) in base.iter().enumerate() {
+        if pairs.iter().any(|(matched, _)| *matched == b) {
+            continue;
+        }
+        let moved = side.iter().enumerate().position(|(s, side_child)| {
+            !pairs.iter().any(|(_, matched)| *matched == s)
+                && (same_id(base_child, side_child) || same_content(base_child, side_child))
+        });
+        if let Some(s) = moved {
+            pairs.push((b, s));
+        }
+    }
+    pairs
+}
+
+/// How strongly `side` looks like a version of `base`, or `None` when it
+/// cannot be one. Different widget types only match through a shared id.
+fn match_score(base: &WidgetNode, side: &WidgetNode) -> Option<u32> {
+    let id_score = if same_id(base, side) {
+        SAME_ID_SCORE
+    } else {
+        0
+    };
+    if base.widget != side.widget {
+        return (id_score > 0).then_some(id_score);
+    }
+    if same_content(base, side) {
+        return Some(IDENTICAL_SCORE + id_score);
+    }
+
+    let keys: BTreeSet<&String> = base.props.keys().chain(side.props.keys()).collect();
+    let equal_props = keys
+        .iter()
+        .filter(|key| base.props.get(**key) == side.props.get(**key))
+        .count();
+    let props_score = if keys.is_empty() {
+        300
+    } else {
+        (300 * equal_props / keys.len()) as u32
+    };
+    let child_widgets = |node: &WidgetNode| -> Vec<String> {
+        node.children
+            .iter()
+            .map(|child| child.widget.clone())
+            .collect()
+    };
+    let children_score = if child_widgets(base) == child_widgets(side) {
+        200
+    } else {
+        0
+    };
+    Some(SIMILAR_SCORE + props_score + children_score + id_score)
+}
+
+fn same_id(a: &WidgetNode, b: &WidgetNode) -> bool {
+    a.id.is_some() && a.id == b.id
+}
+
+fn different_ids(a: &WidgetNode, b: &WidgetNode) -> bool {
+    matches!((&a.id, &b.id), (Some(a), Some(b)) if a != b)
+}
+
+/// Equality ignoring the node's own id.
+fn same_content(a: &WidgetNode, b: &WidgetNode) -> bool {
+    a.widget == b.widget
+        && a.props == b.props
+        && a.children == b.children
+        && a.raw == b.raw
+        && a.builders == b.builders
+        && a.component == b.component
+}
+
+/// For each child of a side, the index of the base child it is a version of.
+fn side_to_base(pairs: Vec<(usize, usize)>, len: usize) -> Vec<Option<usize>> {
+    let mut mapping = vec![None; len];
+    for (base, side) in pairs {
+        mapping[side] = Some(base);
+    }
+    mapping
+}
+
+/// The base child an added child follows on its side, `None` at the start.
+fn insertion_anchor(side_base: &[Option<usize>], idx: usize) -> Option<usize> {
+    side_base[..idx].iter().rev().find_map(|base| *base)
+}
+
+fn side_order(slots: &[ChildSlot], index: impl Fn(&ChildSlot) -> Option<usize>) -> Vec<ChildSlot> {
+    let mut order: Vec<ChildSlot> = slots
+        .iter()
+        .filter(|slot| index(slot).is_some())
+        .copied()
+        .collect();
+    order.sort_by_key(|slot| index(slot));
+    order
+}
+
+/// Whether `order` puts the base children it kept out of base order.
+fn reordered(order: &[ChildSlot]) -> bool {
+    let kept: Vec<usize> = order.iter().filter_map(|slot| slot.base).collect();
+    kept.windows(2).any(|pair| pair[0] > pair[1])
+}
+
+/// Whether the base children kept by both sides appear in the same order.
+fn same_relative_order(left: &[ChildSlot], right: &[ChildSlot]) -> bool {
+    let kept_by_both = |order: &[ChildSlot]| -> Vec<usize> {
+        order
+            .iter()
+            .filter(|slot| slot.left.is_some() && slot.right.is_some())
+            .filter_map(|slot| slot.base)
+            .collect()
+    };
+    kept_by_both(left) == kept_by_both(right)
+}
+
+/// Merges lists of items identified by `key`. Items are merged with `merge`
+/// when all three versions have them; an item deleted on one side merges
+/// cleanly when the other left it unchanged and conflicts otherwise. The
+/// result follows the right side's order, with items only the left has placed
+/// after their predecessor on the left.
+pub(crate) fn merge_keyed<T, K, M>(
+    path: &str,
+    base: &[T],
+    left: &[T],
+    right: &[T],
+    key: K,
+    merge: M,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<T>
+where
+    T: PartialEq + Clone + Serialize,
+    K: Fn(&T) -> String,
+    M: Fn(&str, &T, &T, &T, &mut Vec<MergeConflict>) -> T,
+{
+    let find = |items: &'_ [T], wanted: &str| -> Option<usize> {
+        items.iter().position(|item| key(item) == wanted)
+    };
+
+    let mut order: Vec<String> = right.iter().map(&key).collect();
+    for (pos, item) in left.iter().enumerate() {
+        let item_key = key(item);
+        if order.contains(&item_key) {
+            continue;
+        }
+        let at = left[..pos]
+            .iter()
+            .rev()
+            .find_map(|prev| order.iter().position(|placed| *placed == key(prev)))
+            .map_or(0, |placed| placed + 1);
+        order.insert(at, item_key);
+    }
+    for item in base {
+        let item_key = key(item);
+        if !order.contains(&item_key) {
+            order.push(item_key);
+        }
+    }
+
+    let mut merged = Vec::with_capacity(order.len());
+    for item_key in order {
+        let item_path = format!("{path}[{item_key}]");
+        let base_item = find(base, &item_key).map(|idx| &base[idx]);
+        let left_item = find(left, &item_key).map(|idx| &left[idx]);
+        let right_item = find(right, &item_key).map(|idx| &right[idx]);
+
+        match (base_item, left_item, right_item) {
+            (Some(b), Some(l), Some(r)) => merged.push(merge(&item_path, b, l, r, conflicts)),
+            (base_item, left_item, right_item) => merged.extend(merge_optional(
+                &item_path, base_item, left_item, right_item, None, conflicts,
+            )),
+        }
+    }
+    merged
+}
+
+pub(crate) fn merge_scalar<T>(
     path: &str,
     base: &T,
     left: &T,
     right: &T,
+    span: Option<&SourceSpan>,
     conflicts: &mut Vec<MergeConflict>,
 ) -> T
 where
@@ -206,15 +715,16 @@ where
         return left.clone();
     }
 
-    record_conflict(path, Some(base), Some(left), Some(right), conflicts);
+    record_conflict(path, Some(base), Some(left), Some(right), span, conflicts);
     right.clone()
 }
 
-fn merge_optional<T>(
+pub(crate) fn merge_optional<T>(
     path: &str,
     base: Option<&T>,
     left: Option<&T>,
     right: Option<&T>,
+    span: Option<&SourceSpan>,
     conflicts: &mut Vec<MergeConflict>,
 ) -> Option<T>
 where
@@ -234,15 +744,16 @@ where
         return None;
     }
 
-    record_conflict(path, base, left, right, conflicts);
+    record_conflict(path, base, left, right, span, conflicts);
     right.cloned()
 }
 
-fn record_conflict<T>(
+pub(crate) fn record_conflict<T>(
     path: &str,
     base: Option<&T>,
     left: Option<&T>,
     right: Option<&T>,
+    span: Option<&SourceSpan>,
     conflicts: &mut Vec<MergeConflict>,
 ) where
     T: Serialize,
@@ -252,9 +763,22 @@ fn record_conflict<T>(
         base: base.and_then(to_json),
         left: left.and_then(to_json),
         right: right.and_then(to_json),
+        span: span.cloned(),
+        node_id: None,
     });
 }
 
+/// Tags the conflict just recorded with the first id among `nodes`.
+fn tag_last_conflict<const N: usize>(conflicts: &mut [MergeConflict], nodes: [&WidgetNode; N]) {
+    if let Some(conflict) = conflicts.last_mut() {
+        conflict.node_id = nodes.iter().find_map(|node| node.id.clone());
+    }
+}
+
+fn first_span<const N: usize>(candidates: [&Option<SourceSpan>; N]) -> Option<&SourceSpan> {
+    candidates.into_iter().find_map(Option::as_ref)
+}
+
 fn to_json<T: Serialize>(value: &T) -> Option<serde_json::Value> {
     serde_json::to_value(value).ok()
 }
@@ -271,15 +795,21 @@ mod tests {
             map.insert((*key).to_string(), value.clone());
         }
         WidgetNode {
+            id: None,
             widget: widget.to_string(),
             props: map,
             children,
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         }
     }
 
     fn literal(value: &str) -> PropValue {
         PropValue::Literal {
             value: serde_json::Value::String(value.to_string()),
+            span: None,
         }
     }
 
@@ -288,6 +818,9 @@ mod tests {
         let base = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[("value", literal("Hello"))], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let left = ScreenGraph {
             id: "Dashboard".to_string(),
@@ -296,10 +829,16 @@ mod tests {
                 &[("value", literal("Hello")), ("color", literal("red"))],
                 vec![],
             ),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let right = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[("value", literal("Hi"))], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let outcome = merge_screen_graphs(&base, &left, &right);
@@ -316,14 +855,23 @@ mod tests {
         let base = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[("value", literal("Hello"))], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let left = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[("value", literal("Hello left"))], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let right = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[("value", literal("Hello right"))], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let outcome = merge_screen_graphs(&base, &left, &right);
@@ -349,17 +897,26 @@ mod tests {
         let base = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Column", &[], vec![base_child]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let left = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Column", &[], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let right_child = widget("Text", &[("value", literal("Updated"))], vec![]);
         let right = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Column", &[], vec![right_child]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let outcome = merge_screen_graphs(&base, &left, &right);
@@ -377,6 +934,9 @@ mod tests {
         let base = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Column", &[], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let left = ScreenGraph {
@@ -386,6 +946,9 @@ mod tests {
                 &[],
                 vec![widget("Text", &[("value", literal("Left"))], vec![])],
             ),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let right = ScreenGraph {
@@ -395,6 +958,9 @@ mod tests {
                 &[],
                 vec![widget("Text", &[("value", literal("Right"))], vec![])],
             ),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let outcome = merge_screen_graphs(&base, &left, &right);
@@ -406,19 +972,187 @@ mod tests {
         );
     }
 
+    fn screen(children: Vec<WidgetNode>) -> ScreenGraph {
+        ScreenGraph {
+            id: "Dashboard".to_string(),
+            root: widget("Column", &[], children),
+            state: None,
+            consumer: None,
+            statements: None,
+        }
+    }
+
+    fn text(value: &str) -> WidgetNode {
+        widget("Text", &[("value", literal(value))], vec![])
+    }
+
+    fn values(outcome: &MergeOutcome) -> Vec<PropValue> {
+        outcome
+            .screen
+            .root
+            .children
+            .iter()
+            .map(|child| child.props["value"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn insert_on_one_side_and_edit_on_the_other_merge_cleanly() {
+        let base = screen(vec![text("a"), text("b")]);
+        let left = screen(vec![widget("Spacer", &[], vec![]), text("a"), text("b")]);
+        let right = screen(vec![text("a"), text("b!")]);
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        let children = &outcome.screen.root.children;
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[0].widget, "Spacer");
+        assert_eq!(children[2].props["value"], literal("b!"));
+    }
+
+    #[test]
+    fn moves_deletes_and_edits_from_both_sides_merge_cleanly() {
+        let base = screen(vec![text("a"), text("b"), text("c"), text("d")]);
+        // Left moves "c" to the front and deletes "d"; right edits "c" and
+        // deletes "a".
+        let left = screen(vec![text("c"), text("a"), text("b")]);
+        let right = screen(vec![text("b"), text("c!"), text("d")]);
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        assert_eq!(values(&outcome), vec![literal("c!"), literal("b")]);
+    }
+
+    #[test]
+    fn ids_match_children_across_edits_and_retypes() {
+        let mut base_child = text("a");
+        base_child.id = Some("greeting".to_string());
+        let mut left_child = widget("SelectableText", &[("value", literal("a"))], vec![]);
+        left_child.id = Some("greeting".to_string());
+        let mut right_child = text("hello");
+        right_child.id = Some("greeting".to_string());
+
+        let base = screen(vec![text("x"), base_child]);
+        let left = screen(vec![left_child, text("x")]);
+        let right = screen(vec![text("x"), right_child]);
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        let merged = &outcome.screen.root.children[0];
+        assert_eq!(merged.widget, "SelectableText");
+        assert_eq!(merged.props["value"], literal("hello"));
+        assert_eq!(outcome.screen.root.children[1].props["value"], literal("x"));
+    }
+
+    #[test]
+    fn move_on_one_side_and_edit_on_the_other_keep_every_child() {
+        let parse = |children: &str| {
+            let source = format!(
+                "class Dashboard extends StatelessWidget {{\n  Widget build(BuildContext context) {{\n    return Column(children: [{children}]);\n  }}\n}}\n"
+            );
+            crate::build_graphs_from_source(&source).remove(0)
+        };
+        // Unkeyed siblings are identified by position, so a reorder is only
+        // recognised as a move when the children carry keys.
+        let base = parse(
+            "Text('a', key: ValueKey('a')), Text('b', key: ValueKey('b')), Text('c', key: ValueKey('c'))",
+        );
+        let left = parse(
+            "Text('c', key: ValueKey('c')), Text('a', key: ValueKey('a')), Text('b', key: ValueKey('b'))",
+        );
+        let right = parse(
+            "Text('a', key: ValueKey('a')), Text('B', key: ValueKey('b')), Text('c', key: ValueKey('c'))",
+        );
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        let texts: Vec<_> = outcome
+            .screen
+            .root
+            .children
+            .iter()
+            .map(|child| child.props["data"].clone())
+            .collect();
+        assert_eq!(texts, vec![literal("c"), literal("a"), literal("B")]);
+    }
+
+    #[test]
+    fn edits_to_one_child_on_both_sides_raise_a_single_conflict() {
+        let parse = |children: &str| {
+            let source = format!(
+                "class Dashboard extends StatelessWidget {{\n  Widget build(BuildContext context) {{\n    return Column(children: [{children}]);\n  }}\n}}\n"
+            );
+            crate::build_graphs_from_source(&source).remove(0)
+        };
+        let base = parse("Text('a'), Text('b')");
+        let left = parse("Text('a'), Text('b-left')");
+        let mut right = parse("Text('a'), Text('b-right')");
+        right.root.children[1].id = Some("right-only".to_string());
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1, "{:?}", outcome.conflicts);
+        let conflict = &outcome.conflicts[0];
+        assert_eq!(conflict.path, "screen.root.children[1].props.data");
+        assert_eq!(conflict.node_id, base.root.children[1].id);
+        assert_eq!(outcome.screen.root.children[1].id, base.root.children[1].id);
+    }
+
+    #[test]
+    fn insertions_with_different_ids_are_not_paired() {
+        let mut left_child = text("left");
+        left_child.id = Some("left-child".to_string());
+        let mut right_child = text("right");
+        right_child.id = Some("right-child".to_string());
+
+        let base = screen(vec![text("x")]);
+        let left = screen(vec![text("x"), left_child]);
+        let right = screen(vec![text("x"), right_child]);
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(outcome.conflicts.is_empty(), "{:?}", outcome.conflicts);
+        assert_eq!(
+            values(&outcome),
+            vec![literal("x"), literal("left"), literal("right")]
+        );
+    }
+
+    #[test]
+    fn conflicting_reorders_are_reported_once() {
+        let base = screen(vec![text("a"), text("b"), text("c")]);
+        let left = screen(vec![text("c"), text("a"), text("b")]);
+        let right = screen(vec![text("b"), text("a"), text("c")]);
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].path, "screen.root.children");
+        assert_eq!(
+            values(&outcome),
+            vec![literal("b"), literal("a"), literal("c")]
+        );
+    }
+
     #[test]
     fn id_conflicts_are_reported() {
         let base = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let left = ScreenGraph {
             id: "Dashboard".to_string(),
             root: widget("Text", &[], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
         let right = ScreenGraph {
             id: "AnalyzerDashboard".to_string(),
             root: widget("Text", &[], vec![]),
+            state: None,
+            consumer: None,
+            statements: None,
         };
 
         let outcome = merge_screen_graphs(&base, &left, &right);
@@ -426,4 +1160,34 @@ mod tests {
         assert_eq!(outcome.conflicts[0].path, "screen.id");
         assert_eq!(outcome.screen.id, "AnalyzerDashboard");
     }
+
+    #[test]
+    fn conflicts_carry_source_spans_and_ignore_span_differences() {
+        let parse = |file: &str, text: &str| {
+            let source = format!(
+                "class Dashboard extends StatelessWidget {{\n  Widget build(BuildContext context) {{\n    return Text({text});\n  }}\n}}\n"
+            );
+            crate::build_graphs_from_file_source(file, &source).remove(0)
+        };
+        let base = parse("base.dart", "'Hello'");
+        let left = parse("left.dart", "'Left'");
+        let right = parse("right.dart", "'Right'");
+
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].node_id, base.root.id);
+        assert!(outcome.conflicts[0].node_id.is_some());
+        let span = outcome.conflicts[0].span.as_ref().expect("conflict span");
+        assert_eq!(outcome.conflicts[0].path, "screen.root.props.data");
+        assert_eq!(span.file.as_deref(), Some("left.dart"));
+        assert_eq!((span.line, span.column), (3, 17));
+
+        // Identical content at a different location is not a change.
+        let unchanged = merge_screen_graphs(&base, &base, &parse("moved.dart", "  'Hello'"));
+        assert!(unchanged.conflicts.is_empty());
+        assert_eq!(
+            unchanged.screen.root.span.and_then(|span| span.file),
+            Some("base.dart".to_string())
+        );
+    }
 }
@@ -0,0 +1,639 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::document_merge::merge_documents_by;
+use crate::{
+    merge_documents, merge_logic_graphs, merge_screen_graphs, DocumentMergeOutcome, LogicGraph,
+    LogicMergeOutcome, MergeConflict, MergeOutcome, SchemaDocument, ScreenGraph,
+};
+
+/// How [merge_screen_graphs_with], [merge_logic_graphs_with] and
+/// [merge_documents_with] settle conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    PreferLeft,
+    /// Keep the right side's value, as [merge_screen_graphs] does.
+    #[default]
+    PreferRight,
+    /// Return [MergeError::Conflicts] instead of an outcome.
+    Fail,
+    /// Keep the children of both sides where they conflict, and the right
+    /// side's value for every other conflict. Logic graphs have no children,
+    /// so their conflicts keep the right side's value.
+    KeepBoth,
+}
+
+/// The value a conflict is resolved to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "take", content = "value", rename_all = "kebab-case")]
+pub enum Resolution {
+    Base,
+    Left,
+    Right,
+    /// Keep the left and then the right child; only valid for conflicts on a
+    /// child, such as both sides adding one at the same place.
+    KeepBoth,
+    /// A value written by the user, in the JSON form of the conflict's values.
+    Value(Value),
+}
+
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("{} merge conflict(s), the first at {}", .0.len(), .0[0].path)]
+    Conflicts(Vec<MergeConflict>),
+    #[error("no unresolved conflict at {0}")]
+    UnknownConflict(String),
+    #[error("both sides can only be kept for a child, not at {0}")]
+    CannotKeepBoth(String),
+    #[error("cannot resolve {path}: {message}")]
+    InvalidResolution { path: String, message: String },
+    #[error("{} conflict(s) are unresolved: {}", .0.len(), .0.join(", "))]
+    Unresolved(Vec<String>),
+}
+
+/// Merges like [merge_screen_graphs] but settles conflicts with `strategy`.
+/// Conflicts stay listed in the outcome, holding the strategy's choice, so
+/// they can still be reviewed and changed with [MergeOutcome::resolve].
+pub fn merge_screen_graphs_with(
+    base: &ScreenGraph,
+    left: &ScreenGraph,
+    right: &ScreenGraph,
+    strategy: MergeStrategy,
+) -> Result<MergeOutcome, MergeError> {
+    let mut outcome = merge_screen_graphs(base, left, right);
+    match strategy {
+        MergeStrategy::PreferRight => {}
+        MergeStrategy::Fail if outcome.conflicts.is_empty() => {}
+        MergeStrategy::Fail => return Err(MergeError::Conflicts(outcome.conflicts)),
+        MergeStrategy::PreferLeft => {
+            for idx in 0..outcome.conflicts.len() {
+                outcome.apply(idx, &Resolution::Left)?;
+            }
+        }
+        MergeStrategy::KeepBoth => {
+            for idx in 0..outcome.conflicts.len() {
+                if names_child(&outcome.conflicts[idx].path) {
+                    outcome.apply(idx, &Resolution::KeepBoth)?;
+                }
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Merges like [merge_logic_graphs] but settles conflicts with `strategy`.
+/// [MergeStrategy::PreferLeft] merges with the sides swapped, since every
+/// tie in the merge goes to the right side; the left side's order of flows,
+/// nodes and edges is kept too. Conflicts stay listed with their sides as
+/// passed.
+pub fn merge_logic_graphs_with(
+    base: &LogicGraph,
+    left: &LogicGraph,
+    right: &LogicGraph,
+    strategy: MergeStrategy,
+) -> Result<LogicMergeOutcome, MergeError> {
+    let outcome = match strategy {
+        MergeStrategy::PreferLeft => {
+            let mut outcome = merge_logic_graphs(base, right, left);
+            swap_sides(&mut outcome.conflicts);
+            outcome
+        }
+        _ => merge_logic_graphs(base, left, right),
+    };
+    if strategy == MergeStrategy::Fail && !outcome.conflicts.is_empty() {
+        return Err(MergeError::Conflicts(outcome.conflicts));
+    }
+    Ok(outcome)
+}
+
+/// Merges like [merge_documents] but settles conflicts with `strategy`, the
+/// way [merge_logic_graphs_with] does. [MergeStrategy::KeepBoth] keeps the
+/// children of both sides within screens.
+pub fn merge_documents_with(
+    base: &SchemaDocument,
+    left: &SchemaDocument,
+    right: &SchemaDocument,
+    strategy: MergeStrategy,
+) -> Result<DocumentMergeOutcome, MergeError> {
+    let outcome = match strategy {
+        MergeStrategy::PreferLeft => {
+            let mut outcome = merge_documents(base, right, left);
+            swap_sides(&mut outcome.conflicts);
+            outcome
+        }
+        MergeStrategy::KeepBoth => {
+            let failure = RefCell::new(None);
+            let outcome = merge_documents_by(base, left, right, |base, left, right| {
+                merge_screen_graphs_with(base, left, right, strategy).unwrap_or_else(|err| {
+                    failure.borrow_mut().get_or_insert(err);
+                    merge_screen_graphs(base, left, right)
+                })
+            });
+            if let Some(err) = failure.into_inner() {
+                return Err(err);
+            }
+            outcome
+        }
+        _ => merge_documents(base, left, right),
+    };
+    if strategy == MergeStrategy::Fail && !outcome.conflicts.is_empty() {
+        return Err(MergeError::Conflicts(outcome.conflicts));
+    }
+    Ok(outcome)
+}
+
+/// Restores the sides of conflicts found by a merge with swapped sides.
+fn swap_sides(conflicts: &mut [MergeConflict]) {
+    for conflict in conflicts {
+        std::mem::swap(&mut conflict.left, &mut conflict.right);
+    }
+}
+
+impl MergeOutcome {
+    /// Applies `resolution` to the conflict at `path` and removes it from
+    /// [Self::conflicts]. Inserting or removing a child renumbers the paths of
+    /// the conflicts that remain, so always use paths as currently listed.
+    pub fn resolve(&mut self, path: &str, resolution: Resolution) -> Result<(), MergeError> {
+        let idx = self.conflict_index(path)?;
+        self.apply(idx, &resolution)?;
+        self.conflicts.remove(idx);
+        Ok(())
+    }
+
+    /// Applies resolutions keyed by the conflict paths as listed before any
+    /// of them is applied, and returns the screen if no conflict is left.
+    pub fn apply_resolutions<I, S>(mut self, resolutions: I) -> Result<ScreenGraph, MergeError>
+    where
+        I: IntoIterator<Item = (S, Resolution)>,
+        S: AsRef<str>,
+    {
+        let mut targets = Vec::new();
+        for (path, resolution) in resolutions {
+            targets.push((self.conflict_index(path.as_ref())?, resolution));
+        }
+        for (idx, resolution) in &targets {
+            self.apply(*idx, resolution)?;
+        }
+
+        let resolved: BTreeSet<usize> = targets.iter().map(|(idx, _)| *idx).collect();
+        let mut idx = 0;
+        self.conflicts.retain(|_| {
+            idx += 1;
+            !resolved.contains(&(idx - 1))
+        });
+        self.into_resolved()
+    }
+
+    /// The merged screen, or [MergeError::Unresolved] while conflicts remain.
+    pub fn into_resolved(self) -> Result<ScreenGraph, MergeError> {
+        if self.conflicts.is_empty() {
+            Ok(self.screen)
+        } else {
+            Err(MergeError::Unresolved(
+                self.conflicts
+                    .into_iter()
+                    .map(|conflict| conflict.path)
+                    .collect(),
+            ))
+        }
+    }
+
+    fn conflict_index(&self, path: &str) -> Result<usize, MergeError> {
+        self.conflicts
+            .iter()
+            .position(|conflict| conflict.path == path)
+            .ok_or_else(|| MergeError::UnknownConflict(path.to_string()))
+    }
+
+    /// Writes the value chosen by `resolution` for conflict `idx` into the
+    /// screen, leaving the conflict listed.
+    fn apply(&mut self, idx: usize, resolution: &Resolution) -> Result<(), MergeError> {
+        let conflict = &self.conflicts[idx];
+        let path = conflict.path.clone();
+        let invalid = |message: String| MergeError::InvalidResolution {
+            path: path.clone(),
+            message,
+        };
+        let segments = parse_path(&path).ok_or_else(|| invalid("unsupported path".into()))?;
+        let mut screen =
+            serde_json::to_value(&self.screen).map_err(|err| invalid(err.to_string()))?;
+
+        let (last, parents) = segments
+            .split_last()
+            .ok_or_else(|| invalid("path names the whole screen".into()))?;
+        let parent = navigate(&mut screen, parents)
+            .ok_or_else(|| invalid("path is not in the merged screen".into()))?;
+        let pick = |resolution: &Resolution| match resolution {
+            Resolution::Base => Ok(conflict.base.clone()),
+            Resolution::Left => Ok(conflict.left.clone()),
+            Resolution::Right => Ok(conflict.right.clone()),
+            Resolution::Value(value) => Ok(Some(value.clone())),
+            Resolution::KeepBoth => Err(MergeError::CannotKeepBoth(path.clone())),
+        };
+
+        let mut renumber = None;
+        match (last, parent) {
+            (Segment::Index(slot), Value::Array(children)) => {
+                let candidates: Vec<&Value> = [&conflict.base, &conflict.left, &conflict.right]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                let occupied = if conflict.left.is_some()
+                    && conflict.right.is_some()
+                    && conflict.left != conflict.right
+                    && children.get(*slot) == conflict.left.as_ref()
+                    && children.get(slot + 1) == conflict.right.as_ref()
+                {
+                    2
+                } else {
+                    usize::from(
+                        children
+                            .get(*slot)
+                            .is_some_and(|child| candidates.contains(&child)),
+                    )
+                };
+                let chosen: Vec<Value> = match resolution {
+                    Resolution::KeepBoth => {
+                        let mut both: Vec<Value> = conflict.left.iter().cloned().collect();
+                        if conflict.right != conflict.left {
+                            both.extend(conflict.right.clone());
+                        }
+                        both
+                    }
+                    other => pick(other)?.into_iter().collect(),
+                };
+                if *slot > children.len() {
+                    return Err(invalid("child index is out of range".into()));
+                }
+                let inserted = chosen.len();
+                children.splice(*slot..slot + occupied, chosen);
+                let list = path[..path.rfind('[').unwrap_or(path.len())].to_string();
+                let (slot, occupied) = (*slot, occupied);
+                let shift: Renumber = Box::new(move |j: usize| {
+                    if j >= slot + occupied {
+                        j + inserted - occupied
+                    } else {
+                        j
+                    }
+                });
+                renumber = Some((list, shift));
+            }
+            (Segment::Field(field), Value::Object(object))
+                if field == "children" && object.get("children").is_some_and(Value::is_array) =>
+            {
+                let order: Vec<String> = serde_json::from_value(
+                    pick(resolution)?.ok_or_else(|| invalid("missing child order".into()))?,
+                )
+                .map_err(|err| invalid(err.to_string()))?;
+                let Some(Value::Array(children)) = object.get_mut("children") else {
+                    unreachable!("checked above");
+                };
+                let permutation = reorder(children, &order);
+                renumber = Some((
+                    path.clone(),
+                    Box::new(move |j: usize| {
+                        permutation.iter().position(|old| *old == j).unwrap_or(j)
+                    }),
+                ));
+            }
+            (Segment::Field(field), Value::Object(object)) => match pick(resolution)? {
+                Some(value) => {
+                    object.insert(field.clone(), value);
+                }
+                None => {
+                    object.remove(field);
+                }
+            },
+            (Segment::Field(argument), Value::Array(builders)) => {
+                let existing = builders
+                    .iter()
+                    .position(|builder| builder["argument"] == argument.as_str());
+                match (pick(resolution)?, existing) {
+                    (Some(value), Some(at)) => builders[at] = value,
+                    (Some(value), None) => builders.push(value),
+                    (None, Some(at)) => {
+                        builders.remove(at);
+                    }
+                    (None, None) => {}
+                }
+            }
+            _ => return Err(invalid("path is not in the merged screen".into())),
+        }
+
+        self.screen = serde_json::from_value(screen).map_err(|err| invalid(err.to_string()))?;
+        if let Some((list, renumber)) = renumber {
+            for (other, conflict) in self.conflicts.iter_mut().enumerate() {
+                if other != idx {
+                    conflict.path = renumber_path(&conflict.path, &list, &renumber);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+type Renumber = Box<dyn Fn(usize) -> usize>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// Splits `screen.root.children[0].props.title` into segments below the
+/// screen.
+fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    let rest = path.strip_prefix("screen")?;
+    let mut segments = Vec::new();
+    for part in rest.split('.').skip(1) {
+        let (field, mut indices) = match part.find('[') {
+            Some(at) => part.split_at(at),
+            None => (part, ""),
+        };
+        if field.is_empty() {
+            return None;
+        }
+        segments.push(Segment::Field(field.to_string()));
+        while let Some(index) = indices.strip_prefix('[') {
+            let end = index.find(']')?;
+            segments.push(Segment::Index(index[..end].parse().ok()?));
+            indices = &index[end + 1..];
+        }
+        if !indices.is_empty() {
+            return None;
+        }
+    }
+    Some(segments)
+}
+
+/// Whether a conflict path names a child rather than a field.
+fn names_child(path: &str) -> bool {
+    parse_path(path).is_some_and(|segments| matches!(segments.last(), Some(Segment::Index(_))))
+}
+
+fn navigate<'a>(value: &'a mut Value, segments: &[Segment]) -> Option<&'a mut Value> {
+    segments
+        .iter()
+        .try_fold(value, |value, segment| match (segment, value) {
+            (Segment::Index(idx), Value::Array(items)) => items.get_mut(*idx),
+            (Segment::Field(argument), Value::Array(builders)) => builders
+                .iter_mut()
+                .find(|builder| builder["argument"] == argument.as_str()),
+            (Segment::Field(field), Value::Object(object)) => object.get_mut(field),
+            _ => None,
+        })
+}
+
+/// Sorts `children` to follow `order`, a list of node ids or, for nodes
+/// without one, widget names. Children not in `order` keep their relative
+/// place after those that are. Returns the old index of each new position.
+fn reorder(children: &mut Vec<Value>, order: &[String]) -> Vec<usize> {
+    let mut used = vec![false; order.len()];
+    let mut keyed: Vec<(usize, usize)> = children
+        .iter()
+        .enumerate()
+        .map(|(old, child)| {
+            let label = child["id"].as_str().or_else(|| child["widget"].as_str());
+            let rank = order
+                .iter()
+                .enumerate()
+                .position(|(at, entry)| !used[at] && Some(entry.as_str()) == label);
+            if let Some(rank) = rank {
+                used[rank] = true;
+            }
+            (rank.unwrap_or(usize::MAX), old)
+        })
+        .collect();
+    keyed.sort_by_key(|(rank, _)| *rank);
+
+    let permutation: Vec<usize> = keyed.into_iter().map(|(_, old)| old).collect();
+    let mut previous = std::mem::take(children);
+    *children = permutation
+        .iter()
+        .map(|old| std::mem::take(&mut previous[*old]))
+        .collect();
+    permutation
+}
+
+/// Rewrites the index directly after `list` in `path`, if `path` is below it.
+fn renumber_path(path: &str, list: &str, renumber: &Renumber) -> String {
+    let Some(rest) = path
+        .strip_prefix(list)
+        .and_then(|rest| rest.strip_prefix('['))
+    else {
+        return path.to_string();
+    };
+    let Some(end) = rest.find(']') else {
+        return path.to_string();
+    };
+    match rest[..end].parse::<usize>() {
+        Ok(index) => format!("{list}[{}]{}", renumber(index), &rest[end + 1..]),
+        Err(_) => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PropValue, WidgetNode};
+    use serde_json::json;
+
+    fn text(value: &str) -> WidgetNode {
+        WidgetNode {
+            id: None,
+            widget: "Text".to_string(),
+            props: [(
+                "value".to_string(),
+                PropValue::Literal {
+                    value: json!(value),
+                    span: None,
+                },
+            )]
+            .into(),
+            children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
+        }
+    }
+
+    fn screen(children: Vec<WidgetNode>) -> ScreenGraph {
+        ScreenGraph {
+            id: "Dashboard".to_string(),
+            root: WidgetNode {
+                widget: "Column".to_string(),
+                props: Default::default(),
+                children,
+                ..text("")
+            },
+            state: None,
+            consumer: None,
+            statements: None,
+        }
+    }
+
+    fn values(screen: &ScreenGraph) -> Vec<Value> {
+        screen
+            .root
+            .children
+            .iter()
+            .map(|child| match &child.props["value"] {
+                PropValue::Literal { value, .. } => value.clone(),
+                other => panic!("unexpected prop {other:?}"),
+            })
+            .collect()
+    }
+
+    /// Both sides add a different child at the top, and edit "b" differently.
+    fn sides() -> (ScreenGraph, ScreenGraph, ScreenGraph) {
+        (
+            screen(vec![text("a"), text("b")]),
+            screen(vec![text("left"), text("a"), text("b-left")]),
+            screen(vec![text("right"), text("a"), text("b-right")]),
+        )
+    }
+
+    #[test]
+    fn strategies_pick_a_side_or_fail() {
+        let (base, left, right) = sides();
+
+        let right_wins =
+            merge_screen_graphs_with(&base, &left, &right, MergeStrategy::PreferRight).unwrap();
+        assert_eq!(right_wins, merge_screen_graphs(&base, &left, &right));
+
+        let left_wins =
+            merge_screen_graphs_with(&base, &left, &right, MergeStrategy::PreferLeft).unwrap();
+        assert_eq!(left_wins.conflicts.len(), 2);
+        assert_eq!(
+            values(&left_wins.screen),
+            [json!("left"), json!("a"), json!("b-left")]
+        );
+
+        let err = merge_screen_graphs_with(&base, &left, &right, MergeStrategy::Fail).unwrap_err();
+        assert!(matches!(err, MergeError::Conflicts(ref conflicts) if conflicts.len() == 2));
+        assert!(merge_screen_graphs_with(&base, &left, &base, MergeStrategy::Fail).is_ok());
+    }
+
+    #[test]
+    fn keep_both_keeps_conflicting_children_and_renumbers_paths() {
+        let (base, left, right) = sides();
+        let outcome =
+            merge_screen_graphs_with(&base, &left, &right, MergeStrategy::KeepBoth).unwrap();
+        assert_eq!(
+            values(&outcome.screen),
+            [json!("left"), json!("right"), json!("a"), json!("b-right")]
+        );
+        let paths: Vec<&str> = outcome
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "screen.root.children[0]",
+                "screen.root.children[3].props.value"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolving_one_conflict_at_a_time_yields_the_screen() {
+        let (base, left, right) = sides();
+        let mut outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(matches!(
+            outcome.clone().into_resolved(),
+            Err(MergeError::Unresolved(paths)) if paths.len() == 2
+        ));
+
+        outcome
+            .resolve("screen.root.children[0]", Resolution::KeepBoth)
+            .unwrap();
+        assert_eq!(
+            outcome.conflicts[0].path,
+            "screen.root.children[3].props.value"
+        );
+        outcome
+            .resolve(
+                "screen.root.children[3].props.value",
+                Resolution::Value(json!({ "type": "literal", "value": "b-both" })),
+            )
+            .unwrap();
+        let screen = outcome.into_resolved().unwrap();
+        assert_eq!(
+            values(&screen),
+            [json!("left"), json!("right"), json!("a"), json!("b-both")]
+        );
+    }
+
+    #[test]
+    fn batch_resolutions_use_the_original_paths() {
+        let base = screen(vec![text("a"), text("b")]);
+        // Left edits "a" and "b"; right deletes "a" and edits "b" differently.
+        let left = screen(vec![text("a-left"), text("b-left")]);
+        let right = screen(vec![text("b-right")]);
+        let outcome = merge_screen_graphs(&base, &left, &right);
+        let paths: Vec<String> = outcome
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "screen.root.children[0]",
+                "screen.root.children[0].props.value"
+            ]
+        );
+
+        let screen = outcome
+            .apply_resolutions([
+                (paths[0].as_str(), Resolution::Left),
+                (paths[1].as_str(), Resolution::Base),
+            ])
+            .unwrap();
+        assert_eq!(values(&screen), [json!("a-left"), json!("b")]);
+    }
+
+    #[test]
+    fn order_conflicts_resolve_by_reordering() {
+        let labelled = |value: &str| WidgetNode {
+            id: Some(value.to_string()),
+            ..text(value)
+        };
+        let base = screen(vec![labelled("a"), labelled("b"), labelled("c")]);
+        let left = screen(vec![labelled("c"), labelled("a"), labelled("b")]);
+        let right = screen(vec![labelled("b"), labelled("a"), labelled("c")]);
+
+        let mut outcome = merge_screen_graphs(&base, &left, &right);
+        assert_eq!(outcome.conflicts[0].path, "screen.root.children");
+        outcome
+            .resolve("screen.root.children", Resolution::Left)
+            .unwrap();
+        assert_eq!(
+            values(&outcome.into_resolved().unwrap()),
+            [json!("c"), json!("a"), json!("b")]
+        );
+    }
+
+    #[test]
+    fn invalid_resolutions_are_rejected() {
+        let (base, left, right) = sides();
+        let mut outcome = merge_screen_graphs(&base, &left, &right);
+        assert!(matches!(
+            outcome.resolve("screen.root.props.missing", Resolution::Left),
+            Err(MergeError::UnknownConflict(_))
+        ));
+        assert!(matches!(
+            outcome.resolve("screen.root.children[2].props.value", Resolution::KeepBoth),
+            Err(MergeError::CannotKeepBoth(_))
+        ));
+        assert_eq!(outcome.conflicts.len(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashSet};
+
+use sha2::{Digest, Sha256};
+
+use crate::{PropValue, ScreenGraph, WidgetNode};
+
+/// Hex digits kept from the path hash. Ids are unique within a screen, where
+/// 48 bits make an accidental collision vanishingly unlikely; collisions that
+/// do happen are resolved by rehashing.
+const ID_LENGTH: usize = 12;
+
+/// Gives every node of `graph` without an id a stable one. Ids already set,
+/// for example by a previous import or by hand, are kept.
+///
+/// An id is derived from the node's ancestors and from the node itself: its
+/// `key` prop when it has one, otherwise its widget type and how many siblings
+/// of that type come before it. Props are left out, so re-importing a tree
+/// whose structure is unchanged yields the same ids even when props were
+/// edited, and inserting a sibling of another type leaves the others alone.
+/// Keyed nodes also keep their ids when reordered.
+pub fn assign_node_ids(graph: &mut ScreenGraph) {
+    let mut taken: HashSet<String> = HashSet::new();
+    collect_ids(&graph.root, &mut taken);
+    let root_segment = segment(&graph.root);
+    assign(&mut graph.root, "", &root_segment, &mut taken);
+}
+
+impl ScreenGraph {
+    /// The node with id `id`, searching children and builder templates.
+    pub fn find_node(&self, id: &str) -> Option<&WidgetNode> {
+        find(&self.root, id)
+    }
+
+    pub fn find_node_mut(&mut self, id: &str) -> Option<&mut WidgetNode> {
+        find_mut(&mut self.root, id)
+    }
+
+    /// Slash-delimited ids from the root to the node with id `id`, the form
+    /// logic nodes use as `widgetPath`.
+    pub fn widget_path(&self, id: &str) -> Option<String> {
+        let mut path = Vec::new();
+        path_to(&self.root, id, &mut path).then(|| path.join("/"))
+    }
+
+    /// Resolves a `widgetPath` produced by [Self::widget_path]. Only the last
+    /// segment is needed to find the node, so paths stay valid when the node
+    /// moves.
+    pub fn resolve_widget_path(&self, widget_path: &str) -> Option<&WidgetNode> {
+        let id = widget_path.rsplit('/').next()?;
+        self.find_node(id)
+    }
+}
+
+fn collect_ids(node: &WidgetNode, taken: &mut HashSet<String>) {
+    if let Some(id) = &node.id {
+        taken.insert(id.clone());
+    }
+    for child in node_children(node) {
+        collect_ids(child, taken);
+    }
+}
+
+fn assign(node: &mut WidgetNode, context: &str, own: &str, taken: &mut HashSet<String>) {
+    if node.id.is_none() {
+        node.id = Some(unique_id(&format!("{context}/{own}"), taken));
+    }
+
+    let context = format!("{context}/{}", segment(node));
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for child in &mut node.children {
+        let segment = segment(child);
+        let occurrence = seen.entry(segment.clone()).or_default();
+        let own = match *occurrence {
+            0 => segment,
+            n => format!("{segment}~{n}"),
+        };
+        *occurrence += 1;
+        assign(child, &context, &own, taken);
+    }
+    for builder in &mut node.builders {
+        let own = format!("{}:{}", builder.argument, segment(&builder.template));
+        assign(&mut builder.template, &context, &own, taken);
+    }
+}
+
+/// Names `node` by its `key` prop, or without one by its widget type. Props
+/// and source spans are left out so editing or moving a node keeps its id.
+fn segment(node: &WidgetNode) -> String {
+    keyed_segment(node).unwrap_or_else(|| node.widget.clone())
+}
+
+fn keyed_segment(node: &WidgetNode) -> Option<String> {
+    match node.props.get("key")? {
+        PropValue::Literal { value, .. } => Some(format!("{}@{value}", node.widget)),
+        PropValue::Expression { expression, .. } => Some(format!("{}@{expression}", node.widget)),
+        PropValue::Binding { .. } => None,
+    }
+}
+
+fn unique_id(path: &str, taken: &mut HashSet<String>) -> String {
+    let mut attempt = 0usize;
+    loop {
+        let mut hasher = Sha256::new();
+        hasher.update(path.as_bytes());
+        if attempt > 0 {
+            hasher.update(attempt.to_le_bytes());
+        }
+        let id: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()[..ID_LENGTH]
+            .to_string();
+        if taken.insert(id.clone()) {
+            return id;
+        }
+        attempt += 1;
+    }
+}
+
+/// Children followed by builder templates.
+fn node_children(node: &WidgetNode) -> impl Iterator<Item = &WidgetNode> {
+    node.children.iter().chain(
+        node.builders
+            .iter()
+            .map(|builder| builder.template.as_ref()),
+    )
+}
+
+fn find<'a>(node: &'a WidgetNode, id: &str) -> Option<&'a WidgetNode> {
+    if node.id.as_deref() == Some(id) {
+        return Some(node);
+    }
+    node_children(node).find_map(|child| find(child, id))
+}
+
+fn find_mut<'a>(node: &'a mut WidgetNode, id: &str) -> Option<&'a mut WidgetNode> {
+    if node.id.as_deref() == Some(id) {
+        return Some(node);
+    }
+    let WidgetNode {
+        children, builders, ..
+    } = node;
+    children
+        .iter_mut()
+        .chain(builders.iter_mut().map(|builder| builder.template.as_mut()))
+        .find_map(|child| find_mut(child, id))
+}
+
+fn path_to(node: &WidgetNode, id: &str, path: &mut Vec<String>) -> bool {
+    let Some(node_id) = &node.id else {
+        return false;
+    };
+    path.push(node_id.clone());
+    if node_id == id || node_children(node).any(|child| path_to(child, id, path)) {
+        return true;
+    }
+    path.pop();
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_graphs_from_source;
+
+    fn graph(body: &str) -> ScreenGraph {
+        let source = format!(
+            "class Home extends StatelessWidget {{\n  Widget build(BuildContext context) {{\n    return {body};\n  }}\n}}\n"
+        );
+        build_graphs_from_source(&source).remove(0)
+    }
+
+    fn ids(node: &WidgetNode) -> Vec<String> {
+        let mut ids = vec![node.id.clone().expect("node id")];
+        for child in node_children(node) {
+            ids.extend(self::ids(child));
+        }
+        ids
+    }
+
+    #[test]
+    fn import_assigns_unique_ids_matching_the_schema_pattern() {
+        let graph = graph(
+            "Column(children: [Text('a'), Text('b'), ListView.builder(itemBuilder: (context, index) => Text('c'))])",
+        );
+        let ids = ids(&graph.root);
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 5);
+        for id in &ids {
+            assert!(
+                id.len() >= 8
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+                "{id}"
+            );
+        }
+    }
+
+    #[test]
+    fn ids_survive_reimport_and_unrelated_insertions() {
+        let before = graph("Column(children: [Text('a'), Icon(Icons.add), Text('b')])");
+        let again = graph("Column(children: [Text('a'), Icon(Icons.add), Text('b')])");
+        assert_eq!(ids(&before.root), ids(&again.root));
+
+        // A new widget type in front leaves the existing siblings' ids alone.
+        let inserted = graph("Column(children: [Spacer(), Text('a'), Icon(Icons.add), Text('b')])");
+        let before_ids = ids(&before.root);
+        let inserted_ids = ids(&inserted.root);
+        assert_eq!(inserted_ids[0], before_ids[0]);
+        assert_eq!(inserted_ids[2..], before_ids[1..]);
+    }
+
+    #[test]
+    fn prop_edits_keep_ids() {
+        let before = graph("Row(children: [Text('a'), Text('b')])");
+        let after = graph("Row(children: [Text('a'), Text('b-left')])");
+        assert_eq!(ids(&before.root), ids(&after.root));
+
+        // Identical siblings are told apart, and editing a container leaves
+        // it and its children alone.
+        let twins = graph("Column(children: [Text('a'), Text('a')])");
+        assert_ne!(twins.root.children[0].id, twins.root.children[1].id);
+        let edited = graph(
+            "Row(children: [Padding(padding: p, child: Column(children: [Text('a'), Text('a')]))])",
+        );
+        let edited_padding = graph(
+            "Row(children: [Padding(padding: q, child: Column(children: [Text('a'), Text('a')]))])",
+        );
+        assert_eq!(ids(&edited.root), ids(&edited_padding.root));
+    }
+
+    #[test]
+    fn keyed_children_keep_ids_when_reordered() {
+        let before = graph(
+            "Column(children: [Text('a', key: ValueKey('a')), Text('b', key: ValueKey('b'))])",
+        );
+        let after = graph(
+            "Column(children: [Text('b', key: ValueKey('b')), Text('a', key: ValueKey('a'))])",
+        );
+        assert_eq!(before.root.children[0].id, after.root.children[1].id);
+        assert_eq!(before.root.children[1].id, after.root.children[0].id);
+    }
+
+    #[test]
+    fn existing_ids_are_kept_and_paths_resolve() {
+        let mut graph = graph("Column(children: [Text('a')])");
+        graph.root.children[0].id = Some("greeting".to_string());
+        graph.root.children.push(WidgetNode {
+            id: None,
+            ..graph.root.children[0].clone()
+        });
+        assign_node_ids(&mut graph);
+
+        assert_eq!(graph.root.children[0].id.as_deref(), Some("greeting"));
+        let added = graph.root.children[1].id.clone().expect("assigned id");
+        let path = graph.widget_path(&added).expect("path to new node");
+        assert_eq!(
+            path,
+            format!("{}/{added}", graph.root.id.as_deref().unwrap())
+        );
+        assert_eq!(
+            graph.resolve_widget_path(&path).map(|node| &node.id),
+            Some(&Some(added))
+        );
+        assert!(graph.find_node("missing").is_none());
+    }
+}
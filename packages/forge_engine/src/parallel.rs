@@ -0,0 +1,191 @@
+use std::{
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Callback told about every finished item of a parallel task. It is called
+/// from worker threads, in completion order.
+pub type ProgressCallback<'a> = &'a (dyn Fn(TaskProgress<'_>) + Sync);
+
+/// Progress of a parallel import or render after one more item finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskProgress<'a> {
+    /// Items finished so far, this one included.
+    pub completed: usize,
+    pub total: usize,
+    /// File or screen the item was about.
+    pub item: &'a str,
+    /// Whether the item failed; the other items are processed regardless.
+    pub failed: bool,
+}
+
+/// How many worker threads a parallel task may use and whom to tell about
+/// its progress.
+#[derive(Clone, Copy, Default)]
+pub struct Parallelism<'a> {
+    jobs: Option<NonZeroUsize>,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> Parallelism<'a> {
+    /// Uses one worker per available CPU and reports no progress.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the task to `jobs` worker threads; `0` restores the default.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = NonZeroUsize::new(jobs);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressCallback<'a>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Number of worker threads the task will use.
+    pub fn jobs(&self) -> usize {
+        self.jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
+
+    /// Applies `task` to every item on up to [Self::jobs] threads and returns
+    /// the results in item order, whatever order they finished in. A panic
+    /// inside `task` fails that item only, with the panic message as error.
+    pub(crate) fn map<T, R, E, F>(
+        &self,
+        items: &[T],
+        name: impl Fn(&T) -> &str + Sync,
+        task: F,
+    ) -> Vec<Result<R, E>>
+    where
+        T: Sync,
+        R: Send,
+        E: Send + From<String>,
+        F: Fn(&T) -> Result<R, E> + Sync,
+    {
+        let slots: Vec<Mutex<Option<Result<R, E>>>> =
+            items.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+        let worker = || loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                break;
+            };
+            let result = panic::catch_unwind(AssertUnwindSafe(|| task(item)))
+                .unwrap_or_else(|payload| Err(E::from(panic_message(payload.as_ref()))));
+            if let Some(progress) = self.progress {
+                progress(TaskProgress {
+                    completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total: items.len(),
+                    item: name(item),
+                    failed: result.is_err(),
+                });
+            }
+            *slots[index].lock().unwrap_or_else(|err| err.into_inner()) = Some(result);
+        };
+
+        let workers = self.jobs().min(items.len());
+        if workers <= 1 {
+            worker();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(worker);
+                }
+            });
+        }
+
+        slots
+            .into_iter()
+            .map(|slot| {
+                slot.into_inner()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .expect("every item is processed")
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for Parallelism<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Parallelism")
+            .field("jobs", &self.jobs)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    format!("panicked: {message}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_item_order() {
+        let items: Vec<String> = (0..64).map(|idx| idx.to_string()).collect();
+        let results = Parallelism::new().with_jobs(8).map(
+            &items,
+            |item| item.as_str(),
+            |item| -> Result<usize, String> {
+                // Finish the early items last.
+                let value: usize = item.parse().unwrap();
+                thread::sleep(std::time::Duration::from_micros((64 - value as u64) * 20));
+                Ok(value * 2)
+            },
+        );
+        let values: Vec<usize> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(values, (0..64).map(|value| value * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn failing_and_panicking_items_do_not_stop_the_rest() {
+        let items = ["ok", "err", "panic", "ok"];
+        let reported = Mutex::new(Vec::new());
+        let progress = |update: TaskProgress<'_>| {
+            reported
+                .lock()
+                .unwrap()
+                .push((update.item.to_string(), update.failed, update.total));
+        };
+        let results = Parallelism::new()
+            .with_jobs(2)
+            .with_progress(&progress)
+            .map(
+                &items,
+                |item| item,
+                |item| match *item {
+                    "err" => Err("bad file".to_string()),
+                    "panic" => panic!("boom"),
+                    other => Ok(other.len()),
+                },
+            );
+
+        assert_eq!(results[0], Ok(2));
+        assert_eq!(results[1], Err("bad file".to_string()));
+        assert_eq!(results[2], Err("panicked: boom".to_string()));
+        assert_eq!(results[3], Ok(2));
+
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort();
+        assert_eq!(reported.len(), 4);
+        assert_eq!(reported.iter().filter(|(_, failed, _)| *failed).count(), 2);
+        assert!(reported.iter().all(|(_, _, total)| *total == 4));
+    }
+}
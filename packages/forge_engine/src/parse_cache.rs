@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    build_graphs_with_report, workspace::component_symbols, ComponentSymbol, EngineError,
+    ParseReport, ScreenGraph,
+};
+
+/// Version of the engine that wrote a cache entry. Entries written by another
+/// version are treated as misses, so parser changes never serve stale graphs.
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Revision of the cached parse output within one engine version. Bump it
+/// whenever the parser's output changes shape, such as when node ids were
+/// introduced.
+const CACHE_REVISION: u32 = 2;
+
+/// Everything derived from parsing one Dart file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedParse {
+    pub graphs: Vec<ScreenGraph>,
+    pub report: ParseReport,
+    /// Widget classes the file declares, for the workspace symbol index.
+    #[serde(default)]
+    pub components: Vec<ComponentSymbol>,
+}
+
+/// Hits and misses of a [ParseCache] since it was opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    engine_version: String,
+    #[serde(default)]
+    revision: u32,
+    file: Option<String>,
+    parse: CachedParse,
+}
+
+/// Persistent cache of parsed Dart files, stored as one JSON file per entry
+/// in a directory. Entries are keyed by a SHA-256 of the engine version, the
+/// file path and the file contents, so editing a file or upgrading the
+/// engine re-parses it while unchanged files are read back as they were.
+/// The cache may be shared between threads parsing different files.
+#[derive(Debug)]
+pub struct ParseCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ParseCache {
+    /// Opens the cache stored in `dir`, creating the directory if needed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, EngineError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|source| EngineError::IoError {
+            path: dir.display().to_string(),
+            source,
+        })?;
+        Ok(Self {
+            dir,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the parse of `source`, reading it from the cache when an entry
+    /// for the same contents exists and parsing and storing it otherwise.
+    /// Unreadable or corrupt entries count as misses and are overwritten.
+    pub fn parse(&self, source: &str, file: Option<&str>) -> Result<CachedParse, EngineError> {
+        let key = cache_key(source, file);
+        let path = self.dir.join(format!("{key}.json"));
+
+        if let Some(parse) = read_entry(&path, file) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(parse);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let parse = parse_uncached(source, file);
+        let entry = CacheEntry {
+            engine_version: ENGINE_VERSION.to_string(),
+            revision: CACHE_REVISION,
+            file: file.map(str::to_string),
+            parse,
+        };
+        let payload = serde_json::to_vec(&entry).map_err(|source| EngineError::JsonError {
+            path: path.display().to_string(),
+            source,
+        })?;
+        fs::write(&path, payload).map_err(|source| EngineError::IoError {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Ok(entry.parse)
+    }
+}
+
+/// Parses `source` without consulting a cache.
+pub(crate) fn parse_uncached(source: &str, file: Option<&str>) -> CachedParse {
+    let (graphs, report) = build_graphs_with_report(source, file);
+    CachedParse {
+        graphs,
+        report,
+        components: component_symbols(file.unwrap_or_default(), source),
+    }
+}
+
+fn read_entry(path: &Path, file: Option<&str>) -> Option<CachedParse> {
+    let payload = fs::read(path).ok()?;
+    let entry: CacheEntry = serde_json::from_slice(&payload).ok()?;
+    (entry.engine_version == ENGINE_VERSION
+        && entry.revision == CACHE_REVISION
+        && entry.file.as_deref() == file)
+        .then_some(entry.parse)
+}
+
+/// Hex SHA-256 of the cache revision, engine version, file path and contents. The fields are
+/// length-prefixed so no two inputs share a key.
+fn cache_key(source: &str, file: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_REVISION.to_le_bytes());
+    for part in [ENGINE_VERSION, file.unwrap_or_default(), source] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.update([u8::from(file.is_some())]);
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "class Home extends StatelessWidget {\n  Widget build(BuildContext context) => Text('Hi');\n}\n";
+
+    #[test]
+    fn second_parse_of_unchanged_source_is_a_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ParseCache::open(dir.path()).unwrap();
+
+        let first = cache.parse(SOURCE, Some("lib/home.dart")).unwrap();
+        let second = cache.parse(SOURCE, Some("lib/home.dart")).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, parse_uncached(SOURCE, Some("lib/home.dart")));
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1 });
+
+        let edited = SOURCE.replace("Hi", "Hello");
+        cache.parse(&edited, Some("lib/home.dart")).unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+    }
+
+    #[test]
+    fn entries_persist_across_opens() {
+        let dir = tempfile::tempdir().unwrap();
+        ParseCache::open(dir.path())
+            .unwrap()
+            .parse(SOURCE, None)
+            .unwrap();
+
+        let cache = ParseCache::open(dir.path()).unwrap();
+        cache.parse(SOURCE, None).unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 0 });
+    }
+
+    #[test]
+    fn corrupt_and_foreign_version_entries_are_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{}.json", cache_key(SOURCE, None)));
+        let cache = ParseCache::open(dir.path()).unwrap();
+
+        fs::write(&path, "{ not json").unwrap();
+        cache.parse(SOURCE, None).unwrap();
+
+        let mut entry: serde_json::Value =
+            serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        entry["engine_version"] = "0.0.0-old".into();
+        fs::write(&path, entry.to_string()).unwrap();
+        cache.parse(SOURCE, None).unwrap();
+
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[test]
+    fn key_covers_path_and_contents() {
+        assert_ne!(
+            cache_key(SOURCE, Some("a.dart")),
+            cache_key(SOURCE, Some("b.dart"))
+        );
+        assert_ne!(cache_key(SOURCE, None), cache_key(SOURCE, Some("")));
+        assert_ne!(cache_key("ab", Some("c")), cache_key("b", Some("ac")));
+    }
+}
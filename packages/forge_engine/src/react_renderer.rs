@@ -1,7 +1,8 @@
+use crate::expr_codegen::CodegenTarget;
 use crate::renderer_adapter::{
     RenderContext, RenderDialect, RenderResult, RenderUnit, RendererAdapter,
 };
-use crate::{BindingReference, PropValue, WidgetNode};
+use crate::{BindingReference, PropValue, WidgetBuilder, WidgetNode, FOR_EACH_WIDGET};
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
 
@@ -16,7 +17,17 @@ impl RendererAdapter for ReactRenderer {
     }
 
     fn render_node(&self, node: &WidgetNode, ctx: &RenderContext<'_>) -> RenderResult {
-        let mut unit = RenderUnit::new(render_element(node, ctx, ctx.indent));
+        let code = if node.is_structural() {
+            // A JSX expression container needs an enclosing element.
+            let pad = " ".repeat(ctx.indent);
+            format!(
+                "{pad}<>\n{}\n{pad}</>",
+                render_element(node, ctx, ctx.indent + 2)
+            )
+        } else {
+            render_element(node, ctx, ctx.indent)
+        };
+        let mut unit = RenderUnit::new(code);
         append_imports(&mut unit.imports, ctx.options.dialect);
         unit.dependencies = REACT_DEPENDENCIES
             .iter()
@@ -39,11 +50,48 @@ impl RendererAdapter for ReactRenderer {
             .map(|(name, version)| (name.to_string(), version.to_string()))
             .collect()
     }
+
+    fn codegen_target(&self) -> Option<CodegenTarget> {
+        Some(CodegenTarget::TypeScript)
+    }
 }
 
 fn render_element(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) -> String {
     let indent_str = " ".repeat(indent);
-    let props = render_props(&node.props, ctx);
+    if let Some(raw) = &node.raw {
+        let location = node
+            .span
+            .as_ref()
+            .map(|span| format!(" at {span}"))
+            .unwrap_or_default();
+        let summary = raw
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .replace("*/", "* /");
+        return format!("{indent_str}{{/* Unsupported Dart{location}: {summary} */}}");
+    }
+    if node.is_structural() {
+        return format!("{indent_str}{{{}}}", render_structural(node, ctx, indent));
+    }
+    render_widget_element(node, ctx, indent, "")
+}
+
+/// Renders a regular element. `extra_props` is appended to the rendered props,
+/// such as the `key` of a repeated element.
+fn render_widget_element(
+    node: &WidgetNode,
+    ctx: &RenderContext<'_>,
+    indent: usize,
+    extra_props: &str,
+) -> String {
+    let indent_str = " ".repeat(indent);
+    let mut props = render_props(&node.props, ctx);
+    for builder in &node.builders {
+        props.push_str(&render_builder(builder, ctx, indent));
+    }
+    props.push_str(extra_props);
 
     if node.children.is_empty() {
         return format!("{indent_str}<{}{} />", node.widget, props);
@@ -63,6 +111,98 @@ fn render_element(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) ->
     buffer
 }
 
+/// Renders a builder callback as a render prop returning its template.
+/// Statements of a Dart block body have no JSX equivalent and are noted in a
+/// comment.
+fn render_builder(builder: &WidgetBuilder, ctx: &RenderContext<'_>, indent: usize) -> String {
+    let indent_str = " ".repeat(indent);
+    let note = builder
+        .statements
+        .as_ref()
+        .map(|statements| {
+            format!(
+                "{}/* Dart statements omitted: {} */\n",
+                " ".repeat(indent + 2),
+                statements
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .replace("*/", "* /")
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        " {}={{({}) => (\n{note}{}\n{indent_str})}}",
+        builder.argument,
+        builder.parameters.join(", "),
+        render_branch(&builder.template, ctx, indent + 2, "")
+    )
+}
+
+/// Renders the JavaScript expression for a structural node, without the
+/// surrounding braces: `cond && (...)`, `cond ? (...) : (...)` or
+/// `items.map((item, index) => (...))`.
+fn render_structural(node: &WidgetNode, ctx: &RenderContext<'_>, indent: usize) -> String {
+    let indent_str = " ".repeat(indent);
+    if node.widget == FOR_EACH_WIDGET {
+        let iterable = node
+            .iterable()
+            .map_or_else(|| "[]".to_string(), |value| render_prop_value(value, ctx));
+        let body = node.children.first().map_or_else(
+            || format!("{}null", " ".repeat(indent + 2)),
+            |template| render_branch(template, ctx, indent + 2, " key={index}"),
+        );
+        return format!(
+            "{iterable}.map(({}, index) => (\n{body}\n{indent_str}))",
+            node.item_name()
+        );
+    }
+
+    let condition = node.condition().map_or_else(
+        || "false".to_string(),
+        |value| render_prop_value(value, ctx),
+    );
+    let then = node.children.first().map_or_else(
+        || format!("{}null", " ".repeat(indent + 2)),
+        |child| render_branch(child, ctx, indent + 2, ""),
+    );
+    match node.children.get(1) {
+        Some(otherwise) => format!(
+            "{condition} ? (\n{then}\n{indent_str}) : (\n{}\n{indent_str})",
+            render_branch(otherwise, ctx, indent + 2, "")
+        ),
+        None => format!("{condition} && (\n{then}\n{indent_str})"),
+    }
+}
+
+/// Renders a child of a structural node as a JSX expression. Structural and
+/// raw children only render as expression containers, so they are wrapped in
+/// a fragment that carries `extra_props`.
+fn render_branch(
+    node: &WidgetNode,
+    ctx: &RenderContext<'_>,
+    indent: usize,
+    extra_props: &str,
+) -> String {
+    if !node.is_structural() && node.raw.is_none() {
+        return render_widget_element(node, ctx, indent, extra_props);
+    }
+
+    let indent_str = " ".repeat(indent);
+    let (open, close) = if extra_props.is_empty() {
+        ("<>".to_string(), "</>")
+    } else {
+        (
+            format!("<React.Fragment{extra_props}>"),
+            "</React.Fragment>",
+        )
+    };
+    format!(
+        "{indent_str}{open}\n{}\n{indent_str}{close}",
+        render_element(node, ctx, indent + 2)
+    )
+}
+
 fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) -> String {
     if props.is_empty() {
         return String::new();
@@ -75,7 +215,7 @@ fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) ->
         rendered.push('=');
 
         match value {
-            PropValue::Literal { value: literal } => {
+            PropValue::Literal { value: literal, .. } => {
                 rendered.push_str(&render_literal_prop(literal));
             }
             _ => {
@@ -90,9 +230,9 @@ fn render_props(props: &BTreeMap<String, PropValue>, ctx: &RenderContext<'_>) ->
 
 fn render_prop_value(value: &PropValue, ctx: &RenderContext<'_>) -> String {
     match value {
-        PropValue::Literal { value } => serialize_literal(value),
-        PropValue::Expression { expression } => expression.clone(),
-        PropValue::Binding { binding } => render_binding(binding, ctx),
+        PropValue::Literal { value, .. } => serialize_literal(value),
+        PropValue::Expression { expression, .. } => expression.clone(),
+        PropValue::Binding { binding, .. } => render_binding(binding, ctx),
     }
 }
 
@@ -161,14 +301,20 @@ mod tests {
     #[test]
     fn renderer_emits_jsx_element() {
         let node = WidgetNode {
+            id: None,
             widget: "Button".to_string(),
             props: BTreeMap::from([(
                 "text".to_string(),
                 PropValue::Literal {
                     value: Value::String("Click Me".into()),
+                    span: None,
                 },
             )]),
             children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         };
         let renderer = ReactRenderer;
         let adapter = RiverpodAdapter::new();
@@ -187,4 +333,19 @@ mod tests {
             .iter()
             .any(|line| line.contains("import React")));
     }
+
+    #[test]
+    fn renderer_compiles_logic_expressions_to_typescript() {
+        let adapter = RiverpodAdapter::new();
+        let options = RenderOptions {
+            pretty: true,
+            include_comments: false,
+            dialect: RenderDialect::Tsx,
+        };
+        let ctx = RenderContext::new(0, &adapter, &options);
+        let unit = ReactRenderer
+            .render_expression("providers.cart.total * 2 > input.limit", &ctx)
+            .expect("compiled expression");
+        assert_eq!(unit.code, "(cartProvider?.total * 2) > input?.limit");
+    }
 }
@@ -1,7 +1,9 @@
+use crate::expr_codegen::{CodegenTarget, ExprCodegen};
 use crate::state_adapter::StateAdapter;
-use crate::WidgetNode;
+use crate::{Parallelism, ScreenGraph, SourceSpan, WidgetNode};
 use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Rendering dialects supported by the engine.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -66,6 +68,10 @@ pub struct RenderError {
     pub node_id: Option<String>,
     pub message: String,
     pub severity: RenderSeverity,
+    /// Source location of the widget that failed to render, when it was
+    /// imported from Dart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
 }
 
 impl RenderError {
@@ -74,6 +80,7 @@ impl RenderError {
             node_id: None,
             message: message.into(),
             severity: RenderSeverity::Error,
+            span: None,
         }
     }
 
@@ -86,6 +93,33 @@ impl RenderError {
         self.severity = severity;
         self
     }
+
+    pub fn with_span(mut self, span: SourceSpan) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Attaches the id and source location of `node`, where it has them.
+    pub fn at_node(mut self, node: &WidgetNode) -> Self {
+        self.node_id = node.id.clone().or(self.node_id);
+        self.span = node.span.clone().or(self.span);
+        self
+    }
+}
+
+impl From<String> for RenderError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{span}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
@@ -100,6 +134,9 @@ pub struct RenderUnit {
     pub code: String,
     pub imports: Vec<String>,
     pub dependencies: HashMap<String, String>,
+    /// Definitions the code calls, such as the `forge*` helpers of compiled
+    /// logic expressions, to be emitted once per generated file.
+    pub helpers: Vec<String>,
 }
 
 impl RenderUnit {
@@ -108,6 +145,7 @@ impl RenderUnit {
             code: code.into(),
             imports: Vec::new(),
             dependencies: HashMap::new(),
+            helpers: Vec::new(),
         }
     }
 
@@ -160,6 +198,60 @@ pub trait RendererAdapter: Send + Sync {
     fn dependencies(&self) -> HashMap<String, String> {
         HashMap::new()
     }
+
+    /// Language logic expressions compile to for this renderer, `None` when
+    /// it cannot emit logic.
+    fn codegen_target(&self) -> Option<CodegenTarget> {
+        None
+    }
+
+    /// Compiles a Forge logic expression, such as the `expression` prop of a
+    /// flow node, to code that evaluates like the simulator. Provider reads
+    /// resolve through `ctx.state_adapter`, the same way widget bindings do.
+    fn render_expression(&self, source: &str, ctx: &RenderContext<'_>) -> RenderResult {
+        let target = self.codegen_target().ok_or_else(|| {
+            RenderError::new(format!(
+                "the {} renderer does not emit logic expressions",
+                self.name()
+            ))
+        })?;
+        let compiled = ExprCodegen::new(target, ctx.state_adapter)
+            .compile(source)
+            .map_err(|err| RenderError::new(format!("cannot compile `{source}`: {err}")))?;
+        let mut unit = RenderUnit::new(compiled.code).with_imports(compiled.imports);
+        unit.helpers = compiled.helpers;
+        Ok(unit)
+    }
+}
+
+/// Render output of one screen of [render_screens].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenRender {
+    pub screen: String,
+    pub result: RenderResult,
+}
+
+/// Renders every screen's tree with `renderer`, in parallel. Results keep the
+/// order of `screens`; a screen that fails to render does not stop the rest.
+pub fn render_screens(
+    renderer: &dyn RendererAdapter,
+    screens: &[ScreenGraph],
+    ctx: &RenderContext<'_>,
+    parallelism: Parallelism<'_>,
+) -> Vec<ScreenRender> {
+    let results = parallelism.map(
+        screens,
+        |screen| screen.id.as_str(),
+        |screen| renderer.render_tree(&screen.root, ctx),
+    );
+    screens
+        .iter()
+        .zip(results)
+        .map(|(screen, result)| ScreenRender {
+            screen: screen.id.clone(),
+            result,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -184,9 +276,14 @@ mod tests {
     fn renderer_contract_produces_output() {
         let renderer = MockRenderer;
         let node = WidgetNode {
+            id: None,
             widget: "Text".to_string(),
             props: Default::default(),
             children: Vec::new(),
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         };
         let adapter = RiverpodAdapter::new();
         let options = RenderOptions::default();
@@ -196,4 +293,91 @@ mod tests {
         assert!(!unit.code.trim().is_empty());
         assert!(unit.code.contains("Text"));
     }
+
+    #[test]
+    fn render_screens_keeps_screen_order_and_isolates_failures() {
+        struct FailingRenderer;
+
+        impl RendererAdapter for FailingRenderer {
+            fn name(&self) -> &'static str {
+                "failing"
+            }
+
+            fn render_node(&self, node: &WidgetNode, _ctx: &RenderContext<'_>) -> RenderResult {
+                match node.widget.as_str() {
+                    "Broken" => Err(RenderError::new("cannot render Broken")),
+                    "Panics" => panic!("renderer bug"),
+                    widget => Ok(RenderUnit::new(widget)),
+                }
+            }
+        }
+
+        let screens: Vec<ScreenGraph> = ["Text", "Broken", "Column", "Panics", "Row"]
+            .iter()
+            .enumerate()
+            .map(|(idx, widget)| ScreenGraph {
+                id: format!("Screen{idx}"),
+                root: WidgetNode {
+                    id: None,
+                    widget: widget.to_string(),
+                    props: Default::default(),
+                    children: Vec::new(),
+                    raw: None,
+                    builders: Vec::new(),
+                    component: None,
+                    span: None,
+                },
+                state: None,
+                consumer: None,
+                statements: None,
+            })
+            .collect();
+        let adapter = RiverpodAdapter::new();
+        let options = RenderOptions::default();
+        let ctx = RenderContext::new(0, &adapter, &options);
+
+        let renders = render_screens(
+            &FailingRenderer,
+            &screens,
+            &ctx,
+            Parallelism::new().with_jobs(3),
+        );
+        let summary: Vec<_> = renders
+            .iter()
+            .map(|render| {
+                (
+                    render.screen.as_str(),
+                    match &render.result {
+                        Ok(unit) => unit.code.clone(),
+                        Err(err) => err.message.clone(),
+                    },
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Screen0", "Text".to_string()),
+                ("Screen1", "cannot render Broken".to_string()),
+                ("Screen2", "Column".to_string()),
+                ("Screen3", "panicked: renderer bug".to_string()),
+                ("Screen4", "Row".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_error_reports_node_location() {
+        let node = crate::build_graphs_from_file_source(
+            "lib/home.dart",
+            "class Home extends StatelessWidget {\n  Widget build(BuildContext context) {\n    return Text('Hi');\n  }\n}\n",
+        )
+        .remove(0)
+        .root;
+
+        let err = RenderError::new("unsupported widget").at_node(&node);
+        assert_eq!(err.to_string(), "lib/home.dart:3:12: unsupported widget");
+        assert_eq!(err.span.map(|span| span.offset), Some(87));
+        assert_eq!(RenderError::new("plain").to_string(), "plain");
+    }
 }
@@ -18,7 +18,7 @@ impl ThemeHandle {
 pub type ContextListener = Box<dyn FnMut() + Send + 'static>;
 
 /// Shared evaluation context storing runtime state, providers, and theming.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct RuntimeContext {
     theme: Option<ThemeHandle>,
     providers: HashMap<String, Value>,
@@ -26,6 +26,17 @@ pub struct RuntimeContext {
     listeners: Vec<ContextListener>,
 }
 
+impl std::fmt::Debug for RuntimeContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RuntimeContext")
+            .field("theme", &self.theme)
+            .field("providers", &self.providers)
+            .field("state", &self.state)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
 impl RuntimeContext {
     pub fn new() -> Self {
         Self::default()
@@ -1,7 +1,8 @@
 use crate::{
     BindingReference, BindingTarget, Flow, LogicEdge, LogicGraph, LogicNode, PropValue,
-    ScreenGraph, WidgetNode,
+    ProviderAccess, ScreenGraph, WidgetNode,
 };
+use serde::Deserialize;
 use serde_json::{json, Map, Value};
 
 /// Canonical project metadata embedded in serialized Forge documents.
@@ -112,6 +113,90 @@ impl SchemaDocument {
     pub fn to_string_pretty(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(&self.to_value())
     }
+
+    /// Reads a document in the form written by [Self::to_value].
+    pub fn from_value(mut value: Value) -> Result<Self, serde_json::Error> {
+        if let Some(screens) = value.get_mut("screens").and_then(Value::as_array_mut) {
+            for screen in screens {
+                if let Some(root) = screen.get_mut("root") {
+                    nest_binding_props(root);
+                }
+            }
+        }
+        let document: DocumentRepr = serde_json::from_value(value)?;
+        Ok(Self {
+            version: document.forge_schema_version,
+            project: SchemaProject {
+                id: document.project.id,
+                name: document.project.name,
+                description: document.project.description,
+            },
+            screens: document.screens,
+            logic: document.logic,
+            metadata: document.metadata,
+        })
+    }
+
+    pub fn from_json(payload: &str) -> Result<Self, serde_json::Error> {
+        Self::from_value(serde_json::from_str(payload)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct DocumentRepr {
+    forge_schema_version: String,
+    project: ProjectRepr,
+    #[serde(default)]
+    screens: Vec<ScreenGraph>,
+    #[serde(default)]
+    logic: Vec<LogicGraph>,
+    #[serde(default)]
+    metadata: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct ProjectRepr {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Binding props are written flattened (see the [BindingReference] schema
+/// node); nests them back under `binding` as [PropValue] deserializes them.
+fn nest_binding_props(node: &mut Value) {
+    if let Some(props) = node.get_mut("props").and_then(Value::as_object_mut) {
+        for prop in props.values_mut() {
+            let Some(obj) = prop.as_object_mut() else {
+                continue;
+            };
+            if obj.get("type").and_then(Value::as_str) != Some("binding")
+                || obj.contains_key("binding")
+            {
+                continue;
+            }
+            let mut binding = std::mem::take(obj);
+            binding.remove("type");
+            if let Some(target) = binding.remove("target") {
+                binding.insert("type".to_string(), target);
+            }
+            if let Some(provider) = binding.remove("provider") {
+                binding.insert("provider_id".to_string(), provider);
+            }
+            obj.insert("type".to_string(), Value::String("binding".into()));
+            obj.insert("binding".to_string(), Value::Object(binding));
+        }
+    }
+    if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+        children.iter_mut().for_each(nest_binding_props);
+    }
+    if let Some(builders) = node.get_mut("builders").and_then(Value::as_array_mut) {
+        for builder in builders {
+            if let Some(template) = builder.get_mut("template") {
+                nest_binding_props(template);
+            }
+        }
+    }
 }
 
 /// Writes Forge in-memory graphs into deterministic schema documents.
@@ -171,6 +256,21 @@ impl ToSchemaNode for ScreenGraph {
         obj.insert("id".to_string(), Value::String(self.id.clone()));
         obj.insert("name".to_string(), Value::String(self.id.clone()));
         obj.insert("root".to_string(), self.root.to_schema_node());
+        if let Some(state) = &self.state {
+            obj.insert(
+                "state".to_string(),
+                serde_json::to_value(state).unwrap_or(Value::Null),
+            );
+        }
+        if let Some(consumer) = &self.consumer {
+            obj.insert(
+                "consumer".to_string(),
+                serde_json::to_value(consumer).unwrap_or(Value::Null),
+            );
+        }
+        if let Some(statements) = &self.statements {
+            obj.insert("statements".to_string(), Value::String(statements.clone()));
+        }
         Value::Object(obj)
     }
 }
@@ -178,6 +278,9 @@ impl ToSchemaNode for ScreenGraph {
 impl ToSchemaNode for WidgetNode {
     fn to_schema_node(&self) -> Value {
         let mut obj = Map::new();
+        if let Some(id) = &self.id {
+            obj.insert("id".to_string(), Value::String(id.clone()));
+        }
         obj.insert("widget".to_string(), Value::String(self.widget.clone()));
 
         if !self.props.is_empty() {
@@ -197,6 +300,49 @@ impl ToSchemaNode for WidgetNode {
             .collect();
         obj.insert("children".to_string(), Value::Array(children));
 
+        if let Some(raw) = &self.raw {
+            obj.insert("raw".to_string(), Value::String(raw.clone()));
+        }
+
+        if let Some(component) = &self.component {
+            obj.insert("component".to_string(), Value::String(component.clone()));
+        }
+
+        if !self.builders.is_empty() {
+            let builders = self
+                .builders
+                .iter()
+                .map(|builder| {
+                    let mut entry = Map::new();
+                    entry.insert(
+                        "argument".to_string(),
+                        Value::String(builder.argument.clone()),
+                    );
+                    entry.insert(
+                        "kind".to_string(),
+                        serde_json::to_value(builder.kind).unwrap_or(Value::Null),
+                    );
+                    entry.insert(
+                        "parameters".to_string(),
+                        Value::Array(
+                            builder
+                                .parameters
+                                .iter()
+                                .cloned()
+                                .map(Value::String)
+                                .collect(),
+                        ),
+                    );
+                    if let Some(statements) = &builder.statements {
+                        entry.insert("statements".to_string(), Value::String(statements.clone()));
+                    }
+                    entry.insert("template".to_string(), builder.template.to_schema_node());
+                    Value::Object(entry)
+                })
+                .collect();
+            obj.insert("builders".to_string(), Value::Array(builders));
+        }
+
         Value::Object(obj)
     }
 }
@@ -204,19 +350,19 @@ impl ToSchemaNode for WidgetNode {
 impl ToSchemaNode for PropValue {
     fn to_schema_node(&self) -> Value {
         match self {
-            PropValue::Literal { value } => {
+            PropValue::Literal { value, .. } => {
                 let mut obj = Map::new();
                 obj.insert("type".to_string(), Value::String("literal".into()));
                 obj.insert("value".to_string(), value.clone());
                 Value::Object(obj)
             }
-            PropValue::Expression { expression } => {
+            PropValue::Expression { expression, .. } => {
                 let mut obj = Map::new();
                 obj.insert("type".to_string(), Value::String("expression".into()));
                 obj.insert("expression".to_string(), Value::String(expression.clone()));
                 Value::Object(obj)
             }
-            PropValue::Binding { binding } => binding.to_schema_node(),
+            PropValue::Binding { binding, .. } => binding.to_schema_node(),
         }
     }
 }
@@ -240,6 +386,15 @@ impl ToSchemaNode for BindingReference {
         if let Some(ty) = &self.type_hint {
             obj.insert("type_hint".to_string(), Value::String(ty.clone()));
         }
+        if self.access != ProviderAccess::Watch {
+            obj.insert(
+                "access".to_string(),
+                Value::String(self.access.as_str().into()),
+            );
+        }
+        if self.notifier {
+            obj.insert("notifier".to_string(), Value::Bool(true));
+        }
 
         Value::Object(obj)
     }
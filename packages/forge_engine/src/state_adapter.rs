@@ -1,8 +1,8 @@
-use crate::{BindingReference, BindingTarget};
+use crate::{BindingReference, BindingTarget, ProviderAccess};
 
 /// Represents an adapter capable of resolving state bindings that appear inside
 /// widget properties.
-pub trait StateAdapter {
+pub trait StateAdapter: Send + Sync {
     /// The canonical name of the adapter (e.g. "riverpod").
     fn name(&self) -> &'static str;
 
@@ -22,6 +22,10 @@ pub struct ResolvedBinding {
     pub path: Option<String>,
     /// Type hint supplied by the binding, if available.
     pub type_hint: Option<String>,
+    /// How the provider is accessed (`watch`, `read` or `listen`).
+    pub access: ProviderAccess,
+    /// Whether the binding targets the provider's notifier.
+    pub notifier: bool,
     /// Adapter that produced the resolution.
     pub adapter: &'static str,
 }
@@ -71,6 +75,8 @@ impl StateAdapter for RiverpodAdapter {
             provider_id,
             path: binding.path.clone(),
             type_hint: binding.type_hint.clone(),
+            access: binding.access,
+            notifier: binding.notifier,
             adapter: self.name(),
         })
     }
@@ -88,12 +94,14 @@ mod tests {
             provider_id: Some(reference.to_string()),
             path: path.map(|p| p.to_string()),
             type_hint: None,
+            access: ProviderAccess::Watch,
+            notifier: false,
         }
     }
 
     #[test]
     fn riverpod_adapter_matches_provider_suffix() {
-        let adapter = RiverpodAdapter::default();
+        let adapter = RiverpodAdapter;
         let binding = make_binding("balanceProvider", None);
         assert!(adapter.can_resolve(&binding));
         let resolved = adapter.resolve(&binding).expect("resolved binding");
@@ -105,7 +113,7 @@ mod tests {
 
     #[test]
     fn riverpod_adapter_rejects_non_provider_target() {
-        let adapter = RiverpodAdapter::default();
+        let adapter = RiverpodAdapter;
         let mut binding = make_binding("balanceProvider", None);
         binding.target = BindingTarget::Widget;
         assert!(!adapter.can_resolve(&binding));
@@ -114,7 +122,7 @@ mod tests {
 
     #[test]
     fn riverpod_adapter_supports_paths() {
-        let adapter = RiverpodAdapter::default();
+        let adapter = RiverpodAdapter;
         let binding = make_binding("userProvider", Some("state.name"));
         let mut binding = binding;
         binding.type_hint = Some("User".to_string());
@@ -125,8 +133,19 @@ mod tests {
 
     #[test]
     fn riverpod_adapter_ignores_malformed_identifiers() {
-        let adapter = RiverpodAdapter::default();
+        let adapter = RiverpodAdapter;
         let binding = make_binding("invalid-provider", None);
         assert!(!adapter.can_resolve(&binding));
     }
+
+    #[test]
+    fn riverpod_adapter_preserves_access_and_notifier() {
+        let adapter = RiverpodAdapter;
+        let mut binding = make_binding("counterProvider", None);
+        binding.access = ProviderAccess::Read;
+        binding.notifier = true;
+        let resolved = adapter.resolve(&binding).expect("resolved binding");
+        assert_eq!(resolved.access, ProviderAccess::Read);
+        assert!(resolved.notifier);
+    }
 }
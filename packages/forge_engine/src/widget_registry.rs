@@ -87,15 +87,100 @@ pub enum WidgetRegistryError {
     AlreadyRegistered(String),
 }
 
-static REGISTRY: Lazy<RwLock<HashMap<String, WidgetDescriptor>>> = Lazy::new(|| {
-    RwLock::new(HashMap::new())
-});
+/// Flutter framework widgets the quick parser models, registered up front.
+const FRAMEWORK_WIDGETS: &[&str] = &[
+    "Align",
+    "AlertDialog",
+    "AnimatedContainer",
+    "AnimatedOpacity",
+    "AppBar",
+    "AspectRatio",
+    "BottomNavigationBar",
+    "Builder",
+    "Card",
+    "Center",
+    "Checkbox",
+    "Chip",
+    "CircleAvatar",
+    "CircularProgressIndicator",
+    "ClipRRect",
+    "Column",
+    "ConstrainedBox",
+    "Container",
+    "DecoratedBox",
+    "Divider",
+    "Drawer",
+    "DropdownButton",
+    "ElevatedButton",
+    "Expanded",
+    "FilledButton",
+    "FittedBox",
+    "Flexible",
+    "FloatingActionButton",
+    "Form",
+    "GestureDetector",
+    "GridView",
+    "Hero",
+    "Icon",
+    "IconButton",
+    "Image",
+    "InkWell",
+    "LinearProgressIndicator",
+    "ListTile",
+    "ListView",
+    "Material",
+    "MaterialApp",
+    "Opacity",
+    "OutlinedButton",
+    "Padding",
+    "Placeholder",
+    "Positioned",
+    "RichText",
+    "Row",
+    "SafeArea",
+    "Scaffold",
+    "SingleChildScrollView",
+    "SizedBox",
+    "Slider",
+    "Spacer",
+    "Stack",
+    "Switch",
+    "TabBar",
+    "TabBarView",
+    "Text",
+    "TextButton",
+    "TextField",
+    "TextFormField",
+    "Tooltip",
+    "Visibility",
+    "Wrap",
+];
+
+const FRAMEWORK_CATEGORY: &str = "flutter";
+
+static REGISTRY: Lazy<RwLock<HashMap<String, WidgetDescriptor>>> =
+    Lazy::new(|| RwLock::new(framework_widgets()));
+
+fn framework_widgets() -> HashMap<String, WidgetDescriptor> {
+    FRAMEWORK_WIDGETS
+        .iter()
+        .map(|name| (name.to_string(), framework_descriptor(name)))
+        .collect()
+}
+
+fn framework_descriptor(name: &str) -> WidgetDescriptor {
+    WidgetDescriptor::new(name).category(FRAMEWORK_CATEGORY)
+}
 
-/// Registers a widget descriptor with the global registry.
+/// Registers a widget descriptor with the global registry. A framework widget's
+/// descriptor, which lists no props, may be replaced once.
 pub fn register_widget(descriptor: WidgetDescriptor) -> Result<(), WidgetRegistryError> {
     let mut registry = REGISTRY.write().unwrap();
     let key = descriptor.name.clone();
-    if registry.contains_key(&key) {
+    if registry
+        .get(&key)
+        .is_some_and(|existing| *existing != framework_descriptor(&key))
+    {
         return Err(WidgetRegistryError::AlreadyRegistered(key));
     }
     registry.insert(key, descriptor);
@@ -116,7 +201,8 @@ pub fn list_widgets() -> Vec<WidgetDescriptor> {
     descriptors
 }
 
-/// Clears the registry. Intended for tests and should not be used at runtime.
+/// Resets the registry to the framework widgets. Intended for tests and should
+/// not be used at runtime.
 pub fn clear_registry() {
-    REGISTRY.write().unwrap().clear();
+    *REGISTRY.write().unwrap() = framework_widgets();
 }
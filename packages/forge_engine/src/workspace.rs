@@ -0,0 +1,682 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Component, Path},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    discover_dart_files, identifier_at, parse_cache::parse_uncached, scan_dart_classes,
+    split_class_members, CacheStats, ClassMember, DiagnosticSeverity, EngineError, Parallelism,
+    ParseCache, ParseDiagnostic, ParseReport, PropValue, ScreenGraph, TokenStream, WidgetNode,
+};
+
+/// Base classes of the widget classes indexed by [import_workspace].
+const WIDGET_BASES: &[&str] = &[
+    "StatelessWidget",
+    "StatefulWidget",
+    "ConsumerWidget",
+    "ConsumerStatefulWidget",
+];
+
+/// Props every widget accepts through `Key? key` or `super.key`.
+const IMPLICIT_PARAMETERS: &[&str] = &["key"];
+
+/// Result of importing every Dart file below a project root.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkspaceImport {
+    /// Imported files, relative to the root and in path order.
+    pub files: Vec<String>,
+    /// Graphs of every widget class, in file and declaration order.
+    pub graphs: Vec<ScreenGraph>,
+    pub symbols: SymbolIndex,
+    pub dependencies: DependencyGraph,
+    /// Diagnostics of all files, plus problems found while linking usages.
+    pub report: ParseReport,
+    /// Files that could not be imported, in path order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<FileFailure>,
+    /// Parse cache hits and misses, when imported with a [ParseCache].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStats>,
+}
+
+/// A file left out of a [WorkspaceImport] because reading or parsing it failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFailure {
+    /// Path relative to the workspace root.
+    pub file: String,
+    pub error: String,
+}
+
+/// Widget classes declared in the workspace, by class name.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    pub components: BTreeMap<String, ComponentSymbol>,
+}
+
+impl SymbolIndex {
+    /// Indexes the widget classes declared in `source`. Classes already in
+    /// the index keep their first declaration and are returned as duplicates.
+    pub fn index_source(&mut self, file: &str, source: &str) -> Vec<String> {
+        self.insert_all(component_symbols(file, source))
+    }
+
+    /// Adds `symbols`, returning the names of those already indexed.
+    pub fn insert_all(&mut self, symbols: Vec<ComponentSymbol>) -> Vec<String> {
+        let mut duplicates = Vec::new();
+        for symbol in symbols {
+            if self.components.contains_key(&symbol.name) {
+                duplicates.push(symbol.name);
+            } else {
+                self.components.insert(symbol.name.clone(), symbol);
+            }
+        }
+        duplicates
+    }
+
+    /// Looks up the class of `widget`, treating named constructors such as
+    /// `ProfileCard.compact` as their class.
+    pub fn resolve(&self, widget: &str) -> Option<&ComponentSymbol> {
+        let class = widget.split('.').next().unwrap_or(widget);
+        self.components.get(class)
+    }
+}
+
+/// Widget classes declared in `source`, in declaration order.
+pub(crate) fn component_symbols(file: &str, source: &str) -> Vec<ComponentSymbol> {
+    scan_dart_classes(source)
+        .into_iter()
+        .filter(|class| WIDGET_BASES.contains(&class.base))
+        .map(|class| ComponentSymbol {
+            name: class.name.to_string(),
+            file: file.to_string(),
+            base: class.base.to_string(),
+            parameters: constructor_parameters(class.name, &split_class_members(class.body)),
+        })
+        .collect()
+}
+
+/// A widget class declared in the workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentSymbol {
+    pub name: String,
+    /// Declaring file, relative to the workspace root.
+    pub file: String,
+    /// Widget base class, such as `StatelessWidget`.
+    pub base: String,
+    /// Parameters of the unnamed constructor, `key` excluded.
+    pub parameters: Vec<ConstructorParameter>,
+}
+
+impl ComponentSymbol {
+    pub fn parameter(&self, name: &str) -> Option<&ConstructorParameter> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.name == name)
+    }
+
+    /// Name of the positional parameter at `index`.
+    pub fn positional(&self, index: usize) -> Option<&ConstructorParameter> {
+        self.parameters
+            .iter()
+            .filter(|parameter| !parameter.named)
+            .nth(index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstructorParameter {
+    pub name: String,
+    /// Declared type, or the field type for `this.name` parameters.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub ty: Option<String>,
+    pub named: bool,
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default: Option<String>,
+}
+
+/// Which widget classes build which. Classes used by another class are
+/// components; the others are screens.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<DependencyNode>,
+    /// `from` builds `to`; sorted and without duplicates.
+    pub edges: Vec<DependencyEdge>,
+}
+
+impl DependencyGraph {
+    /// Files whose widgets build widgets declared in another file, as
+    /// `(from, to)` pairs.
+    pub fn file_edges(&self) -> BTreeSet<(&str, &str)> {
+        let files: BTreeMap<&str, &str> = self
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.file.as_str()))
+            .collect();
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                Some((
+                    *files.get(edge.from.as_str())?,
+                    *files.get(edge.to.as_str())?,
+                ))
+            })
+            .filter(|(from, to)| from != to)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyNode {
+    pub name: String,
+    pub file: String,
+    pub role: ComponentRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentRole {
+    Screen,
+    Component,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// Imports every Dart file below `root`. Hidden directories and `build`
+/// output are skipped. Widget classes are indexed across files, so a custom
+/// widget used in one file and declared in another is marked as a project
+/// component, its positional props are named after its constructor
+/// parameters and props it does not declare are reported.
+///
+/// Files are parsed in parallel. A file that cannot be read or parsed is
+/// listed in [WorkspaceImport::failures] and the others are imported anyway.
+pub fn import_workspace<P: AsRef<Path>>(root: P) -> Result<WorkspaceImport, EngineError> {
+    import_workspace_with(root, None, Parallelism::new())
+}
+
+/// Same as [import_workspace], but only files whose contents changed since
+/// they were stored in `cache` are parsed again.
+pub fn import_workspace_cached<P: AsRef<Path>>(
+    root: P,
+    cache: &ParseCache,
+) -> Result<WorkspaceImport, EngineError> {
+    import_workspace_with(root, Some(cache), Parallelism::new())
+}
+
+/// Same as [import_workspace], with an optional parse cache and control over
+/// the worker threads and progress reporting.
+pub fn import_workspace_with<P: AsRef<Path>>(
+    root: P,
+    cache: Option<&ParseCache>,
+    parallelism: Parallelism<'_>,
+) -> Result<WorkspaceImport, EngineError> {
+    let root = root.as_ref();
+    let initial_stats = cache.map(ParseCache::stats);
+    if !root.exists() {
+        return Err(EngineError::WorkspaceNotFound(root.display().to_string()));
+    }
+    let canonical_root = root.canonicalize().map_err(|source| EngineError::IoError {
+        path: root.display().to_string(),
+        source,
+    })?;
+
+    let mut discovered: Vec<String> = discover_dart_files(&canonical_root)?
+        .into_iter()
+        .filter_map(|path| workspace_relative(&canonical_root, Path::new(&path)))
+        .collect();
+    discovered.sort();
+
+    let results = parallelism.map(&discovered, String::as_str, |file| {
+        let path = canonical_root.join(file);
+        let source = fs::read_to_string(&path).map_err(|source| {
+            EngineError::IoError {
+                path: path.display().to_string(),
+                source,
+            }
+            .to_string()
+        })?;
+        match cache {
+            Some(cache) => cache
+                .parse(&source, Some(file))
+                .map_err(|err| err.to_string()),
+            None => Ok(parse_uncached(&source, Some(file))),
+        }
+    });
+
+    let mut files = Vec::with_capacity(discovered.len());
+    let mut parses = Vec::with_capacity(discovered.len());
+    let mut failures = Vec::new();
+    for (file, result) in discovered.into_iter().zip(results) {
+        match result {
+            Ok(parse) => {
+                files.push(file);
+                parses.push(parse);
+            }
+            Err(error) => failures.push(FileFailure { file, error }),
+        }
+    }
+
+    let mut report = ParseReport::default();
+    let mut symbols = SymbolIndex::default();
+    for parse in &mut parses {
+        for duplicate in symbols.insert_all(std::mem::take(&mut parse.components)) {
+            report.diagnostics.push(ParseDiagnostic {
+                severity: DiagnosticSeverity::Warning,
+                message: format!(
+                    "`{duplicate}` is also declared in {}; usages resolve to that declaration",
+                    symbols.components[&duplicate].file
+                ),
+                screen: Some(duplicate),
+                span: None,
+            });
+        }
+    }
+
+    let mut graphs = Vec::new();
+    for parse in parses {
+        report.diagnostics.extend(parse.report.diagnostics);
+        report.skipped_classes.extend(parse.report.skipped_classes);
+        graphs.extend(parse.graphs);
+    }
+
+    let mut edges = BTreeSet::new();
+    for graph in &mut graphs {
+        let mut used = BTreeSet::new();
+        link_node(&mut graph.root, &graph.id, &symbols, &mut used, &mut report);
+        for name in used {
+            if name != graph.id {
+                edges.insert(DependencyEdge {
+                    from: graph.id.clone(),
+                    to: name,
+                });
+            }
+        }
+    }
+
+    let used: BTreeSet<&str> = edges.iter().map(|edge| edge.to.as_str()).collect();
+    let nodes = symbols
+        .components
+        .values()
+        .map(|symbol| DependencyNode {
+            name: symbol.name.clone(),
+            file: symbol.file.clone(),
+            role: if used.contains(symbol.name.as_str()) {
+                ComponentRole::Component
+            } else {
+                ComponentRole::Screen
+            },
+        })
+        .collect();
+
+    Ok(WorkspaceImport {
+        files,
+        graphs,
+        dependencies: DependencyGraph {
+            nodes,
+            edges: edges.into_iter().collect(),
+        },
+        symbols,
+        report,
+        failures,
+        cache: cache.zip(initial_stats).map(|(cache, initial)| CacheStats {
+            hits: cache.stats().hits - initial.hits,
+            misses: cache.stats().misses - initial.misses,
+        }),
+    })
+}
+
+/// `path` relative to `root` with `/` separators, or `None` for files in
+/// hidden or `build` directories.
+fn workspace_relative(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        let Component::Normal(part) = component else {
+            return None;
+        };
+        parts.push(part.to_str()?);
+    }
+    let (_, directories) = parts.split_last()?;
+    if directories
+        .iter()
+        .any(|part| part.starts_with('.') || *part == "build")
+    {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Marks project components below `node`, renames their positional props and
+/// reports props their constructor does not declare. Names of the components
+/// used are added to `used`.
+fn link_node(
+    node: &mut WidgetNode,
+    screen: &str,
+    symbols: &SymbolIndex,
+    used: &mut BTreeSet<String>,
+    report: &mut ParseReport,
+) {
+    if let Some(symbol) = symbols.resolve(&node.widget).filter(|_| node.raw.is_none()) {
+        used.insert(symbol.name.clone());
+        node.component = Some(symbol.file.clone());
+
+        let props = std::mem::take(&mut node.props);
+        for (name, value) in props {
+            let name = match positional_index(&name) {
+                Some(index) => match symbol.positional(index) {
+                    Some(parameter) => parameter.name.clone(),
+                    None => {
+                        warn_unknown_prop(
+                            report,
+                            screen,
+                            &value,
+                            node,
+                            format!(
+                                "`{}` does not take a positional argument at index {index}",
+                                symbol.name
+                            ),
+                        );
+                        name
+                    }
+                },
+                None => {
+                    if symbol.parameter(&name).is_none()
+                        && !IMPLICIT_PARAMETERS.contains(&name.as_str())
+                    {
+                        warn_unknown_prop(
+                            report,
+                            screen,
+                            &value,
+                            node,
+                            format!("`{}` has no parameter `{name}`", symbol.name),
+                        );
+                    }
+                    name
+                }
+            };
+            node.props.insert(name, value);
+        }
+    }
+
+    for child in &mut node.children {
+        link_node(child, screen, symbols, used, report);
+    }
+    for builder in &mut node.builders {
+        link_node(&mut builder.template, screen, symbols, used, report);
+    }
+}
+
+fn warn_unknown_prop(
+    report: &mut ParseReport,
+    screen: &str,
+    value: &PropValue,
+    node: &WidgetNode,
+    message: String,
+) {
+    report.diagnostics.push(ParseDiagnostic {
+        severity: DiagnosticSeverity::Warning,
+        message,
+        screen: Some(screen.to_string()),
+        span: value.span().or(node.span.as_ref()).cloned(),
+    });
+}
+
+/// Index of a `positionalN` prop the parser names after its position.
+fn positional_index(name: &str) -> Option<usize> {
+    name.strip_prefix("positional")?.parse().ok()
+}
+
+/// Parameters of the unnamed constructor declared in `members`. Classes
+/// without one get Dart's implicit default constructor and no parameters.
+fn constructor_parameters(class: &str, members: &[ClassMember<'_>]) -> Vec<ConstructorParameter> {
+    let field_types: BTreeMap<&str, &str> = members
+        .iter()
+        .filter_map(|member| match member {
+            ClassMember::Field(field) => Some((field.name.as_str(), field.ty.as_deref()?)),
+            _ => None,
+        })
+        .collect();
+
+    members
+        .iter()
+        .find_map(|member| match member {
+            ClassMember::Method { name, source, .. } if *name == class => {
+                parse_parameter_list(source, class, &field_types)
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Parses the parameter list following `class` in a constructor declaration.
+fn parse_parameter_list(
+    source: &str,
+    class: &str,
+    field_types: &BTreeMap<&str, &str>,
+) -> Option<Vec<ConstructorParameter>> {
+    let stream = TokenStream::new(source).ok()?;
+    let name = (0..stream.len()).find(|idx| stream.is_identifier(*idx, class))?;
+    let open = name + 1;
+    if !stream.is_symbol(open, "(") {
+        return None;
+    }
+    let close = stream.closing(open)?;
+
+    let mut parameters = Vec::new();
+    let mut idx = open + 1;
+    while idx < close {
+        if stream.is_symbol(idx, "{") || stream.is_symbol(idx, "[") {
+            let named = stream.is_symbol(idx, "{");
+            let group_close = stream.closing(idx)?;
+            parse_parameter_group(
+                &stream,
+                idx + 1,
+                group_close,
+                named,
+                !named,
+                field_types,
+                &mut parameters,
+            );
+            idx = group_close + 1;
+            continue;
+        }
+        let end = segment_end(&stream, idx, close);
+        parameters.extend(parse_parameter(
+            &stream,
+            idx,
+            end,
+            false,
+            false,
+            field_types,
+        ));
+        idx = end + 1;
+    }
+    Some(parameters)
+}
+
+/// Parses the comma-separated parameters between `start` and `end`.
+fn parse_parameter_group(
+    stream: &TokenStream<'_>,
+    start: usize,
+    end: usize,
+    named: bool,
+    optional: bool,
+    field_types: &BTreeMap<&str, &str>,
+    parameters: &mut Vec<ConstructorParameter>,
+) {
+    let mut idx = start;
+    while idx < end {
+        let segment_end = segment_end(stream, idx, end);
+        parameters.extend(parse_parameter(
+            stream,
+            idx,
+            segment_end,
+            named,
+            optional,
+            field_types,
+        ));
+        idx = segment_end + 1;
+    }
+}
+
+/// Index of the top-level `,` ending the parameter starting at `start`, or
+/// `end` for the last parameter.
+fn segment_end(stream: &TokenStream<'_>, start: usize, end: usize) -> usize {
+    let mut idx = start;
+    while idx < end && !stream.is_symbol(idx, ",") {
+        if stream.is_symbol(idx, "{") || stream.is_symbol(idx, "[") {
+            // A parameter group starts the next segment.
+            if idx == start {
+                return stream.closing(idx).map_or(end, |close| close + 1).min(end);
+            }
+            return idx;
+        }
+        idx = stream.skip(idx);
+    }
+    idx.min(end)
+}
+
+/// Parses one parameter such as `required this.user`, `int count = 0` or
+/// `super.key`. Returns `None` for `key` and for empty segments.
+fn parse_parameter(
+    stream: &TokenStream<'_>,
+    start: usize,
+    end: usize,
+    named: bool,
+    optional: bool,
+    field_types: &BTreeMap<&str, &str>,
+) -> Option<ConstructorParameter> {
+    let mut first = crate::skip_annotations(stream, start);
+    let required_keyword = stream.is_identifier(first, "required");
+    if required_keyword {
+        first += 1;
+    }
+
+    let mut decl_end = end;
+    let mut default = None;
+    let mut idx = first;
+    while idx < end {
+        if stream.is_symbol(idx, "=") || stream.is_symbol(idx, ":") {
+            decl_end = idx;
+            if idx + 1 < end {
+                let value = stream.span(idx + 1, end - 1).text(stream.source());
+                default = Some(value.trim().to_string());
+            }
+            break;
+        }
+        idx = stream.skip(idx);
+    }
+    if decl_end <= first {
+        return None;
+    }
+
+    let last = decl_end - 1;
+    let name = identifier_at(stream, last)?;
+    if IMPLICIT_PARAMETERS.contains(&name) {
+        return None;
+    }
+    let field_formal = last >= first + 2
+        && stream.is_symbol(last - 1, ".")
+        && (stream.is_identifier(last - 2, "this") || stream.is_identifier(last - 2, "super"));
+    let ty = if field_formal {
+        let declared =
+            (last - 2 > first).then(|| stream.span(first, last - 3).text(stream.source()));
+        declared
+            .or_else(|| field_types.get(name).copied())
+            .map(str::to_string)
+    } else {
+        (last > first).then(|| {
+            stream
+                .span(first, last - 1)
+                .text(stream.source())
+                .to_string()
+        })
+    };
+
+    Some(ConstructorParameter {
+        name: name.to_string(),
+        ty,
+        named,
+        required: if named { required_keyword } else { !optional },
+        default,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(source: &str, class: &str) -> Vec<ConstructorParameter> {
+        let mut index = SymbolIndex::default();
+        index.index_source("lib/widget.dart", source);
+        index.components[class].parameters.clone()
+    }
+
+    #[test]
+    fn constructor_parameters_cover_positional_named_and_field_formals() {
+        let params = parameters(
+            r#"class ProfileCard extends StatelessWidget {
+  const ProfileCard(this.user, [this.compact = false], {super.key, required this.onTap, int? badge});
+  final User user;
+  final bool compact;
+  final VoidCallback onTap;
+  Widget build(BuildContext context) => Text(user.name);
+}"#,
+            "ProfileCard",
+        );
+        let summary: Vec<_> = params
+            .iter()
+            .map(|p| {
+                (
+                    p.name.as_str(),
+                    p.ty.as_deref(),
+                    p.named,
+                    p.required,
+                    p.default.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("user", Some("User"), false, true, None),
+                ("compact", Some("bool"), false, false, Some("false")),
+                ("onTap", Some("VoidCallback"), true, true, None),
+                ("badge", Some("int?"), true, false, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn classes_without_a_constructor_have_no_parameters() {
+        let params = parameters(
+            "class Empty extends StatelessWidget {\n  Widget build(BuildContext context) => Text('');\n}",
+            "Empty",
+        );
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn hidden_and_build_directories_are_skipped() {
+        let root = Path::new("/project");
+        assert_eq!(
+            workspace_relative(root, Path::new("/project/lib/home.dart")).as_deref(),
+            Some("lib/home.dart")
+        );
+        assert_eq!(
+            workspace_relative(root, Path::new("/project/.dart_tool/gen.dart")),
+            None
+        );
+        assert_eq!(
+            workspace_relative(root, Path::new("/project/build/out.dart")),
+            None
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use forge_engine::{
-    AnalysisOutcome, AnalyzerService, PropValue, ScreenGraph, WidgetNode, ANALYSIS_REPORT_VERSION,
+    build_graphs_with_report, AnalysisOutcome, AnalyzerService, PropValue, ScreenGraph,
+    SubprocessAnalyzer, WidgetNode, ANALYSIS_REPORT_VERSION,
 };
 use jsonschema::JSONSchema;
 use serde_json::{json, Value};
 use std::collections::BTreeMap;
 
+mod support;
+
 fn load_schema() -> JSONSchema {
     let schema_str = include_str!("../../../forge_spec/analysis_report.schema.json");
     let schema_json: Value = serde_json::from_str(schema_str).expect("valid analysis schema JSON");
@@ -17,16 +20,25 @@ fn make_simple_graph(id: &str, text: &str) -> ScreenGraph {
         "value".to_string(),
         PropValue::Literal {
             value: serde_json::Value::String(text.to_string()),
+            span: None,
         },
     );
 
     ScreenGraph {
         id: id.to_string(),
         root: WidgetNode {
+            id: None,
             widget: "Text".to_string(),
             props,
             children: vec![],
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         },
+        state: None,
+        consumer: None,
+        statements: None,
     }
 }
 
@@ -62,6 +74,31 @@ fn analysis_report_matches_schema_fallback_path() {
     validate_outcome(&outcome);
 }
 
+#[test]
+fn analysis_report_matches_schema_with_source_spans() {
+    let source = r#"class SampleScreen extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    return Column(children: [Text('Hello'), header]);
+  }
+}
+"#;
+    let (mut graphs, report) = build_graphs_with_report(source, Some("lib/sample.dart"));
+    let graph = graphs.remove(0);
+    // The raw `header` child keeps the score under the threshold, so the
+    // stand-in analyzer runs and its renamed graph is merged.
+    let analyzer = SubprocessAnalyzer::new(support::stub_analyzer_path()).with_args(["rename"]);
+    let outcome = AnalyzerService::new(0.9)
+        .with_analyzer(analyzer)
+        .run_with_report(source, &graph, graph.clone(), &report);
+    assert_eq!(outcome.diagnostics.len(), 2);
+    assert!(outcome.decision.breakdown.is_some());
+    let conflict = &outcome.merge.conflicts[0];
+    assert_eq!(conflict.path, "screen.id");
+    assert!(outcome.quick_graph.root.span.is_some());
+    validate_outcome(&outcome);
+}
+
 fn validate_outcome(outcome: &AnalysisOutcome) {
     let validator = load_schema();
     let outcome_json = serde_json::to_value(outcome).expect("serialize outcome");
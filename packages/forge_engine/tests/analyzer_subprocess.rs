@@ -0,0 +1,123 @@
+use forge_engine::{
+    build_graphs_with_report, AnalyzerBackend, AnalyzerError, AnalyzerRequest, AnalyzerService,
+    DiagnosticSeverity, ScreenGraph, SubprocessAnalyzer,
+};
+use std::time::{Duration, Instant};
+
+mod support;
+
+const SOURCE: &str = r#"class SampleScreen extends StatelessWidget {
+  @override
+  Widget build(BuildContext context) {
+    return Column(children: [Text('Hello'), header]);
+  }
+}
+"#;
+
+fn stub(mode: &str) -> SubprocessAnalyzer {
+    SubprocessAnalyzer::new(support::stub_analyzer_path())
+        .with_args([mode])
+        .with_timeout(Duration::from_secs(10))
+}
+
+fn quick_graph() -> ScreenGraph {
+    let (mut graphs, _) = build_graphs_with_report(SOURCE, Some("lib/sample.dart"));
+    graphs.remove(0)
+}
+
+#[test]
+fn subprocess_analyzer_round_trips_the_protocol() {
+    let graph = quick_graph();
+    let response = stub("rename")
+        .analyze(&AnalyzerRequest::new(SOURCE, &graph))
+        .expect("analyzer response");
+
+    assert_eq!(response.graph.id, "SampleScreen__analyzer");
+    assert_eq!(response.graph.root, graph.root);
+    assert_eq!(response.diagnostics.len(), 1);
+    assert_eq!(
+        response.diagnostics[0].message,
+        format!("analyzed {} bytes of lib/sample.dart", SOURCE.len())
+    );
+}
+
+#[test]
+fn service_merges_the_subprocess_graph() {
+    let (mut graphs, report) = build_graphs_with_report(SOURCE, Some("lib/sample.dart"));
+    let graph = graphs.remove(0);
+    let service = AnalyzerService::new(0.9).with_analyzer(stub("rename"));
+    let outcome = service.run_with_report(SOURCE, &graph, graph.clone(), &report);
+
+    assert!(outcome.analyzer_invoked);
+    assert_eq!(
+        outcome
+            .analyzer_graph
+            .as_ref()
+            .map(|graph| graph.id.as_str()),
+        Some("SampleScreen__analyzer")
+    );
+    assert_eq!(outcome.merge.conflicts.len(), 1);
+    assert_eq!(outcome.merge.conflicts[0].path, "screen.id");
+    // The raw `header` warning from the parse, then the analyzer's own.
+    assert_eq!(outcome.diagnostics.len(), 2);
+    assert!(outcome.diagnostics[1].message.starts_with("analyzed "));
+}
+
+#[test]
+fn crashing_analyzer_reports_status_and_stderr() {
+    let err = stub("crash")
+        .analyze(&AnalyzerRequest::new(SOURCE, &quick_graph()))
+        .unwrap_err();
+    match &err {
+        AnalyzerError::Exited { stderr, .. } => {
+            assert_eq!(stderr.trim(), "analyzer crashed on purpose")
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(err.to_string().ends_with(": analyzer crashed on purpose"));
+}
+
+#[test]
+fn hanging_analyzer_is_killed_after_the_timeout() {
+    let analyzer = stub("hang").with_timeout(Duration::from_millis(200));
+    let started = Instant::now();
+    let err = analyzer
+        .analyze(&AnalyzerRequest::new(SOURCE, &quick_graph()))
+        .unwrap_err();
+    assert!(matches!(err, AnalyzerError::Timeout { .. }), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn malformed_response_is_rejected() {
+    let err = stub("garbage")
+        .analyze(&AnalyzerRequest::new(SOURCE, &quick_graph()))
+        .unwrap_err();
+    assert!(
+        matches!(err, AnalyzerError::InvalidResponse { .. }),
+        "{err}"
+    );
+}
+
+#[test]
+fn analyzer_may_answer_without_reading_the_request() {
+    let response = stub("ignore-input")
+        .analyze(&AnalyzerRequest::new(SOURCE, &quick_graph()))
+        .expect("analyzer response");
+    assert_eq!(response.graph.id, "Ignored");
+}
+
+#[test]
+fn service_keeps_quick_graph_when_the_analyzer_crashes() {
+    let (mut graphs, report) = build_graphs_with_report(SOURCE, Some("lib/sample.dart"));
+    let graph = graphs.remove(0);
+    let service = AnalyzerService::new(0.9).with_analyzer(stub("crash"));
+    let outcome = service.run_with_report(SOURCE, &graph, graph.clone(), &report);
+
+    assert!(outcome.analyzer_invoked);
+    assert_eq!(outcome.merge.screen, graph);
+    assert!(outcome.merge.conflicts.is_empty());
+    let last = outcome.diagnostics.last().expect("analyzer diagnostic");
+    assert_eq!(last.severity, DiagnosticSeverity::Error);
+    assert!(last.message.contains("exited with"), "{}", last.message);
+}
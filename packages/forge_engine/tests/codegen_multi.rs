@@ -1,6 +1,6 @@
 use forge_engine::{
-    read_graph, AngularRenderer, ReactRenderer, RenderContext, RenderDialect, RenderOptions,
-    RendererAdapter, RiverpodAdapter, ScreenGraph,
+    read_graph, render_screens, AngularRenderer, Parallelism, ReactRenderer, RenderContext,
+    RenderDialect, RenderOptions, RendererAdapter, RiverpodAdapter, ScreenGraph,
 };
 use std::path::Path;
 
@@ -52,3 +52,146 @@ fn angular_renderer_emits_html() {
     assert!(unit.code.contains("[disabled]=\"isDisabled\""));
     assert!(unit.code.contains("ariaLabel=\"Submit\""));
 }
+
+#[test]
+fn react_renderer_emits_conditionals_and_loops() {
+    let graph = load_ui_fixture("conditional_list");
+    let adapter = RiverpodAdapter::new();
+    let options = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Jsx,
+    };
+    let ctx = RenderContext::new(0, &adapter, &options);
+
+    let unit = ReactRenderer
+        .render_node(&graph.root, &ctx)
+        .expect("render react tree");
+
+    assert_eq!(
+        unit.code,
+        r#"<List>
+  {loading && (
+    <Spinner />
+  )}
+  {items.length > 0 ? (
+    <Summary />
+  ) : (
+    <Empty />
+  )}
+  {items.map((item, index) => (
+    <Row label={item.name} key={index} />
+  ))}
+</List>"#
+    );
+}
+
+#[test]
+fn angular_renderer_emits_control_flow_blocks() {
+    let graph = load_ui_fixture("conditional_list");
+    let adapter = RiverpodAdapter::new();
+    let options = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Html,
+    };
+    let ctx = RenderContext::new(0, &adapter, &options);
+
+    let unit = AngularRenderer
+        .render_node(&graph.root, &ctx)
+        .expect("render angular tree");
+
+    assert_eq!(
+        unit.code,
+        r#"<List>
+  @if (loading) {
+    <Spinner />
+  }
+  @if (items.length > 0) {
+    <Summary />
+  } @else {
+    <Empty />
+  }
+  @for (item of items; track $index) {
+    <Row [label]="item.name" />
+  }
+</List>"#
+    );
+}
+
+#[test]
+fn builder_templates_render_as_render_props_and_ng_templates() {
+    let graph = load_ui_fixture("builder_list");
+    let adapter = RiverpodAdapter::new();
+    let jsx = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Jsx,
+    };
+    let unit = ReactRenderer
+        .render_node(&graph.root, &RenderContext::new(0, &adapter, &jsx))
+        .expect("render react tree");
+    assert_eq!(
+        unit.code,
+        r#"<ListView itemCount={orders.length} itemBuilder={(context, index) => (
+  /* Dart statements omitted: final order = orders[index]; */
+  <OrderTile order={order} />
+)} />"#
+    );
+
+    let html = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Html,
+    };
+    let unit = AngularRenderer
+        .render_node(&graph.root, &RenderContext::new(0, &adapter, &html))
+        .expect("render angular tree");
+    assert_eq!(
+        unit.code,
+        r#"<ListView [itemCount]="orders.length">
+  <ng-template #itemBuilder let-context="context" let-index="index">
+    <!-- Dart statements omitted: final order = orders[index]; -->
+    <OrderTile [order]="order" />
+  </ng-template>
+</ListView>"#
+    );
+}
+
+#[test]
+fn parallel_render_matches_sequential_render() {
+    let screens: Vec<ScreenGraph> = ["react_basic", "conditional_list", "builder_list"]
+        .iter()
+        .cycle()
+        .take(12)
+        .map(|stem| load_ui_fixture(stem))
+        .collect();
+    let adapter = RiverpodAdapter::new();
+    let options = RenderOptions {
+        pretty: true,
+        include_comments: false,
+        dialect: RenderDialect::Jsx,
+    };
+    let ctx = RenderContext::new(0, &adapter, &options);
+
+    let sequential: Vec<String> = screens
+        .iter()
+        .map(|screen| {
+            ReactRenderer
+                .render_tree(&screen.root, &ctx)
+                .expect("render react tree")
+                .code
+        })
+        .collect();
+    let parallel: Vec<String> = render_screens(
+        &ReactRenderer,
+        &screens,
+        &ctx,
+        Parallelism::new().with_jobs(4),
+    )
+    .into_iter()
+    .map(|render| render.result.expect("render react tree").code)
+    .collect();
+
+    assert_eq!(parallel, sequential);
+}
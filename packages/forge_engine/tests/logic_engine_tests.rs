@@ -1,10 +1,15 @@
 use forge_engine::{
-    simulate_flow, BuiltinLogicNodeKind, EvalConfig, Flow, LogicEdge, LogicGraph, LogicNode,
+    simulate_flow, BuiltinLogicNodeKind, EvalConfig, Flow, LogicEdge, LogicError, LogicGraph,
+    LogicNode,
 };
 use serde_json::json;
 use std::collections::HashMap;
 
 fn make_flow() -> LogicGraph {
+    make_flow_with("providers.data.value + 1")
+}
+
+fn make_flow_with(expression: &str) -> LogicGraph {
     let nodes = vec![
         LogicNode {
             id: "start".into(),
@@ -21,7 +26,7 @@ fn make_flow() -> LogicGraph {
             name: Some("Transform".into()),
             kind: Some(BuiltinLogicNodeKind::Transform),
             custom_kind: None,
-            props: json!({ "expression": "providers.data.value + 1" }),
+            props: json!({ "expression": expression }),
             inputs: vec!["input".into()],
             outputs: vec!["out".into()],
             metadata: None,
@@ -86,3 +91,60 @@ fn simple_flow_returns_incremented_value() {
     assert_eq!(result.return_value, Some(json!(2.0)));
     assert_eq!(result.traces.len(), 3);
 }
+
+#[test]
+fn transform_totals_a_cart_with_list_operations() {
+    let flow = make_flow_with(
+        "providers.cart.items.filter(i => !i.removed).reduce((sum, i) => sum + i.price * i.qty, 0)",
+    );
+    let mut providers = HashMap::new();
+    providers.insert(
+        "cart".to_string(),
+        json!({"items": [
+            {"price": 2.5, "qty": 2, "removed": false},
+            {"price": 10, "qty": 1, "removed": true},
+            {"price": 1, "qty": 3, "removed": false},
+        ]}),
+    );
+    let result = simulate_flow(
+        &flow,
+        "flow.basic",
+        None,
+        Some(&providers),
+        EvalConfig::default(),
+    )
+    .expect("simulation");
+
+    assert!(result.success);
+    assert_eq!(result.return_value, Some(json!(8.0)));
+}
+
+#[test]
+fn declared_provider_shapes_reject_mistyped_fields_before_running() {
+    let mut flow = make_flow_with("concat(providers.user.nmae, \"!\")");
+    flow.metadata = Some(json!({"types": {"providers": {"user": {
+        "type": "object",
+        "properties": {"name": {"type": "string"}},
+    }}}}));
+    let mut providers = HashMap::new();
+    providers.insert("user".to_string(), json!({"name": "Ada"}));
+
+    let err = simulate_flow(
+        &flow,
+        "flow.basic",
+        None,
+        Some(&providers),
+        EvalConfig::default(),
+    )
+    .expect_err("type errors stop the simulation");
+    let LogicError::TypeCheck { diagnostics, .. } = err else {
+        panic!("expected a type error, got {err}");
+    };
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].node_id.as_deref(), Some("transform"));
+    assert_eq!(
+        diagnostics[0].message,
+        "unknown field 'nmae'; did you mean 'name'?"
+    );
+    assert_eq!((diagnostics[0].start, diagnostics[0].end), (7, 26));
+}
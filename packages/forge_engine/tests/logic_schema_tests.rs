@@ -20,18 +20,32 @@ fn validate_fixture(validator: &JSONSchema, fixture_path: &Path) {
         )
     });
 
-    let result: Result<(), Vec<String>> = validator.validate(&json).map(|_| ()).map_err(|errors| {
-        errors
-            .map(|err| format!("{} at {}", err, err.instance_path))
-            .collect::<Vec<_>>()
-    });
+    // Fixtures are serialized `LogicGraph`s; the schema describes a single flow.
+    let flows = json
+        .get("flows")
+        .and_then(Value::as_array)
+        .unwrap_or_else(|| panic!("fixture {} has no flows array", fixture_path.display()));
+    assert!(
+        !flows.is_empty(),
+        "fixture {} contains no flows",
+        fixture_path.display()
+    );
 
-    if let Err(messages) = result {
-        panic!(
-            "fixture {} failed logic schema validation: {}",
-            fixture_path.display(),
-            messages.join("; ")
-        );
+    for flow in flows {
+        let result: Result<(), Vec<String>> =
+            validator.validate(flow).map(|_| ()).map_err(|errors| {
+                errors
+                    .map(|err| format!("{} at {}", err, err.instance_path))
+                    .collect::<Vec<_>>()
+            });
+
+        if let Err(messages) = result {
+            panic!(
+                "fixture {} failed logic schema validation: {}",
+                fixture_path.display(),
+                messages.join("; ")
+            );
+        }
     }
 }
 
@@ -1,6 +1,6 @@
 use forge_engine::{
-    BuiltinLogicNodeKind, Flow, ForgeGraph, LogicEdge, LogicGraph, LogicNode, PropValue,
-    SchemaDocument, SchemaProject, SchemaWriter, ScreenGraph, WidgetNode,
+    build_graphs_from_source, BuiltinLogicNodeKind, Flow, ForgeGraph, LogicEdge, LogicGraph,
+    LogicNode, PropValue, SchemaDocument, SchemaProject, SchemaWriter, ScreenGraph, WidgetNode,
 };
 use serde_json::{json, Value};
 use std::collections::BTreeMap;
@@ -11,6 +11,7 @@ fn make_screen(id: &str) -> ScreenGraph {
         "title".to_string(),
         PropValue::Literal {
             value: Value::String("Forge".into()),
+            span: None,
         },
     );
 
@@ -18,20 +19,34 @@ fn make_screen(id: &str) -> ScreenGraph {
         "value".into(),
         PropValue::Expression {
             expression: "context.title".into(),
+            span: None,
         },
     )]);
 
     ScreenGraph {
         id: id.to_string(),
         root: WidgetNode {
+            id: None,
             widget: "AppBar".into(),
             props,
             children: vec![WidgetNode {
+                id: None,
                 widget: "Text".into(),
                 props: child_props,
                 children: Vec::new(),
+                raw: None,
+                builders: Vec::new(),
+                component: None,
+                span: None,
             }],
+            raw: None,
+            builders: Vec::new(),
+            component: None,
+            span: None,
         },
+        state: None,
+        consumer: None,
+        statements: None,
     }
 }
 
@@ -115,6 +130,33 @@ fn schema_writer_serializes_basic_screen_graph() {
     );
 }
 
+#[test]
+fn schema_writer_preserves_imported_node_ids() {
+    let source = r#"class Home extends StatelessWidget {
+  Widget build(BuildContext context) {
+    return Column(children: [Text('a'), Text('b')]);
+  }
+}
+"#;
+    let screen = build_graphs_from_source(source).remove(0);
+    let document = make_document(
+        make_project("proj-ids", "Ids"),
+        vec![screen.clone()],
+        vec![],
+    );
+    let value: Value = serde_json::from_str(&document.to_string_pretty().unwrap()).unwrap();
+
+    let root = &value["screens"][0]["root"];
+    assert_eq!(root["id"], json!(screen.root.id));
+    assert_eq!(root["children"][1]["id"], json!(screen.root.children[1].id));
+    assert_ne!(root["children"][0]["id"], root["children"][1]["id"]);
+
+    // Ids also survive a plain serde round trip of the graph.
+    let json = serde_json::to_string(&screen).unwrap();
+    let restored: ScreenGraph = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, screen);
+}
+
 #[test]
 fn schema_writer_includes_logic_graphs() {
     let project = make_project("proj-logic", "Forge Logic");
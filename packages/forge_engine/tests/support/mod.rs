@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+/// Path of the `stub_analyzer` example, the stand-in for the Dart analyzer.
+/// `cargo test` builds examples next to the integration test binaries; run
+/// `cargo build --examples` first when invoking a single test target.
+pub fn stub_analyzer_path() -> PathBuf {
+    let mut dir = std::env::current_exe().expect("test executable path");
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    let path = dir
+        .join("examples")
+        .join(format!("stub_analyzer{}", std::env::consts::EXE_SUFFIX));
+    assert!(
+        path.exists(),
+        "{} is missing; build it with `cargo build --examples`",
+        path.display()
+    );
+    path
+}
@@ -0,0 +1,212 @@
+use forge_engine::{
+    import_workspace, import_workspace_cached, import_workspace_with, CacheStats, ComponentRole,
+    DiagnosticSeverity, EngineError, Parallelism, ParseCache, PropValue, TaskProgress, WidgetNode,
+};
+use std::{fs, path::Path, sync::Mutex};
+
+const HOME: &str = r#"import 'package:flutter/material.dart';
+import 'widgets/profile_card.dart';
+
+class HomeScreen extends StatelessWidget {
+  const HomeScreen({super.key});
+
+  @override
+  Widget build(BuildContext context) {
+    return Column(
+      children: [
+        ProfileCard(user, onTap: () => open(user)),
+        ProfileCard(guest, onTap: () {}, elevation: 4),
+      ],
+    );
+  }
+}
+"#;
+
+const PROFILE_CARD: &str = r#"import 'package:flutter/material.dart';
+
+class ProfileCard extends StatelessWidget {
+  const ProfileCard(this.user, {super.key, required this.onTap});
+
+  final User user;
+  final VoidCallback onTap;
+
+  @override
+  Widget build(BuildContext context) {
+    return Card(child: Text(user.name));
+  }
+}
+"#;
+
+fn write(root: &Path, file: &str, source: &str) {
+    let path = root.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+}
+
+fn workspace() -> tempfile::TempDir {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "lib/home.dart", HOME);
+    write(root.path(), "lib/widgets/profile_card.dart", PROFILE_CARD);
+    write(root.path(), ".dart_tool/generated.dart", PROFILE_CARD);
+    write(root.path(), "build/cache.dart", HOME);
+    root
+}
+
+fn first_card(root: &WidgetNode) -> &WidgetNode {
+    &root.children[0]
+}
+
+#[test]
+fn workspace_import_resolves_components_across_files() {
+    let root = workspace();
+    let workspace = import_workspace(root.path()).unwrap();
+
+    assert_eq!(
+        workspace.files,
+        ["lib/home.dart", "lib/widgets/profile_card.dart"]
+    );
+    let ids: Vec<_> = workspace
+        .graphs
+        .iter()
+        .map(|graph| graph.id.as_str())
+        .collect();
+    assert_eq!(ids, ["HomeScreen", "ProfileCard"]);
+
+    let symbol = &workspace.symbols.components["ProfileCard"];
+    assert_eq!(symbol.file, "lib/widgets/profile_card.dart");
+    let params: Vec<_> = symbol.parameters.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(params, ["user", "onTap"]);
+
+    let card = first_card(&workspace.graphs[0].root);
+    assert_eq!(
+        card.component.as_deref(),
+        Some("lib/widgets/profile_card.dart")
+    );
+    assert!(matches!(
+        card.props.get("user"),
+        Some(PropValue::Expression { expression, .. }) if expression == "user"
+    ));
+    assert!(!card.props.contains_key("positional0"));
+    assert!(card.props.contains_key("onTap"));
+    assert_eq!(workspace.graphs[0].root.component, None);
+}
+
+#[test]
+fn workspace_import_reports_props_the_component_does_not_declare() {
+    let root = workspace();
+    let workspace = import_workspace(root.path()).unwrap();
+
+    let warnings: Vec<_> = workspace
+        .report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == DiagnosticSeverity::Warning)
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(warnings, ["`ProfileCard` has no parameter `elevation`"]);
+    let span = workspace.report.diagnostics[0].span.as_ref().unwrap();
+    assert_eq!(span.file.as_deref(), Some("lib/home.dart"));
+    assert_eq!(span.line, 12);
+}
+
+#[test]
+fn workspace_import_builds_the_dependency_graph() {
+    let root = workspace();
+    let graph = import_workspace(root.path()).unwrap().dependencies;
+
+    let roles: Vec<_> = graph
+        .nodes
+        .iter()
+        .map(|node| (node.name.as_str(), node.role))
+        .collect();
+    assert_eq!(
+        roles,
+        [
+            ("HomeScreen", ComponentRole::Screen),
+            ("ProfileCard", ComponentRole::Component),
+        ]
+    );
+    assert_eq!(graph.edges.len(), 1);
+    assert_eq!(graph.edges[0].from, "HomeScreen");
+    assert_eq!(graph.edges[0].to, "ProfileCard");
+    assert_eq!(
+        graph.file_edges().into_iter().collect::<Vec<_>>(),
+        [("lib/home.dart", "lib/widgets/profile_card.dart")]
+    );
+}
+
+#[test]
+fn missing_workspace_root_is_an_error() {
+    let root = tempfile::tempdir().unwrap();
+    let err = import_workspace(root.path().join("missing")).unwrap_err();
+    assert!(matches!(err, EngineError::WorkspaceNotFound(_)), "{err}");
+}
+
+#[test]
+fn cached_reimport_only_parses_changed_files() {
+    let root = workspace();
+    let cache_dir = tempfile::tempdir().unwrap();
+    let uncached = import_workspace(root.path()).unwrap();
+
+    let cache = ParseCache::open(cache_dir.path()).unwrap();
+    let cold = import_workspace_cached(root.path(), &cache).unwrap();
+    assert_eq!(cold.cache, Some(CacheStats { hits: 0, misses: 2 }));
+
+    let cache = ParseCache::open(cache_dir.path()).unwrap();
+    let warm = import_workspace_cached(root.path(), &cache).unwrap();
+    assert_eq!(warm.cache, Some(CacheStats { hits: 2, misses: 0 }));
+    assert_eq!(warm.graphs, uncached.graphs);
+    assert_eq!(warm.symbols, uncached.symbols);
+    assert_eq!(warm.report, uncached.report);
+
+    write(
+        root.path(),
+        "lib/widgets/profile_card.dart",
+        &PROFILE_CARD.replace("user.name", "user.email"),
+    );
+    let edited = import_workspace_cached(root.path(), &cache).unwrap();
+    assert_eq!(edited.cache, Some(CacheStats { hits: 1, misses: 1 }));
+    assert_eq!(edited.graphs[0], uncached.graphs[0]);
+    assert_ne!(edited.graphs[1], uncached.graphs[1]);
+}
+
+#[test]
+fn unreadable_file_does_not_block_the_rest() {
+    let root = workspace();
+    // Not UTF-8, so the file cannot be read as Dart source.
+    fs::write(root.path().join("lib/broken.dart"), [0xff, 0xfe, 0x00]).unwrap();
+
+    let finished = Mutex::new(Vec::new());
+    let progress = |update: TaskProgress<'_>| {
+        finished
+            .lock()
+            .unwrap()
+            .push((update.item.to_string(), update.failed));
+    };
+    let workspace = import_workspace_with(
+        root.path(),
+        None,
+        Parallelism::new().with_jobs(2).with_progress(&progress),
+    )
+    .unwrap();
+
+    assert_eq!(
+        workspace.files,
+        ["lib/home.dart", "lib/widgets/profile_card.dart"]
+    );
+    assert_eq!(workspace.failures.len(), 1);
+    assert_eq!(workspace.failures[0].file, "lib/broken.dart");
+    assert!(workspace.failures[0].error.contains("broken.dart"));
+    assert_eq!(workspace.graphs.len(), 2);
+
+    let mut finished = finished.into_inner().unwrap();
+    finished.sort();
+    assert_eq!(
+        finished,
+        [
+            ("lib/broken.dart".to_string(), true),
+            ("lib/home.dart".to_string(), false),
+            ("lib/widgets/profile_card.dart".to_string(), false),
+        ]
+    );
+}